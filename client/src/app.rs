@@ -1,16 +1,64 @@
+use crate::connection::{self, ServerConnection};
+use crate::outbox::Outbox;
 use crate::ui::create_chat::ChatCreationPhase;
+use crate::ui::group_settings::GroupSettingsMode;
+use crate::utils::config::{Args, ClientConfig, ServerProfile};
 use quinn::Connection;
 use ratatui::widgets::ListState;
 use shared::client_response::Command::{CreateChat, GetFriends};
-use shared::client_response::{ClientRequest, Command};
-use shared::models::chat_models::{Chat, ChatList, ChatMessage, ChatMessages, Count};
+use shared::client_response::{ClientRequest, Command, TypingSignal};
+use shared::codec::{self, Codec};
+use shared::models::auth_models::{
+    AuthResponseModel, SessionInfo, SessionList, NOT_LOGGED_IN, TOKEN_EXPIRED,
+};
+use shared::models::chat_models::{
+    Chat, ChatCursor, ChatList, ChatMember, ChatMembers, ChatMessage, ChatMessages, Count,
+    MessageReceipt, MessageReceipts, ReadReceipt,
+};
 use shared::models::user_models::FriendRequestList;
-use shared::models::user_models::{User, UserList};
-use shared::server_response::ServerResponse;
+use shared::models::user_models::{Presence, PresenceList, User, UserList};
+use shared::server_response::{ServerEvent, ServerResponse};
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
 use tracing::error;
 
-const PAGE_SIZE: u64 = 10;
+// How many chats or messages to fetch at a time
+const CHAT_BATCH: u64 = 20;
+const MESSAGE_BATCH: u64 = 30;
+
+// How often to repeat a typing signal. The server drops indicators that
+// aren't refreshed within a few seconds.
+const TYPING_RESEND: Duration = Duration::from_secs(2);
+
+// How long without a key press before we show as away
+const AWAY_AFTER: Duration = Duration::from_secs(5 * 60);
+
+// Reconnect attempts start quickly and back off to once every 30 seconds
+const RECONNECT_MIN_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+// How long a single reconnect attempt may take
+const RECONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// How long to wait before retrying a queued message that failed to send
+const OUTBOX_RETRY: Duration = Duration::from_secs(5);
+
+// Queued messages go out at most this many a second, half the server's
+// default rate limit, leaving room for everything else the client sends
+const OUTBOX_BATCH: usize = 10;
+const OUTBOX_PACE: Duration = Duration::from_secs(1);
+
+// Retry state while the connection to the server is down
+pub struct Reconnect {
+    pub attempt: u32,
+    pub next_try: Instant,
+    // Why the last attempt failed, if one has
+    pub last_error: Option<String>,
+    // The attempt in progress. It runs in the background so the UI keeps drawing.
+    pending: Option<JoinHandle<Result<ServerConnection, String>>>,
+}
 
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub enum ActiveField {
@@ -22,6 +70,9 @@ pub enum ActiveField {
 #[derive(Debug)]
 pub enum FormState {
     MainMenu,
+    ServerPicker {
+        selected_index: usize,
+    },
     LoginForm {
         username: String,
         password: String,
@@ -55,39 +106,78 @@ pub enum FormState {
     },
     ConfirmUnfriend {
         selected_index: usize,
-        selected_option: usize,
+        selected_option: usize, // Usize where 0 = unfriend, 1 = block and 2 = go back
+    },
+    BlockedUsers {
+        selected_index: usize,
     },
     Chats {
         selected_index: usize,
-        page: u64,
-        page_count: u64,
+        // Where the next batch of older chats starts, if there is one
+        next_cursor: Option<ChatCursor>,
     },
     Chat {
         chat_name: String,
         chat_id: i32,
-        page: u64,
-        page_count: u64,
-        page_size: u64,
+        // Whether the server has messages older than the first loaded one
+        has_older: bool,
+        // How many messages up from the newest the view is scrolled
+        scroll_offset: usize,
         input_buffer: String,
         messages: Vec<ChatMessage>,
+        // Index into messages of the message picked for editing or deleting
+        selected_message: Option<usize>,
+        // The message being rewritten in input_buffer, if any
+        editing_message_id: Option<i32>,
+        // Other members who have read the newest message
+        seen_by: Vec<MessageReceipt>,
+        // Other members typing right now, as (user_id, username)
+        typing: Vec<(i32, String)>,
+        // Everyone in the chat but us, for the presence in the header
+        member_ids: Vec<i32>,
+    },
+    GroupSettings {
+        chat_id: i32,
+        chat_name: String,
+        is_group: bool,
+        members: Vec<ChatMember>,
+        selected_index: usize,
+        mode: GroupSettingsMode,
     },
     ProfileView {
         new_password: String,
         active_field: ActiveField,
+        // Every device the user is signed in on
+        sessions: Vec<SessionInfo>,
+        selected_session: usize,
     },
     ChatCreation(ChatCreationPhase),
-    Close,
     Exit,
 }
 
 pub struct App {
     pub state: FormState,
-    pub conn: Arc<Connection>,
+    // None until a server has been picked
+    pub conn: Option<Arc<Connection>>,
+    // Events pushed by the server we're connected to
+    pub events: Option<spmc::Receiver<ServerEvent>>,
+    // The codec the connection's handshake settled on
+    pub codec: Codec,
+    // The saved profiles, and where to save them back to
+    pub config: ClientConfig,
+    pub config_path: PathBuf,
+    pub args: Args,
+    // Name and profile of the server we're connected to
+    pub server: Option<(String, ServerProfile)>,
+    // Set while the connection is down and being retried
+    pub reconnect: Option<Reconnect>,
     pub selected_index: usize,
     pub message: String,
     pub logged_in: bool,
     pub username: String,
     pub jwt: String,
+    // Used to resume the session once the jwt expires
+    pub refresh_token: String,
     pub user_id: i32,
     pub unread_count: u64,
     pub list_state: ListState,
@@ -95,19 +185,38 @@ pub struct App {
     pub friend_request_num: usize,
     pub friend_list: UserList,
     pub friend_list_num: usize,
+    pub blocked_users: UserList,
     pub chats: Vec<Chat>,
+    // When we last told the server we're typing, if we still are
+    pub typing_sent_at: Option<Instant>,
+    // Latest known presence of friends, by user id
+    pub presence: HashMap<i32, Presence>,
+    pub last_input: Instant,
+    pub away: bool,
+    // Messages waiting to be sent, opened once logged in
+    pub outbox: Option<Outbox>,
+    // When to try the outbox again after a failed send
+    outbox_retry_at: Option<Instant>,
 }
 
 impl App {
-    pub fn new(conn: Arc<Connection>) -> Self {
+    pub fn new(config: ClientConfig, config_path: PathBuf, args: Args) -> Self {
         App {
             state: FormState::MainMenu,
-            conn,
+            conn: None,
+            events: None,
+            codec: Codec::Json,
+            config,
+            config_path,
+            args,
+            server: None,
+            reconnect: None,
             selected_index: 0,
             message: String::new(),
             logged_in: false,
             username: "".to_string(),
             jwt: "".to_string(),
+            refresh_token: "".to_string(),
             user_id: -1,
             unread_count: 0,
             list_state: ListState::default(),
@@ -118,61 +227,374 @@ impl App {
             friend_request_num: 0,
             friend_list: UserList { users: vec![] },
             friend_list_num: 0,
+            blocked_users: UserList { users: vec![] },
             chats: Vec::new(),
+            typing_sent_at: None,
+            presence: HashMap::new(),
+            last_input: Instant::now(),
+            away: false,
+            outbox: None,
+            outbox_retry_at: None,
         }
     }
 
-    pub async fn refresh(&mut self) {
-        match &self.state {
-            FormState::Chat {
+    /// Applies an event pushed by the server to the local state in place
+    pub async fn handle_event(&mut self, event: ServerEvent) {
+        match event {
+            ServerEvent::NewMessage { chat_id, message } => {
+                self.receive_message(chat_id, message).await;
+            }
+            ServerEvent::MessageEdited { chat_id, message } => {
+                self.update_message(chat_id, message);
+            }
+            ServerEvent::MessageDeleted {
                 chat_id,
-                chat_name,
-                page,
-                input_buffer,
-                ..
+                message_id,
+            } => {
+                if let FormState::Chat {
+                    chat_id: open_chat_id,
+                    messages,
+                    ..
+                } = &mut self.state
+                {
+                    if *open_chat_id == chat_id {
+                        if let Some(msg) = messages.iter_mut().find(|m| m.id == message_id) {
+                            msg.deleted = true;
+                            msg.content.clear();
+                        }
+                    }
+                }
+            }
+            ServerEvent::ChatCreated { chat } => {
+                self.unread_count += chat.unread_count;
+                if let FormState::Chats { .. } = self.state {
+                    self.chats.retain(|c| c.id != chat.id);
+                    self.chats.insert(0, chat);
+                }
+            }
+            ServerEvent::ChatUpdated { chat } => {
+                match &mut self.state {
+                    FormState::Chat {
+                        chat_id, chat_name, ..
+                    }
+                    | FormState::GroupSettings {
+                        chat_id, chat_name, ..
+                    } if *chat_id == chat.id => {
+                        *chat_name = chat.chat_name.clone();
+                    }
+                    _ => {}
+                }
+                if let Some(c) = self.chats.iter_mut().find(|c| c.id == chat.id) {
+                    c.chat_name = chat.chat_name;
+                    c.read_only = chat.read_only;
+                }
+            }
+            ServerEvent::ChatRemoved { chat_id } => {
+                if let Some(pos) = self.chats.iter().position(|c| c.id == chat_id) {
+                    let chat = self.chats.remove(pos);
+                    self.unread_count = self.unread_count.saturating_sub(chat.unread_count);
+                }
+                match &mut self.state {
+                    FormState::Chat {
+                        chat_id: open_chat_id,
+                        ..
+                    }
+                    | FormState::GroupSettings {
+                        chat_id: open_chat_id,
+                        ..
+                    } if *open_chat_id == chat_id => {
+                        self.enter_chats_view().await;
+                        self.message = "You are no longer a member of that chat".into();
+                    }
+                    FormState::Chats { selected_index, .. } => {
+                        *selected_index = (*selected_index).min(self.chats.len().saturating_sub(1));
+                    }
+                    _ => {}
+                }
+            }
+            ServerEvent::FriendRequestReceived { sender } => {
+                if !self.friend_requests.incoming.contains(&sender) {
+                    self.message = format!("New friend request from {}", sender.username);
+                    self.friend_requests.incoming.push(sender);
+                    self.friend_request_num = self.friend_requests.len();
+                }
+            }
+            ServerEvent::FriendRequestAccepted { friend } => {
+                self.friend_requests.outgoing.retain(|u| u.id != friend.id);
+                if !self.friend_list.users.contains(&friend) {
+                    self.message = format!("You are now friends with {}", friend.username);
+                    self.friend_list.users.push(friend);
+                    self.friend_list_num = self.friend_list.users.len();
+                }
+            }
+            ServerEvent::FriendRemoved { friend_id } => {
+                self.friend_list.users.retain(|u| u.id != friend_id);
+                self.friend_list_num = self.friend_list.users.len();
+                // Indexes into the friend list may no longer be valid
+                match &mut self.state {
+                    FormState::FriendList { selected_index } => {
+                        *selected_index =
+                            (*selected_index).min(self.friend_list_num.saturating_sub(1));
+                    }
+                    FormState::ConfirmUnfriend { .. } => {
+                        self.state = FormState::FriendList { selected_index: 0 };
+                    }
+                    _ => {}
+                }
+            }
+            ServerEvent::FriendRequestRemoved { user_id } => {
+                self.friend_requests.incoming.retain(|u| u.id != user_id);
+                self.friend_requests.outgoing.retain(|u| u.id != user_id);
+                self.friend_request_num = self.friend_requests.len();
+                // Indexes into the request list may no longer be valid
+                match &mut self.state {
+                    FormState::FriendRequests { selected_index } => {
+                        *selected_index =
+                            (*selected_index).min(self.friend_request_num.saturating_sub(1));
+                    }
+                    FormState::ConfirmFriendRequest { .. } => {
+                        self.state = FormState::FriendRequests { selected_index: 0 };
+                    }
+                    _ => {}
+                }
+            }
+            ServerEvent::Typing {
+                chat_id,
+                user_id,
+                username,
+                typing,
             } => {
-                let input_buffer = Some(input_buffer.clone());
-                self.enter_chat_view(*chat_id, chat_name.clone(), *page, PAGE_SIZE, input_buffer)
-                    .await;
+                if let FormState::Chat {
+                    chat_id: open_chat_id,
+                    typing: typing_users,
+                    ..
+                } = &mut self.state
+                {
+                    if *open_chat_id == chat_id {
+                        typing_users.retain(|(id, _)| *id != user_id);
+                        if typing {
+                            typing_users.push((user_id, username));
+                        }
+                    }
+                }
+            }
+            ServerEvent::PresenceChanged { presence } => {
+                self.presence.insert(presence.user_id, presence);
+            }
+            ServerEvent::MessagesRead { receipt } => {
+                if receipt.reader.user_id == self.user_id {
+                    // Another session of ours read the chat
+                    if let Some(chat) = self.chats.iter_mut().find(|c| c.id == receipt.chat_id) {
+                        self.unread_count = self.unread_count.saturating_sub(chat.unread_count);
+                        chat.unread_count = 0;
+                    }
+                } else {
+                    self.receive_read_receipt(receipt);
+                }
+            }
+        }
+    }
+
+    /// Adds a new message to the open chat, or counts it as unread
+    pub async fn receive_message(&mut self, chat_id: i32, message: ChatMessage) {
+        let from_self = message.sender_id == self.user_id;
+
+        // Membership changes show up as system messages, so reload the members
+        if let FormState::GroupSettings {
+            chat_id: open_chat_id,
+            ..
+        } = self.state
+        {
+            if open_chat_id == chat_id && message.is_system {
+                self.refresh_group_members(chat_id).await;
+                return;
+            }
+        }
+
+        if let FormState::Chat {
+            chat_id: open_chat_id,
+            scroll_offset,
+            messages,
+            seen_by,
+            typing,
+            ..
+        } = &mut self.state
+        {
+            if *open_chat_id == chat_id {
+                // A resent message may already be loaded
+                if messages.iter().any(|m| m.id == message.id) {
+                    return;
+                }
+                // Sending a message ends the sender's typing
+                typing.retain(|(id, _)| *id != message.sender_id);
+                // It's read as soon as it lands in the open chat
+                messages.push(ChatMessage {
+                    read: true,
+                    ..message
+                });
+                // Nobody else has seen the new message yet
+                seen_by.clear();
+                // Keep a scrolled back view where it is
+                if *scroll_offset > 0 {
+                    *scroll_offset += 1;
+                }
+                if !from_self {
+                    self.mark_messages_read(chat_id).await;
+                }
+                return;
             }
-            FormState::Chats { page, .. } => {
-                self.enter_chats_view(*page, PAGE_SIZE).await;
+        }
+
+        if !from_self {
+            self.unread_count += 1;
+        }
+
+        if let Some(pos) = self.chats.iter().position(|c| c.id == chat_id) {
+            if !from_self {
+                self.chats[pos].unread_count += 1;
             }
-            FormState::UserMenu { .. } => {
-                self.set_user_menu().await;
+            // Move the chat to the top of the list
+            if let FormState::Chats { .. } = self.state {
+                let chat = self.chats.remove(pos);
+                self.chats.insert(0, chat);
             }
-            FormState::FriendList { .. } => {
-                self.set_friend_list().await;
+        } else if let FormState::Chats { .. } = self.state {
+            // The chat isn't loaded yet, so pull in the most recent chats
+            self.enter_chats_view().await;
+        }
+    }
+
+    /// Ticks our messages another member has now read, and adds them to
+    /// the newest message's "seen by" list
+    fn receive_read_receipt(&mut self, receipt: ReadReceipt) {
+        let FormState::Chat {
+            chat_id,
+            messages,
+            seen_by,
+            ..
+        } = &mut self.state
+        else {
+            return;
+        };
+        if *chat_id != receipt.chat_id {
+            return;
+        }
+
+        let reader_id = receipt.reader.user_id;
+        for msg in messages.iter_mut() {
+            if msg.id <= receipt.last_read_id && msg.sender_id != reader_id {
+                msg.seen = true;
             }
-            FormState::FriendRequests { .. } => {
-                self.set_friend_requests().await;
+        }
+
+        if let Some(last) = messages.last() {
+            if last.id <= receipt.last_read_id
+                && last.sender_id != reader_id
+                && !seen_by.iter().any(|r| r.user_id == reader_id)
+            {
+                seen_by.push(receipt.reader);
             }
-            _ => { // These states don't need refreshing as they have no values that could be stale
+        }
+    }
+
+    /// Replaces a message in the open chat with its edited version
+    pub fn update_message(&mut self, chat_id: i32, message: ChatMessage) {
+        if let FormState::Chat {
+            chat_id: open_chat_id,
+            messages,
+            ..
+        } = &mut self.state
+        {
+            if *open_chat_id == chat_id {
+                if let Some(msg) = messages.iter_mut().find(|m| m.id == message.id) {
+                    *msg = message;
+                }
             }
         }
     }
 
+    /// Sends a request. If the connection has lost its session, it is
+    /// resumed with our tokens and the request retried once.
     pub async fn send_request(
         &mut self,
         request: &ClientRequest,
     ) -> Result<ServerResponse, Box<dyn std::error::Error>> {
-        let bytes = serde_json::to_vec(request)?;
-        let len = (bytes.len() as u32).to_be_bytes();
+        if self.reconnect.is_some() {
+            return Err("Reconnecting to the server, try again in a moment".into());
+        }
+        let mut response = self.send_once(request).await?;
+        if response.success
+            || self.refresh_token.is_empty()
+            || response.message.as_deref() != Some(NOT_LOGGED_IN)
+        {
+            return Ok(response);
+        }
 
-        let (mut send, mut recv) = self.conn.open_bi().await?;
+        if self.resume_session().await {
+            return self.send_once(request).await;
+        }
 
-        send.write_all(&len).await?;
-        send.write_all(&bytes).await?;
-        send.finish().await?;
+        // The session is over, so the user has to log in again
+        self.clear_session();
+        self.set_main_menu();
+        response.message = Some("Session expired, please log in again.".into());
+        Ok(response)
+    }
+
+    /// Logs the connection back in to our session, renewing the tokens
+    /// first if the access token has expired. Returns whether it worked.
+    async fn resume_session(&mut self) -> bool {
+        let request = ClientRequest {
+            command: Command::ResumeSession {
+                token: self.jwt.clone(),
+            },
+        };
+        match self.send_once(&request).await {
+            Ok(response) if response.success => true,
+            Ok(response) if response.message.as_deref() == Some(TOKEN_EXPIRED) => {
+                self.refresh_session().await
+            }
+            _ => false,
+        }
+    }
 
-        let mut len_buf = [0u8; 4];
-        recv.read_exact(&mut len_buf).await?;
-        let resp_len = u32::from_be_bytes(len_buf) as usize;
+    /// Trades the refresh token for a new token pair, which also logs the
+    /// connection in to the session. Returns whether it worked.
+    async fn refresh_session(&mut self) -> bool {
+        let request = ClientRequest {
+            command: Command::RefreshToken {
+                refresh_token: self.refresh_token.clone(),
+            },
+        };
+        let Ok(response) = self.send_once(&request).await else {
+            return false;
+        };
+        let auth = response
+            .data
+            .filter(|_| response.success)
+            .and_then(|data| serde_json::from_value::<AuthResponseModel>(data).ok());
+        match auth {
+            Some(auth) => {
+                self.jwt = auth.token;
+                self.refresh_token = auth.refresh_token;
+                true
+            }
+            None => false,
+        }
+    }
+
+    async fn send_once(
+        &mut self,
+        request: &ClientRequest,
+    ) -> Result<ServerResponse, Box<dyn std::error::Error>> {
+        let conn = self.conn.as_ref().ok_or("Not connected to a server")?;
+        let (mut send, mut recv) = conn.open_bi().await?;
 
-        let mut resp_buf = vec![0u8; resp_len];
-        recv.read_exact(&mut resp_buf).await?;
-        let response = serde_json::from_slice(&resp_buf)?;
+        codec::write_message(&mut send, self.codec, request).await?;
+        send.finish().await?;
 
+        let response =
+            codec::read_message(&mut recv, self.codec, connection::MAX_MESSAGE_SIZE).await?;
         Ok(response)
     }
 
@@ -180,7 +602,6 @@ impl App {
         let member_ids: Vec<i32> = users.iter().map(|u| u.id).collect();
         let is_group = member_ids.len() > 2;
         let request = ClientRequest {
-            jwt: Some(self.jwt.clone()),
             command: CreateChat {
                 name,
                 is_group,
@@ -207,7 +628,6 @@ impl App {
 
     pub async fn get_friends(&mut self) -> Vec<User> {
         let request = ClientRequest {
-            jwt: Some(self.jwt.clone()),
             command: GetFriends,
         };
         match self.send_request(&request).await {
@@ -236,12 +656,338 @@ impl App {
         Vec::new()
     }
 
+    /// Connects to a saved profile by name
+    pub async fn connect_to(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let profile = self
+            .config
+            .profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("No saved profile named {:?}", name))?;
+        self.connect_profile(name.to_string(), profile).await
+    }
+
+    /// Connects to a server, replacing any current connection. Whoever was
+    /// logged in on the old server is logged out locally. A refused
+    /// certificate comes back as a [`CertificateChanged`](crate::utils::tls::CertificateChanged) error.
+    pub async fn connect_profile(
+        &mut self,
+        name: String,
+        profile: ServerProfile,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let connected = connection::connect(&profile, &self.args).await?;
+        self.message = match connected.newly_pinned {
+            Some(fingerprint) => format!(
+                "Connected to {}. First visit, trusting its certificate {} from now on",
+                name, fingerprint
+            ),
+            None => format!("Connected to {}", name),
+        };
+        if let Some(old) = self.conn.replace(connected.conn) {
+            old.close(0u32.into(), b"switching servers");
+        }
+        self.codec = connected.codec;
+        self.events = Some(connected.events);
+        self.server = Some((name, profile));
+        self.reconnect = None;
+        self.clear_session();
+        self.chats.clear();
+        self.unread_count = 0;
+        Ok(())
+    }
+
+    /// Whether the connection has dropped since we last checked
+    pub fn connection_closed(&self) -> bool {
+        self.conn
+            .as_ref()
+            .is_some_and(|conn| conn.close_reason().is_some())
+    }
+
+    /// Starts retrying the connection, keeping the current screen as it is
+    pub fn connection_lost(&mut self) {
+        if self.reconnect.is_some() || self.server.is_none() {
+            return;
+        }
+        self.events = None;
+        self.typing_sent_at = None;
+        self.reconnect = Some(Reconnect {
+            attempt: 0,
+            next_try: Instant::now(),
+            last_error: None,
+            pending: None,
+        });
+    }
+
+    /// Starts the next reconnect attempt once its backoff has passed, and
+    /// picks up its result when it's done. Once connected, the session is
+    /// resumed and anything missed is reloaded.
+    pub async fn try_reconnect(&mut self) {
+        let Some((_, profile)) = self.server.clone() else {
+            return;
+        };
+        let Some(reconnect) = &mut self.reconnect else {
+            return;
+        };
+
+        let Some(pending) = reconnect.pending.take_if(|pending| pending.is_finished()) else {
+            if reconnect.pending.is_none() && Instant::now() >= reconnect.next_try {
+                let args = self.args.clone();
+                reconnect.pending = Some(tokio::spawn(async move {
+                    match tokio::time::timeout(
+                        RECONNECT_TIMEOUT,
+                        connection::connect(&profile, &args),
+                    )
+                    .await
+                    {
+                        Ok(result) => result.map_err(|e| e.to_string()),
+                        Err(_) => Err("timed out".to_string()),
+                    }
+                }));
+            }
+            return;
+        };
+
+        let result = pending
+            .await
+            .unwrap_or_else(|e| Err(format!("reconnect task failed: {}", e)));
+        let connected = match result {
+            Ok(connected) => connected,
+            Err(e) => {
+                reconnect.attempt += 1;
+                reconnect.next_try = Instant::now() + reconnect_delay(reconnect.attempt);
+                reconnect.last_error = Some(e);
+                return;
+            }
+        };
+        self.conn = Some(connected.conn);
+        self.codec = connected.codec;
+        self.events = Some(connected.events);
+        self.reconnect = None;
+
+        // Nobody was logged in, so there's nothing to resume
+        if self.refresh_token.is_empty() {
+            return;
+        }
+        if self.resume_session().await {
+            self.resync().await;
+            self.message = "Reconnected".into();
+            self.flush_outbox().await;
+        } else {
+            self.clear_session();
+            self.set_main_menu();
+            self.message = "Session expired, please log in again.".into();
+        }
+    }
+
+    /// Catches up on whatever the server sent while we were disconnected,
+    /// without leaving the current screen
+    async fn resync(&mut self) {
+        // The new connection starts out online
+        if self.away {
+            self.away = false;
+            self.set_away(true).await;
+        }
+
+        let request = ClientRequest {
+            command: Command::GetUnreadMessageCount,
+        };
+        if let Ok(response) = self.send_request(&request).await {
+            if let Some(count) = response
+                .data
+                .and_then(|data| serde_json::from_value::<Count>(data).ok())
+            {
+                self.unread_count = count.count;
+            }
+        }
+
+        match &self.state {
+            FormState::Chat {
+                chat_id,
+                member_ids,
+                ..
+            } => {
+                let (chat_id, member_ids) = (*chat_id, member_ids.clone());
+                let Some(latest) = self.get_chat_messages(chat_id, None).await else {
+                    return;
+                };
+                // Swap in the latest messages, keeping whatever was being typed or edited
+                if let FormState::Chat {
+                    has_older,
+                    scroll_offset,
+                    messages,
+                    selected_message,
+                    seen_by,
+                    typing,
+                    ..
+                } = &mut self.state
+                {
+                    *has_older = latest.has_more;
+                    *scroll_offset = 0;
+                    *messages = latest.messages;
+                    *selected_message = None;
+                    seen_by.clear();
+                    typing.clear();
+                }
+                self.load_presence(member_ids).await;
+                self.mark_messages_read(chat_id).await;
+            }
+            FormState::Chats { .. } => self.enter_chats_view().await,
+            _ => {}
+        }
+    }
+
+    /// Loads the logged in user's unsent messages for this server and sends them
+    pub async fn open_outbox(&mut self) {
+        let Some((_, profile)) = &self.server else {
+            return;
+        };
+        match Outbox::open(&profile.address(), self.user_id) {
+            Ok(outbox) => self.outbox = Some(outbox),
+            Err(e) => {
+                error!("Failed to open outbox: {}", e);
+                self.message = format!("Couldn't load unsent messages: {}", e);
+                return;
+            }
+        }
+        self.flush_outbox().await;
+    }
+
+    /// Queues a message and sends everything queued. While the connection is
+    /// down, or earlier messages are still waiting their turn, it waits in the
+    /// outbox, shown as pending in its chat.
+    pub async fn queue_message(&mut self, chat_id: i32, content: String) {
+        let Some(outbox) = &mut self.outbox else {
+            self.message = "Messages can't be sent until the outbox loads".into();
+            return;
+        };
+        if let Err(e) = outbox.push(chat_id, content) {
+            error!("Failed to save outbox: {}", e);
+        }
+        if self.outbox_retry_at.is_none() {
+            self.flush_outbox().await;
+        }
+    }
+
+    /// Sends queued messages in order, a batch at a time. If one can't reach
+    /// the server or is turned away for now, it and everything after it stay
+    /// queued and are retried shortly. One the server rejects for good is dropped.
+    pub async fn flush_outbox(&mut self) {
+        self.outbox_retry_at = None;
+        for _ in 0..OUTBOX_BATCH {
+            let Some(pending) = self.outbox.as_ref().and_then(|o| o.front()).cloned() else {
+                return;
+            };
+            let request = ClientRequest {
+                command: Command::SendMessage {
+                    chat_id: pending.chat_id,
+                    content: pending.content.clone(),
+                    idempotency_key: Some(pending.idempotency_key.clone()),
+                },
+            };
+            let Ok(response) = self.send_request(&request).await else {
+                self.outbox_retry_at = Some(Instant::now() + OUTBOX_RETRY);
+                return;
+            };
+
+            // Turned away for now, like when rate limited. It stays first in
+            // line so later messages don't overtake it.
+            if !response.success && !response.is_rejected() {
+                self.message = format!(
+                    "Couldn't send \"{}\" yet, will retry: {}",
+                    pending.content,
+                    response.message.unwrap_or("unknown error".into())
+                );
+                self.outbox_retry_at = Some(Instant::now() + OUTBOX_RETRY);
+                return;
+            }
+
+            // The session ended, so the rest waits for the next login
+            let Some(outbox) = &mut self.outbox else {
+                return;
+            };
+            if let Err(e) = outbox.remove(&pending.idempotency_key) {
+                error!("Failed to save outbox: {}", e);
+            }
+
+            // The server turned it down for good, so retrying won't help
+            if !response.success {
+                self.message = format!(
+                    "Couldn't send \"{}\": {}",
+                    pending.content,
+                    response.message.unwrap_or("unknown error".into())
+                );
+                continue;
+            }
+            match response.data.map(serde_json::from_value::<ChatMessage>) {
+                Some(Ok(message)) => self.receive_message(pending.chat_id, message).await,
+                Some(Err(e)) => self.message = format!("Parse error: {}", e),
+                None => {}
+            }
+        }
+
+        // Anything left goes out in the next batch
+        if self.outbox.as_ref().is_some_and(|o| !o.is_empty()) {
+            self.outbox_retry_at = Some(Instant::now() + OUTBOX_PACE);
+        }
+    }
+
+    /// Sends more of the outbox once the wait after a failed send, or the
+    /// pause between batches, is over
+    pub async fn retry_outbox(&mut self) {
+        if self.reconnect.is_some() {
+            return;
+        }
+        if self.outbox_retry_at.is_some_and(|at| Instant::now() >= at) {
+            self.flush_outbox().await;
+        }
+    }
+
+    /// Remembers the logged in username in the current profile for next time
+    pub fn save_username(&mut self) {
+        let Some((name, profile)) = &mut self.server else {
+            return;
+        };
+        if profile.username.as_ref() == Some(&self.username) {
+            return;
+        }
+        profile.username = Some(self.username.clone());
+        // Servers given with --server aren't saved
+        let Some(saved) = self.config.profiles.get_mut(name.as_str()) else {
+            return;
+        };
+        saved.username = Some(self.username.clone());
+        if let Err(e) = self.config.save(&self.config_path) {
+            error!("Failed to save config: {}", e);
+        }
+    }
+
     // Switch states
+    pub fn set_server_picker(&mut self) {
+        let current = self.server.as_ref().map(|(name, _)| name);
+        let selected_index = self
+            .config
+            .profiles
+            .keys()
+            .position(|name| Some(name) == current)
+            .unwrap_or(0);
+        self.state = FormState::ServerPicker { selected_index };
+    }
+
     pub fn set_login_form(&mut self) {
+        let username = self
+            .server
+            .as_ref()
+            .and_then(|(_, profile)| profile.username.clone());
+        // Start on the password when the username is already filled in
+        let active_field = if username.is_some() {
+            ActiveField::Password
+        } else {
+            ActiveField::Username
+        };
         self.state = FormState::LoginForm {
-            username: String::new(),
+            username: username.unwrap_or_default(),
             password: String::new(),
-            active_field: ActiveField::Username,
+            active_field,
         };
     }
 
@@ -271,7 +1017,6 @@ impl App {
 
     pub async fn set_friend_requests(&mut self) {
         let req = ClientRequest {
-            jwt: Option::from(self.jwt.clone()),
             command: Command::GetFriendRequests {},
         };
         match self.send_request(&req).await {
@@ -280,7 +1025,7 @@ impl App {
                     if let Some(data) = resp.data {
                         match serde_json::from_value::<FriendRequestList>(data) {
                             Ok(requests) => {
-                                self.friend_request_num = requests.incoming.len();
+                                self.friend_request_num = requests.len();
                                 self.friend_requests = requests;
                                 self.state = FormState::FriendRequests { selected_index: 0 };
                             }
@@ -315,7 +1060,6 @@ impl App {
 
     pub async fn set_friend_list(&mut self) {
         let req = ClientRequest {
-            jwt: Option::from(self.jwt.clone()),
             command: Command::GetFriends {},
         };
         match self.send_request(&req).await {
@@ -324,9 +1068,39 @@ impl App {
                     if let Some(data) = resp.data {
                         match serde_json::from_value::<UserList>(data) {
                             Ok(friends) => {
+                                let friend_ids = friends.users.iter().map(|u| u.id).collect();
                                 self.friend_list_num = friends.users.len();
                                 self.friend_list = friends;
-                                self.state = FormState::FriendList { selected_index: 0 }
+                                self.state = FormState::FriendList { selected_index: 0 };
+                                self.load_presence(friend_ids).await;
+                            }
+                            Err(e) => {
+                                self.message = format!("Parse error: {}", e);
+                            }
+                        }
+                    }
+                } else if let Some(message) = resp.message.clone() {
+                    self.message = message;
+                }
+            }
+            Err(e) => {
+                self.message = e.to_string();
+            }
+        }
+    }
+
+    pub async fn set_blocked_users(&mut self) {
+        let req = ClientRequest {
+            command: Command::GetBlockedUsers,
+        };
+        match self.send_request(&req).await {
+            Ok(resp) => {
+                if resp.success {
+                    if let Some(data) = resp.data {
+                        match serde_json::from_value::<UserList>(data) {
+                            Ok(blocked) => {
+                                self.blocked_users = blocked;
+                                self.state = FormState::BlockedUsers { selected_index: 0 }
                             }
                             Err(e) => {
                                 self.message = format!("Parse error: {}", e);
@@ -368,7 +1142,6 @@ impl App {
 
     pub async fn set_user_menu(&mut self) {
         let request = ClientRequest {
-            jwt: Some(self.jwt.clone()),
             command: Command::GetUnreadMessageCount,
         };
 
@@ -415,20 +1188,42 @@ impl App {
         }
     }
 
-    pub fn set_profile_view(&mut self) {
+    pub async fn set_profile_view(&mut self) {
+        let sessions = self.get_sessions().await;
         self.state = FormState::ProfileView {
             new_password: String::new(),
 
             active_field: ActiveField::Password,
+            sessions,
+            selected_session: 0,
         }
     }
 
+    pub async fn get_sessions(&mut self) -> Vec<SessionInfo> {
+        let request = ClientRequest {
+            command: Command::ListSessions,
+        };
+        match self.send_request(&request).await {
+            Ok(response) if response.success => {
+                match response.data.map(serde_json::from_value::<SessionList>) {
+                    Some(Ok(list)) => return list.sessions,
+                    Some(Err(e)) => self.message = format!("Parse error: {}", e),
+                    None => self.message = "No session data returned".into(),
+                }
+            }
+            Ok(response) => {
+                self.message = response.message.unwrap_or("Failed to get sessions".into());
+            }
+            Err(err) => {
+                self.message = format!("Error: {}", err);
+            }
+        }
+        Vec::new()
+    }
+
     pub async fn logout(&mut self) -> () {
         let req = ClientRequest {
-            jwt: Option::from(self.jwt.clone()),
-            command: Command::Logout {
-                username: self.username.clone(),
-            },
+            command: Command::Logout,
         };
         match self.send_request(&req).await {
             Ok(response) => {
@@ -440,16 +1235,52 @@ impl App {
                 error!("Error sending logout request: {:?}", e);
             }
         }
+        self.clear_session();
+    }
+
+    /// Forgets the logged in user and their tokens
+    fn clear_session(&mut self) {
         self.jwt = "".to_string();
+        self.refresh_token = "".to_string();
         self.username = "".to_string();
         self.user_id = -1;
+        self.away = false;
+        self.presence.clear();
+        self.outbox = None;
+        self.outbox_retry_at = None;
     }
 
-    pub async fn enter_chats_view(&mut self, page: u64, page_size: u64) {
-        let page_count = self.get_chats_page_count(page_size).await;
+    pub async fn enter_chats_view(&mut self) {
+        if let Some(chats) = self.get_chat_list(None).await {
+            self.chats = chats.chats;
+            self.state = FormState::Chats {
+                selected_index: self.selected_index.min(self.chats.len().saturating_sub(1)),
+                next_cursor: chats.next_cursor,
+            };
+            self.message = "".into();
+        }
+    }
 
-        if let Some(page_count) = page_count {
-            self.get_chat_list(page, page_size, page_count).await;
+    /// Appends the next batch of older chats to the chat list
+    pub async fn load_more_chats(&mut self) {
+        let FormState::Chats {
+            next_cursor: Some(cursor),
+            ..
+        } = self.state
+        else {
+            return;
+        };
+
+        if let Some(chats) = self.get_chat_list(Some(cursor)).await {
+            // Chats can move to the top while scrolling, so skip repeats
+            for chat in chats.chats {
+                if !self.chats.iter().any(|c| c.id == chat.id) {
+                    self.chats.push(chat);
+                }
+            }
+            if let FormState::Chats { next_cursor, .. } = &mut self.state {
+                *next_cursor = chats.next_cursor;
+            }
         }
     }
 
@@ -457,44 +1288,90 @@ impl App {
         &mut self,
         chat_id: i32,
         chat_name: String,
-        mut page: u64,
-        page_size: u64,
         input_buffer: Option<String>,
     ) {
-        // Get the number of pages in the chat
-        let page_count = self.get_chat_page_count(chat_id, page_size).await;
+        if let Some(messages) = self.get_chat_messages(chat_id, None).await {
+            let seen_by = match messages.messages.last() {
+                Some(last) => self.get_message_receipts(chat_id, last.id).await,
+                None => Vec::new(),
+            };
+            let member_ids: Vec<i32> = match self.get_chat_members(chat_id).await {
+                Some(members) => members
+                    .members
+                    .into_iter()
+                    .map(|m| m.id)
+                    .filter(|id| *id != self.user_id)
+                    .collect(),
+                None => Vec::new(),
+            };
+            self.load_presence(member_ids.clone()).await;
+            self.state = FormState::Chat {
+                chat_name,
+                chat_id,
+                has_older: messages.has_more,
+                scroll_offset: 0,
+                input_buffer: input_buffer.unwrap_or("".to_string()),
+                messages: messages.messages,
+                selected_message: None,
+                editing_message_id: None,
+                seen_by,
+                typing: Vec::new(),
+                member_ids,
+            };
+            self.message = "".into();
 
-        if let Some(page_count) = page_count {
-            if page > page_count {
-               page = page_count; 
-            }
-            self.get_chat_messages(
+            // Mark the messages in the chat as read if they were retrieved
+            self.mark_messages_read(chat_id).await;
+        }
+    }
+
+    /// Prepends the batch of messages before the oldest loaded one to the
+    /// open chat. Returns how many were added.
+    pub async fn load_older_messages(&mut self) -> usize {
+        let (chat_id, oldest_id) = match &self.state {
+            FormState::Chat {
                 chat_id,
-                chat_name,
-                page_count,
-                page,
-                page_size,
-                input_buffer,
-            )
-            .await;
+                has_older: true,
+                messages,
+                ..
+            } => (*chat_id, messages.first().map(|m| m.id)),
+            _ => return 0,
+        };
+
+        let Some(older) = self.get_chat_messages(chat_id, oldest_id).await else {
+            return 0;
+        };
+
+        if let FormState::Chat {
+            has_older,
+            messages,
+            selected_message,
+            ..
+        } = &mut self.state
+        {
+            let added = older.messages.len();
+            *has_older = older.has_more;
+            messages.splice(0..0, older.messages);
+            // Keep the cursor on the same message
+            if let Some(selected) = selected_message {
+                *selected += added;
+            }
+            return added;
         }
+        0
     }
 
     pub async fn get_chat_messages(
         &mut self,
         chat_id: i32,
-        chat_name: String,
-        page_count: u64,
-        page: u64,
-        page_size: u64,
-        input_buffer: Option<String>,
-    ) {
+        before_id: Option<i32>,
+    ) -> Option<ChatMessages> {
         let request = ClientRequest {
-            jwt: Some(self.jwt.clone()),
             command: Command::GetChatMessages {
                 chat_id,
-                page,
-                page_size,
+                before_id,
+                after_id: None,
+                limit: MESSAGE_BATCH,
             },
         };
 
@@ -503,18 +1380,7 @@ impl App {
                 if response.success {
                     if let Some(data) = response.data {
                         match serde_json::from_value::<ChatMessages>(data) {
-                            Ok(messages) => {
-                                self.state = FormState::Chat {
-                                    chat_name,
-                                    chat_id,
-                                    page_count,
-                                    page_size,
-                                    messages: messages.messages,
-                                    page,
-                                    input_buffer: input_buffer.unwrap_or("".to_string()),
-                                };
-                                self.message = "".into();
-                            }
+                            Ok(messages) => return Some(messages),
                             Err(e) => {
                                 self.message = format!("Parse error: {}", e);
                             }
@@ -523,64 +1389,153 @@ impl App {
                         self.message = "No chat data returned".into();
                     }
                 } else {
-                    self.message = response.message.unwrap_or("Failed to get chats".into());
+                    self.message = response.message.unwrap_or("Failed to get chat".into());
                 }
             }
             Err(err) => {
                 self.message = format!("Error: {}", err);
             }
         }
+        None
+    }
+
+    /// Tells the chat's other members whether we're typing. Signals go out as
+    /// datagrams, at most every TYPING_RESEND while typing continues.
+    pub fn signal_typing(&mut self, chat_id: i32, typing: bool) {
+        if self.reconnect.is_some() {
+            return;
+        }
+        let resend_due = self
+            .typing_sent_at
+            .is_none_or(|sent_at| sent_at.elapsed() >= TYPING_RESEND);
+        if typing && !resend_due || !typing && self.typing_sent_at.is_none() {
+            return;
+        }
+        self.typing_sent_at = typing.then(Instant::now);
 
-        // Mark the messages in the chat as read if they were retrieved
+        let signal = TypingSignal { chat_id, typing };
+        let Some(conn) = &self.conn else {
+            return;
+        };
+        if let Ok(bytes) = self.codec.encode(&signal) {
+            // Typing indicators are best effort, so a failed send is ignored
+            if let Err(e) = conn.send_datagram(bytes.into()) {
+                error!("Failed to send typing signal: {}", e);
+            }
+        }
+    }
+
+    /// Fetches the presence of the given users. Only friends' presence is
+    /// shared, so anyone else is left unknown.
+    pub async fn load_presence(&mut self, user_ids: Vec<i32>) {
+        if user_ids.is_empty() {
+            return;
+        }
         let request = ClientRequest {
-            jwt: Some(self.jwt.clone()),
-            command: Command::MarkMessagesRead { chat_id },
+            command: Command::GetPresence { user_ids },
         };
 
         match self.send_request(&request).await {
+            Ok(response) if response.success => {
+                if let Some(data) = response.data {
+                    match serde_json::from_value::<PresenceList>(data) {
+                        Ok(list) => {
+                            for presence in list.presences {
+                                self.presence.insert(presence.user_id, presence);
+                            }
+                        }
+                        Err(e) => self.message = format!("Parse error: {}", e),
+                    }
+                }
+            }
             Ok(response) => {
-                if !response.success {
-                    if let Some(message) = response.message {
-                        self.message = message;
-                    } else {
-                        self.message = "Couldn't mark messages as read!".into();
+                self.message = response.message.unwrap_or("Failed to get presence".into());
+            }
+            Err(err) => self.message = format!("Error: {}", err),
+        }
+    }
+
+    /// Notes a key press, coming back from away if we were idle
+    pub async fn record_input(&mut self) {
+        self.last_input = Instant::now();
+        if self.away {
+            self.set_away(false).await;
+        }
+    }
+
+    /// Shows us as away once we've been idle for AWAY_AFTER
+    pub async fn check_idle(&mut self) {
+        if !self.jwt.is_empty() && !self.away && self.last_input.elapsed() >= AWAY_AFTER {
+            self.set_away(true).await;
+        }
+    }
+
+    async fn set_away(&mut self, away: bool) {
+        let request = ClientRequest {
+            command: Command::SetAway { away },
+        };
+        match self.send_request(&request).await {
+            Ok(response) if response.success => self.away = away,
+            Ok(_) => {}
+            Err(err) => self.message = format!("Error: {}", err),
+        }
+    }
+
+    /// Who other than the sender (and us) has read a message
+    pub async fn get_message_receipts(
+        &mut self,
+        chat_id: i32,
+        message_id: i32,
+    ) -> Vec<MessageReceipt> {
+        let request = ClientRequest {
+            command: Command::GetMessageReceipts {
+                chat_id,
+                message_id,
+            },
+        };
+
+        match self.send_request(&request).await {
+            Ok(response) if response.success => {
+                if let Some(data) = response.data {
+                    match serde_json::from_value::<MessageReceipts>(data) {
+                        Ok(receipts) => {
+                            return receipts
+                                .seen_by
+                                .into_iter()
+                                .filter(|r| r.user_id != self.user_id)
+                                .collect();
+                        }
+                        Err(e) => {
+                            self.message = format!("Parse error: {}", e);
+                        }
                     }
                 }
             }
+            Ok(response) => {
+                self.message = response
+                    .message
+                    .unwrap_or("Failed to get read receipts".into());
+            }
             Err(err) => {
                 self.message = format!("Error: {}", err);
             }
         }
+        Vec::new()
     }
 
-    pub async fn get_chat_list(&mut self, page: u64, page_size: u64, page_count: u64) {
+    pub async fn mark_messages_read(&mut self, chat_id: i32) {
         let request = ClientRequest {
-            jwt: Some(self.jwt.clone()),
-            command: Command::GetChats { page, page_size },
+            command: Command::MarkMessagesRead { chat_id },
         };
+
         match self.send_request(&request).await {
             Ok(response) => {
-                if response.success {
-                    if let Some(data) = response.data {
-                        match serde_json::from_value::<ChatList>(data) {
-                            Ok(chats) => {
-                                self.chats = chats.chats;
-                                self.state = FormState::Chats {
-                                    page_count,
-                                    page,
-                                    selected_index: self.selected_index,
-                                };
-                                self.message = "".into();
-                            }
-                            Err(e) => {
-                                self.message = format!("Parse error: {}", e);
-                            }
-                        }
+                if !response.success {
+                    if let Some(message) = response.message {
+                        self.message = message;
                     } else {
-                        self.message = "No chats data returned".into();
+                        self.message = "Couldn't mark messages as read!".into();
                     }
-                } else {
-                    self.message = response.message.unwrap_or("Failed to get chats".into());
                 }
             }
             Err(err) => {
@@ -589,28 +1544,28 @@ impl App {
         }
     }
 
-    pub async fn get_chat_page_count(&mut self, chat_id: i32, page_size: u64) -> Option<u64> {
+    pub async fn get_chat_list(&mut self, before: Option<ChatCursor>) -> Option<ChatList> {
         let request = ClientRequest {
-            jwt: Some(self.jwt.clone()),
-            command: Command::GetChatPages { chat_id, page_size },
+            command: Command::GetChats {
+                before,
+                limit: CHAT_BATCH,
+            },
         };
         match self.send_request(&request).await {
             Ok(response) => {
                 if response.success {
                     if let Some(data) = response.data {
-                        match serde_json::from_value::<Count>(data) {
-                            Ok(count) => return Some(count.count),
+                        match serde_json::from_value::<ChatList>(data) {
+                            Ok(chats) => return Some(chats),
                             Err(e) => {
                                 self.message = format!("Parse error: {}", e);
                             }
                         }
                     } else {
-                        self.message = "No page count returned".into();
+                        self.message = "No chats data returned".into();
                     }
                 } else {
-                    self.message = response
-                        .message
-                        .unwrap_or("Failed to get chat pages".into());
+                    self.message = response.message.unwrap_or("Failed to get chats".into());
                 }
             }
             Err(err) => {
@@ -620,28 +1575,56 @@ impl App {
         None
     }
 
-    pub async fn get_chats_page_count(&mut self, page_size: u64) -> Option<u64> {
+    pub async fn set_group_settings(&mut self, chat_id: i32, chat_name: String) {
+        if let Some(chat_members) = self.get_chat_members(chat_id).await {
+            self.state = FormState::GroupSettings {
+                chat_id,
+                chat_name,
+                is_group: chat_members.is_group,
+                members: chat_members.members,
+                selected_index: 0,
+                mode: GroupSettingsMode::Browse,
+            };
+        }
+    }
+
+    /// Reloads the member list of the open group settings screen
+    pub async fn refresh_group_members(&mut self, chat_id: i32) {
+        let Some(chat_members) = self.get_chat_members(chat_id).await else {
+            return;
+        };
+        if let FormState::GroupSettings {
+            members,
+            selected_index,
+            ..
+        } = &mut self.state
+        {
+            *selected_index = (*selected_index).min(chat_members.members.len().saturating_sub(1));
+            *members = chat_members.members;
+        }
+    }
+
+    pub async fn get_chat_members(&mut self, chat_id: i32) -> Option<ChatMembers> {
         let request = ClientRequest {
-            jwt: Some(self.jwt.clone()),
-            command: Command::GetChatsPages { page_size },
+            command: Command::GetChatMembers { chat_id },
         };
         match self.send_request(&request).await {
             Ok(response) => {
                 if response.success {
                     if let Some(data) = response.data {
-                        match serde_json::from_value::<Count>(data) {
-                            Ok(count) => return Some(count.count),
+                        match serde_json::from_value::<ChatMembers>(data) {
+                            Ok(members) => return Some(members),
                             Err(e) => {
                                 self.message = format!("Parse error: {}", e);
                             }
                         }
                     } else {
-                        self.message = "No page count returned".into();
+                        self.message = "No members returned".into();
                     }
                 } else {
                     self.message = response
                         .message
-                        .unwrap_or("Failed to get chats pages".into());
+                        .unwrap_or("Failed to get chat members".into());
                 }
             }
             Err(err) => {
@@ -651,3 +1634,10 @@ impl App {
         None
     }
 }
+
+// Doubles with every failed attempt, up to RECONNECT_MAX_DELAY
+fn reconnect_delay(attempt: u32) -> Duration {
+    RECONNECT_MIN_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt.min(16)))
+        .min(RECONNECT_MAX_DELAY)
+}
@@ -0,0 +1,160 @@
+use crate::utils::config::{Args, ServerProfile};
+use crate::utils::tls::{client_crypto, PinEvent, PinEvents, TrustMode};
+use quinn::{
+    ClientConfig, Connection, ConnectionError, Endpoint, ReadError, RecvStream, TransportConfig,
+};
+use shared::codec::{
+    self, ClientHello, Codec, CodecError, ServerHello, HANDSHAKE_CODEC, MAX_HANDSHAKE_SIZE,
+};
+use shared::server_response::ServerEvent;
+use std::error::Error;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::lookup_host;
+
+/// Largest response or event we'll read from the server
+pub const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// A connection that has finished the handshake
+pub struct ServerConnection {
+    pub conn: Arc<Connection>,
+    // What every message after the handshake is encoded with
+    pub codec: Codec,
+    // Events pushed by the server
+    pub events: spmc::Receiver<ServerEvent>,
+    // The fingerprint, if the server was seen for the first time and pinned
+    pub newly_pinned: Option<String>,
+}
+
+/// Resolves the profile's host, connects and verifies the server, agrees on
+/// a protocol version and codec, and starts forwarding the server's events
+pub async fn connect(
+    profile: &ServerProfile,
+    args: &Args,
+) -> Result<ServerConnection, Box<dyn Error>> {
+    let address = profile.address();
+    let server_addr: SocketAddr = lookup_host((profile.host.as_str(), profile.port))
+        .await?
+        .next()
+        .ok_or_else(|| format!("Couldn't resolve {}", profile.host))?;
+
+    // QUIC Client
+    let pin_events = PinEvents::default();
+    let rustls_cfg = client_crypto(
+        TrustMode::for_profile(args, profile),
+        &address,
+        pin_events.clone(),
+    )?;
+    let mut client_cfg = ClientConfig::new(Arc::new(rustls_cfg));
+
+    let mut transport_config = TransportConfig::default();
+    transport_config.max_idle_timeout(Some(
+        Duration::from_secs(300) //300 sec = 5 minutes
+            .try_into()
+            .expect("valid idle timeout"),
+    ));
+
+    client_cfg.transport_config(Arc::new(transport_config));
+
+    let bind_addr = if server_addr.is_ipv6() {
+        "[::]:0"
+    } else {
+        "0.0.0.0:0"
+    };
+    let mut endpoint = Endpoint::client(bind_addr.parse()?)?;
+    endpoint.set_default_client_config(client_cfg);
+
+    let connected = endpoint.connect(server_addr, &profile.host)?.await;
+    let pin_event = pin_events.lock().unwrap_or_else(|e| e.into_inner()).take();
+    let new_conn = match connected {
+        Ok(conn) => conn,
+        // A changed certificate gets its own error, for the caller to warn about
+        Err(e) => {
+            return Err(match pin_event {
+                Some(PinEvent::Changed(changed)) => changed.into(),
+                _ => e.into(),
+            })
+        }
+    };
+    let newly_pinned = match pin_event {
+        Some(PinEvent::Pinned(fingerprint)) => Some(fingerprint),
+        _ => None,
+    };
+    let conn = Arc::new(new_conn);
+
+    // --codec limits what we offer, otherwise the server picks from everything we speak
+    let codecs = match args.codec {
+        Some(codec) => vec![codec],
+        None => Codec::ALL.to_vec(),
+    };
+    let codec = match handshake(&conn, ClientHello::new(codecs)).await {
+        Ok(codec) => codec,
+        // A server that turns the handshake down closes the connection, saying why
+        Err(e) => match conn.close_reason() {
+            Some(reason) => return Err(format!("Handshake failed: {}", reason).into()),
+            None => return Err(e.into()),
+        },
+    };
+
+    let conn_clone = conn.clone();
+    let (tx, rx) = spmc::channel::<ServerEvent>();
+
+    tokio::spawn(async move {
+        let recv_stream = conn_clone.accept_uni().await;
+        if let Ok(stream) = recv_stream {
+            check_for_events(stream, codec, tx).await;
+        }
+    });
+
+    Ok(ServerConnection {
+        conn,
+        codec,
+        events: rx,
+        newly_pinned,
+    })
+}
+
+/// Sends our hello on the connection's first stream and reads the server's
+/// choice of protocol version and codec
+async fn handshake(conn: &Connection, hello: ClientHello) -> Result<Codec, CodecError> {
+    let (mut send, mut recv) = conn.open_bi().await.map_err(io::Error::other)?;
+    codec::write_message(&mut send, HANDSHAKE_CODEC, &hello).await?;
+    send.finish().await.map_err(io::Error::other)?;
+
+    match codec::read_message(&mut recv, HANDSHAKE_CODEC, MAX_HANDSHAKE_SIZE).await? {
+        ServerHello::Accepted { codec, .. } => Ok(codec),
+        ServerHello::Rejected { reason } => Err(CodecError::Rejected(reason)),
+    }
+}
+
+/// Reads server events off the unidirectional stream and forwards them to the UI loop
+async fn check_for_events(mut recv: RecvStream, codec: Codec, mut tx: spmc::Sender<ServerEvent>) {
+    loop {
+        match codec::read_frame(&mut recv, MAX_MESSAGE_SIZE).await {
+            Ok(buf) => match codec.decode::<ServerEvent>(&buf) {
+                Ok(event) => {
+                    if let Err(e) = tx.send(event) {
+                        println!("Failed to forward event: {}", e);
+                    }
+                }
+                Err(e) => eprintln!("Invalid server event: {}", e),
+            },
+            Err(CodecError::Io(e)) if is_locally_closed(&e) => break,
+            Err(e) => {
+                eprintln!("recv error: {:?}", e);
+                break;
+            }
+        }
+    }
+}
+
+// Whether we closed the connection ourselves, to switch servers
+fn is_locally_closed(e: &io::Error) -> bool {
+    matches!(
+        e.get_ref()
+            .and_then(|inner| inner.downcast_ref::<ReadError>()),
+        Some(ReadError::ConnectionLost(ConnectionError::LocallyClosed))
+    )
+}
@@ -1,104 +1,49 @@
 mod app;
+mod connection;
 mod event;
+mod outbox;
 mod run;
 mod ui;
 mod utils;
 
 use crate::app::App;
+use clap::Parser;
 use run::run_app;
 
-use quinn::{ClientConfig, Endpoint, RecvStream, TransportConfig};
-use rustls::client::{ClientConfig as RustlsClientConfig, ServerCertVerified, ServerCertVerifier};
-use shared::server_response::Refresh;
 use std::error::Error;
-use std::net::SocketAddr;
-use std::sync::Arc;
-use std::time::{Duration, SystemTime};
-use tokio::time::timeout;
-
-struct TestVerifier;
-impl ServerCertVerifier for TestVerifier {
-    fn verify_server_cert(
-        &self,
-        _: &rustls::Certificate,
-        _: &[rustls::Certificate],
-        _: &rustls::ServerName,
-        _: &mut dyn Iterator<Item = &[u8]>,
-        _: &[u8],
-        _: SystemTime,
-    ) -> Result<ServerCertVerified, rustls::Error> {
-        Ok(ServerCertVerified::assertion())
-    }
-}
+use utils::config::{default_config_path, Args, ClientConfig, ServerProfile};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    let serv_addr = utils::constants::SERVER_ADDR.to_owned();
-    // QUIC Client
-    let rustls_cfg = RustlsClientConfig::builder()
-        .with_safe_defaults()
-        .with_custom_certificate_verifier(Arc::new(TestVerifier))
-        .with_no_client_auth();
-    let mut client_cfg = ClientConfig::new(Arc::new(rustls_cfg));
-
-    let mut transport_config = TransportConfig::default();
-    transport_config.max_idle_timeout(Some(
-        Duration::from_secs(300) //300 sec = 5 minutes
-            .try_into()
-            .expect("valid idle timeout"),
-    ));
-
-    client_cfg.transport_config(Arc::new(transport_config));
-
-    let mut endpoint = Endpoint::client("0.0.0.0:0".parse()?)?;
-    endpoint.set_default_client_config(client_cfg);
-
-    let port = format!("{}:{}", serv_addr, "8080");
-    let server_addr: SocketAddr = port.parse()?;
-    let new_conn = endpoint.connect(server_addr, &*serv_addr)?.await?;
-    let conn = Arc::new(new_conn);
-    let conn_clone = conn.clone();
-    let (tx, rx) = spmc::channel::<u8>();
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+    let config_path = args.config.clone().unwrap_or_else(default_config_path);
+    let config = ClientConfig::load(&config_path)?;
+    let initial = config.initial_profile(args.profile.as_deref())?;
+    let server = args.server.clone();
+
+    if args.insecure {
+        eprintln!("WARNING: --insecure is set, the server's certificate will not be checked");
+    }
 
-    tokio::spawn(async move {
-        let recv_stream = conn_clone.accept_uni().await;
-        if let Ok(stream) = recv_stream {
-            check_for_refresh(stream, tx).await;
+    let mut app = App::new(config, config_path, args);
+    let connected = match (server, initial) {
+        // A server given on the command line is used for this run without being saved
+        (Some(address), _) => {
+            let profile = ServerProfile::from_address(&address)?;
+            app.connect_profile(profile.address(), profile).await
         }
-    });
-
-    let mut app = App::new(conn);
-    run_app(&mut app, rx).await?;
-    Ok(())
-}
-
-async fn check_for_refresh(mut recv: RecvStream, mut tx: spmc::Sender<u8>) {
-    loop {
-        let mut len_buf = [0u8; 4];
-
-        let result = timeout(Duration::from_millis(10), recv.read_exact(&mut len_buf)).await;
-
-        match result {
-            Ok(Ok(_)) => {
-                let len = u32::from_be_bytes(len_buf) as usize;
-                let mut buf = vec![0u8; len];
-                if let Ok(_) = recv.read_exact(&mut buf).await {
-                    let refresh_req: Result<Refresh, _> = serde_json::from_slice(&buf);
-                    if refresh_req.is_ok() {
-                        if let Err(e) = tx.send(0u8) {
-                            println!("Failed to send refresh: {}", e);
-                        }
-                    }
-                }
-            }
-            Ok(Err(e)) => {
-                eprintln!("recv error: {:?}", e);
-                break;
-            }
-            Err(_) => {
-                // timeout -> no data, keep looping or return
-                continue;
-            }
+        (None, Some(name)) => app.connect_to(&name).await,
+        (None, None) => {
+            app.set_server_picker();
+            Ok(())
         }
+    };
+    // The UI hasn't taken over the terminal yet, so say why plainly
+    if let Err(e) = connected {
+        eprintln!("Couldn't connect: {}", e);
+        std::process::exit(1);
     }
+    run_app(&mut app).await?;
+    Ok(())
 }
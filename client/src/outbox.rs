@@ -0,0 +1,97 @@
+use crate::utils::config::data_dir;
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// A message waiting to be sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingMessage {
+    // Sent with every attempt, so the server stores the message only once
+    pub idempotency_key: String,
+    pub chat_id: i32,
+    pub content: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// Messages written while they couldn't be sent, oldest first. Saved to disk
+/// on every change so they survive the client restarting.
+#[derive(Debug)]
+pub struct Outbox {
+    path: PathBuf,
+    messages: Vec<PendingMessage>,
+}
+
+impl Outbox {
+    /// Opens the outbox of a user on a server ("host:port")
+    pub fn open(server: &str, user_id: i32) -> io::Result<Self> {
+        // Keep the file name portable
+        let server: String = server
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let path = data_dir()
+            .join("outbox")
+            .join(format!("{}-{}.json", server, user_id));
+
+        let messages = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(Outbox { path, messages })
+    }
+
+    /// Queues a message behind any already waiting
+    pub fn push(&mut self, chat_id: i32, content: String) -> io::Result<()> {
+        self.messages.push(PendingMessage {
+            idempotency_key: Uuid::new_v4().to_string(),
+            chat_id,
+            content,
+            created_at: Utc::now().naive_utc(),
+        });
+        self.save()
+    }
+
+    /// The message to send next
+    pub fn front(&self) -> Option<&PendingMessage> {
+        self.messages.first()
+    }
+
+    pub fn remove(&mut self, idempotency_key: &str) -> io::Result<()> {
+        self.messages
+            .retain(|m| m.idempotency_key != idempotency_key);
+        self.save()
+    }
+
+    /// The messages waiting to be sent to a chat, oldest first
+    pub fn for_chat(&self, chat_id: i32) -> impl Iterator<Item = &PendingMessage> {
+        self.messages.iter().filter(move |m| m.chat_id == chat_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    // Writes to a temporary file first, so a crash can't leave half an outbox
+    fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&self.messages)?)?;
+        fs::rename(tmp, &self.path)
+    }
+}
@@ -7,12 +7,9 @@ use crossterm::{
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
-use std::io::{self, Stdout};
+use std::io;
 
-pub async fn run_app(
-    app: &mut App,
-    rx: spmc::Receiver<u8>,
-) -> Result<(), Box<dyn std::error::Error>> {
+pub async fn run_app(app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
     let backend = CrosstermBackend::new(io::stdout());
     let mut terminal = Terminal::new(backend)?;
 
@@ -20,54 +17,54 @@ pub async fn run_app(
     execute!(terminal.backend_mut(), EnterAlternateScreen)?;
 
     loop {
-        match rx.try_recv() {
-            Ok(_) => {
-                app.refresh().await;
+        // No events until we've connected to a server
+        let received = app.events.as_ref().map(|rx| rx.try_recv());
+        match received {
+            Some(Ok(event)) => {
+                app.handle_event(event).await;
             }
-            Err(spmc::TryRecvError::Empty) => {
+            Some(Err(spmc::TryRecvError::Empty)) | None => {
                 // Nothing to report, continue
             }
-            Err(spmc::TryRecvError::Disconnected) => {
-                // Something has gone wrong, close the program
-                app.state = FormState::Close;
+            Some(Err(spmc::TryRecvError::Disconnected)) => {
+                // The event stream ended with the connection, so start reconnecting
+                app.connection_lost();
             }
         }
+        if app.connection_closed() {
+            app.connection_lost();
+        }
+        app.try_reconnect().await;
+        app.retry_outbox().await;
+
         // 1) Draw the appropriate UI for the current state
-        terminal.draw(|f| match &app.state {
-            FormState::MainMenu => ui::main_menu::render::<CrosstermBackend<Stdout>>(f, app),
-            FormState::LoginForm { .. } => ui::login::render::<CrosstermBackend<Stdout>>(f, app),
-            FormState::RegisterForm { .. } => {
-                ui::registration::render::<CrosstermBackend<Stdout>>(f, app)
-            }
-            FormState::UserMenu { .. } => ui::user_menu::render::<CrosstermBackend<Stdout>>(f, app),
-            FormState::AddFriend { .. } => {
-                ui::add_friends::render::<CrosstermBackend<Stdout>>(f, app)
-            }
-            FormState::FriendMenu { .. } => {
-                ui::friends_menu::render::<CrosstermBackend<Stdout>>(f, app)
-            }
-            FormState::FriendRequests { .. } => {
-                ui::friend_requests::render::<CrosstermBackend<Stdout>>(f, app)
-            }
-            FormState::ConfirmFriendRequest { .. } => {
-                ui::confirm_friend_request::render::<CrosstermBackend<Stdout>>(f, app)
-            }
-            FormState::FriendList { .. } => {
-                ui::friend_list::render::<CrosstermBackend<Stdout>>(f, app)
-            }
-            FormState::ConfirmUnfriend { .. } => {
-                ui::confirm_unfriend::render::<CrosstermBackend<Stdout>>(f, app)
-            }
-            FormState::Chats { .. } => ui::chats::render::<CrosstermBackend<Stdout>>(f, app),
-            FormState::Chat { .. } => ui::chat::render::<CrosstermBackend<Stdout>>(f, app),
-            FormState::ChatCreation(_) => {
-                ui::create_chat::render::<CrosstermBackend<Stdout>>(f, app)
+        terminal.draw(|f| {
+            match &app.state {
+                FormState::MainMenu => ui::main_menu::render(f, app),
+                FormState::ServerPicker { .. } => ui::main_menu::render_server_picker(f, app),
+                FormState::LoginForm { .. } => ui::login::render(f, app),
+                FormState::RegisterForm { .. } => ui::registration::render(f, app),
+                FormState::UserMenu { .. } => ui::user_menu::render(f, app),
+                FormState::AddFriend { .. } => ui::add_friends::render(f, app),
+                FormState::FriendMenu { .. } => ui::friends_menu::render(f, app),
+                FormState::FriendRequests { .. } => ui::friend_requests::render(f, app),
+                FormState::ConfirmFriendRequest { .. } => {
+                    ui::confirm_friend_request::render(f, app)
+                }
+                FormState::FriendList { .. } => ui::friend_list::render(f, app),
+                FormState::ConfirmUnfriend { .. } => ui::confirm_unfriend::render(f, app),
+                FormState::BlockedUsers { .. } => ui::blocked_users::render(f, app),
+                FormState::Chats { .. } => ui::chats::render(f, app),
+                FormState::Chat { .. } => ui::chat::render(f, app),
+                FormState::ChatCreation(_) => ui::create_chat::render(f, app),
+                FormState::GroupSettings { .. } => ui::group_settings::render(f, app),
+                FormState::ProfileView { .. } => ui::profile::render(f, app),
+                FormState::Exit => {}
             }
-            FormState::ProfileView { .. } => {
-                ui::profile::render::<CrosstermBackend<Stdout>>(f, app)
+            // Drawn over whatever screen is showing, which stays usable underneath
+            if let Some(reconnect) = &app.reconnect {
+                ui::reconnect_banner::render(f, reconnect, app.outbox.as_ref());
             }
-            FormState::Close { .. } => ui::close::render::<CrosstermBackend<Stdout>>(f, app),
-            FormState::Exit => return,
         })?;
 
         if matches!(app.state, FormState::Exit) {
@@ -76,7 +73,12 @@ pub async fn run_app(
         }
 
         // 2) Poll for a key event
-        if let Some(key) = event::poll_event()? {
+        let key = event::poll_event()?;
+        match key {
+            Some(_) => app.record_input().await,
+            None => app.check_idle().await,
+        }
+        if let Some(key) = key {
             match &mut app.state {
                 // Registration form input
                 FormState::RegisterForm { .. } => {
@@ -111,11 +113,19 @@ pub async fn run_app(
                     ui::confirm_unfriend::handle_input(app, key).await;
                 }
 
+                FormState::BlockedUsers { .. } => {
+                    ui::blocked_users::handle_input(app, key).await;
+                }
+
                 // Main menu navigation
                 FormState::MainMenu => {
                     ui::main_menu::handle_input(app, key).await;
                 }
 
+                FormState::ServerPicker { .. } => {
+                    ui::main_menu::handle_server_picker_input(app, key).await;
+                }
+
                 FormState::Chats { .. } => {
                     ui::chats::handle_input(app, key).await;
                 }
@@ -128,6 +138,10 @@ pub async fn run_app(
                     ui::create_chat::handle_input(app, key).await;
                 }
 
+                FormState::GroupSettings { .. } => {
+                    ui::group_settings::handle_input(app, key).await;
+                }
+
                 // User menu navigation (post-login)
                 FormState::UserMenu { .. } => {
                     ui::user_menu::handle_input(app, key).await;
@@ -137,12 +151,6 @@ pub async fn run_app(
                     ui::profile::handle_input(app, key).await;
                 }
 
-                FormState::Close { .. } => {
-                    if ui::close::handle_input(app, key).await {
-                        break;
-                    }
-                }
-
                 // Any other state: do nothing
                 _ => {}
             }
@@ -1,7 +1,6 @@
 use crate::app::{ActiveField, App, FormState};
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
-    backend::Backend,
     layout::{Constraint, Direction, Layout},
     style::{Color, Style},
     text::Text,
@@ -11,7 +10,7 @@ use ratatui::{
 use shared::client_response::{ClientRequest, Command};
 use KeyCode::*;
 
-pub fn render<B: Backend>(f: &mut Frame, app: &App) {
+pub fn render(f: &mut Frame, app: &App) {
     if let FormState::AddFriend { id, active_field } = &app.state {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
@@ -54,8 +53,6 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
         _ => return,
     };
 
-    let app_jwt = app.jwt.clone();
-
     match key.code {
         Backspace => {
             id.pop();
@@ -66,7 +63,6 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
                 return;
             }
             let req = ClientRequest {
-                jwt: Option::from(app_jwt),
                 command: Command::SendFriendRequest {
                     receiver_username: id.clone(),
                 },
@@ -0,0 +1,110 @@
+use crate::app::{App, FormState};
+use crossterm::event::KeyCode::{Down, Enter, Esc, Up};
+use crossterm::event::KeyEvent;
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, List, ListItem},
+    Frame,
+};
+use shared::client_response::{ClientRequest, Command};
+
+pub fn render(f: &mut Frame, app: &App) {
+    let selected = if let FormState::BlockedUsers { selected_index } = app.state {
+        selected_index
+    } else {
+        0
+    };
+
+    let items: Vec<ListItem> = app
+        .blocked_users
+        .users
+        .iter()
+        .enumerate()
+        .map(|(i, user)| {
+            let style = if i == selected {
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            ListItem::new(user.username.clone()).style(style)
+        })
+        .collect();
+
+    let block = if items.is_empty() {
+        Block::default()
+            .borders(Borders::ALL)
+            .title("No blocked users")
+    } else {
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Blocked Users")
+            .title("Select a User to Unblock Them")
+    };
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().bg(Color::DarkGray));
+
+    let area = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(4)
+        .constraints([Constraint::Min(0)])
+        .split(f.area())[0];
+
+    f.render_widget(list, area);
+}
+
+pub async fn handle_input(app: &mut App, key: KeyEvent) {
+    let blocked_num = app.blocked_users.users.len();
+
+    match key.code {
+        Up | Down => {
+            if let FormState::BlockedUsers { selected_index } = &mut app.state {
+                match key.code {
+                    Up if *selected_index > 0 => {
+                        *selected_index -= 1;
+                    }
+                    Down if *selected_index + 1 < blocked_num => {
+                        *selected_index += 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Enter => {
+            let idx = match &app.state {
+                FormState::BlockedUsers { selected_index } if *selected_index < blocked_num => {
+                    *selected_index
+                }
+                _ => return,
+            };
+            let req = ClientRequest {
+                command: Command::UnblockUser {
+                    blocked_id: app.blocked_users.users[idx].id,
+                },
+            };
+            match app.send_request(&req).await {
+                Ok(response) => {
+                    if let Some(message) = response.message {
+                        app.message = message;
+                    }
+                }
+                Err(err) => {
+                    app.message = err.to_string();
+                }
+            }
+            app.set_blocked_users().await;
+        }
+
+        Esc => {
+            app.message.clear();
+            app.set_friend_menu()
+        }
+
+        _ => {}
+    }
+}
@@ -1,76 +1,131 @@
 use crate::app::{App, FormState};
+use crate::outbox::PendingMessage;
+use crate::ui::presence;
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::layout::Position;
 use ratatui::text::{Line, Span};
 use ratatui::{
-    backend::Backend,
     layout::{Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
     text::Text,
     widgets::{Block, Borders, Paragraph},
     Frame,
 };
-use shared::client_response::Command::{GetChatMessages, SendMessage};
 use shared::client_response::{ClientRequest, Command};
-use shared::models::chat_models::{ChatMessages, Count};
-use std::cmp::PartialEq;
+use shared::models::chat_models::ChatMessage;
+use shared::models::user_models::PresenceStatus;
 use unicode_width::UnicodeWidthStr;
 
-const CHATS_PAGE_SIZE: u64 = 10;
+// Own messages sit on the right, everyone else's on the left
+fn message_line<'a>(msg: &'a ChatMessage, sent_at: DateTime<Local>, user_id: i32) -> Line<'a> {
+    let faded = Style::default()
+        .fg(Color::Gray)
+        .add_modifier(Modifier::ITALIC);
+    let time_span = Span::styled(
+        sent_at.format("%H:%M").to_string(),
+        Style::default().fg(Color::DarkGray),
+    );
+
+    if msg.is_system {
+        return Line::from(Span::styled(msg.content.as_str(), faded)).centered();
+    }
 
-enum PageSize {
-    Small = 5,
-    Medium = 10,
-    Large = 20,
-    XLarge = 40,
-}
+    let content_span = if msg.deleted {
+        Span::styled("message deleted", faded)
+    } else {
+        Span::raw(msg.content.as_str())
+    };
+    let edited_span = if msg.edited && !msg.deleted {
+        Span::styled(" (edited)", faded)
+    } else {
+        Span::raw("")
+    };
 
-impl PageSize {
-    fn get_size(&self) -> u64 {
-        match self {
-            PageSize::Small => 5,
-            PageSize::Medium => 10,
-            PageSize::Large => 20,
-            PageSize::XLarge => 40,
-        }
+    if msg.sender_id == user_id {
+        // One tick once the server has it, two once someone has read it
+        let tick_span = if msg.seen {
+            Span::styled("✓✓", Style::default().fg(Color::Green))
+        } else {
+            Span::styled("✓", Style::default().fg(Color::DarkGray))
+        };
+        Line::from(vec![
+            tick_span,
+            Span::raw(" "),
+            time_span,
+            Span::raw("  "),
+            content_span,
+            edited_span,
+            Span::styled(
+                " :You",
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ])
+        .right_aligned()
+    } else {
+        Line::from(vec![
+            Span::styled(
+                format!("{}: ", msg.username),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            content_span,
+            edited_span,
+            Span::raw("  "),
+            time_span,
+        ])
     }
+}
 
-    fn decrease(&mut self) -> Self {
-        match self {
-            PageSize::Small => PageSize::Small,
-            PageSize::Medium => PageSize::Small,
-            PageSize::Large => PageSize::Medium,
-            PageSize::XLarge => PageSize::Large,
-        }
-    }
+// A message still in the outbox, shown where it will land once sent
+fn pending_line(pending: &PendingMessage) -> Line<'_> {
+    let written_at = Local.from_utc_datetime(&pending.created_at);
+    Line::from(vec![
+        Span::styled("…", Style::default().fg(Color::Yellow)),
+        Span::raw(" "),
+        Span::styled(
+            written_at.format("%H:%M").to_string(),
+            Style::default().fg(Color::DarkGray),
+        ),
+        Span::raw("  "),
+        Span::styled(pending.content.as_str(), Style::default().fg(Color::Gray)),
+        Span::styled(
+            " (pending) :You",
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::ITALIC),
+        ),
+    ])
+    .right_aligned()
+}
 
-    fn increase(&mut self) -> Self {
-        match self {
-            PageSize::Small => PageSize::Medium,
-            PageSize::Medium => PageSize::Large,
-            PageSize::Large => PageSize::XLarge,
-            PageSize::XLarge => PageSize::XLarge,
-        }
-    }
+fn separator_line(label: String, color: Color) -> Line<'static> {
+    Line::from(Span::styled(
+        format!("── {} ──", label),
+        Style::default().fg(color),
+    ))
+    .centered()
+}
 
-    fn from_u64(size: u64) -> Self {
-        match size {
-            5 => PageSize::Small,
-            10 => PageSize::Medium,
-            20 => PageSize::Large,
-            40 => PageSize::XLarge,
-            _ => PageSize::Medium,
-        }
+fn day_label(day: NaiveDate) -> String {
+    let today = Local::now().date_naive();
+    if day == today {
+        "Today".to_string()
+    } else if today.pred_opt() == Some(day) {
+        "Yesterday".to_string()
+    } else {
+        day.format("%a %-d %b %Y").to_string()
     }
 }
 
-pub fn render<B: Backend>(f: &mut Frame, app: &mut App) {
+pub fn render(f: &mut Frame, app: &mut App) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(4)
         .constraints([
             Constraint::Min(9),    // Messages list
-            Constraint::Length(1), // Page info
+            Constraint::Length(1), // Scroll info
             Constraint::Length(1), // Spacer
             Constraint::Length(3), // New message input
             Constraint::Length(3), // Message area
@@ -79,55 +134,147 @@ pub fn render<B: Backend>(f: &mut Frame, app: &mut App) {
 
     if let FormState::Chat {
         chat_name,
-        page,
-        page_count,
+        chat_id,
+        has_older,
+        scroll_offset: scrolled,
         messages,
         input_buffer,
+        selected_message,
+        editing_message_id,
+        seen_by,
+        typing,
+        member_ids,
         ..
     } = &mut app.state
     {
-        let lines: Vec<Line> = messages
-            .iter()
-            .map(|msg| {
-                let name_span = if msg.username == app.username {
-                    Span::styled(
-                        format!("{}: ", msg.username),
-                        Style::default()
-                            .fg(Color::Cyan)
-                            .add_modifier(Modifier::BOLD),
-                    )
-                } else {
-                    Span::raw(format!("{}: ", msg.username))
+        // Messages below the scroll position are left out, so the newest
+        // visible message sits at the bottom of the box
+        let visible = messages.len().saturating_sub(*scrolled);
+
+        let mut lines: Vec<Line> = Vec::new();
+        let mut last_day = None;
+        let mut marked_unread = false;
+        for (i, msg) in messages[..visible].iter().enumerate() {
+            let sent_at = Local.from_utc_datetime(&msg.timestamp);
+            let day = sent_at.date_naive();
+            if last_day != Some(day) {
+                lines.push(separator_line(day_label(day), Color::DarkGray));
+                last_day = Some(day);
+            }
+            if !msg.read && !marked_unread {
+                lines.push(separator_line("New messages".to_string(), Color::Red));
+                marked_unread = true;
+            }
+
+            let line = message_line(msg, sent_at, app.user_id);
+            if *selected_message == Some(i) {
+                lines.push(line.style(Style::default().bg(Color::DarkGray)));
+            } else {
+                lines.push(line);
+            }
+        }
+
+        // Receipts go under the newest message, when it's in view
+        if *scrolled == 0 && !seen_by.is_empty() {
+            let names: Vec<&str> = seen_by.iter().map(|r| r.username.as_str()).collect();
+            let seen_line = Line::from(Span::styled(
+                format!("Seen by {}", names.join(", ")),
+                Style::default()
+                    .fg(Color::DarkGray)
+                    .add_modifier(Modifier::ITALIC),
+            ));
+            let seen_line = match messages.last() {
+                Some(last) if last.sender_id == app.user_id => seen_line.right_aligned(),
+                _ => seen_line,
+            };
+            lines.push(seen_line);
+        }
+
+        // Messages waiting in the outbox come after everything sent
+        if *scrolled == 0 {
+            if let Some(outbox) = &app.outbox {
+                let sent = |p: &&PendingMessage| {
+                    messages
+                        .iter()
+                        .any(|m| m.idempotency_key.as_ref() == Some(&p.idempotency_key))
                 };
-                let content_span = Span::raw(&msg.content);
-                Line::from(vec![name_span, content_span])
-            })
-            .collect();
+                for pending in outbox.for_chat(*chat_id).filter(|p| !sent(p)) {
+                    lines.push(pending_line(pending));
+                }
+            }
+        }
+
+        // Estimate how many rows the wrapped lines take to scroll past the overflow
+        let inner_width = chunks[0].width.saturating_sub(2).max(1) as usize;
+        let inner_height = chunks[0].height.saturating_sub(2) as usize;
+        let rows: usize = lines
+            .iter()
+            .map(|line| line.width().div_ceil(inner_width).max(1))
+            .sum();
+        let overflow = rows.saturating_sub(inner_height);
+
+        // Direct chats show the other person's presence, groups how many are online
+        let mut title = vec![Span::raw(format!("{} ", chat_name))];
+        match member_ids.as_slice() {
+            [other] => {
+                if let Some(p) = app.presence.get(other) {
+                    title.push(presence::status_dot(Some(p)));
+                    title.push(Span::styled(
+                        presence::status_label(p),
+                        Style::default().fg(Color::Gray),
+                    ));
+                }
+            }
+            ids => {
+                let online = ids
+                    .iter()
+                    .filter_map(|id| app.presence.get(id))
+                    .filter(|p| p.status != PresenceStatus::Offline)
+                    .count();
+                if online > 0 {
+                    title.push(Span::styled(
+                        format!("· {} online", online),
+                        Style::default().fg(Color::Green),
+                    ));
+                }
+            }
+        }
 
         let chat_paragraph = Paragraph::new(lines)
             .block(
                 Block::default()
-                    .title(chat_name.as_str())
+                    .title(Line::from(title))
                     .borders(Borders::ALL),
             )
-            .wrap(ratatui::widgets::Wrap { trim: true });
+            .wrap(ratatui::widgets::Wrap { trim: true })
+            .scroll((overflow as u16, 0));
 
         f.render_widget(chat_paragraph, chunks[0]);
 
-        let page_number = {
-            if *page_count == 0 {
-                0
-            } else {
-                *page + 1
-            }
+        let scroll_info = if *scrolled > 0 {
+            format!("{} newer messages below", *scrolled)
+        } else if *has_older || overflow > 0 {
+            "Press [Up] to scroll back".to_string()
+        } else {
+            String::new()
         };
-
-        let page_info = Paragraph::new(format!("Page {} of {}", page_number, *page_count)).style(
+        let typing_info = match typing.as_slice() {
+            [] => String::new(),
+            [(_, name)] => format!("{} is typing…", name),
+            [(_, first), (_, second)] => format!("{} and {} are typing…", first, second),
+            _ => "Several people are typing…".to_string(),
+        };
+        let scroll_info = match (typing_info.is_empty(), scroll_info.is_empty()) {
+            (true, _) => scroll_info,
+            (false, true) => typing_info,
+            (false, false) => format!("{} | {}", typing_info, scroll_info),
+        };
+        let scroll_info = Paragraph::new(scroll_info).style(
             Style::default()
                 .fg(Color::Gray)
                 .add_modifier(Modifier::ITALIC),
         );
-        f.render_widget(page_info, chunks[1]);
+        f.render_widget(scroll_info, chunks[1]);
 
         let visible_width = chunks[3].width.saturating_sub(4) as usize;
         let scroll_offset = if visible_width == 0 {
@@ -137,7 +284,15 @@ pub fn render<B: Backend>(f: &mut Frame, app: &mut App) {
         };
 
         let new_chat = Paragraph::new(Text::from(input_buffer.clone()))
-            .block(Block::default().title("New Message").borders(Borders::ALL))
+            .block(
+                Block::default()
+                    .title(if editing_message_id.is_some() {
+                        "Edit Message"
+                    } else {
+                        "New Message"
+                    })
+                    .borders(Borders::ALL),
+            )
             .style(Style::default().fg(Color::White).bg(Color::Black))
             .scroll((0, scroll_offset as u16));
 
@@ -153,10 +308,17 @@ pub fn render<B: Backend>(f: &mut Frame, app: &mut App) {
 
         f.render_widget(new_chat, chunks[3]);
 
+        let help = if selected_message.is_some() {
+            "[e] Edit  [d] Delete  [Esc] Stop selecting"
+        } else if editing_message_id.is_some() {
+            "Press [Enter] to save, [Esc] to cancel the edit"
+        } else {
+            "Press [Shift+Tab] to select messages, [Tab] for chat settings, [Esc] to return to chat list"
+        };
         let combined_message = if app.message.is_empty() {
-            "Press [Esc] to return to chat list".to_string()
+            help.to_string()
         } else {
-            format!("{} | Press [Esc] to return to chat list", app.message)
+            format!("{} | {}", app.message, help)
         };
 
         let message = Paragraph::new(Text::from(combined_message)).style(Style::default());
@@ -170,28 +332,157 @@ pub fn render<B: Backend>(f: &mut Frame, app: &mut App) {
 }
 
 pub async fn handle_input(app: &mut App, key: KeyEvent) {
+    if let FormState::Chat {
+        selected_message: Some(_),
+        ..
+    } = app.state
+    {
+        handle_selection(app, key).await;
+        return;
+    }
+
     match key.code {
         KeyCode::Char(c) => handle_char(app, c).await,
         KeyCode::Backspace => handle_backspace(app).await,
         KeyCode::Enter => handle_enter(app).await,
         KeyCode::Up => handle_up(app).await,
         KeyCode::Down => handle_down(app).await,
-        KeyCode::Left => handle_left(app).await,
-        KeyCode::Right => handle_right(app).await,
+        KeyCode::Tab => {
+            if let FormState::Chat {
+                chat_id, chat_name, ..
+            } = &app.state
+            {
+                let (chat_id, chat_name) = (*chat_id, chat_name.clone());
+                app.message.clear();
+                app.set_group_settings(chat_id, chat_name).await;
+            }
+        }
+        KeyCode::BackTab => {
+            if let FormState::Chat {
+                messages,
+                selected_message,
+                editing_message_id: None,
+                ..
+            } = &mut app.state
+            {
+                *selected_message = messages.len().checked_sub(1);
+            }
+        }
         KeyCode::Esc => {
+            // Escape cancels an edit before it leaves the chat
+            if let FormState::Chat {
+                input_buffer,
+                editing_message_id: editing @ Some(_),
+                ..
+            } = &mut app.state
+            {
+                *editing = None;
+                input_buffer.clear();
+                return;
+            }
+            if let FormState::Chat { chat_id, .. } = app.state {
+                app.signal_typing(chat_id, false);
+            }
             app.message.clear();
-            app.enter_chats_view(0, CHATS_PAGE_SIZE).await;
+            app.enter_chats_view().await;
         }
         _ => {}
     }
 }
 
+// Moves the message cursor and acts on the selected message
+async fn handle_selection(app: &mut App, key: KeyEvent) {
+    let FormState::Chat {
+        chat_id,
+        messages,
+        selected_message: selected_message @ Some(_),
+        input_buffer,
+        editing_message_id,
+        ..
+    } = &mut app.state
+    else {
+        return;
+    };
+    let chat_id = *chat_id;
+    let selected = selected_message.unwrap_or(0);
+    let Some(message) = messages.get(selected).cloned() else {
+        *selected_message = None;
+        return;
+    };
+
+    match key.code {
+        KeyCode::Up if selected > 0 => *selected_message = Some(selected - 1),
+        KeyCode::Up => {
+            // The cursor is on the oldest loaded message, so fetch older ones
+            let added = app.load_older_messages().await;
+            if let FormState::Chat {
+                selected_message: Some(selected),
+                ..
+            } = &mut app.state
+            {
+                if added > 0 {
+                    *selected -= 1;
+                }
+            }
+        }
+        KeyCode::Down if selected + 1 < messages.len() => *selected_message = Some(selected + 1),
+        KeyCode::Esc | KeyCode::BackTab => *selected_message = None,
+        KeyCode::Char('e') => {
+            if message.sender_id != app.user_id || message.is_system || message.deleted {
+                app.message = "You can only edit your own messages".into();
+                return;
+            }
+            *input_buffer = message.content;
+            *editing_message_id = Some(message.id);
+            *selected_message = None;
+        }
+        KeyCode::Char('d') => {
+            let request = ClientRequest {
+                command: Command::DeleteMessage {
+                    chat_id,
+                    message_id: message.id,
+                },
+            };
+            match app.send_request(&request).await {
+                Ok(response) if response.success => {
+                    app.message.clear();
+                    if let Some(data) = response.data {
+                        match serde_json::from_value::<ChatMessage>(data) {
+                            Ok(message) => app.update_message(chat_id, message),
+                            Err(e) => app.message = format!("Parse error: {}", e),
+                        }
+                    }
+                }
+                Ok(response) => {
+                    app.message = response
+                        .message
+                        .unwrap_or("Failed to delete message".into());
+                }
+                Err(err) => app.message = format!("Error: {}", err),
+            }
+        }
+        _ => {}
+    }
+
+    // Scroll so the selected message is the newest one in view
+    if let FormState::Chat {
+        messages,
+        selected_message: Some(selected),
+        scroll_offset,
+        ..
+    } = &mut app.state
+    {
+        *scroll_offset = messages.len().saturating_sub(*selected + 1);
+    }
+}
+
 pub async fn handle_char(app: &mut App, c: char) {
     let input_buffer = match &mut app.state {
         FormState::Chat { input_buffer, .. } => input_buffer,
         _ => return,
     };
     input_buffer.push(c);
+    signal_typing(app);
 }
 
 pub async fn handle_backspace(app: &mut App) {
@@ -200,25 +491,59 @@ pub async fn handle_backspace(app: &mut App) {
         _ => return,
     };
     input_buffer.pop();
+    signal_typing(app);
+}
+
+// Composing a new message shows as typing until the input is cleared. Edits don't count.
+fn signal_typing(app: &mut App) {
+    if let FormState::Chat {
+        chat_id,
+        input_buffer,
+        editing_message_id: None,
+        ..
+    } = &app.state
+    {
+        let (chat_id, typing) = (*chat_id, !input_buffer.is_empty());
+        app.signal_typing(chat_id, typing);
+    }
 }
 
 pub async fn handle_enter(app: &mut App) {
-    let (input_buffer, chat_id) = match &mut app.state {
+    let (input_buffer, chat_id, editing_message_id) = match &mut app.state {
         FormState::Chat {
             input_buffer,
             chat_id,
+            editing_message_id,
             ..
-        } => (input_buffer, chat_id),
+        } => (input_buffer, chat_id, editing_message_id),
         _ => return,
     };
     if input_buffer.trim().is_empty() {
         return;
     }
+    if let Some(message_id) = *editing_message_id {
+        let (chat_id, content) = (*chat_id, input_buffer.clone());
+        save_edit(app, chat_id, message_id, content).await;
+        return;
+    }
+    let (chat_id, content) = (*chat_id, std::mem::take(input_buffer));
+    if let FormState::Chat { scroll_offset, .. } = &mut app.state {
+        // Jump back to the most recent messages, where the new one goes
+        *scroll_offset = 0;
+    }
+    app.message.clear();
+    app.signal_typing(chat_id, false);
+
+    // It shows as pending until the server has it
+    app.queue_message(chat_id, content).await;
+}
+
+async fn save_edit(app: &mut App, chat_id: i32, message_id: i32, content: String) {
     let request = ClientRequest {
-        jwt: Some(app.jwt.clone()),
-        command: SendMessage {
-            chat_id: *chat_id,
-            content: input_buffer.clone(),
+        command: Command::EditMessage {
+            chat_id,
+            message_id,
+            content,
         },
     };
     let response = match app.send_request(&request).await {
@@ -228,189 +553,62 @@ pub async fn handle_enter(app: &mut App) {
             return;
         }
     };
-    if response.success {
-        let (input_buffer, chat_id, page_size) = match &mut app.state {
-            FormState::Chat {
-                input_buffer,
-                chat_id,
-                page_size,
-                ..
-            } => (input_buffer, chat_id, page_size),
-            _ => return,
-        };
+    if !response.success {
+        app.message = response.message.unwrap_or("Failed to edit message".into());
+        return;
+    }
+
+    if let FormState::Chat {
+        input_buffer,
+        editing_message_id,
+        ..
+    } = &mut app.state
+    {
         input_buffer.clear();
-        app.message.clear();
-        let chat_id = *chat_id;
-        let page_size = *page_size;
-        get_messages(app, chat_id, 0, page_size).await;
-    } else {
-        app.message = response.message.unwrap_or("Failed to send message".into());
+        *editing_message_id = None;
+    }
+    app.message.clear();
+    if let Some(data) = response.data {
+        match serde_json::from_value::<ChatMessage>(data) {
+            Ok(message) => app.update_message(chat_id, message),
+            Err(e) => app.message = format!("Parse error: {}", e),
+        }
     }
 }
 
 pub async fn handle_up(app: &mut App) {
-    let (page, page_count, chat_id, page_size) = match &mut app.state {
+    let (scroll_offset, message_count, has_older) = match &app.state {
         FormState::Chat {
-            page,
-            page_count,
-            chat_id,
-            page_size,
+            scroll_offset,
+            messages,
+            has_older,
             ..
-        } => (page, page_count, chat_id, page_size),
+        } => (*scroll_offset, messages.len(), *has_older),
         _ => return,
     };
-    if *page_count == 0 || *page >= *page_count - 1 {
+
+    // Reaching the oldest loaded message pulls in the batch before it
+    if scroll_offset + 1 >= message_count && (!has_older || app.load_older_messages().await == 0) {
         app.message = "No more messages in chat!".to_string();
         return;
     }
-    let chat_id = *chat_id;
-    let next_page = *page + 1;
-    let page_size = *page_size;
 
-    get_messages(app, chat_id, next_page, page_size).await;
+    if let FormState::Chat { scroll_offset, .. } = &mut app.state {
+        *scroll_offset += 1;
+    }
 }
 
 pub async fn handle_down(app: &mut App) {
-    let (page, chat_id, page_size) = match &mut app.state {
-        FormState::Chat {
-            page,
-            chat_id,
-            page_size,
-            ..
-        } => (page, chat_id, page_size),
+    let scroll_offset = match &mut app.state {
+        FormState::Chat { scroll_offset, .. } => scroll_offset,
         _ => return,
     };
-    if *page <= 0 {
+    if *scroll_offset == 0 {
         app.message = "Already at most recent messages!".to_string();
         return;
     }
-    let chat_id = *chat_id;
-    let next_page = *page - 1;
-    let page_size = *page_size;
-
-    get_messages(app, chat_id, next_page, page_size).await;
-}
-
-pub async fn handle_left(app: &mut App) {
-    let (page, page_count, chat_id, page_size) = match &mut app.state {
-        FormState::Chat {
-            page,
-            page_count,
-            chat_id,
-            page_size,
-            ..
-        } => (page, page_count, chat_id, page_size),
-        _ => return,
-    };
-
-    if *page_size == PageSize::Small.get_size() {
-        return;
-    }
-
-    *page_size = PageSize::from_u64(*page_size).decrease().get_size();
-
-    let chat_id = *chat_id;
-    let next_page = *page;
-    let page_size = *page_size;
-
-    get_messages(app, chat_id, next_page, page_size).await;
-}
-
-pub async fn handle_right(app: &mut App) {
-    let (page, chat_id, page_size) = match &mut app.state {
-        FormState::Chat {
-            page,
-            chat_id,
-            page_size,
-            ..
-        } => (page, chat_id, page_size),
-        _ => return,
-    };
-
-    if *page_size == PageSize::XLarge.get_size() {
-        return;
-    }
-
-    *page_size = PageSize::from_u64(*page_size).increase().get_size();
-
-    let chat_id = *chat_id;
-    let next_page = *page;
-    let page_size = *page_size;
-
-    get_messages(app, chat_id, next_page, page_size).await;
-}
-
-pub async fn get_messages(app: &mut App, chat_id: i32, mut new_page: u64, page_size: u64) {
-    let request = ClientRequest {
-        jwt: Some(app.jwt.clone()),
-        command: Command::GetChatPages { chat_id, page_size },
-    };
-
-    match app.send_request(&request).await {
-        Ok(response) => {
-            if response.success {
-                if let Some(data) = response.data {
-                    match serde_json::from_value::<Count>(data) {
-                        Ok(count) => {
-                            let page_count = match &mut app.state {
-                                FormState::Chat { page_count, .. } => page_count,
-                                _ => return,
-                            };
-                            *page_count = count.count;
-                            if new_page >= count.count && new_page != 0 {
-                                new_page = count.count - 1;
-                            }
-                        }
-                        Err(e) => {
-                            app.message = format!("Parse error: {}", e);
-                        }
-                    }
-                } else {
-                    app.message = "No page count returned".into();
-                }
-            } else {
-                app.message = response.message.unwrap_or("Failed to get chat".into());
-            }
-        }
-        Err(err) => {
-            app.message = format!("Error: {}", err);
-        }
-    }
-    
-    let request = ClientRequest {
-        jwt: Some(app.jwt.clone()),
-        command: GetChatMessages {
-            chat_id,
-            page: new_page,
-            page_size,
-        },
-    };
-    let response = match app.send_request(&request).await {
-        Ok(response) => response,
-        Err(err) => {
-            app.message = format!("Error: {}", err);
-            return;
-        }
-    };
-    if response.success {
-        let (messages, page) = match &mut app.state {
-            FormState::Chat { messages, page, .. } => (messages, page),
-            _ => return,
-        };
-        if let Some(data) = response.data {
-            match serde_json::from_value::<ChatMessages>(data) {
-                Ok(new_messages) => {
-                    *messages = new_messages.messages;
-                    *page = new_page;
-                }
-                Err(e) => {
-                    app.message = format!("Parse error: {}", e);
-                }
-            }
-        } else {
-            app.message = "No chat data returned".into();
-        }
-    } else {
-        app.message = response.message.unwrap_or("Failed to send message".into());
+    *scroll_offset -= 1;
+    if *scroll_offset == 0 {
+        app.message.clear();
     }
 }
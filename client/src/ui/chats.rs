@@ -2,7 +2,6 @@ use crate::app::{App, FormState};
 use crate::ui::create_chat::ChatCreationPhase;
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
-    backend::Backend,
     layout::{Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
     text::Text,
@@ -10,15 +9,13 @@ use ratatui::{
     Frame,
 };
 
-const PAGE_SIZE: u64 = 10;
-
-pub fn render<B: Backend>(f: &mut Frame, app: &App) {
+pub fn render(f: &mut Frame, app: &App) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(4)
         .constraints([
             Constraint::Min(5),    // Chat list
-            Constraint::Length(1), // Page info
+            Constraint::Length(1), // More chats hint
             Constraint::Length(3), // Add Chat
             Constraint::Length(3), // Message
         ])
@@ -26,8 +23,7 @@ pub fn render<B: Backend>(f: &mut Frame, app: &App) {
 
     if let FormState::Chats {
         selected_index,
-        page,
-        page_count,
+        next_cursor,
     } = &app.state
     {
         let items: Vec<ListItem> = app
@@ -43,11 +39,14 @@ pub fn render<B: Backend>(f: &mut Frame, app: &App) {
                     Style::default()
                 };
 
-                let display_name = if chat.unread_count > 0 {
+                let mut display_name = if chat.unread_count > 0 {
                     format!("{} ({})", chat.chat_name, chat.unread_count)
                 } else {
                     chat.chat_name.clone()
                 };
+                if chat.read_only {
+                    display_name.push_str(" [read-only]");
+                }
 
                 ListItem::new(display_name).style(style)
             })
@@ -59,20 +58,17 @@ pub fn render<B: Backend>(f: &mut Frame, app: &App) {
 
         f.render_widget(list, chunks[0]);
 
-        let page_number = {
-            if *page_count == 0 {
-                0
-            } else {
-                *page + 1
-            }
+        let more_info = if next_cursor.is_some() {
+            "Scroll down for older chats"
+        } else {
+            ""
         };
-
-        let page_info = Paragraph::new(format!("Page {} of {}", page_number, *page_count)).style(
+        let more_info = Paragraph::new(more_info).style(
             Style::default()
                 .fg(Color::Gray)
                 .add_modifier(Modifier::ITALIC),
         );
-        f.render_widget(page_info, chunks[1]);
+        f.render_widget(more_info, chunks[1]);
 
         let add_chat = Paragraph::new(Text::from("[Tab] Add New Chat"))
             .block(Block::default().title("New Chat").borders(Borders::ALL));
@@ -88,7 +84,7 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
         match key.code {
             KeyCode::Enter | KeyCode::Char('\r') => {
                 if let Some(chat) = app.chats.get(*selected_index) {
-                    app.enter_chat_view(chat.id, chat.chat_name.clone(), 0, PAGE_SIZE, None)
+                    app.enter_chat_view(chat.id, chat.chat_name.clone(), None)
                         .await;
                 }
             }
@@ -100,46 +96,22 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
                     friends,
                 });
             }
-            KeyCode::Right => {
-                let (page, page_count) = match &mut app.state {
-                    FormState::Chats {
-                        page, page_count, ..
-                    } => (page, page_count),
-                    _ => return,
-                };
-                if *page_count == 0 || *page >= *page_count - 1 {
-                    app.message = "No more chats to view!".to_string();
-                    return;
-                }
-                let next_page = *page + 1;
-
-                app.enter_chats_view(next_page, PAGE_SIZE).await;
-            }
-            KeyCode::Left => {
-                let page = match &mut app.state {
-                    FormState::Chats { page, .. } => page,
-                    _ => return,
-                };
-                if *page <= 0 {
-                    app.message = "Already at most recent chats!".to_string();
-                    return;
-                }
-                let next_page = *page - 1;
-
-                app.enter_chats_view(next_page, PAGE_SIZE).await;
-            }
-            KeyCode::Up => {
-                if *selected_index > 0 {
-                    *selected_index -= 1;
-                }
+            KeyCode::Up if *selected_index > 0 => {
+                *selected_index -= 1;
             }
             KeyCode::Down => {
-                if app.chats.len() <= 0 {
+                if app.chats.is_empty() {
                     return;
                 }
 
-                if *selected_index < app.chats.len() - 1 {
-                    *selected_index += 1;
+                // Fetch older chats on reaching the bottom of the list
+                if *selected_index + 1 >= app.chats.len() {
+                    app.load_more_chats().await;
+                }
+                if let FormState::Chats { selected_index, .. } = &mut app.state {
+                    if *selected_index + 1 < app.chats.len() {
+                        *selected_index += 1;
+                    }
                 }
             }
             KeyCode::Esc => {
@@ -2,7 +2,6 @@ use crate::app::{App, FormState};
 use crossterm::event::KeyCode::{Down, Enter, Esc, Up};
 use crossterm::event::KeyEvent;
 use ratatui::{
-    backend::Backend,
     layout::{Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
     widgets::{Block, Borders, List, ListItem},
@@ -10,7 +9,7 @@ use ratatui::{
 };
 use shared::client_response::{ClientRequest, Command};
 
-pub fn render<B: Backend>(f: &mut Frame, app: &mut App) {
+pub fn render(f: &mut Frame, app: &mut App) {
     let options = ["Accept", "Decline"];
     let area = Layout::default()
         .direction(Direction::Vertical)
@@ -74,17 +73,14 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
             let fr_req = &app.friend_requests.incoming[req_idx];
             let cmd = if opt == 0 {
                 Command::AcceptFriendRequest {
-                    sender_id: fr_req.id.clone(),
+                    sender_id: fr_req.id,
                 }
             } else {
                 Command::DeclineFriendRequest {
-                    sender_id: fr_req.id.clone(),
+                    sender_id: fr_req.id,
                 }
             };
-            let req = ClientRequest {
-                jwt: Option::from(app.jwt.clone()),
-                command: cmd,
-            };
+            let req = ClientRequest { command: cmd };
             match app.send_request(&req).await {
                 Ok(response) => {
                     if response.success {
@@ -2,7 +2,6 @@ use crate::app::{App, FormState};
 use crossterm::event::KeyCode::{Down, Enter, Esc, Up};
 use crossterm::event::KeyEvent;
 use ratatui::{
-    backend::Backend,
     layout::{Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
     widgets::{Block, Borders, List, ListItem},
@@ -10,8 +9,8 @@ use ratatui::{
 };
 use shared::client_response::{ClientRequest, Command};
 
-pub fn render<B: Backend>(f: &mut Frame, app: &mut App) {
-    let options = ["Unfriend", "Go Back"];
+pub fn render(f: &mut Frame, app: &mut App) {
+    let options = ["Unfriend", "Block", "Go Back"];
     let area = Layout::default()
         .direction(Direction::Vertical)
         .margin(4)
@@ -53,12 +52,20 @@ pub fn render<B: Backend>(f: &mut Frame, app: &mut App) {
 
 pub async fn handle_input(app: &mut App, key: KeyEvent) {
     match key.code {
-        Up | Down => {
+        Up => {
             if let FormState::ConfirmUnfriend {
                 selected_option, ..
             } = &mut app.state
             {
-                *selected_option = (*selected_option + 1) % 2;
+                *selected_option = (*selected_option + 2) % 3;
+            }
+        }
+        Down => {
+            if let FormState::ConfirmUnfriend {
+                selected_option, ..
+            } = &mut app.state
+            {
+                *selected_option = (*selected_option + 1) % 3;
             }
         }
         Enter => {
@@ -72,21 +79,25 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
                 return;
             };
             let fr_req = &app.friend_list.users[req_idx];
-            if opt == 0 {
-                let cmd = {
-                    Command::RemoveFriend {
-                        friend_id: fr_req.id,
-                    }
-                };
-                let req = ClientRequest {
-                    jwt: Option::from(app.jwt.clone()),
-                    command: cmd,
-                };
+            let cmd = match opt {
+                0 => Some(Command::RemoveFriend {
+                    friend_id: fr_req.id,
+                }),
+                1 => Some(Command::BlockUser {
+                    blocked_id: fr_req.id,
+                }),
+                _ => None,
+            };
+            if let Some(cmd) = cmd {
+                let req = ClientRequest { command: cmd };
                 match app.send_request(&req).await {
                     Ok(response) => {
                         if response.success {
                             app.friend_list.users.remove(req_idx);
                         }
+                        if let Some(message) = response.message {
+                            app.message = message;
+                        }
                     }
                     Err(err) => {
                         app.message = err.to_string();
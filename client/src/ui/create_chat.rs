@@ -1,7 +1,6 @@
 use crate::app::{App, FormState};
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
-    backend::Backend,
     layout::{Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
     text::Text,
@@ -11,8 +10,6 @@ use ratatui::{
 use shared::models::user_models::User;
 use std::mem;
 
-const PAGE_SIZE: u64 = 10;
-
 // Additional FormState variant to support the chat creation flow
 #[derive(Debug, Clone)]
 pub enum ChatCreationPhase {
@@ -28,7 +25,7 @@ pub enum ChatCreationPhase {
 }
 
 // Render function for the chat creation UI
-pub fn render<B: Backend>(f: &mut Frame, app: &mut App) {
+pub fn render(f: &mut Frame, app: &mut App) {
     let size = f.area();
     let layout = Layout::default()
         .direction(Direction::Vertical)
@@ -134,24 +131,18 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
             chosen,
             friends,
         } => match key.code {
-            KeyCode::Down => {
-                if *selected + 1 < friends.len() {
-                    *selected += 1;
-                }
+            KeyCode::Down if *selected + 1 < friends.len() => {
+                *selected += 1;
             }
-            KeyCode::Up => {
-                if *selected > 0 {
-                    *selected -= 1;
-                }
+            KeyCode::Up if *selected > 0 => {
+                *selected -= 1;
             }
-            KeyCode::Char(' ') => {
-                if friends.len() > 0 {
-                    let friend = &friends[*selected];
-                    if chosen.contains(friend) {
-                        chosen.retain(|f| f != friend);
-                    } else {
-                        chosen.push(friend.clone());
-                    }
+            KeyCode::Char(' ') if !friends.is_empty() => {
+                let friend = &friends[*selected];
+                if chosen.contains(friend) {
+                    chosen.retain(|f| f != friend);
+                } else {
+                    chosen.push(friend.clone());
                 }
             }
             KeyCode::Enter => {
@@ -167,12 +158,12 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
                         username: app.username.clone(),
                     });
                     app.create_chat(chosen.clone(), None).await;
-                    app.enter_chats_view(0, PAGE_SIZE).await;
+                    app.enter_chats_view().await;
                     return;
                 }
             }
             KeyCode::Esc => {
-                app.enter_chats_view(0, PAGE_SIZE).await;
+                app.enter_chats_view().await;
                 return;
             }
             _ => {}
@@ -194,7 +185,7 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
                         username: app.username.clone(),
                     });
                     app.create_chat(chosen.clone(), Some(name)).await;
-                    app.enter_chats_view(0, PAGE_SIZE).await;
+                    app.enter_chats_view().await;
                     return;
                 } else {
                     app.message = "Group name cannot be empty.".into();
@@ -202,7 +193,7 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
             }
             KeyCode::Esc => {
                 app.message.clear();
-                app.enter_chats_view(0, PAGE_SIZE).await;
+                app.enter_chats_view().await;
                 return;
             }
             _ => {}
@@ -1,15 +1,16 @@
 use crate::app::{App, FormState};
+use crate::ui::presence;
 use crossterm::event::KeyCode::{Down, Enter, Esc, Up};
 use crossterm::event::KeyEvent;
 use ratatui::{
-    backend::Backend,
     layout::{Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
+    text::{Line, Span},
     widgets::{Block, Borders, List, ListItem},
     Frame,
 };
 
-pub fn render<B: Backend>(f: &mut Frame, app: &mut App) {
+pub fn render(f: &mut Frame, app: &mut App) {
     Layout::default()
         .direction(Direction::Vertical)
         .margin(4)
@@ -34,7 +35,6 @@ pub fn render<B: Backend>(f: &mut Frame, app: &mut App) {
         .iter()
         .enumerate()
         .map(|(i, opt)| {
-            let display = opt.username.clone();
             let style = if i == selected {
                 Style::default()
                     .fg(Color::Yellow)
@@ -42,7 +42,18 @@ pub fn render<B: Backend>(f: &mut Frame, app: &mut App) {
             } else {
                 Style::default()
             };
-            ListItem::new(display).style(style)
+            let friend_presence = app.presence.get(&opt.id);
+            let mut spans = vec![
+                presence::status_dot(friend_presence),
+                Span::styled(opt.username.clone(), style),
+            ];
+            if let Some(p) = friend_presence {
+                spans.push(Span::styled(
+                    format!("  {}", presence::status_label(p)),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            ListItem::new(Line::from(spans))
         })
         .collect();
 
@@ -64,7 +75,7 @@ pub fn render<B: Backend>(f: &mut Frame, app: &mut App) {
                     Block::default()
                         .borders(Borders::ALL)
                         .title("Friend List")
-                        .title("Select Friend to Unfriend Them"),
+                        .title("Select Friend to Unfriend or Block Them"),
                 )
                 .highlight_style(Style::default().bg(Color::DarkGray))
         }
@@ -84,15 +95,11 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
         Up | Down => {
             if let FormState::FriendList { selected_index } = &mut app.state {
                 match key.code {
-                    Up => {
-                        if *selected_index > 0 {
-                            *selected_index -= 1;
-                        }
+                    Up if *selected_index > 0 => {
+                        *selected_index -= 1;
                     }
-                    Down => {
-                        if *selected_index + 1 < app.friend_list_num {
-                            *selected_index += 1;
-                        }
+                    Down if *selected_index + 1 < app.friend_list_num => {
+                        *selected_index += 1;
                     }
                     _ => {}
                 }
@@ -100,7 +107,7 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
         }
 
         Enter => {
-            if app.friend_list_num <= 0 {
+            if app.friend_list_num == 0 {
                 return;
             }
             let idx = if let FormState::FriendList { selected_index } = &app.state {
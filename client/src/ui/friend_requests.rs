@@ -1,14 +1,14 @@
 use crate::app::{App, FormState};
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
-    backend::Backend,
     layout::{Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
     widgets::{Block, Borders, List, ListItem},
     Frame,
 };
+use shared::client_response::{ClientRequest, Command};
 
-pub fn render<B: Backend>(f: &mut Frame, app: &mut App) {
+pub fn render(f: &mut Frame, app: &mut App) {
     Layout::default()
         .direction(Direction::Vertical)
         .margin(4)
@@ -28,12 +28,19 @@ pub fn render<B: Backend>(f: &mut Frame, app: &mut App) {
         0
     };
 
+    // Incoming requests are listed first, followed by the ones this user sent
     let items: Vec<ListItem> = fr_list
         .incoming
         .iter()
+        .map(|u| u.username.clone())
+        .chain(
+            fr_list
+                .outgoing
+                .iter()
+                .map(|u| format!("{} (sent)", u.username)),
+        )
         .enumerate()
-        .map(|(i, opt)| {
-            let display = opt.username.clone();
+        .map(|(i, display)| {
             let style = if i == selected {
                 Style::default()
                     .fg(Color::Yellow)
@@ -52,7 +59,7 @@ pub fn render<B: Backend>(f: &mut Frame, app: &mut App) {
             Block::default()
                 .borders(Borders::ALL)
                 .title("Friend Requests")
-                .title("Select a request to accept/deny"),
+                .title("Select a request to accept/deny, or a sent one to cancel it"),
         )
         .highlight_style(Style::default().bg(Color::DarkGray));
 
@@ -72,15 +79,11 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
         Up | Down => {
             if let FormState::FriendRequests { selected_index } = &mut app.state {
                 match key.code {
-                    Up => {
-                        if *selected_index > 0 {
-                            *selected_index -= 1;
-                        }
+                    Up if *selected_index > 0 => {
+                        *selected_index -= 1;
                     }
-                    Down => {
-                        if *selected_index + 1 < app.friend_request_num {
-                            *selected_index += 1;
-                        }
+                    Down if *selected_index + 1 < app.friend_request_num => {
+                        *selected_index += 1;
                     }
                     _ => {}
                 }
@@ -88,7 +91,7 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
         }
 
         Enter => {
-            if app.friend_request_num <= 0 {
+            if app.friend_request_num == 0 {
                 return;
             }
             let idx = if let FormState::FriendRequests { selected_index } = &app.state {
@@ -96,7 +99,28 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
             } else {
                 return;
             };
-            app.set_confirm_friend_request(idx);
+            if idx < app.friend_requests.incoming.len() {
+                app.set_confirm_friend_request(idx);
+                return;
+            }
+
+            // Cancel the selected outgoing request
+            let receiver_id =
+                app.friend_requests.outgoing[idx - app.friend_requests.incoming.len()].id;
+            let req = ClientRequest {
+                command: Command::CancelFriendRequest { receiver_id },
+            };
+            match app.send_request(&req).await {
+                Ok(response) => {
+                    if let Some(message) = response.message {
+                        app.message = message;
+                    }
+                }
+                Err(err) => {
+                    app.message = err.to_string();
+                }
+            }
+            app.set_friend_requests().await;
         }
 
         Esc => {
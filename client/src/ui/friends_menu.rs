@@ -1,15 +1,14 @@
 use crate::app::{App, FormState};
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
-    backend::Backend,
     layout::{Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
     widgets::{Block, Borders, List, ListItem},
     Frame,
 };
 
-pub fn render<B: Backend>(f: &mut Frame, app: &App) {
-    let options = ["Friend Requests", "Current Friends"];
+pub fn render(f: &mut Frame, app: &App) {
+    let options = ["Friend Requests", "Current Friends", "Blocked Users"];
     Layout::default()
         .direction(Direction::Vertical)
         .margin(4)
@@ -60,15 +59,11 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
 
     if let FormState::FriendMenu { selected_index } = &mut app.state {
         match key.code {
-            Up => {
-                if *selected_index > 0 {
-                    *selected_index -= 1;
-                }
+            Up if *selected_index > 0 => {
+                *selected_index -= 1;
             }
-            Down => {
-                if *selected_index < 2 {
-                    *selected_index += 1;
-                }
+            Down if *selected_index < 2 => {
+                *selected_index += 1;
             }
             Enter | Char('\r') => match *selected_index {
                 0 => {
@@ -77,6 +72,9 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
                 1 => {
                     app.set_friend_list().await;
                 }
+                2 => {
+                    app.set_blocked_users().await;
+                }
                 _ => {}
             },
             Esc => {
@@ -0,0 +1,313 @@
+use crate::app::{App, FormState};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::Text,
+    widgets::{Block, Borders, List, ListItem, Paragraph},
+    Frame,
+};
+use shared::client_response::{ClientRequest, Command};
+use shared::models::chat_models::ChatRole;
+use shared::models::user_models::User;
+use std::mem;
+
+// What the settings screen is currently doing on top of the member list
+#[derive(Debug, Clone)]
+pub enum GroupSettingsMode {
+    Browse,
+    Renaming {
+        name_input: String,
+    },
+    Adding {
+        selected: usize,
+        chosen: Vec<User>,
+        friends: Vec<User>,
+    },
+}
+
+pub fn render(f: &mut Frame, app: &App) {
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(2)
+        .constraints([
+            Constraint::Min(5),
+            Constraint::Length(3),
+            Constraint::Length(1),
+        ])
+        .split(f.area());
+
+    let FormState::GroupSettings {
+        chat_name,
+        is_group,
+        members,
+        selected_index,
+        mode,
+        ..
+    } = &app.state
+    else {
+        return;
+    };
+
+    match mode {
+        GroupSettingsMode::Browse | GroupSettingsMode::Renaming { .. } => {
+            let items: Vec<ListItem> = members
+                .iter()
+                .enumerate()
+                .map(|(i, member)| {
+                    let role = match member.role {
+                        ChatRole::Owner => " (owner)",
+                        ChatRole::Admin => " (admin)",
+                        ChatRole::Member => "",
+                    };
+                    let style = if i == *selected_index {
+                        Style::default()
+                            .fg(Color::Yellow)
+                            .add_modifier(Modifier::BOLD)
+                    } else {
+                        Style::default()
+                    };
+                    ListItem::new(format!("{}{}", member.username, role)).style(style)
+                })
+                .collect();
+
+            let list = List::new(items).block(
+                Block::default()
+                    .title(format!("{} - Members", chat_name))
+                    .borders(Borders::ALL),
+            );
+            f.render_widget(list, layout[0]);
+        }
+        GroupSettingsMode::Adding {
+            selected,
+            chosen,
+            friends,
+        } => {
+            let items: Vec<ListItem> = friends
+                .iter()
+                .enumerate()
+                .map(|(i, friend)| {
+                    let mut style = if chosen.contains(friend) {
+                        Style::default()
+                            .fg(Color::Green)
+                            .add_modifier(Modifier::BOLD)
+                    } else {
+                        Style::default()
+                    };
+
+                    if i == *selected {
+                        style = style.fg(Color::Yellow).add_modifier(Modifier::ITALIC);
+                    }
+
+                    ListItem::new(friend.username.clone()).style(style)
+                })
+                .collect();
+
+            let list = List::new(items).block(
+                Block::default()
+                    .title("Add Friends (Space to toggle, Enter to add)")
+                    .borders(Borders::ALL),
+            );
+            f.render_widget(list, layout[0]);
+        }
+    }
+
+    let footer = match mode {
+        GroupSettingsMode::Renaming { name_input } => Paragraph::new(name_input.clone())
+            .block(Block::default().title("New Name").borders(Borders::ALL))
+            .style(Style::default().fg(Color::White)),
+        _ if !is_group => Paragraph::new(Text::from("Direct chats can't be managed"))
+            .style(Style::default().fg(Color::Gray)),
+        _ => Paragraph::new(Text::from(
+            "[r] Rename  [a] Add  [k] Remove  [m] Toggle admin  [o] Make owner  [l] Leave",
+        ))
+        .style(Style::default().fg(Color::Gray)),
+    };
+    f.render_widget(footer, layout[1]);
+
+    let hint = if app.message.is_empty() {
+        "Press [Esc] to go back".to_string()
+    } else {
+        format!("{} | Press [Esc] to go back", app.message)
+    };
+    let hint = Paragraph::new(hint).style(Style::default().fg(Color::DarkGray));
+    f.render_widget(hint, layout[2]);
+}
+
+pub async fn handle_input(app: &mut App, key: KeyEvent) {
+    // Temporarily replace app.state so we can move out the settings mode
+    let original_state = mem::replace(&mut app.state, FormState::Exit);
+
+    let FormState::GroupSettings {
+        chat_id,
+        chat_name,
+        is_group,
+        members,
+        mut selected_index,
+        mut mode,
+    } = original_state
+    else {
+        app.state = original_state;
+        return;
+    };
+
+    let selected_member = members.get(selected_index).cloned();
+    let mut changed = false;
+    let mut left = false;
+
+    match &mut mode {
+        GroupSettingsMode::Browse => match key.code {
+            KeyCode::Up if selected_index > 0 => selected_index -= 1,
+            KeyCode::Down if selected_index + 1 < members.len() => selected_index += 1,
+            KeyCode::Esc => {
+                app.message.clear();
+                app.enter_chat_view(chat_id, chat_name, None).await;
+                return;
+            }
+            KeyCode::Char('r') if is_group => {
+                mode = GroupSettingsMode::Renaming {
+                    name_input: chat_name.clone(),
+                };
+            }
+            KeyCode::Char('a') if is_group => {
+                let friends = app
+                    .get_friends()
+                    .await
+                    .into_iter()
+                    .filter(|f| !members.iter().any(|m| m.id == f.id))
+                    .collect();
+                mode = GroupSettingsMode::Adding {
+                    selected: 0,
+                    chosen: Vec::new(),
+                    friends,
+                };
+            }
+            KeyCode::Char('k') if is_group => {
+                if let Some(member) = selected_member {
+                    let command = Command::RemoveChatMember {
+                        chat_id,
+                        user_id: member.id,
+                    };
+                    changed = send_command(app, command).await;
+                }
+            }
+            KeyCode::Char('m') if is_group => {
+                if let Some(member) = selected_member {
+                    let role = match member.role {
+                        ChatRole::Admin => ChatRole::Member,
+                        _ => ChatRole::Admin,
+                    };
+                    let command = Command::SetChatMemberRole {
+                        chat_id,
+                        user_id: member.id,
+                        role,
+                    };
+                    changed = send_command(app, command).await;
+                }
+            }
+            KeyCode::Char('o') if is_group => {
+                if let Some(member) = selected_member {
+                    let command = Command::TransferOwnership {
+                        chat_id,
+                        new_owner_id: member.id,
+                    };
+                    changed = send_command(app, command).await;
+                }
+            }
+            KeyCode::Char('l') if is_group => {
+                left = send_command(app, Command::LeaveChat { chat_id }).await;
+            }
+            _ => {}
+        },
+        GroupSettingsMode::Renaming { name_input } => match key.code {
+            KeyCode::Char(c) => name_input.push(c),
+            KeyCode::Backspace => {
+                name_input.pop();
+            }
+            KeyCode::Enter => {
+                let name = name_input.trim().to_string();
+                if name.is_empty() {
+                    app.message = "Group name cannot be empty.".into();
+                } else if name.len() > 20 {
+                    app.message = "Group name cannot be longer than 20 characters.".into();
+                } else if send_command(app, Command::RenameChat { chat_id, name }).await {
+                    mode = GroupSettingsMode::Browse;
+                    changed = true;
+                }
+            }
+            KeyCode::Esc => {
+                app.message.clear();
+                mode = GroupSettingsMode::Browse;
+            }
+            _ => {}
+        },
+        GroupSettingsMode::Adding {
+            selected,
+            chosen,
+            friends,
+        } => match key.code {
+            KeyCode::Down if *selected + 1 < friends.len() => {
+                *selected += 1;
+            }
+            KeyCode::Up if *selected > 0 => {
+                *selected -= 1;
+            }
+            KeyCode::Char(' ') if !friends.is_empty() => {
+                let friend = &friends[*selected];
+                if chosen.contains(friend) {
+                    chosen.retain(|f| f != friend);
+                } else {
+                    chosen.push(friend.clone());
+                }
+            }
+            KeyCode::Enter if !chosen.is_empty() => {
+                let command = Command::AddChatMembers {
+                    chat_id,
+                    member_ids: chosen.iter().map(|u| u.id).collect(),
+                };
+                if send_command(app, command).await {
+                    mode = GroupSettingsMode::Browse;
+                    changed = true;
+                }
+            }
+            KeyCode::Esc => {
+                app.message.clear();
+                mode = GroupSettingsMode::Browse;
+            }
+            _ => {}
+        },
+    }
+
+    if left {
+        app.enter_chats_view().await;
+        return;
+    }
+
+    app.state = FormState::GroupSettings {
+        chat_id,
+        chat_name,
+        is_group,
+        members,
+        selected_index,
+        mode,
+    };
+    // A successful change reloads the member list with the new roles
+    if changed {
+        app.refresh_group_members(chat_id).await;
+    }
+}
+
+/// Sends a group command, showing the server's reply. Returns whether it succeeded.
+async fn send_command(app: &mut App, command: Command) -> bool {
+    let request = ClientRequest { command };
+    match app.send_request(&request).await {
+        Ok(response) => {
+            app.message = response.message.unwrap_or_default();
+            response.success
+        }
+        Err(err) => {
+            app.message = format!("Error: {}", err);
+            false
+        }
+    }
+}
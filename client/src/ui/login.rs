@@ -1,7 +1,6 @@
 use crate::app::{ActiveField, App, FormState};
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
-    backend::Backend,
     layout::{Constraint, Direction, Layout},
     style::{Color, Style},
     text::Text,
@@ -11,7 +10,7 @@ use ratatui::{
 use shared::client_response::{ClientRequest, Command};
 use shared::models::auth_models::AuthResponseModel;
 
-pub fn render<B: Backend>(f: &mut Frame, app: &App) {
+pub fn render(f: &mut Frame, app: &App) {
     if let FormState::LoginForm {
         username,
         password,
@@ -104,7 +103,6 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
         }
         Enter => {
             let req = ClientRequest {
-                jwt: None,
                 command: Command::Login {
                     username: username.clone(),
                     password: password.clone(),
@@ -119,10 +117,13 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
                                 Ok(auth_response) => {
                                     if let Some(jwt) = response.jwt.clone() {
                                         app.jwt = jwt;
+                                        app.refresh_token = auth_response.refresh_token;
                                         app.user_id = auth_response.user_id;
                                         app.username = username.clone();
+                                        app.save_username();
                                         app.message = format!("Welcome {}!", username);
                                         app.set_user_menu().await;
+                                        app.open_outbox().await;
                                     }
                                 }
                                 Err(e) => {
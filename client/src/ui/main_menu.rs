@@ -1,14 +1,13 @@
-use crate::app::App;
+use crate::app::{App, FormState};
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
-    backend::Backend,
     layout::{Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
     widgets::{Block, Borders, List, ListItem, Paragraph},
     Frame,
 };
 
-pub fn render<B: Backend>(f: &mut Frame, app: &App) {
+pub fn render(f: &mut Frame, app: &App) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(4)
@@ -18,6 +17,8 @@ pub fn render<B: Backend>(f: &mut Frame, app: &App) {
     // Display personalized message if logged in
     let main_menu_message = if app.logged_in {
         format!("Welcome, {}", app.username) // Display the stored username
+    } else if let Some((name, profile)) = &app.server {
+        format!("Connected to {} ({})", name, profile.address())
     } else {
         "Select an option".into() // Default message if not logged in
     };
@@ -40,7 +41,7 @@ pub fn render<B: Backend>(f: &mut Frame, app: &App) {
     ];
 
     // Pre-login options
-    let pre_login_options = vec!["Login", "Register", "Close"];
+    let pre_login_options = vec!["Login", "Register", "Change Server", "Close"];
 
     // Choose the correct options based on login status
     let options = if app.logged_in {
@@ -77,22 +78,123 @@ pub fn render<B: Backend>(f: &mut Frame, app: &App) {
 
 pub async fn handle_input(app: &mut App, key: KeyEvent) {
     match key.code {
-        KeyCode::Up => {
-            if app.selected_index > 0 {
-                app.selected_index -= 1;
-            }
+        KeyCode::Up if app.selected_index > 0 => {
+            app.selected_index -= 1;
         }
-        KeyCode::Down => {
-            if app.selected_index < 2 {
-                app.selected_index += 1;
-            }
+        KeyCode::Down if app.selected_index < 3 => {
+            app.selected_index += 1;
         }
         KeyCode::Enter | KeyCode::Char('\r') => match app.selected_index {
             0 => app.set_login_form(),
             1 => app.set_register_form(),
-            2 => app.set_exit(),
+            2 => {
+                app.message.clear();
+                app.set_server_picker();
+            }
+            3 => app.set_exit(),
             _ => {}
         },
         _ => {}
     }
 }
+
+/// Lists the saved server profiles to connect to
+pub fn render_server_picker(f: &mut Frame, app: &App) {
+    let FormState::ServerPicker { selected_index } = &app.state else {
+        return;
+    };
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(4)
+        .constraints([
+            Constraint::Min(3),
+            Constraint::Length(3),
+            Constraint::Length(1),
+        ])
+        .split(f.area());
+
+    let current = app.server.as_ref().map(|(name, _)| name);
+    let items: Vec<ListItem> = app
+        .config
+        .profiles
+        .iter()
+        .enumerate()
+        .map(|(i, (name, profile))| {
+            let mut label = format!("{} ({})", name, profile.address());
+            if let Some(username) = &profile.username {
+                label.push_str(&format!(" as {}", username));
+            }
+            if Some(name) == current {
+                label.push_str(" - connected");
+            }
+            let style = if i == *selected_index {
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            ListItem::new(label).style(style)
+        })
+        .collect();
+
+    let list = List::new(items).block(Block::default().borders(Borders::ALL).title("Servers"));
+    f.render_widget(list, chunks[0]);
+
+    let status = if app.config.profiles.is_empty() {
+        format!(
+            "No servers saved. Add a profile to {}, or run with --server <host>",
+            app.config_path.display()
+        )
+    } else {
+        app.message.clone()
+    };
+    let status = Paragraph::new(status)
+        .block(Block::default().borders(Borders::ALL))
+        .style(Style::default().fg(Color::White));
+    f.render_widget(status, chunks[1]);
+
+    let hint = if app.conn.is_some() {
+        "[Enter] Connect  [Esc] Back"
+    } else {
+        "[Enter] Connect  [Esc] Quit"
+    };
+    f.render_widget(
+        Paragraph::new(hint).style(Style::default().fg(Color::DarkGray)),
+        chunks[2],
+    );
+}
+
+pub async fn handle_server_picker_input(app: &mut App, key: KeyEvent) {
+    let FormState::ServerPicker { selected_index } = &mut app.state else {
+        return;
+    };
+
+    match key.code {
+        KeyCode::Up if *selected_index > 0 => *selected_index -= 1,
+        KeyCode::Down if *selected_index + 1 < app.config.profiles.len() => {
+            *selected_index += 1;
+        }
+        KeyCode::Enter => {
+            let Some(name) = app.config.profiles.keys().nth(*selected_index).cloned() else {
+                return;
+            };
+            app.message = format!("Connecting to {}...", name);
+            match app.connect_to(&name).await {
+                Ok(()) => {
+                    app.selected_index = 0;
+                    app.set_main_menu();
+                }
+                Err(e) => app.message = format!("Couldn't connect to {}: {}", name, e),
+            }
+        }
+        // Nothing to go back to before the first connection
+        KeyCode::Esc if app.conn.is_none() => app.set_exit(),
+        KeyCode::Esc => {
+            app.message.clear();
+            app.set_main_menu();
+        }
+        _ => {}
+    }
+}
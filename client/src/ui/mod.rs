@@ -1,15 +1,18 @@
 pub mod add_friends;
+pub mod blocked_users;
 pub mod chat;
 pub mod chats;
-pub mod close;
 pub mod confirm_friend_request;
 pub mod confirm_unfriend;
 pub mod create_chat;
 pub mod friend_list;
 pub mod friend_requests;
 pub mod friends_menu;
+pub mod group_settings;
 pub mod login;
 pub mod main_menu;
+pub mod presence;
 pub mod profile;
+pub mod reconnect_banner;
 pub mod registration;
 pub mod user_menu;
@@ -0,0 +1,36 @@
+use chrono::{NaiveDateTime, Utc};
+use ratatui::{
+    style::{Color, Style},
+    text::Span,
+};
+use shared::models::user_models::{Presence, PresenceStatus};
+
+// Green while online, yellow while idle, grey when offline or unknown
+pub fn status_dot(presence: Option<&Presence>) -> Span<'static> {
+    let color = match presence.map(|p| p.status) {
+        Some(PresenceStatus::Online) => Color::Green,
+        Some(PresenceStatus::Away) => Color::Yellow,
+        _ => Color::DarkGray,
+    };
+    Span::styled("● ", Style::default().fg(color))
+}
+
+// "online", "away" or "last seen 5 min ago"
+pub fn status_label(presence: &Presence) -> String {
+    match (presence.status, presence.last_seen_at) {
+        (PresenceStatus::Online, _) => "online".to_string(),
+        (PresenceStatus::Away, _) => "away".to_string(),
+        (PresenceStatus::Offline, Some(last_seen_at)) => last_seen_label(last_seen_at),
+        (PresenceStatus::Offline, None) => "offline".to_string(),
+    }
+}
+
+fn last_seen_label(last_seen_at: NaiveDateTime) -> String {
+    let minutes = (Utc::now().naive_utc() - last_seen_at).num_minutes().max(0);
+    match minutes {
+        0 => "last seen just now".to_string(),
+        1..=59 => format!("last seen {} min ago", minutes),
+        60..=1439 => format!("last seen {} h ago", minutes / 60),
+        _ => format!("last seen {} days ago", minutes / 1440),
+    }
+}
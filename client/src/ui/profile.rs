@@ -1,21 +1,22 @@
 use crate::app::{ActiveField, App, FormState};
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
-    backend::Backend,
     layout::{Constraint, Direction, Layout},
-    style::{Color, Style},
+    style::{Color, Modifier, Style},
     text::Text,
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, List, ListItem, Paragraph},
     Frame,
 };
 use shared::client_response::{ClientRequest, Command};
 
-pub fn render<B: Backend>(f: &mut Frame, app: &App) {
+pub fn render(f: &mut Frame, app: &App) {
     // Debug: show when render runs and current state
 
     if let FormState::ProfileView {
         new_password,
         active_field,
+        sessions,
+        selected_session,
     } = &app.state
     {
         let chunks = Layout::default()
@@ -41,6 +42,34 @@ pub fn render<B: Backend>(f: &mut Frame, app: &App) {
             .block(Block::default().borders(Borders::ALL).title("Password"))
             .style(pass_style);
         f.render_widget(pass_para, chunks[1]);
+
+        let items: Vec<ListItem> = sessions
+            .iter()
+            .enumerate()
+            .map(|(i, session)| {
+                let current = if session.current { " (this device)" } else { "" };
+                let style = if i == *selected_session {
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(format!(
+                    "Signed in {}, last active {}{}",
+                    session.created_at.format("%Y-%m-%d %H:%M"),
+                    session.last_used_at.format("%Y-%m-%d %H:%M"),
+                    current
+                ))
+                .style(style)
+            })
+            .collect();
+        let list = List::new(items).block(
+            Block::default()
+                .title("Sessions ([Del] to sign out a device)")
+                .borders(Borders::ALL),
+        );
+        f.render_widget(list, chunks[2]);
     }
 }
 
@@ -48,28 +77,25 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
     use KeyCode::*;
 
     // Only match if we're in the ProfileView
-    let (new_password, active_field) = match &mut app.state {
+    let (new_password, active_field, sessions, selected_session) = match &mut app.state {
         FormState::ProfileView {
             new_password,
             active_field,
-        } => (new_password, active_field),
+            sessions,
+            selected_session,
+        } => (new_password, active_field, sessions, selected_session),
         _ => return,
     };
 
     match key.code {
-        Backspace => {
-            if *active_field == ActiveField::Password {
-                new_password.pop();
-            }
+        Backspace if *active_field == ActiveField::Password => {
+            new_password.pop();
         }
-        Char(c) => {
-            if *active_field == ActiveField::Password {
-                new_password.push(c);
-            }
+        Char(c) if *active_field == ActiveField::Password => {
+            new_password.push(c);
         }
         Enter => {
             let req = ClientRequest {
-                jwt: Some(app.jwt.clone()),
                 command: Command::UpdateProfile {
                     new_password: new_password.clone(),
                 },
@@ -90,6 +116,48 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
             }
         }
 
+        Up if *selected_session > 0 => {
+            *selected_session -= 1;
+        }
+        Down if *selected_session + 1 < sessions.len() => {
+            *selected_session += 1;
+        }
+        Delete => {
+            let Some(session) = sessions.get(*selected_session).cloned() else {
+                return;
+            };
+            if session.current {
+                app.message = "Use Log Out to end this session.".into();
+                return;
+            }
+            let req = ClientRequest {
+                command: Command::RevokeSession {
+                    session_id: session.id,
+                },
+            };
+            match app.send_request(&req).await {
+                Ok(response) if response.success => {
+                    app.message = "Device signed out.".into();
+                    let sessions = app.get_sessions().await;
+                    if let FormState::ProfileView {
+                        sessions: current,
+                        selected_session,
+                        ..
+                    } = &mut app.state
+                    {
+                        *selected_session = (*selected_session).min(sessions.len().saturating_sub(1));
+                        *current = sessions;
+                    }
+                }
+                Ok(response) => {
+                    app.message = response.message.unwrap_or_default();
+                }
+                Err(err) => {
+                    app.message = err.to_string();
+                }
+            }
+        }
+
         Esc => {
             app.set_user_menu().await;
             app.message = "Returning to main menu...".into();
@@ -0,0 +1,49 @@
+use crate::app::Reconnect;
+use crate::outbox::Outbox;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+use std::time::Instant;
+
+// One line across the top of the screen while the connection is down
+pub fn render(f: &mut Frame, reconnect: &Reconnect, outbox: Option<&Outbox>) {
+    let area = f.area();
+    let banner = Rect {
+        height: area.height.min(1),
+        ..area
+    };
+
+    let wait = reconnect
+        .next_try
+        .saturating_duration_since(Instant::now())
+        .as_secs();
+    let mut text = if reconnect.attempt == 0 || wait == 0 {
+        "Connection lost. Reconnecting…".to_string()
+    } else {
+        format!(
+            "Connection lost. Reconnecting in {}s (attempt {})…",
+            wait,
+            reconnect.attempt + 1
+        )
+    };
+    if let Some(outbox) = outbox.filter(|o| !o.is_empty()) {
+        text.push_str(&format!(" {} unsent, will send once back.", outbox.len()));
+    }
+    if let Some(error) = &reconnect.last_error {
+        text.push_str(&format!(" Last error: {}", error));
+    }
+
+    f.render_widget(Clear, banner);
+    f.render_widget(
+        Paragraph::new(text).style(
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ),
+        banner,
+    );
+}
@@ -3,16 +3,13 @@ use crossterm::event::KeyCode;
 use crossterm::event::KeyEvent;
 use ratatui::widgets::Paragraph;
 use ratatui::{
-    backend::Backend,
     layout::{Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
     widgets::{Block, Borders, List, ListItem},
     Frame,
 };
 
-const PAGE_SIZE: u64 = 10;
-
-pub fn render<B: Backend>(f: &mut Frame, app: &App) {
+pub fn render(f: &mut Frame, app: &App) {
     let options = [
         "Chats",
         "Add Friends",
@@ -81,7 +78,7 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
             KeyCode::Enter | KeyCode::Char('\r') => match selected_index {
                 0 => {
                     app.message.clear();
-                    app.enter_chats_view(0, PAGE_SIZE).await
+                    app.enter_chats_view().await
                 } // index 0 = Chats
                 1 => {
                     app.message.clear();
@@ -91,7 +88,7 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
                     app.message.clear();
                     app.set_friend_menu();
                 }
-                3 => app.set_profile_view(),
+                3 => app.set_profile_view().await,
 
                 4 => {
                     app.message.clear();
@@ -99,15 +96,11 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) {
                 } // Log Out
                 _ => {}
             },
-            KeyCode::Up => {
-                if selected_index > 0 {
-                    app.set_user_menu_selected_index(selected_index - 1);
-                }
+            KeyCode::Up if selected_index > 0 => {
+                app.set_user_menu_selected_index(selected_index - 1);
             }
-            KeyCode::Down => {
-                if selected_index < 4 {
-                    app.set_user_menu_selected_index(selected_index + 1);
-                }
+            KeyCode::Down if selected_index < 4 => {
+                app.set_user_menu_selected_index(selected_index + 1);
             }
             _ => {}
         }
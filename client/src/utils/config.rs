@@ -0,0 +1,172 @@
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use shared::codec::Codec;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_PORT: u16 = 8080;
+
+/// Command line flags. They pick which server to talk to and override how it's trusted.
+#[derive(Debug, Clone, Parser)]
+#[command(name = "client", about = "QUIC messaging client")]
+pub struct Args {
+    /// Client config file with the saved server profiles
+    #[arg(long, env = "CLIENT_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Connect to this saved profile instead of the default
+    #[arg(long, short)]
+    pub profile: Option<String>,
+
+    /// Connect to a server that isn't saved, as host or host:port
+    #[arg(long, conflicts_with = "profile")]
+    pub server: Option<String>,
+
+    /// Verify the server against the CA certificates in this PEM bundle
+    #[arg(long, env = "CA_CERT_PATH")]
+    pub ca_cert: Option<PathBuf>,
+
+    /// Accept any server certificate. Only for local testing.
+    #[arg(long)]
+    pub insecure: bool,
+
+    /// Only offer this wire codec (json or msgpack). JSON is handy for debugging.
+    #[arg(long, env = "WIRE_CODEC")]
+    pub codec: Option<Codec>,
+}
+
+/// The client's config file: named server profiles and which one to use by default
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientConfig {
+    pub default_profile: Option<String>,
+    pub profiles: BTreeMap<String, ServerProfile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServerProfile {
+    // Hostname or IP, resolved on every connect
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    // Certificate fingerprint to require, instead of pinning on first use
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned_cert: Option<String>,
+    // CA bundle to verify this server against
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_cert: Option<PathBuf>,
+    // Filled in on the login form, and saved after logging in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+impl ServerProfile {
+    /// Parses "host" or "host:port". IPv6 addresses need brackets when a port is given.
+    pub fn from_address(address: &str) -> Result<Self, Box<dyn Error>> {
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port)) if !host.contains(':') || host.ends_with(']') => {
+                let port = port
+                    .parse()
+                    .map_err(|_| format!("Invalid port in server address {:?}", address))?;
+                (host.trim_matches(['[', ']']), port)
+            }
+            _ => (address.trim_matches(['[', ']']), DEFAULT_PORT),
+        };
+        if host.is_empty() {
+            return Err(format!("Missing host in server address {:?}", address).into());
+        }
+        Ok(ServerProfile {
+            host: host.to_string(),
+            port,
+            pinned_cert: None,
+            ca_cert: None,
+            username: None,
+        })
+    }
+
+    /// "host:port", the key servers are pinned under
+    pub fn address(&self) -> String {
+        if self.host.contains(':') {
+            format!("[{}]:{}", self.host, self.port)
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+}
+
+impl ClientConfig {
+    /// Reads the config file. Without one, the SERVER_ADDR env var becomes a
+    /// single "default" profile so older setups keep working.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if path.exists() {
+            let contents = fs::read_to_string(path)?;
+            let config: ClientConfig = toml::from_str(&contents)
+                .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))?;
+            if let Some(name) = &config.default_profile {
+                if !config.profiles.contains_key(name) {
+                    return Err(format!("default_profile {:?} isn't a saved profile", name).into());
+                }
+            }
+            return Ok(config);
+        }
+
+        let mut config = ClientConfig::default();
+        if let Ok(address) = std::env::var("SERVER_ADDR") {
+            config.profiles.insert(
+                "default".to_string(),
+                ServerProfile::from_address(&address)?,
+            );
+            config.default_profile = Some("default".to_string());
+        }
+        Ok(config)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The profile to connect to at startup: the one named on the command line,
+    /// else the default, else the only one saved. None leaves the choice to the user.
+    pub fn initial_profile(
+        &self,
+        requested: Option<&str>,
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        if let Some(name) = requested {
+            if !self.profiles.contains_key(name) {
+                return Err(format!("No saved profile named {:?}", name).into());
+            }
+            return Ok(Some(name.to_string()));
+        }
+        if self.default_profile.is_some() {
+            return Ok(self.default_profile.clone());
+        }
+        if self.profiles.len() == 1 {
+            return Ok(self.profiles.keys().next().cloned());
+        }
+        Ok(None)
+    }
+}
+
+/// ~/.quic-messaging/config.toml
+pub fn default_config_path() -> PathBuf {
+    data_dir().join("config.toml")
+}
+
+/// Where the client keeps its files
+pub fn data_dir() -> PathBuf {
+    let home = std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .unwrap_or_else(|_| ".".to_string());
+    Path::new(&home).join(".quic-messaging")
+}
@@ -1 +1,2 @@
-pub mod constants;
\ No newline at end of file
+pub mod config;
+pub mod tls;
\ No newline at end of file
@@ -0,0 +1,247 @@
+use crate::utils::config::{data_dir, Args, ServerProfile};
+use rustls::client::{ClientConfig as RustlsClientConfig, ServerCertVerified, ServerCertVerifier};
+use rustls::{Certificate, RootCertStore, ServerName};
+use shared::tls::fingerprint;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// How the client decides whether to trust the server's certificate
+pub enum TrustMode {
+    /// Verify the certificate chain against the CA certificates in a PEM bundle
+    CaBundle(PathBuf),
+    /// Trust the first certificate a server presents and pin it in the known-servers file
+    Pinned(PathBuf),
+    /// Require a certificate with this fingerprint
+    Fingerprint(String),
+    /// Accept any certificate. Only for local testing.
+    Insecure,
+}
+
+impl TrustMode {
+    /// Picks the mode for a profile. Command line flags win over the profile's
+    /// own settings, and without either the server is pinned on first use.
+    pub fn for_profile(args: &Args, profile: &ServerProfile) -> Self {
+        if args.insecure {
+            return TrustMode::Insecure;
+        }
+        if let Some(path) = args.ca_cert.clone().or_else(|| profile.ca_cert.clone()) {
+            return TrustMode::CaBundle(path);
+        }
+        if let Some(fingerprint) = &profile.pinned_cert {
+            return TrustMode::Fingerprint(fingerprint.clone());
+        }
+        TrustMode::Pinned(known_servers_path())
+    }
+}
+
+/// What the pinning check decided during a handshake. rustls only passes a
+/// verifier's error on as text, so the connection reads this back afterwards.
+#[derive(Debug, Clone)]
+pub enum PinEvent {
+    /// A server seen for the first time was trusted with this fingerprint
+    Pinned(String),
+    /// A pinned server presented a different certificate and was refused
+    Changed(CertificateChanged),
+}
+
+/// Where the verifier leaves its [`PinEvent`] for the connection to pick up
+pub type PinEvents = Arc<Mutex<Option<PinEvent>>>;
+
+/// A pinned server presented a certificate with a different fingerprint.
+/// Someone could be intercepting the connection, so it was refused.
+#[derive(Debug, Clone)]
+pub struct CertificateChanged {
+    pub server: String,
+    pub pinned: String,
+    pub presented: String,
+    // How to trust the new certificate if it was replaced on purpose
+    pub fix: String,
+}
+
+impl fmt::Display for CertificateChanged {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "WARNING: the certificate for {} has changed, someone could be intercepting the connection. \
+             Pinned {}, presented {}. If it was replaced on purpose, {}.",
+            self.server, self.pinned, self.presented, self.fix
+        )
+    }
+}
+
+impl Error for CertificateChanged {}
+
+/// Builds the rustls config for connecting to `server` ("host:port") under the given mode.
+/// A pinning verifier reports what it decided in `events`.
+pub fn client_crypto(
+    mode: TrustMode,
+    server: &str,
+    events: PinEvents,
+) -> Result<RustlsClientConfig, Box<dyn Error>> {
+    let builder = RustlsClientConfig::builder().with_safe_defaults();
+    let config = match mode {
+        TrustMode::CaBundle(path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(&path)? {
+                roots.add(&cert)?;
+            }
+            builder.with_root_certificates(roots).with_no_client_auth()
+        }
+        TrustMode::Pinned(path) => builder
+            .with_custom_certificate_verifier(Arc::new(PinningVerifier::new(
+                PinStore::File(path),
+                server,
+                events,
+            )))
+            .with_no_client_auth(),
+        TrustMode::Fingerprint(fingerprint) => builder
+            .with_custom_certificate_verifier(Arc::new(PinningVerifier::new(
+                PinStore::Fixed(fingerprint.to_uppercase()),
+                server,
+                events,
+            )))
+            .with_no_client_auth(),
+        TrustMode::Insecure => builder
+            .with_custom_certificate_verifier(Arc::new(InsecureVerifier))
+            .with_no_client_auth(),
+    };
+    Ok(config)
+}
+
+// ~/.quic-messaging/known_servers, unless KNOWN_SERVERS_PATH says otherwise
+fn known_servers_path() -> PathBuf {
+    match std::env::var("KNOWN_SERVERS_PATH") {
+        Ok(path) => path.into(),
+        Err(_) => data_dir().join("known_servers"),
+    }
+}
+
+fn load_certs(path: &Path) -> io::Result<Vec<Certificate>> {
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    Ok(rustls_pemfile::certs(&mut reader)?
+        .into_iter()
+        .map(Certificate)
+        .collect())
+}
+
+// Where a server's expected fingerprint comes from
+enum PinStore {
+    // The known-servers file, where each line is "host:port fingerprint"
+    File(PathBuf),
+    // A fingerprint from the server's profile, never written anywhere
+    Fixed(String),
+}
+
+/// Checks the server's certificate against its pinned fingerprint. With a known-servers
+/// file, an unknown server is pinned on first connect; a pinned server presenting a
+/// different certificate is refused either way.
+struct PinningVerifier {
+    store: PinStore,
+    server: String,
+    events: PinEvents,
+    // Serializes the read-then-append so two handshakes can't both pin
+    lock: Mutex<()>,
+}
+
+impl PinningVerifier {
+    fn new(store: PinStore, server: &str, events: PinEvents) -> Self {
+        PinningVerifier {
+            store,
+            server: server.to_string(),
+            events,
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn report(&self, event: PinEvent) {
+        *self.events.lock().unwrap_or_else(|e| e.into_inner()) = Some(event);
+    }
+
+    fn pinned_fingerprint(&self) -> Option<String> {
+        let path = match &self.store {
+            PinStore::Fixed(fingerprint) => return Some(fingerprint.clone()),
+            PinStore::File(path) => path,
+        };
+        let contents = fs::read_to_string(path).ok()?;
+        contents.lines().find_map(|line| {
+            let (server, fingerprint) = line.trim().split_once(' ')?;
+            (server == self.server).then(|| fingerprint.trim().to_string())
+        })
+    }
+
+    fn pin(&self, fingerprint: &str) -> io::Result<()> {
+        let PinStore::File(path) = &self.store else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{} {}", self.server, fingerprint)
+    }
+
+    fn pin_location(&self) -> String {
+        match &self.store {
+            PinStore::File(path) => format!("remove its line from {}", path.display()),
+            PinStore::Fixed(_) => "update pinned_cert in its profile".to_string(),
+        }
+    }
+}
+
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        _: &[Certificate],
+        _: &ServerName,
+        _: &mut dyn Iterator<Item = &[u8]>,
+        _: &[u8],
+        _: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let presented = fingerprint(&end_entity.0);
+
+        match self.pinned_fingerprint() {
+            Some(pinned) if pinned == presented => Ok(ServerCertVerified::assertion()),
+            Some(pinned) => {
+                let changed = CertificateChanged {
+                    server: self.server.clone(),
+                    pinned,
+                    presented,
+                    fix: self.pin_location(),
+                };
+                let error = rustls::Error::General(changed.to_string());
+                self.report(PinEvent::Changed(changed));
+                Err(error)
+            }
+            None => {
+                self.pin(&presented)
+                    .map_err(|e| rustls::Error::General(format!("Failed to pin server: {}", e)))?;
+                self.report(PinEvent::Pinned(presented));
+                Ok(ServerCertVerified::assertion())
+            }
+        }
+    }
+}
+
+/// Accepts any certificate. Used only with --insecure.
+struct InsecureVerifier;
+
+impl ServerCertVerifier for InsecureVerifier {
+    fn verify_server_cert(
+        &self,
+        _: &Certificate,
+        _: &[Certificate],
+        _: &ServerName,
+        _: &mut dyn Iterator<Item = &[u8]>,
+        _: &[u8],
+        _: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
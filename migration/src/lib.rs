@@ -0,0 +1,31 @@
+pub use sea_orm_migration::prelude::*;
+
+mod m20250601_000001_create_users;
+mod m20250601_000002_create_friends;
+mod m20250601_000003_create_chats;
+mod m20250601_000004_create_messages;
+mod m20250602_000001_add_chat_roles;
+mod m20250602_000002_add_message_edits;
+mod m20250602_000003_add_last_seen;
+mod m20250602_000004_create_sessions;
+mod m20250602_000005_add_idempotency_keys;
+
+/// Every schema change, oldest first. New migrations go at the end and are never edited once released.
+pub struct Migrator;
+
+#[async_trait::async_trait]
+impl MigratorTrait for Migrator {
+    fn migrations() -> Vec<Box<dyn MigrationTrait>> {
+        vec![
+            Box::new(m20250601_000001_create_users::Migration),
+            Box::new(m20250601_000002_create_friends::Migration),
+            Box::new(m20250601_000003_create_chats::Migration),
+            Box::new(m20250601_000004_create_messages::Migration),
+            Box::new(m20250602_000001_add_chat_roles::Migration),
+            Box::new(m20250602_000002_add_message_edits::Migration),
+            Box::new(m20250602_000003_add_last_seen::Migration),
+            Box::new(m20250602_000004_create_sessions::Migration),
+            Box::new(m20250602_000005_add_idempotency_keys::Migration),
+        ]
+    }
+}
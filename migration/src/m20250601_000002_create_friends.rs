@@ -0,0 +1,201 @@
+use sea_orm_migration::sea_orm::{DbBackend, Statement};
+use sea_orm_migration::sea_query::extension::postgres::Type;
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20250601_000001_create_users::Users;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Friends::Table)
+                    .if_not_exists()
+                    .col(integer(Friends::UserId))
+                    .col(integer(Friends::FriendId))
+                    .primary_key(Index::create().col(Friends::UserId).col(Friends::FriendId))
+                    .foreign_key(&mut user_key(
+                        "fk_friends_user",
+                        Friends::Table,
+                        Friends::UserId,
+                    ))
+                    .foreign_key(&mut user_key(
+                        "fk_friends_friend",
+                        Friends::Table,
+                        Friends::FriendId,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(BlockedUsers::Table)
+                    .if_not_exists()
+                    .col(integer(BlockedUsers::UserId))
+                    .col(integer(BlockedUsers::BlockedId))
+                    .primary_key(
+                        Index::create()
+                            .col(BlockedUsers::UserId)
+                            .col(BlockedUsers::BlockedId),
+                    )
+                    .foreign_key(&mut user_key(
+                        "fk_blocked_users_user",
+                        BlockedUsers::Table,
+                        BlockedUsers::UserId,
+                    ))
+                    .foreign_key(&mut user_key(
+                        "fk_blocked_users_blocked",
+                        BlockedUsers::Table,
+                        BlockedUsers::BlockedId,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Postgres enums are their own type, made before the column that uses it
+        if manager.get_database_backend() == DbBackend::Postgres
+            && !has_type(manager, "status").await?
+        {
+            manager
+                .create_type(
+                    Type::create()
+                        .as_enum(Status::Enum)
+                        .values([Status::Pending, Status::Accepted, Status::Rejected])
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(FriendRequests::Table)
+                    .if_not_exists()
+                    .col(pk_auto(FriendRequests::Id))
+                    .col(integer(FriendRequests::SenderId))
+                    .col(integer(FriendRequests::ReceiverId))
+                    .col(
+                        enumeration(
+                            FriendRequests::Status,
+                            Status::Enum,
+                            [Status::Pending, Status::Accepted, Status::Rejected],
+                        )
+                        .default("pending"),
+                    )
+                    .col(date_time(FriendRequests::SentAt).default(Expr::current_timestamp()))
+                    .foreign_key(&mut user_key(
+                        "fk_friend_requests_sender",
+                        FriendRequests::Table,
+                        FriendRequests::SenderId,
+                    ))
+                    .foreign_key(&mut user_key(
+                        "fk_friend_requests_receiver",
+                        FriendRequests::Table,
+                        FriendRequests::ReceiverId,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Only one request between the same two users at a time
+        if !manager
+            .has_index("friend_requests", "unique_request")
+            .await?
+        {
+            manager
+                .create_index(
+                    Index::create()
+                        .name("unique_request")
+                        .table(FriendRequests::Table)
+                        .col(FriendRequests::SenderId)
+                        .col(FriendRequests::ReceiverId)
+                        .unique()
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in [
+            FriendRequests::Table.into_iden(),
+            BlockedUsers::Table.into_iden(),
+            Friends::Table.into_iden(),
+        ] {
+            manager
+                .drop_table(Table::drop().table(table).to_owned())
+                .await?;
+        }
+        if manager.get_database_backend() == DbBackend::Postgres {
+            manager
+                .drop_type(Type::drop().name(Status::Enum).to_owned())
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+// Whether the Postgres schema already has the named type, like a database set up by hand would
+async fn has_type(manager: &SchemaManager<'_>, name: &str) -> Result<bool, DbErr> {
+    let query = Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT 1 FROM pg_type JOIN pg_namespace ON pg_namespace.oid = pg_type.typnamespace \
+         WHERE pg_type.typname = $1 AND pg_namespace.nspname = current_schema()",
+        [name.into()],
+    );
+    Ok(manager.get_connection().query_one(query).await?.is_some())
+}
+
+// A foreign key from `column` to users.id
+fn user_key<T: IntoIden + 'static, C: IntoIden>(
+    name: &str,
+    table: T,
+    column: C,
+) -> ForeignKeyCreateStatement {
+    ForeignKey::create()
+        .name(name)
+        .from(table, column)
+        .to(Users::Table, Users::Id)
+        .to_owned()
+}
+
+#[derive(DeriveIden)]
+enum Friends {
+    Table,
+    UserId,
+    FriendId,
+}
+
+#[derive(DeriveIden)]
+enum BlockedUsers {
+    Table,
+    UserId,
+    BlockedId,
+}
+
+#[derive(DeriveIden)]
+enum FriendRequests {
+    Table,
+    Id,
+    SenderId,
+    ReceiverId,
+    Status,
+    SentAt,
+}
+
+#[derive(DeriveIden)]
+enum Status {
+    #[sea_orm(iden = "status")]
+    Enum,
+    Pending,
+    Accepted,
+    Rejected,
+}
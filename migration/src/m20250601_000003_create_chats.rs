@@ -0,0 +1,79 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20250601_000001_create_users::Users;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Chats::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Chats::Id))
+                    .col(string_null(Chats::Name))
+                    .col(boolean(Chats::IsGroup).default(false))
+                    .col(date_time(Chats::CreatedAt).default(Expr::current_timestamp()))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ChatMembers::Table)
+                    .if_not_exists()
+                    .col(integer(ChatMembers::ChatId))
+                    .col(integer(ChatMembers::UserId))
+                    .primary_key(
+                        Index::create()
+                            .col(ChatMembers::ChatId)
+                            .col(ChatMembers::UserId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_chat_members_chat")
+                            .from(ChatMembers::Table, ChatMembers::ChatId)
+                            .to(Chats::Table, Chats::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_chat_members_user")
+                            .from(ChatMembers::Table, ChatMembers::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ChatMembers::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Chats::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Chats {
+    Table,
+    Id,
+    Name,
+    IsGroup,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum ChatMembers {
+    Table,
+    ChatId,
+    UserId,
+}
@@ -0,0 +1,100 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20250601_000001_create_users::Users;
+use crate::m20250601_000003_create_chats::Chats;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Messages::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Messages::Id))
+                    .col(integer(Messages::ChatId))
+                    .col(integer(Messages::SenderId))
+                    .col(string(Messages::SenderUsername))
+                    .col(text(Messages::Content))
+                    .col(boolean(Messages::Read).default(false))
+                    .col(date_time(Messages::Timestamp).default(Expr::current_timestamp()))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_messages_chat")
+                            .from(Messages::Table, Messages::ChatId)
+                            .to(Chats::Table, Chats::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_messages_sender")
+                            .from(Messages::Table, Messages::SenderId)
+                            .to(Users::Table, Users::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(MessageReads::Table)
+                    .if_not_exists()
+                    .col(integer(MessageReads::MessageId))
+                    .col(integer(MessageReads::UserId))
+                    .col(date_time(MessageReads::ReadAt).default(Expr::current_timestamp()))
+                    .primary_key(
+                        Index::create()
+                            .col(MessageReads::MessageId)
+                            .col(MessageReads::UserId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_message_reads_message")
+                            .from(MessageReads::Table, MessageReads::MessageId)
+                            .to(Messages::Table, Messages::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_message_reads_user")
+                            .from(MessageReads::Table, MessageReads::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in [MessageReads::Table.into_iden(), Messages::Table.into_iden()] {
+            manager
+                .drop_table(Table::drop().table(table).to_owned())
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Messages {
+    Table,
+    Id,
+    ChatId,
+    SenderId,
+    SenderUsername,
+    Content,
+    Read,
+    Timestamp,
+}
+
+#[derive(DeriveIden)]
+enum MessageReads {
+    Table,
+    MessageId,
+    UserId,
+    ReadAt,
+}
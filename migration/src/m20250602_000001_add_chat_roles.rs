@@ -0,0 +1,95 @@
+use sea_orm_migration::sea_orm::DbBackend;
+use sea_orm_migration::sea_query::extension::postgres::Type;
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20250601_000003_create_chats::ChatMembers;
+use crate::m20250601_000004_create_messages::Messages;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Postgres enums are their own type, made before the column that uses it
+        if manager.get_database_backend() == DbBackend::Postgres {
+            manager
+                .create_type(
+                    Type::create()
+                        .as_enum(Role::Enum)
+                        .values([Role::Owner, Role::Admin, Role::Member])
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        // Members of existing chats start out as plain members
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatMembers::Table)
+                    .add_column(
+                        enumeration(
+                            Role::Column,
+                            Role::Enum,
+                            [Role::Owner, Role::Admin, Role::Member],
+                        )
+                        .default("member"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Notices about membership and role changes are stored in the chat as system messages
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Messages::Table)
+                    .add_column(boolean(IsSystem::Column).default(false))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Messages::Table)
+                    .drop_column(IsSystem::Column)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChatMembers::Table)
+                    .drop_column(Role::Column)
+                    .to_owned(),
+            )
+            .await?;
+        if manager.get_database_backend() == DbBackend::Postgres {
+            manager
+                .drop_type(Type::drop().name(Role::Enum).to_owned())
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Role {
+    #[sea_orm(iden = "role")]
+    Enum,
+    #[sea_orm(iden = "role")]
+    Column,
+    Owner,
+    Admin,
+    Member,
+}
+
+#[derive(DeriveIden)]
+enum IsSystem {
+    #[sea_orm(iden = "is_system")]
+    Column,
+}
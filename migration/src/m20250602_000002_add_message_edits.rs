@@ -0,0 +1,76 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20250601_000004_create_messages::Messages;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite takes one column per ALTER TABLE
+        for column in [Edited::EditedAt, Edited::DeletedAt] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Messages::Table)
+                        .add_column(date_time_null(column))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(MessageEdits::Table)
+                    .if_not_exists()
+                    .col(pk_auto(MessageEdits::Id))
+                    .col(integer(MessageEdits::MessageId))
+                    .col(text(MessageEdits::PreviousContent))
+                    .col(date_time(MessageEdits::EditedAt).default(Expr::current_timestamp()))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_message_edits_message")
+                            .from(MessageEdits::Table, MessageEdits::MessageId)
+                            .to(Messages::Table, Messages::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MessageEdits::Table).to_owned())
+            .await?;
+        for column in [Edited::DeletedAt, Edited::EditedAt] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Messages::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+// The columns added to messages
+#[derive(DeriveIden)]
+enum Edited {
+    EditedAt,
+    DeletedAt,
+}
+
+#[derive(DeriveIden)]
+enum MessageEdits {
+    Table,
+    Id,
+    MessageId,
+    PreviousContent,
+    EditedAt,
+}
@@ -0,0 +1,58 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20250601_000004_create_messages::Messages;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Messages::Table)
+                    .add_column(string_len_null(IdempotencyKey::Column, 64))
+                    .to_owned(),
+            )
+            .await?;
+
+        // A retried send finds the message it already stored
+        manager
+            .create_index(
+                Index::create()
+                    .name("uq_messages_idempotency_key")
+                    .table(Messages::Table)
+                    .col(Messages::SenderId)
+                    .col(IdempotencyKey::Column)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("uq_messages_idempotency_key")
+                    .table(Messages::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Messages::Table)
+                    .drop_column(IdempotencyKey::Column)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum IdempotencyKey {
+    #[sea_orm(iden = "idempotency_key")]
+    Column,
+}
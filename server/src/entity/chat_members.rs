@@ -1,5 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.10
 
+use super::sea_orm_active_enums::Role;
 use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
@@ -9,6 +10,7 @@ pub struct Model {
     pub chat_id: i32,
     #[sea_orm(primary_key, auto_increment = false)]
     pub user_id: i32,
+    pub role: Role,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -8,7 +8,7 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
     pub name: Option<String>,
-    pub is_group: i8,
+    pub is_group: bool,
     pub created_at: DateTime,
 }
 
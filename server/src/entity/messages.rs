@@ -12,8 +12,12 @@ pub struct Model {
     pub sender_username: String,
     #[sea_orm(column_type = "Text")]
     pub content: String,
-    pub read: i8,
+    pub is_system: bool,
+    pub read: bool,
     pub timestamp: DateTime,
+    pub edited_at: Option<DateTime>,
+    pub deleted_at: Option<DateTime>,
+    pub idempotency_key: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -26,6 +30,8 @@ pub enum Relation {
         on_delete = "NoAction"
     )]
     Chats,
+    #[sea_orm(has_many = "super::message_edits::Entity")]
+    MessageEdits,
     #[sea_orm(has_many = "super::message_reads::Entity")]
     MessageReads,
     #[sea_orm(
@@ -44,6 +50,12 @@ impl Related<super::chats::Entity> for Entity {
     }
 }
 
+impl Related<super::message_edits::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MessageEdits.def()
+    }
+}
+
 impl Related<super::message_reads::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::MessageReads.def()
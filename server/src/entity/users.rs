@@ -10,6 +10,7 @@ pub struct Model {
     #[sea_orm(unique)]
     pub username: String,
     pub password_hash: String,
+    pub last_seen_at: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -20,6 +21,8 @@ pub enum Relation {
     MessageReads,
     #[sea_orm(has_many = "super::messages::Entity")]
     Messages,
+    #[sea_orm(has_many = "super::sessions::Entity")]
+    Sessions,
 }
 
 impl Related<super::chat_members::Entity> for Entity {
@@ -34,6 +37,12 @@ impl Related<super::message_reads::Entity> for Entity {
     }
 }
 
+impl Related<super::sessions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Sessions.def()
+    }
+}
+
 impl Related<super::chats::Entity> for Entity {
     fn to() -> RelationDef {
         super::chat_members::Relation::Chats.def()
@@ -0,0 +1,449 @@
+use crate::handlers::controllers::{chat_controller, user_controller};
+use crate::handlers::policies::session_policy::Identity;
+use crate::utils::config;
+use chrono::NaiveDateTime;
+use dashmap::mapref::entry::Entry;
+use dashmap::{DashMap, DashSet};
+use sea_orm::DatabaseConnection;
+use shared::client_response::TypingSignal;
+use shared::codec::{self, Codec};
+use shared::models::chat_models::ChatMessage;
+use shared::models::user_models::{Presence, PresenceStatus, User};
+use shared::server_response::ServerEvent;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::AsyncWrite;
+use tokio::sync::{Mutex, Notify};
+use tracing::{error, info};
+
+// Every logged in connection of each user
+pub type LoggedIn = Arc<DashMap<i32, Vec<Binding>>>;
+
+// Who is typing in which chat, keyed by (chat_id, user_id), with the time of
+// their latest signal. Kept in memory only.
+pub type TypingMap = Arc<DashMap<(i32, i32), Instant>>;
+
+// Logged in users who have gone idle. Everyone else in logged_in is online.
+pub type AwayUsers = Arc<DashSet<i32>>;
+
+// Who a connection is logged in as. Set by Login, Register or resuming a
+// session, and used to authorize every other command on the connection.
+pub type ConnectionSession = Arc<Mutex<Option<Identity>>>;
+
+/// A connection's event stream, and the codec the connection negotiated
+pub struct EventStream {
+    send: Box<dyn AsyncWrite + Send + Unpin>,
+    codec: Codec,
+}
+
+impl EventStream {
+    pub fn new(send: impl AsyncWrite + Send + Unpin + 'static, codec: Codec) -> Self {
+        EventStream {
+            send: Box::new(send),
+            codec,
+        }
+    }
+}
+
+/// A logged in connection, as its user's other requests see it: where to
+/// push their events, and how to log it out when its session ends
+#[derive(Clone)]
+pub struct Binding {
+    pub session_id: i32,
+    pub events: Arc<Mutex<EventStream>>,
+    session: ConnectionSession,
+    ended: Arc<Notify>,
+}
+
+/// What every connection shares
+#[derive(Clone)]
+pub struct ServerState {
+    pub db: Arc<DatabaseConnection>,
+    pub logged_in: LoggedIn,
+    pub away: AwayUsers,
+    pub typing: TypingMap,
+}
+
+impl ServerState {
+    pub fn new(db: Arc<DatabaseConnection>) -> Self {
+        ServerState {
+            db,
+            logged_in: Arc::new(DashMap::new()),
+            away: Arc::new(DashSet::new()),
+            typing: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Ids of everyone with at least one connection logged in
+    pub fn logged_in_users(&self) -> Vec<i32> {
+        self.logged_in.iter().map(|r| *r.key()).collect()
+    }
+}
+
+/// One client connection, as the requests made on it see it
+#[derive(Clone)]
+pub struct Connection {
+    pub state: ServerState,
+    pub remote: SocketAddr,
+    pub session: ConnectionSession,
+    pub events: Arc<Mutex<EventStream>>,
+    // Notified when the connection's session is ended from another
+    // connection, so it can be closed
+    pub ended: Arc<Notify>,
+}
+
+impl Connection {
+    pub fn new(state: ServerState, remote: SocketAddr, events: EventStream) -> Self {
+        Connection {
+            state,
+            remote,
+            session: Arc::new(Mutex::new(None)),
+            events: Arc::new(Mutex::new(events)),
+            ended: Arc::new(Notify::new()),
+        }
+    }
+
+    fn binding(&self, session_id: i32) -> Binding {
+        Binding {
+            session_id,
+            events: self.events.clone(),
+            session: self.session.clone(),
+            ended: self.ended.clone(),
+        }
+    }
+
+    pub fn db(&self) -> Arc<DatabaseConnection> {
+        self.state.db.clone()
+    }
+}
+
+/// Pushes an event to every open event stream of the given users, skipping
+/// the stream of the connection that caused it (it already has the response)
+pub async fn notify_users(
+    user_ids: Vec<i32>,
+    event: ServerEvent,
+    state: &ServerState,
+    origin: Option<&Connection>,
+) {
+    // Encoded once per codec in use
+    let mut encoded: HashMap<Codec, Vec<u8>> = HashMap::new();
+
+    for user_id in user_ids {
+        // Clone the streams out so the map isn't locked while writing
+        let streams = match state.logged_in.get(&user_id) {
+            Some(streams) => streams.clone(),
+            None => continue,
+        };
+
+        for binding in streams.iter() {
+            if origin.is_some_and(|origin| Arc::ptr_eq(&origin.events, &binding.events)) {
+                continue;
+            }
+
+            let mut stream_lock = binding.events.lock().await;
+            let EventStream { send, codec } = &mut *stream_lock;
+            let bytes = encoded.entry(*codec).or_insert_with(|| {
+                codec
+                    .encode(&event)
+                    .expect("Failed to serialize server event")
+            });
+            info!("Notifying user {} of {:?}", user_id, event);
+            if let Err(e) = codec::write_frame(send, bytes).await {
+                error!("Failed to notify user {}: {}", user_id, e);
+                continue;
+            }
+        }
+    }
+}
+
+/// Tells the receiver of a friend request about it, or about the new
+/// friendship if the request matched one they had already sent
+pub async fn notify_friend_request(sender_id: i32, receiver: User, origin: &Connection) {
+    let state = &origin.state;
+    let sender = match user_controller::get_user_info(sender_id, state.db.clone()).await {
+        Ok(sender) => sender,
+        Err(_) => return,
+    };
+
+    match user_controller::are_friends(sender.id, receiver.id, state.db.clone()).await {
+        Ok(true) => {
+            let event = ServerEvent::FriendRequestAccepted {
+                friend: sender.clone(),
+            };
+            notify_users(vec![receiver.id], event, state, None).await;
+            let event = ServerEvent::FriendRequestAccepted { friend: receiver };
+            notify_users(vec![sender.id], event, state, Some(origin)).await;
+        }
+        Ok(false) => {
+            let event = ServerEvent::FriendRequestReceived { sender };
+            notify_users(vec![receiver.id], event, state, None).await;
+        }
+        Err(e) => error!("Failed to check friendship: {}", e),
+    }
+}
+
+/// Sends each user the chat they were added to, as it appears in their own list
+pub async fn notify_chat_created(
+    chat_id: i32,
+    user_ids: Vec<i32>,
+    state: &ServerState,
+    origin: Option<&Connection>,
+) {
+    for user_id in user_ids {
+        if let Ok(chat) = chat_controller::get_user_chat(chat_id, user_id, state.db.clone()).await {
+            let event = ServerEvent::ChatCreated { chat };
+            notify_users(vec![user_id], event, state, origin).await;
+        }
+    }
+}
+
+/// Sends every member the chat's new details, as it appears in their own list
+pub async fn notify_chat_updated(chat_id: i32, state: &ServerState) {
+    let user_ids = match chat_controller::get_chat_user_ids(chat_id, state.db.clone()).await {
+        Ok(user_ids) => user_ids,
+        Err(_) => return,
+    };
+
+    for user_id in user_ids {
+        if let Ok(chat) = chat_controller::get_user_chat(chat_id, user_id, state.db.clone()).await {
+            let event = ServerEvent::ChatUpdated { chat };
+            notify_users(vec![user_id], event, state, None).await;
+        }
+    }
+}
+
+/// Pushes an event to every member of the chat
+pub async fn notify_chat_members(
+    chat_id: i32,
+    event: ServerEvent,
+    state: &ServerState,
+    origin: Option<&Connection>,
+) {
+    if let Ok(user_ids) = chat_controller::get_chat_user_ids(chat_id, state.db.clone()).await {
+        notify_users(user_ids, event, state, origin).await;
+    }
+}
+
+/// Pushes a new message to the chat's members, except the excluded users
+pub async fn notify_chat_message(
+    chat_id: i32,
+    message: ChatMessage,
+    excluded: &[i32],
+    state: &ServerState,
+    origin: Option<&Connection>,
+) {
+    let user_ids = match chat_controller::get_chat_user_ids(chat_id, state.db.clone()).await {
+        Ok(user_ids) => user_ids,
+        Err(_) => return,
+    };
+
+    let user_ids = user_ids
+        .into_iter()
+        .filter(|id| !excluded.contains(id))
+        .collect();
+    // Only the sender has read a new message
+    let message = ChatMessage {
+        read: false,
+        ..message
+    };
+    let event = ServerEvent::NewMessage { chat_id, message };
+    notify_users(user_ids, event, state, origin).await;
+}
+
+/// Tells a chat's other online members when a user starts or stops typing.
+/// Repeated signals only refresh the timeout, and an indicator that isn't
+/// refreshed within the configured typing timeout is cleared.
+pub async fn handle_typing(signal: TypingSignal, user_id: i32, state: ServerState) {
+    let key = (signal.chat_id, user_id);
+
+    if !signal.typing {
+        if state.typing.remove(&key).is_some() {
+            notify_typing(signal.chat_id, user_id, false, &state).await;
+        }
+        return;
+    }
+
+    let now = Instant::now();
+    if state.typing.insert(key, now).is_none() {
+        // Only members can type in a chat
+        if !notify_typing(signal.chat_id, user_id, true, &state).await {
+            state.typing.remove(&key);
+            return;
+        }
+    }
+
+    // Expire the indicator unless a later signal has refreshed it
+    tokio::spawn(async move {
+        tokio::time::sleep(config::current().typing_timeout()).await;
+        if state
+            .typing
+            .remove_if(&key, |_, last| *last == now)
+            .is_some()
+        {
+            notify_typing(key.0, user_id, false, &state).await;
+        }
+    });
+}
+
+/// Pushes a typing event to the chat's other members. Returns false if the
+/// user isn't in the chat.
+async fn notify_typing(chat_id: i32, user_id: i32, typing: bool, state: &ServerState) -> bool {
+    let (username, user_ids) =
+        match chat_controller::get_typing_recipients(chat_id, user_id, state.db.clone()).await {
+            Ok(recipients) => recipients,
+            Err(_) => return false,
+        };
+
+    let event = ServerEvent::Typing {
+        chat_id,
+        user_id,
+        username,
+        typing,
+    };
+    notify_users(user_ids, event, state, None).await;
+    true
+}
+
+/// A user's live status, from their sessions and whether they're idle
+pub fn presence_status(user_id: i32, state: &ServerState) -> PresenceStatus {
+    if !state.logged_in.contains_key(&user_id) {
+        PresenceStatus::Offline
+    } else if state.away.contains(&user_id) {
+        PresenceStatus::Away
+    } else {
+        PresenceStatus::Online
+    }
+}
+
+/// Tells the user's online friends about their new presence
+pub async fn notify_presence(
+    user_id: i32,
+    status: PresenceStatus,
+    last_seen_at: Option<NaiveDateTime>,
+    state: &ServerState,
+) {
+    let friend_ids = match user_controller::get_friend_ids(user_id, state.db.clone()).await {
+        Ok(friend_ids) => friend_ids,
+        Err(e) => {
+            error!("Failed to get friends of user {}: {}", user_id, e);
+            return;
+        }
+    };
+
+    let presence = Presence {
+        user_id,
+        status,
+        last_seen_at,
+    };
+    notify_users(
+        friend_ids,
+        ServerEvent::PresenceChanged { presence },
+        state,
+        None,
+    )
+    .await;
+}
+
+/// Records when the user's last session ended and tells their friends
+async fn went_offline(user_id: i32, state: &ServerState) {
+    state.away.remove(&user_id);
+    let last_seen_at = match user_controller::record_last_seen(user_id, state.db.clone()).await {
+        Ok(last_seen_at) => Some(last_seen_at),
+        Err(e) => {
+            error!("Failed to record last seen for user {}: {}", user_id, e);
+            None
+        }
+    };
+    notify_presence(user_id, PresenceStatus::Offline, last_seen_at, state).await;
+}
+
+/// Logs the connection in, registering its event stream under the user.
+/// Any session it was already logged in as is logged out first.
+pub async fn bind_session(identity: Identity, connection: &Connection) {
+    unbind_session(connection).await;
+
+    let state = &connection.state;
+    // Checked and changed under one lock, so a concurrent login can't lose a stream
+    let first = match state.logged_in.entry(identity.user_id) {
+        Entry::Occupied(mut streams) => {
+            streams
+                .get_mut()
+                .push(connection.binding(identity.session_id));
+            false
+        }
+        Entry::Vacant(entry) => {
+            entry.insert(vec![connection.binding(identity.session_id)]);
+            true
+        }
+    };
+    connection.session.lock().await.replace(identity);
+    // First session, so friends see them come online
+    if first {
+        notify_presence(identity.user_id, PresenceStatus::Online, None, state).await;
+    }
+}
+
+/// Logs the connection out and drops its event stream. The user goes offline
+/// once their last connection is gone.
+pub async fn unbind_session(connection: &Connection) {
+    let Some(identity) = connection.session.lock().await.take() else {
+        return;
+    };
+    unregister(identity.user_id, &connection.events, &connection.state).await;
+}
+
+/// Logs out every connection bound to the session, once it is revoked or
+/// logged out. All of them but the origin, which is still answering the
+/// request that ended it, are closed.
+pub async fn end_session(identity: Identity, state: &ServerState, origin: Option<&Connection>) {
+    let bound: Vec<Binding> = match state.logged_in.get(&identity.user_id) {
+        Some(bindings) => bindings
+            .iter()
+            .filter(|b| b.session_id == identity.session_id)
+            .cloned()
+            .collect(),
+        None => Vec::new(),
+    };
+
+    for binding in bound {
+        {
+            // Unless it has since logged in as someone else
+            let mut session = binding.session.lock().await;
+            if *session != Some(identity) {
+                continue;
+            }
+            session.take();
+        }
+        unregister(identity.user_id, &binding.events, state).await;
+
+        if !origin.is_some_and(|origin| Arc::ptr_eq(&origin.events, &binding.events)) {
+            binding.ended.notify_one();
+        }
+    }
+}
+
+/// Drops a connection's event stream from its user, and the user with their
+/// last one
+async fn unregister(user_id: i32, events: &Arc<Mutex<EventStream>>, state: &ServerState) {
+    // Checked and changed under one lock, so a concurrent logout can't skip going offline
+    let last = match state.logged_in.entry(user_id) {
+        Entry::Occupied(mut bindings) => {
+            bindings
+                .get_mut()
+                .retain(|binding| !Arc::ptr_eq(&binding.events, events));
+            if bindings.get().is_empty() {
+                bindings.remove();
+                true
+            } else {
+                false
+            }
+        }
+        Entry::Vacant(_) => false,
+    };
+
+    if last {
+        went_offline(user_id, state).await;
+    }
+}
@@ -1,7 +1,8 @@
+use crate::handlers::policies::session_policy::{self, Identity};
 use crate::handlers::services::auth_service;
 use crate::utils::errors::server_error::ServerError;
 use sea_orm::DatabaseConnection;
-use shared::models::auth_models::AuthResponseModel;
+use shared::models::auth_models::{AuthResponseModel, SessionList};
 use shared::models::server_models::ServerResponseModel;
 use std::sync::Arc;
 
@@ -24,9 +25,53 @@ pub async fn login(
 }
 
 pub async fn update_password(
-    jwt: String,
+    user_id: i32,
     new_password: String,
     db: Arc<DatabaseConnection>,
 ) -> Result<ServerResponseModel, ServerError> {
-    auth_service::update_password(jwt, new_password, db).await
+    auth_service::update_password(user_id, new_password, db).await
+}
+
+pub async fn refresh_token(
+    refresh_token: String,
+    db: Arc<DatabaseConnection>,
+) -> Result<AuthResponseModel, ServerError> {
+    auth_service::refresh_token(refresh_token, db).await
+}
+
+pub async fn resume_session(
+    jwt: String,
+    db: Arc<DatabaseConnection>,
+) -> Result<Identity, ServerError> {
+    auth_service::resume_session(jwt, db).await
+}
+
+pub async fn list_sessions(
+    identity: Identity,
+    db: Arc<DatabaseConnection>,
+) -> Result<SessionList, ServerError> {
+    auth_service::list_sessions(identity, db).await
+}
+
+pub async fn revoke_session(
+    user_id: i32,
+    session_id: i32,
+    db: Arc<DatabaseConnection>,
+) -> Result<ServerResponseModel, ServerError> {
+    auth_service::revoke_session(user_id, session_id, db).await
+}
+
+pub async fn logout(
+    identity: Identity,
+    db: Arc<DatabaseConnection>,
+) -> Result<ServerResponseModel, ServerError> {
+    auth_service::logout(identity, db).await
+}
+
+/// Fails with NotLoggedIn once the connection's session has been revoked or has expired
+pub async fn ensure_active(
+    identity: Identity,
+    db: Arc<DatabaseConnection>,
+) -> Result<(), ServerError> {
+    session_policy::ensure_active(identity, db).await
 }
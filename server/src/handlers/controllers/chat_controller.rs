@@ -1,80 +1,103 @@
 use crate::handlers::services::chat_service;
 use crate::utils::errors::server_error::ServerError;
 use sea_orm::DatabaseConnection;
-use shared::models::chat_models::{ChatList, ChatMessages, Count};
-use shared::models::server_models::ServerResponseModel;
+use shared::models::chat_models::{
+    Chat, ChatCursor, ChatList, ChatMembers, ChatMessage, ChatMessages, ChatRole, Count,
+    MessageReceipts, ReadReceipt,
+};
 use std::sync::Arc;
 
 pub async fn get_user_chats(
-    jwt: String,
-    page: u64,
-    page_size: u64,
+    user_id: i32,
+    before: Option<ChatCursor>,
+    limit: u64,
     db: Arc<DatabaseConnection>,
 ) -> Result<ChatList, ServerError> {
     // Get a list of users' chats in timestamp descending order
-    chat_service::get_user_chats(jwt, page, page_size, db.clone()).await
+    chat_service::get_user_chats(user_id, before, limit, db.clone()).await
 }
 
 pub async fn get_chat_messages(
-    jwt: String,
+    user_id: i32,
     chat_id: i32,
-    page: u64,
-    page_size: u64,
+    before_id: Option<i32>,
+    after_id: Option<i32>,
+    limit: u64,
     db: Arc<DatabaseConnection>,
 ) -> Result<ChatMessages, ServerError> {
-    // Get a list of users' chats in timestamp descending order
-    chat_service::get_chat_messages(jwt, chat_id, page, page_size, db.clone()).await
+    // Get a batch of messages on one side of a message id
+    chat_service::get_chat_messages(user_id, chat_id, before_id, after_id, limit, db.clone()).await
 }
 
 pub async fn send_message(
-    jwt: String,
+    user_id: i32,
     chat_id: i32,
     content: String,
+    idempotency_key: Option<String>,
     db: Arc<DatabaseConnection>,
-) -> Result<ServerResponseModel, ServerError> {
-    chat_service::send_message(jwt, chat_id, content, db.clone()).await
+) -> Result<(ChatMessage, bool), ServerError> {
+    chat_service::send_message(user_id, chat_id, content, idempotency_key, db.clone()).await
 }
 
-pub async fn get_chat_page_count(
-    jwt: String,
+pub async fn edit_message(
+    user_id: i32,
     chat_id: i32,
-    page_size: u64,
+    message_id: i32,
+    content: String,
     db: Arc<DatabaseConnection>,
-) -> Result<Count, ServerError> {
-    chat_service::get_chat_page_count(jwt, chat_id, page_size, db.clone()).await
+) -> Result<ChatMessage, ServerError> {
+    chat_service::edit_message(user_id, chat_id, message_id, content, db.clone()).await
 }
 
-pub async fn get_chats_page_count(
-    jwt: String,
-    page_size: u64,
+pub async fn delete_message(
+    user_id: i32,
+    chat_id: i32,
+    message_id: i32,
     db: Arc<DatabaseConnection>,
-) -> Result<Count, ServerError> {
-    chat_service::get_chats_page_count(jwt, page_size, db.clone()).await
+) -> Result<ChatMessage, ServerError> {
+    chat_service::delete_message(user_id, chat_id, message_id, db.clone()).await
 }
 
 pub async fn create_chat(
-    jwt: String,
+    user_id: i32,
     name: Option<String>,
     is_group: bool,
     member_ids: Vec<i32>,
     db: Arc<DatabaseConnection>,
-) -> Result<ServerResponseModel, ServerError> {
-    chat_service::create_chat(jwt, name, is_group, member_ids, db.clone()).await
+) -> Result<Chat, ServerError> {
+    chat_service::create_chat(user_id, name, is_group, member_ids, db.clone()).await
 }
 
 pub async fn get_unread_message_count(
-    jwt: String,
+    user_id: i32,
     db: Arc<DatabaseConnection>,
 ) -> Result<Count, ServerError> {
-    chat_service::get_unread_message_count(jwt, db.clone()).await
+    chat_service::get_unread_message_count(user_id, db.clone()).await
+}
+
+pub async fn get_unread_chat_message_count(
+    user_id: i32,
+    chat_id: i32,
+    db: Arc<DatabaseConnection>,
+) -> Result<Count, ServerError> {
+    chat_service::get_chat_unread_count(user_id, chat_id, db.clone()).await
 }
 
 pub async fn mark_messages_read(
-    jwt: String,
+    user_id: i32,
+    chat_id: i32,
+    db: Arc<DatabaseConnection>,
+) -> Result<Option<ReadReceipt>, ServerError> {
+    chat_service::mark_messages_read(user_id, chat_id, db.clone()).await
+}
+
+pub async fn get_message_receipts(
+    user_id: i32,
     chat_id: i32,
+    message_id: i32,
     db: Arc<DatabaseConnection>,
-) -> Result<ServerResponseModel, ServerError> {
-    chat_service::mark_messages_read(jwt, chat_id, db.clone()).await
+) -> Result<MessageReceipts, ServerError> {
+    chat_service::get_message_receipts(user_id, chat_id, message_id, db.clone()).await
 }
 
 pub async fn get_chat_user_ids(
@@ -83,3 +106,82 @@ pub async fn get_chat_user_ids(
 ) -> Result<Vec<i32>, ServerError> {
     chat_service::get_chat_user_ids(chat_id, db.clone()).await
 }
+
+pub async fn get_typing_recipients(
+    chat_id: i32,
+    user_id: i32,
+    db: Arc<DatabaseConnection>,
+) -> Result<(String, Vec<i32>), ServerError> {
+    chat_service::get_typing_recipients(chat_id, user_id, db.clone()).await
+}
+
+pub async fn get_user_chat(
+    chat_id: i32,
+    user_id: i32,
+    db: Arc<DatabaseConnection>,
+) -> Result<Chat, ServerError> {
+    chat_service::get_user_chat(chat_id, user_id, db.clone()).await
+}
+
+pub async fn get_chat_members(
+    user_id: i32,
+    chat_id: i32,
+    db: Arc<DatabaseConnection>,
+) -> Result<ChatMembers, ServerError> {
+    chat_service::get_chat_members(user_id, chat_id, db.clone()).await
+}
+
+pub async fn add_chat_members(
+    user_id: i32,
+    chat_id: i32,
+    member_ids: Vec<i32>,
+    db: Arc<DatabaseConnection>,
+) -> Result<ChatMessage, ServerError> {
+    chat_service::add_chat_members(user_id, chat_id, member_ids, db.clone()).await
+}
+
+pub async fn remove_chat_member(
+    requester_id: i32,
+    chat_id: i32,
+    user_id: i32,
+    db: Arc<DatabaseConnection>,
+) -> Result<ChatMessage, ServerError> {
+    chat_service::remove_chat_member(requester_id, chat_id, user_id, db.clone()).await
+}
+
+pub async fn leave_chat(
+    user_id: i32,
+    chat_id: i32,
+    db: Arc<DatabaseConnection>,
+) -> Result<ChatMessage, ServerError> {
+    chat_service::leave_chat(user_id, chat_id, db.clone()).await
+}
+
+pub async fn rename_chat(
+    user_id: i32,
+    chat_id: i32,
+    name: String,
+    db: Arc<DatabaseConnection>,
+) -> Result<ChatMessage, ServerError> {
+    chat_service::rename_chat(user_id, chat_id, name, db.clone()).await
+}
+
+pub async fn transfer_ownership(
+    user_id: i32,
+    chat_id: i32,
+    new_owner_id: i32,
+    db: Arc<DatabaseConnection>,
+) -> Result<ChatMessage, ServerError> {
+    chat_service::transfer_ownership(user_id, chat_id, new_owner_id, db.clone()).await
+}
+
+pub async fn set_chat_member_role(
+    requester_id: i32,
+    chat_id: i32,
+    user_id: i32,
+    role: ChatRole,
+    db: Arc<DatabaseConnection>,
+) -> Result<ChatMessage, ServerError> {
+    chat_service::set_chat_member_role(requester_id, chat_id, user_id, role.into(), db.clone())
+        .await
+}
@@ -1,67 +1,136 @@
+use chrono::NaiveDateTime;
 use sea_orm::DatabaseConnection;
 use std::sync::Arc;
 
 use crate::handlers::services::user_service;
 use crate::utils::errors::server_error::ServerError;
 use shared::models::server_models::ServerResponseModel;
-use shared::models::user_models::{FriendRequestList, User, UserList};
+use shared::models::user_models::{
+    FriendRequestList, PresenceList, PresenceStatus, User, UserList,
+};
+use std::collections::HashMap;
 
-pub async fn get_user_info(jwt: String, db: Arc<DatabaseConnection>) -> Result<User, ServerError> {
+pub async fn get_user_info(user_id: i32, db: Arc<DatabaseConnection>) -> Result<User, ServerError> {
     // Use user service to get users basic info
-    user_service::get_info(jwt, db).await
+    user_service::get_info(user_id, db).await
 }
 
 pub async fn get_user_by_username(
+    user_id: i32,
     username: String,
     db: Arc<DatabaseConnection>,
 ) -> Result<User, ServerError> {
-    user_service::get_user_by_username(username, db).await
+    user_service::get_user_by_username(user_id, username, db).await
 }
 
 /// Send a friend request
 pub async fn add_friend(
-    jwt: String,
+    user_id: i32,
     friend_id: i32,
     db: Arc<DatabaseConnection>,
 ) -> Result<ServerResponseModel, ServerError> {
-    user_service::send_friend_request(jwt, friend_id, db).await
+    user_service::send_friend_request(user_id, friend_id, db).await
 }
 
 pub async fn remove_friend(
-    jwt: String,
+    user_id: i32,
     friend_id: i32,
     db: Arc<DatabaseConnection>,
 ) -> Result<ServerResponseModel, ServerError> {
-    user_service::remove_friend(jwt, friend_id, db).await
+    user_service::remove_friend(user_id, friend_id, db).await
 }
 
 pub async fn get_friends(
-    jwt: String,
+    user_id: i32,
     db: Arc<DatabaseConnection>,
 ) -> Result<UserList, ServerError> {
     // Returns UserList of friends, which is a JSON vector of User JSON models
-    user_service::get_friends(jwt, db).await
+    user_service::get_friends(user_id, db).await
 }
 
 pub async fn get_friend_requests(
-    jwt: String,
+    user_id: i32,
     db: Arc<DatabaseConnection>,
 ) -> Result<FriendRequestList, ServerError> {
-    user_service::get_friend_requests(jwt, db).await
+    user_service::get_friend_requests(user_id, db).await
 }
 
 pub async fn accept_friend_request(
-    jwt: String,
+    user_id: i32,
     sender_id: i32,
     db: Arc<DatabaseConnection>,
 ) -> Result<ServerResponseModel, ServerError> {
-    user_service::accept_friend_request(jwt, sender_id, db).await
+    user_service::accept_friend_request(user_id, sender_id, db).await
 }
 
 pub async fn decline_friend_request(
-    jwt: String,
+    user_id: i32,
     sender_id: i32,
     db: Arc<DatabaseConnection>,
 ) -> Result<ServerResponseModel, ServerError> {
-    user_service::decline_friend_request(jwt, sender_id, db).await
+    user_service::decline_friend_request(user_id, sender_id, db).await
+}
+
+pub async fn cancel_friend_request(
+    user_id: i32,
+    receiver_id: i32,
+    db: Arc<DatabaseConnection>,
+) -> Result<ServerResponseModel, ServerError> {
+    user_service::cancel_friend_request(user_id, receiver_id, db).await
+}
+
+pub async fn block_user(
+    user_id: i32,
+    blocked_id: i32,
+    db: Arc<DatabaseConnection>,
+) -> Result<ServerResponseModel, ServerError> {
+    user_service::block_user(user_id, blocked_id, db).await
+}
+
+pub async fn unblock_user(
+    user_id: i32,
+    blocked_id: i32,
+    db: Arc<DatabaseConnection>,
+) -> Result<ServerResponseModel, ServerError> {
+    user_service::unblock_user(user_id, blocked_id, db).await
+}
+
+pub async fn get_blocked_users(
+    user_id: i32,
+    db: Arc<DatabaseConnection>,
+) -> Result<UserList, ServerError> {
+    // Returns UserList of the users this user has blocked
+    user_service::get_blocked_users(user_id, db).await
+}
+
+pub async fn get_friend_ids(
+    user_id: i32,
+    db: Arc<DatabaseConnection>,
+) -> Result<Vec<i32>, ServerError> {
+    user_service::get_friend_ids(user_id, db).await
+}
+
+pub async fn get_presence(
+    user_id: i32,
+    user_ids: Vec<i32>,
+    live: HashMap<i32, PresenceStatus>,
+    db: Arc<DatabaseConnection>,
+) -> Result<PresenceList, ServerError> {
+    // Returns the presence of the requested friends
+    user_service::get_presence(user_id, user_ids, live, db).await
+}
+
+pub async fn record_last_seen(
+    user_id: i32,
+    db: Arc<DatabaseConnection>,
+) -> Result<NaiveDateTime, ServerError> {
+    user_service::record_last_seen(user_id, db).await
+}
+
+pub async fn are_friends(
+    user_id: i32,
+    other_id: i32,
+    db: Arc<DatabaseConnection>,
+) -> Result<bool, ServerError> {
+    user_service::are_friends(user_id, other_id, db).await
 }
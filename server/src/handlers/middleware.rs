@@ -0,0 +1,169 @@
+use crate::handlers::connections::unbind_session;
+use crate::handlers::controllers::auth_controller;
+use crate::handlers::router::{build_response, Access, Middleware, Next, Request};
+use crate::utils::errors::server_error::ServerError;
+use async_trait::async_trait;
+use dashmap::DashMap;
+use shared::server_response::ServerResponse;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+// Requests slower than this are logged as warnings
+const SLOW_REQUEST: Duration = Duration::from_secs(1);
+
+// Addresses tracked before idle ones are forgotten
+const MAX_TRACKED_ADDRESSES: usize = 10_000;
+
+fn error_response(e: ServerError) -> ServerResponse {
+    build_response::<(), _>(Err(e), None, "")
+}
+
+/// Lets commands that need a login through only while the connection's
+/// session is active, and tells their handler who it is logged in as
+pub struct Authentication;
+
+#[async_trait]
+impl Middleware for Authentication {
+    async fn handle(&self, mut request: Request, next: Next<'_>) -> ServerResponse {
+        if request.access == Access::Public {
+            return next.run(request).await;
+        }
+
+        let connection = &request.connection;
+        let Some(identity) = *connection.session.lock().await else {
+            return error_response(ServerError::NotLoggedIn);
+        };
+        // A session revoked elsewhere logs this connection out too
+        if let Err(e) = auth_controller::ensure_active(identity, connection.db()).await {
+            if matches!(e, ServerError::NotLoggedIn) {
+                unbind_session(connection).await;
+            }
+            return error_response(e);
+        }
+
+        request.identity = Some(identity);
+        next.run(request).await
+    }
+}
+
+/// Logs each command, who sent it and how it went. Only the command's name is
+/// logged, never its fields.
+pub struct RequestLogging;
+
+#[async_trait]
+impl Middleware for RequestLogging {
+    async fn handle(&self, request: Request, next: Next<'_>) -> ServerResponse {
+        let name = request.command.name();
+        let remote = request.connection.remote;
+        let user = *request.connection.session.lock().await;
+        match user {
+            Some(identity) => info!("{} from user {} at {}", name, identity.user_id, remote),
+            None => info!("{} from {}", name, remote),
+        }
+
+        let response = next.run(request).await;
+        if !response.success {
+            info!(
+                "{} failed: {}",
+                name,
+                response.message.as_deref().unwrap_or_default()
+            );
+        }
+        response
+    }
+}
+
+/// Logs how long each command took, warning about slow ones
+pub struct Timing;
+
+#[async_trait]
+impl Middleware for Timing {
+    async fn handle(&self, request: Request, next: Next<'_>) -> ServerResponse {
+        let name = request.command.name();
+        let start = Instant::now();
+        let response = next.run(request).await;
+
+        let elapsed = start.elapsed();
+        if elapsed >= SLOW_REQUEST {
+            warn!("{} took {:?}", name, elapsed);
+        } else {
+            debug!("{} took {:?}", name, elapsed);
+        }
+        response
+    }
+}
+
+/// Limits how fast each client address can send commands. Every address has
+/// a bucket of `burst` requests that refills at `per_second`.
+pub struct RateLimit {
+    per_second: f64,
+    burst: f64,
+    buckets: DashMap<IpAddr, Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, per_second: f64, burst: f64, now: Instant) {
+        let earned = now.duration_since(self.updated).as_secs_f64() * per_second;
+        self.tokens = (self.tokens + earned).min(burst);
+        self.updated = now;
+    }
+}
+
+impl RateLimit {
+    pub fn new(per_second: u32, burst: u32) -> Self {
+        RateLimit {
+            per_second: per_second as f64,
+            burst: burst as f64,
+            buckets: DashMap::new(),
+        }
+    }
+
+    /// Takes one request from the address's bucket, failing with RateLimited
+    /// once it's empty
+    pub fn check(&self, addr: IpAddr) -> Result<(), ServerError> {
+        let now = Instant::now();
+        if self.buckets.len() >= MAX_TRACKED_ADDRESSES {
+            self.forget_idle(now);
+        }
+
+        let mut bucket = self.buckets.entry(addr).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        bucket.refill(self.per_second, self.burst, now);
+        if bucket.tokens < 1.0 {
+            return Err(ServerError::RateLimited);
+        }
+        bucket.tokens -= 1.0;
+        Ok(())
+    }
+
+    // Addresses whose buckets have filled back up are the same as new ones
+    fn forget_idle(&self, now: Instant) {
+        self.buckets.retain(|_, bucket| {
+            bucket.refill(self.per_second, self.burst, now);
+            bucket.tokens < self.burst
+        });
+    }
+}
+
+#[async_trait]
+impl Middleware for RateLimit {
+    async fn handle(&self, request: Request, next: Next<'_>) -> ServerResponse {
+        if let Err(e) = self.check(request.connection.remote.ip()) {
+            warn!(
+                "Rate limited {} from {}",
+                request.command.name(),
+                request.connection.remote
+            );
+            return error_response(e);
+        }
+        next.run(request).await
+    }
+}
@@ -1,3 +1,8 @@
+pub mod connections;
 pub mod controllers;
+pub mod middleware;
+pub mod policies;
 pub mod repositories;
+pub mod router;
+pub mod routes;
 pub mod services;
@@ -0,0 +1,91 @@
+use crate::handlers::repositories::store::Repositories;
+use crate::{entity, utils};
+use std::collections::HashSet;
+use utils::errors::server_error::ServerError;
+
+/// Whether either user has blocked the other
+pub async fn is_blocked<R: Repositories + ?Sized>(
+    user_id: i32,
+    other_id: i32,
+    repos: &R,
+) -> Result<bool, ServerError> {
+    let block = repos.users().get_user_blocked(user_id, other_id).await?;
+    Ok(block.is_some())
+}
+
+/// Fails with ActionBlocked if either user has blocked the other
+pub async fn ensure_not_blocked<R: Repositories + ?Sized>(
+    user_id: i32,
+    other_id: i32,
+    repos: &R,
+) -> Result<(), ServerError> {
+    if is_blocked(user_id, other_id, repos).await? {
+        return Err(ServerError::ActionBlocked);
+    }
+    Ok(())
+}
+
+/// Fails with ActionBlocked if the user and any of the others have a block
+/// between them
+pub async fn ensure_not_blocked_by_any<R: Repositories + ?Sized>(
+    user_id: i32,
+    other_ids: &[i32],
+    repos: &R,
+) -> Result<(), ServerError> {
+    let blocked = get_block_ids(user_id, repos).await?;
+    if other_ids.iter().any(|id| blocked.contains(id)) {
+        return Err(ServerError::ActionBlocked);
+    }
+    Ok(())
+}
+
+/// Ids of every user who has blocked, or been blocked by, the user
+pub async fn get_block_ids<R: Repositories + ?Sized>(
+    user_id: i32,
+    repos: &R,
+) -> Result<HashSet<i32>, ServerError> {
+    let blocks = repos.users().get_user_blocks(user_id).await?;
+    Ok(blocks
+        .into_iter()
+        .map(|b| {
+            if b.user_id == user_id {
+                b.blocked_id
+            } else {
+                b.user_id
+            }
+        })
+        .collect())
+}
+
+/// Whether the chat is a 1:1 chat whose members have a block between them,
+/// in which case it is read-only
+pub async fn is_chat_read_only<R: Repositories + ?Sized>(
+    chat: &entity::chats::Model,
+    user_id: i32,
+    repos: &R,
+) -> Result<bool, ServerError> {
+    if chat.is_group {
+        return Ok(false);
+    }
+
+    let member_ids = repos.chats().get_chat_user_ids(chat.id).await?;
+    let others: Vec<i32> = member_ids.into_iter().filter(|id| *id != user_id).collect();
+
+    match ensure_not_blocked_by_any(user_id, &others, repos).await {
+        Ok(()) => Ok(false),
+        Err(ServerError::ActionBlocked) => Ok(true),
+        Err(e) => Err(e),
+    }
+}
+
+/// Fails with ActionBlocked if the user may not send messages to the chat
+pub async fn ensure_can_message<R: Repositories + ?Sized>(
+    chat: &entity::chats::Model,
+    sender_id: i32,
+    repos: &R,
+) -> Result<(), ServerError> {
+    if is_chat_read_only(chat, sender_id, repos).await? {
+        return Err(ServerError::ActionBlocked);
+    }
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use crate::entity::sea_orm_active_enums::Role;
+use crate::handlers::repositories::store::Repositories;
+use crate::{entity, utils};
+use shared::models::chat_models::ChatRole;
+use utils::errors::server_error::ServerError;
+
+/// A chat whose membership has been checked for the requesting user.
+/// Every chat-scoped service call should start by building one of these.
+#[derive(Debug, Clone)]
+pub struct AuthorizedChat {
+    pub chat: entity::chats::Model,
+    pub user_id: i32,
+    pub role: Role,
+}
+
+impl AuthorizedChat {
+    /// Confirms the user is a member of the chat. Chats that don't exist are
+    /// also Forbidden, so non-members can't probe for chat ids.
+    pub async fn for_user<R: Repositories + ?Sized>(
+        user_id: i32,
+        chat_id: i32,
+        repos: &R,
+    ) -> Result<Self, ServerError> {
+        let member = repos
+            .chats()
+            .get_chat_member(chat_id, user_id)
+            .await?
+            .ok_or(ServerError::Forbidden)?;
+
+        let chat = repos
+            .chats()
+            .get_chat_by_id(chat_id)
+            .await?
+            .ok_or(ServerError::Forbidden)?;
+
+        Ok(AuthorizedChat {
+            chat,
+            user_id,
+            role: member.role,
+        })
+    }
+
+    /// Fails unless the chat is a group chat, since 1:1 chats can't be managed
+    pub fn ensure_group(&self) -> Result<(), ServerError> {
+        if !self.chat.is_group {
+            return Err(ServerError::RequestInvalid(
+                "Only group chats can be managed".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Fails with Forbidden unless the user's role is at least the given one
+    pub fn ensure_role(&self, role: Role) -> Result<(), ServerError> {
+        if rank(&self.role) < rank(&role) {
+            return Err(ServerError::Forbidden);
+        }
+        Ok(())
+    }
+
+    /// Fails with Forbidden unless the user's role is above the given one
+    pub fn ensure_outranks(&self, role: &Role) -> Result<(), ServerError> {
+        if rank(&self.role) <= rank(role) {
+            return Err(ServerError::Forbidden);
+        }
+        Ok(())
+    }
+}
+
+fn rank(role: &Role) -> u8 {
+    match role {
+        Role::Owner => 2,
+        Role::Admin => 1,
+        Role::Member => 0,
+    }
+}
+
+impl From<Role> for ChatRole {
+    fn from(role: Role) -> Self {
+        match role {
+            Role::Owner => ChatRole::Owner,
+            Role::Admin => ChatRole::Admin,
+            Role::Member => ChatRole::Member,
+        }
+    }
+}
+
+impl From<ChatRole> for Role {
+    fn from(role: ChatRole) -> Self {
+        match role {
+            ChatRole::Owner => Role::Owner,
+            ChatRole::Admin => Role::Admin,
+            ChatRole::Member => Role::Member,
+        }
+    }
+}
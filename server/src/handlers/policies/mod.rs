@@ -0,0 +1,3 @@
+pub mod block_policy;
+pub mod chat_policy;
+pub mod session_policy;
@@ -0,0 +1,52 @@
+use crate::handlers::repositories::session_repository;
+use crate::utils;
+use chrono::Utc;
+use jsonwebtoken::errors::ErrorKind;
+use sea_orm::DatabaseConnection;
+use std::sync::Arc;
+use utils::errors::server_error::ServerError;
+use utils::jwt;
+
+/// The user and session a connection is logged in as. It is fixed when the
+/// connection logs in or resumes, and every later command acts as this user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Identity {
+    pub user_id: i32,
+    pub session_id: i32,
+}
+
+/// Decodes a token to resume its session on a new connection
+pub async fn authenticate(jwt: &str, db: Arc<DatabaseConnection>) -> Result<Identity, ServerError> {
+    let claims = jwt::decode_jwt(jwt)
+        .map_err(|e| match e.kind() {
+            ErrorKind::ExpiredSignature => ServerError::TokenExpired,
+            _ => ServerError::InvalidToken(e.to_string()),
+        })?
+        .claims;
+
+    let identity = Identity {
+        user_id: claims.user_id,
+        session_id: claims.sid,
+    };
+    ensure_active(identity, db)
+        .await
+        .map_err(|_| ServerError::InvalidToken("Session ended".into()))?;
+    Ok(identity)
+}
+
+/// Fails with NotLoggedIn once the session is revoked or expired, so a
+/// connection stops working as soon as its session does
+pub async fn ensure_active(
+    identity: Identity,
+    db: Arc<DatabaseConnection>,
+) -> Result<(), ServerError> {
+    let session = session_repository::get_session(identity.session_id, &*db)
+        .await?
+        .filter(|s| s.user_id == identity.user_id)
+        .ok_or(ServerError::NotLoggedIn)?;
+
+    if session.revoked_at.is_some() || session.expires_at <= Utc::now().naive_utc() {
+        return Err(ServerError::NotLoggedIn);
+    }
+    Ok(())
+}
@@ -1,222 +1,283 @@
+use crate::entity::sea_orm_active_enums::Role;
 use crate::{entity, utils};
-use chrono::Utc;
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
 use entity::{chat_members, chats};
+use sea_orm::sea_query::{Expr, Func, Order, Query, SelectStatement, SimpleExpr};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
-    QueryOrder, QuerySelect, Set,
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DbErr, EntityTrait, JoinType,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, QueryTrait, RelationTrait, Set,
 };
 use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
 use utils::errors::server_error::ServerError;
 
-pub async fn create_new_chat(
+pub async fn create_new_chat<C: ConnectionTrait>(
     name: Option<String>,
     is_group: bool,
-    member_ids: Vec<i32>,
-    db: Arc<DatabaseConnection>,
+    db: &C,
 ) -> Result<entity::chats::Model, ServerError> {
-    if is_group {
-        // Check for duplicate group chat by name
-        if let Some(ref chat_name) = name {
-            if let Some(_) = chats::Entity::find()
-                .filter(chats::Column::IsGroup.eq(1))
-                .filter(chats::Column::Name.eq(chat_name.clone()))
-                .one(&*db)
-                .await
-                .map_err(ServerError::DatabaseError)?
-            {
-                return Err(ServerError::ChatAlreadyExists);
-            }
-        }
-    } else if member_ids.len() == 2 {
-        let user_a = member_ids[0];
-        let user_b = member_ids[1];
-
-        // Get chat IDs for each user
-        let chat_ids_a: HashSet<i32> = chat_members::Entity::find()
-            .filter(chat_members::Column::UserId.eq(user_a))
-            .select_only()
-            .column(chat_members::Column::ChatId)
-            .into_tuple()
-            .all(&*db)
-            .await
-            .map_err(ServerError::DatabaseError)?
-            .into_iter()
-            .collect();
-
-        let chat_ids_b: HashSet<i32> = chat_members::Entity::find()
-            .filter(chat_members::Column::UserId.eq(user_b))
-            .select_only()
-            .column(chat_members::Column::ChatId)
-            .into_tuple()
-            .all(&*db)
-            .await
-            .map_err(ServerError::DatabaseError)?
-            .into_iter()
-            .collect();
-
-        // Find overlapping chat IDs
-        let common_ids: Vec<i32> = chat_ids_a.intersection(&chat_ids_b).copied().collect();
-
-        if !common_ids.is_empty() {
-            // Check if any overlapping chat is not a group (i.e. a 1:1 chat)
-            if let Some(_) = entity::chats::Entity::find()
-                .filter(chats::Column::Id.is_in(common_ids))
-                .filter(chats::Column::IsGroup.eq(0))
-                .one(&*db)
-                .await
-                .map_err(ServerError::DatabaseError)?
-            {
-                return Err(ServerError::ChatAlreadyExists);
-            }
-        }
-    }
-
     let new_chat = entity::chats::ActiveModel {
         name: Set(name),
-        is_group: Set(is_group as i8),
+        is_group: Set(is_group),
         created_at: Set(Utc::now().naive_utc()),
         ..Default::default()
     };
 
-    Ok(new_chat
-        .insert(&*db)
+    new_chat
+        .insert(db)
         .await
-        .map_err(ServerError::DatabaseError)?)
+        .map_err(ServerError::DatabaseError)
 }
 
-pub async fn add_chat_member(
+/// The 1:1 chat between two users, if they have one
+pub async fn get_direct_chat<C: ConnectionTrait>(
+    user_a: i32,
+    user_b: i32,
+    db: &C,
+) -> Result<Option<entity::chats::Model>, ServerError> {
+    // Get chat IDs for each user
+    let chat_ids_a: HashSet<i32> = chat_members::Entity::find()
+        .filter(chat_members::Column::UserId.eq(user_a))
+        .select_only()
+        .column(chat_members::Column::ChatId)
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(ServerError::DatabaseError)?
+        .into_iter()
+        .collect();
+
+    let chat_ids_b: HashSet<i32> = chat_members::Entity::find()
+        .filter(chat_members::Column::UserId.eq(user_b))
+        .select_only()
+        .column(chat_members::Column::ChatId)
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(ServerError::DatabaseError)?
+        .into_iter()
+        .collect();
+
+    // Find overlapping chat IDs
+    let common_ids: Vec<i32> = chat_ids_a.intersection(&chat_ids_b).copied().collect();
+    if common_ids.is_empty() {
+        return Ok(None);
+    }
+
+    // Any overlapping chat that isn't a group is their 1:1 chat
+    entity::chats::Entity::find()
+        .filter(chats::Column::Id.is_in(common_ids))
+        .filter(chats::Column::IsGroup.eq(false))
+        .one(db)
+        .await
+        .map_err(ServerError::DatabaseError)
+}
+
+pub async fn get_group_chat_by_name<C: ConnectionTrait>(
+    name: String,
+    db: &C,
+) -> Result<Option<entity::chats::Model>, ServerError> {
+    chats::Entity::find()
+        .filter(chats::Column::IsGroup.eq(true))
+        // Case-insensitive on every backend, like MySQL's default collation
+        .filter(Expr::expr(Func::lower(Expr::col(chats::Column::Name))).eq(name.to_lowercase()))
+        .one(db)
+        .await
+        .map_err(ServerError::DatabaseError)
+}
+
+pub async fn rename_chat<C: ConnectionTrait>(
+    chat_id: i32,
+    name: String,
+    db: &C,
+) -> Result<entity::chats::Model, ServerError> {
+    let chat = chats::ActiveModel {
+        id: Set(chat_id),
+        name: Set(Some(name)),
+        ..Default::default()
+    };
+    chat.update(db).await.map_err(ServerError::DatabaseError)
+}
+
+pub async fn add_chat_member<C: ConnectionTrait>(
     chat_id: i32,
     user_id: i32,
-    db: Arc<DatabaseConnection>,
+    role: Role,
+    db: &C,
 ) -> Result<(), ServerError> {
     let member = entity::chat_members::ActiveModel {
         chat_id: Set(chat_id),
         user_id: Set(user_id),
+        role: Set(role),
     };
     member
-        .insert(&*db)
+        .insert(db)
         .await
         .map_err(ServerError::DatabaseError)?;
     Ok(())
 }
 
-pub async fn is_user_chat_member(chat_id: i32, user_id: i32, db: Arc<DatabaseConnection>) -> bool {
-    if let Ok(result) = entity::chat_members::Entity::find()
+pub async fn remove_chat_member<C: ConnectionTrait>(
+    chat_id: i32,
+    user_id: i32,
+    db: &C,
+) -> Result<(), ServerError> {
+    entity::chat_members::Entity::delete_many()
         .filter(entity::chat_members::Column::ChatId.eq(chat_id))
         .filter(entity::chat_members::Column::UserId.eq(user_id))
-        .one(&*db)
+        .exec(db)
         .await
-    {
-        result.is_some()
-    } else {
-        false
-    }
+        .map_err(ServerError::DatabaseError)?;
+    Ok(())
 }
 
-pub async fn get_user_chats(
+pub async fn set_chat_member_role<C: ConnectionTrait>(
+    chat_id: i32,
     user_id: i32,
-    db: Arc<DatabaseConnection>,
-) -> Result<Vec<entity::chats::Model>, ServerError> {
-    // Get all chat IDs the user is in
-    let chat_ids: Vec<i32> = entity::chat_members::Entity::find()
-        .filter(entity::chat_members::Column::UserId.eq(user_id))
-        .select_only()
-        .column(entity::chat_members::Column::ChatId)
-        .into_tuple()
-        .all(&*db)
+    role: Role,
+    db: &C,
+) -> Result<(), ServerError> {
+    let member = entity::chat_members::ActiveModel {
+        chat_id: Set(chat_id),
+        user_id: Set(user_id),
+        role: Set(role),
+    };
+    member
+        .update(db)
         .await
         .map_err(ServerError::DatabaseError)?;
+    Ok(())
+}
 
-    // Get chats
-    let chats: Vec<entity::chats::Model> = entity::chats::Entity::find()
-        .filter(entity::chats::Column::Id.is_in(chat_ids.clone()))
-        .all(&*db)
+pub async fn get_chat_member<C: ConnectionTrait>(
+    chat_id: i32,
+    user_id: i32,
+    db: &C,
+) -> Result<Option<entity::chat_members::Model>, ServerError> {
+    entity::chat_members::Entity::find()
+        .filter(entity::chat_members::Column::ChatId.eq(chat_id))
+        .filter(entity::chat_members::Column::UserId.eq(user_id))
+        .one(db)
         .await
-        .map_err(ServerError::DatabaseError)?;
+        .map_err(ServerError::DatabaseError)
+}
 
-    // Get latest message per chat
-    let last_messages: Vec<entity::messages::Model> = entity::messages::Entity::find()
-        .filter(entity::messages::Column::ChatId.is_in(chat_ids))
-        .order_by_desc(entity::messages::Column::Timestamp)
-        .all(&*db)
+pub async fn get_chat_members<C: ConnectionTrait>(
+    chat_id: i32,
+    db: &C,
+) -> Result<Vec<entity::chat_members::Model>, ServerError> {
+    entity::chat_members::Entity::find()
+        .filter(entity::chat_members::Column::ChatId.eq(chat_id))
+        .all(db)
         .await
-        .map_err(ServerError::DatabaseError)?;
-
-    // Map of chat_id -> last timestamp
-    let mut latest_by_chat: HashMap<i32, chrono::NaiveDateTime> = HashMap::new();
-    for msg in last_messages {
-        latest_by_chat.entry(msg.chat_id).or_insert(msg.timestamp);
-    }
-
-    // Sort chats by last message timestamp descending
-    let mut chats = chats;
-    chats.sort_by_key(|chat| {
-        // Default to the chat's created_at if no message
-        std::cmp::Reverse(
-            latest_by_chat
-                .get(&chat.id)
-                .cloned()
-                .unwrap_or(chat.created_at),
-        )
-    });
+        .map_err(ServerError::DatabaseError)
+}
 
-    Ok(chats)
+pub async fn get_chat_by_id<C: ConnectionTrait>(
+    chat_id: i32,
+    db: &C,
+) -> Result<Option<entity::chats::Model>, ServerError> {
+    entity::chats::Entity::find_by_id(chat_id)
+        .one(db)
+        .await
+        .map_err(ServerError::DatabaseError)
 }
 
-pub async fn get_user_chats_paged(
+/// The user's chats that come after the cursor, most recently active first.
+/// Each chat is paired with its last activity, which is what the cursor is
+/// keyed on (ties are broken by chat id).
+pub async fn get_user_chats_before<C: ConnectionTrait>(
     user_id: i32,
-    page: u64,
-    page_size: u64,
-    db: Arc<DatabaseConnection>,
-) -> Result<Vec<entity::chats::Model>, ServerError> {
-    let chats = get_user_chats(user_id, db.clone()).await?;
-
-    let start = (page * page_size) as usize;
-    let paginated_chats = chats
+    before: Option<(NaiveDateTime, i32)>,
+    limit: u64,
+    db: &C,
+) -> Result<Vec<(entity::chats::Model, NaiveDateTime)>, ServerError> {
+    let user_chat_ids = Query::select()
+        .column(chat_members::Column::ChatId)
+        .from(chat_members::Entity)
+        .and_where(chat_members::Column::UserId.eq(user_id))
+        .to_owned();
+
+    // A chat's last activity is its latest message, or its creation if it has none
+    let last_message = Query::select()
+        .expr(
+            Expr::col((
+                entity::messages::Entity,
+                entity::messages::Column::Timestamp,
+            ))
+            .max(),
+        )
+        .from(entity::messages::Entity)
+        .and_where(
+            Expr::col((entity::messages::Entity, entity::messages::Column::ChatId))
+                .equals((chats::Entity, chats::Column::Id)),
+        )
+        .to_owned();
+    let last_activity: SimpleExpr = Func::coalesce([
+        SimpleExpr::SubQuery(None, Box::new(last_message.into_sub_query_statement())),
+        Expr::col((chats::Entity, chats::Column::CreatedAt)).into(),
+    ])
+    .into();
+
+    // Only the page is read, in order, so a page costs the same however
+    // many chats the user has
+    let page: Vec<(i32, NaiveDateTime)> = chats::Entity::find()
+        .filter(chats::Column::Id.in_subquery(user_chat_ids))
+        .apply_if(before, |query, (activity, chat_id)| {
+            query.filter(
+                Condition::any()
+                    .add(Expr::expr(last_activity.clone()).lt(activity))
+                    .add(
+                        Condition::all()
+                            .add(Expr::expr(last_activity.clone()).eq(activity))
+                            .add(chats::Column::Id.lt(chat_id)),
+                    ),
+            )
+        })
+        .select_only()
+        .column(chats::Column::Id)
+        .expr_as(last_activity.clone(), "last_activity")
+        .order_by(last_activity, Order::Desc)
+        .order_by_desc(chats::Column::Id)
+        .limit(limit)
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    let ids: Vec<i32> = page.iter().map(|(id, _)| *id).collect();
+    let mut chats: HashMap<i32, entity::chats::Model> = chats::Entity::find()
+        .filter(chats::Column::Id.is_in(ids))
+        .all(db)
+        .await?
         .into_iter()
-        .skip(start)
-        .take(page_size as usize)
+        .map(|chat| (chat.id, chat))
         .collect();
 
-    Ok(paginated_chats)
-}
-
-pub async fn get_chats_page_count(
-    user_id: i32,
-    page_size: u64,
-    db: Arc<DatabaseConnection>,
-) -> Result<u64, ServerError> {
-    let total_chats = entity::chat_members::Entity::find()
-        .filter(entity::chat_members::Column::UserId.eq(user_id))
-        .count(&*db)
-        .await
-        .map_err(ServerError::DatabaseError)?;
-
-    let page_count = (total_chats + page_size - 1) / page_size; // ceiling division
-
-    Ok(page_count)
+    Ok(page
+        .into_iter()
+        .filter_map(|(id, last_activity)| chats.remove(&id).map(|chat| (chat, last_activity)))
+        .collect())
 }
 
-pub async fn get_chat_user_ids(
+pub async fn get_chat_user_ids<C: ConnectionTrait>(
     chat_id: i32,
-    db: Arc<DatabaseConnection>,
+    db: &C,
 ) -> Result<Vec<i32>, ServerError> {
     let user_ids: Vec<i32> = entity::chat_members::Entity::find()
         .filter(entity::chat_members::Column::ChatId.eq(chat_id))
         .select_only()
         .column(entity::chat_members::Column::UserId)
         .into_tuple()
-        .all(&*db)
+        .all(db)
         .await
         .map_err(ServerError::DatabaseError)?;
     Ok(user_ids)
 }
 
-pub async fn get_other_usernames_in_chat(
+pub async fn get_other_usernames_in_chat<C: ConnectionTrait>(
     chat_id: i32,
     current_user_id: i32,
-    db: Arc<DatabaseConnection>,
+    db: &C,
 ) -> Result<Vec<String>, ServerError> {
     // Get all user IDs in the chat except the current user
     let user_ids: Vec<i32> = entity::chat_members::Entity::find()
@@ -225,7 +286,7 @@ pub async fn get_other_usernames_in_chat(
         .select_only()
         .column(entity::chat_members::Column::UserId)
         .into_tuple()
-        .all(&*db)
+        .all(db)
         .await
         .map_err(ServerError::DatabaseError)?;
 
@@ -235,75 +296,106 @@ pub async fn get_other_usernames_in_chat(
         .select_only()
         .column(entity::users::Column::Username)
         .into_tuple()
-        .all(&*db)
+        .all(db)
         .await
         .map_err(ServerError::DatabaseError)?;
 
     Ok(users)
 }
 
-pub async fn get_paginated_messages(
+/// Up to `limit` messages older than `before_id` (or the newest messages),
+/// oldest first
+pub async fn get_messages_before<C: ConnectionTrait>(
     chat_id: i32,
-    page: u64,
-    page_size: u64,
-    db: Arc<DatabaseConnection>,
+    before_id: Option<i32>,
+    limit: u64,
+    db: &C,
 ) -> Result<Vec<entity::messages::Model>, ServerError> {
-    let paginator = entity::messages::Entity::find()
+    let mut messages = entity::messages::Entity::find()
         .filter(entity::messages::Column::ChatId.eq(chat_id))
-        .order_by_asc(entity::messages::Column::Timestamp)
-        .paginate(&*db, page_size);
-
-    let total = paginator
-        .num_pages()
-        .await
-        .map_err(ServerError::DatabaseError)?;
-    let page = {
-        if total > 0 {
-            total - page - 1
-        } else {
-            0
-        }
-    };
+        .apply_if(before_id, |query, id| {
+            query.filter(entity::messages::Column::Id.lt(id))
+        })
+        .order_by_desc(entity::messages::Column::Id)
+        .limit(limit)
+        .all(db)
+        .await?;
 
-    let messages: Vec<entity::messages::Model> = paginator.fetch_page(page).await?;
+    messages.reverse();
     Ok(messages)
 }
 
-pub async fn get_chat_page_count(
+/// Up to `limit` messages newer than `after_id`, oldest first
+pub async fn get_messages_after<C: ConnectionTrait>(
     chat_id: i32,
-    page_size: u64,
-    db: Arc<DatabaseConnection>,
-) -> Result<u64, ServerError> {
-    let paginator = entity::messages::Entity::find()
+    after_id: i32,
+    limit: u64,
+    db: &C,
+) -> Result<Vec<entity::messages::Model>, ServerError> {
+    Ok(entity::messages::Entity::find()
         .filter(entity::messages::Column::ChatId.eq(chat_id))
-        .order_by_desc(entity::messages::Column::Timestamp)
-        .paginate(&*db, page_size);
+        .filter(entity::messages::Column::Id.gt(after_id))
+        .order_by_asc(entity::messages::Column::Id)
+        .limit(limit)
+        .all(db)
+        .await?)
+}
 
-    paginator
-        .num_pages()
-        .await
-        .map_err(|err| ServerError::DatabaseError(err))
+pub async fn send_message<C: ConnectionTrait>(
+    chat_id: i32,
+    sender_id: i32,
+    username: String,
+    content: String,
+    idempotency_key: Option<String>,
+    db: &C,
+) -> Result<entity::messages::Model, ServerError> {
+    insert_message(
+        chat_id,
+        sender_id,
+        username,
+        content,
+        false,
+        idempotency_key,
+        db,
+    )
+    .await
 }
 
-pub async fn send_message(
+/// Adds a system message (e.g. "Alice added Bob") on behalf of the user who
+/// caused it
+pub async fn send_system_message<C: ConnectionTrait>(
+    chat_id: i32,
+    actor_id: i32,
+    actor_username: String,
+    content: String,
+    db: &C,
+) -> Result<entity::messages::Model, ServerError> {
+    insert_message(chat_id, actor_id, actor_username, content, true, None, db).await
+}
+
+async fn insert_message<C: ConnectionTrait>(
     chat_id: i32,
     sender_id: i32,
     username: String,
     content: String,
-    db: Arc<DatabaseConnection>,
-) -> Result<(), ServerError> {
+    is_system: bool,
+    idempotency_key: Option<String>,
+    db: &C,
+) -> Result<entity::messages::Model, ServerError> {
     let new_msg = entity::messages::ActiveModel {
         chat_id: Set(chat_id),
         sender_id: Set(sender_id),
         sender_username: Set(username),
         content: Set(content),
-        read: Set(false as i8),
+        is_system: Set(is_system),
+        read: Set(false),
         timestamp: Set(Utc::now().naive_utc()),
+        idempotency_key: Set(idempotency_key),
         ..Default::default()
     };
 
     let inserted_msg: entity::messages::Model = new_msg
-        .insert(&*db)
+        .insert(db)
         .await
         .map_err(ServerError::DatabaseError)?;
 
@@ -316,85 +408,574 @@ pub async fn send_message(
         read_at: Set(now),
     };
 
-    read.insert(&*db)
-        .await
-        .map_err(ServerError::DatabaseError)?;
+    read.insert(db).await.map_err(ServerError::DatabaseError)?;
 
-    Ok(())
+    Ok(inserted_msg)
+}
+
+pub async fn get_message_by_id<C: ConnectionTrait>(
+    message_id: i32,
+    db: &C,
+) -> Result<Option<entity::messages::Model>, ServerError> {
+    Ok(entity::messages::Entity::find_by_id(message_id)
+        .one(db)
+        .await?)
+}
+
+/// The message a user already sent with this idempotency key, if any
+pub async fn get_message_by_idempotency_key<C: ConnectionTrait>(
+    sender_id: i32,
+    idempotency_key: &str,
+    db: &C,
+) -> Result<Option<entity::messages::Model>, ServerError> {
+    Ok(entity::messages::Entity::find()
+        .filter(entity::messages::Column::SenderId.eq(sender_id))
+        .filter(entity::messages::Column::IdempotencyKey.eq(idempotency_key))
+        .one(db)
+        .await?)
+}
+
+/// Replaces a message's content, keeping the old content in its edit history
+pub async fn edit_message<C: ConnectionTrait>(
+    message: entity::messages::Model,
+    content: String,
+    db: &C,
+) -> Result<entity::messages::Model, ServerError> {
+    let now = Utc::now().naive_utc();
+
+    let edit = entity::message_edits::ActiveModel {
+        message_id: Set(message.id),
+        previous_content: Set(message.content.clone()),
+        edited_at: Set(now),
+        ..Default::default()
+    };
+    edit.insert(db).await?;
+
+    let mut active: entity::messages::ActiveModel = message.into();
+    active.content = Set(content);
+    active.edited_at = Set(Some(now));
+
+    Ok(active.update(db).await?)
+}
+
+/// Leaves a tombstone in place of the message. The content and its edit
+/// history are dropped so nothing of the message is kept.
+pub async fn delete_message<C: ConnectionTrait>(
+    message: entity::messages::Model,
+    db: &C,
+) -> Result<entity::messages::Model, ServerError> {
+    entity::message_edits::Entity::delete_many()
+        .filter(entity::message_edits::Column::MessageId.eq(message.id))
+        .exec(db)
+        .await?;
+
+    let mut active: entity::messages::ActiveModel = message.into();
+    active.content = Set(String::new());
+    active.deleted_at = Set(Some(Utc::now().naive_utc()));
+
+    Ok(active.update(db).await?)
 }
 
-pub async fn get_chat_message_ids(
+pub async fn get_message_edits<C: ConnectionTrait>(
+    message_id: i32,
+    db: &C,
+) -> Result<Vec<entity::message_edits::Model>, ServerError> {
+    Ok(entity::message_edits::Entity::find()
+        .filter(entity::message_edits::Column::MessageId.eq(message_id))
+        .order_by_asc(entity::message_edits::Column::EditedAt)
+        .all(db)
+        .await?)
+}
+
+// Ids of the messages the user has read, for use as a subquery
+fn read_message_ids(user_id: i32) -> SelectStatement {
+    Query::select()
+        .column(entity::message_reads::Column::MessageId)
+        .from(entity::message_reads::Entity)
+        .and_where(entity::message_reads::Column::UserId.eq(user_id))
+        .to_owned()
+}
+
+/// Id of the newest message in a chat the user hasn't read
+pub async fn get_last_unread_message_id<C: ConnectionTrait>(
+    user_id: i32,
     chat_id: i32,
-    db: Arc<DatabaseConnection>,
-) -> Result<Vec<i32>, ServerError> {
+    db: &C,
+) -> Result<Option<i32>, ServerError> {
     Ok(entity::messages::Entity::find()
         .filter(entity::messages::Column::ChatId.eq(chat_id))
+        .filter(entity::messages::Column::Id.not_in_subquery(read_message_ids(user_id)))
         .select_only()
-        .column(entity::messages::Column::Id)
-        .into_tuple::<i32>()
-        .all(&*db)
-        .await?)
+        .column_as(entity::messages::Column::Id.max(), "last_unread")
+        .into_tuple::<Option<i32>>()
+        .one(db)
+        .await?
+        .flatten())
 }
 
-pub async fn get_user_chat_unread_messages(
+/// Unread message count per chat, in one grouped query. Chats with nothing
+/// unread are left out.
+pub async fn get_unread_counts<C: ConnectionTrait>(
     user_id: i32,
-    message_ids: Vec<i32>,
-    db: Arc<DatabaseConnection>,
-) -> Result<Vec<i32>, ServerError> {
-    Ok(entity::message_reads::Entity::find()
-        .filter(entity::message_reads::Column::UserId.eq(user_id)) // Get messages user has read
-        .filter(entity::message_reads::Column::MessageId.is_in(message_ids.clone())) // Narrow to messages in the chat
+    chat_ids: Vec<i32>,
+    db: &C,
+) -> Result<HashMap<i32, u64>, ServerError> {
+    let counts: Vec<(i32, i64)> = entity::messages::Entity::find()
         .select_only()
-        .column(entity::message_reads::Column::MessageId) // Get the IDs
+        .column(entity::messages::Column::ChatId)
+        .column_as(entity::messages::Column::Id.count(), "unread")
+        .filter(entity::messages::Column::ChatId.is_in(chat_ids))
+        .filter(entity::messages::Column::Id.not_in_subquery(read_message_ids(user_id)))
+        .group_by(entity::messages::Column::ChatId)
         .into_tuple()
-        .all(&*db)
+        .all(db)
+        .await?;
+
+    Ok(counts
+        .into_iter()
+        .map(|(chat_id, count)| (chat_id, count as u64))
+        .collect())
+}
+
+/// Unread messages across every chat the user is in
+pub async fn get_total_unread_count<C: ConnectionTrait>(
+    user_id: i32,
+    db: &C,
+) -> Result<u64, ServerError> {
+    let user_chat_ids = Query::select()
+        .column(entity::chat_members::Column::ChatId)
+        .from(entity::chat_members::Entity)
+        .and_where(entity::chat_members::Column::UserId.eq(user_id))
+        .to_owned();
+
+    Ok(entity::messages::Entity::find()
+        .filter(entity::messages::Column::ChatId.in_subquery(user_chat_ids))
+        .filter(entity::messages::Column::Id.not_in_subquery(read_message_ids(user_id)))
+        .count(db)
         .await?)
 }
 
-pub async fn mark_messages_read(
+/// Marks every message in the chat up to `last_read_id` read, in a single
+/// `INSERT ... SELECT` however many there are
+pub async fn mark_messages_read<C: ConnectionTrait>(
     user_id: i32,
-    unread_ids: Vec<i32>,
-    db: Arc<DatabaseConnection>,
-) -> Result<(), ServerError> {
+    chat_id: i32,
+    last_read_id: i32,
+    db: &C,
+) -> Result<NaiveDateTime, ServerError> {
     let now = Utc::now().naive_utc();
-    let new_reads: Vec<_> = unread_ids
-        .into_iter()
-        .map(|msg_id| entity::message_reads::ActiveModel {
-            message_id: Set(msg_id),
-            user_id: Set(user_id),
-            read_at: Set(now),
-        })
-        .collect();
-
-    if !new_reads.is_empty() {
-        entity::message_reads::Entity::insert_many(new_reads)
-            .exec(&*db)
-            .await?;
-    }
+    let unread = Query::select()
+        .column(entity::messages::Column::Id)
+        .expr(Expr::val(user_id))
+        .expr(Expr::val(now))
+        .from(entity::messages::Entity)
+        .and_where(entity::messages::Column::ChatId.eq(chat_id))
+        .and_where(entity::messages::Column::Id.lte(last_read_id))
+        .and_where(entity::messages::Column::Id.not_in_subquery(read_message_ids(user_id)))
+        .to_owned();
+    let insert = Query::insert()
+        .into_table(entity::message_reads::Entity)
+        .columns([
+            entity::message_reads::Column::MessageId,
+            entity::message_reads::Column::UserId,
+            entity::message_reads::Column::ReadAt,
+        ])
+        .select_from(unread)
+        .map_err(|e| DbErr::Custom(e.to_string()))?
+        .to_owned();
+
+    db.execute(db.get_database_backend().build(&insert)).await?;
+    Ok(now)
+}
 
-    Ok(())
+/// (message_id, user_id) pairs for every read of the given messages
+pub async fn get_message_readers<C: ConnectionTrait>(
+    message_ids: Vec<i32>,
+    db: &C,
+) -> Result<Vec<(i32, i32)>, ServerError> {
+    Ok(entity::message_reads::Entity::find()
+        .filter(entity::message_reads::Column::MessageId.is_in(message_ids))
+        .select_only()
+        .column(entity::message_reads::Column::MessageId)
+        .column(entity::message_reads::Column::UserId)
+        .into_tuple()
+        .all(db)
+        .await?)
 }
 
-pub async fn get_chat_messages(
-    chat_id: i32,
-    db: Arc<DatabaseConnection>,
-) -> Result<Vec<entity::messages::Model>, ServerError> {
-    Ok(entity::messages::Entity::find()
-        .filter(entity::messages::Column::ChatId.eq(chat_id))
-        .order_by_desc(entity::messages::Column::Timestamp)
-        .all(&*db)
+/// (user_id, username, read_at) for everyone who has read a message, earliest first
+pub async fn get_message_receipts<C: ConnectionTrait>(
+    message_id: i32,
+    db: &C,
+) -> Result<Vec<(i32, String, NaiveDateTime)>, ServerError> {
+    Ok(entity::message_reads::Entity::find()
+        .filter(entity::message_reads::Column::MessageId.eq(message_id))
+        .join(
+            JoinType::InnerJoin,
+            entity::message_reads::Relation::Users.def(),
+        )
+        .select_only()
+        .column(entity::message_reads::Column::UserId)
+        .column(entity::users::Column::Username)
+        .column(entity::message_reads::Column::ReadAt)
+        .order_by_asc(entity::message_reads::Column::ReadAt)
+        .order_by_asc(entity::message_reads::Column::UserId)
+        .into_tuple()
+        .all(db)
         .await?)
 }
 
-pub async fn get_read_entry(
-    user_id: i32,
-    msg_id: i32,
-    db: Arc<DatabaseConnection>,
-) -> Result<Option<entity::message_reads::Model>, ServerError> {
-    entity::message_reads::Entity::find()
-        .filter(entity::message_reads::Column::MessageId.eq(msg_id))
-        .filter(entity::message_reads::Column::UserId.eq(user_id))
-        .one(&*db)
-        .await
-        .map_err(|err| ServerError::DatabaseError(err))
+/// The chat, membership and message queries services make. The functions
+/// above implement it for any sea-orm connection or transaction.
+#[async_trait]
+pub trait ChatRepository: Send + Sync {
+    async fn create_new_chat(
+        &self,
+        name: Option<String>,
+        is_group: bool,
+    ) -> Result<entity::chats::Model, ServerError>;
+    async fn get_direct_chat(
+        &self,
+        user_a: i32,
+        user_b: i32,
+    ) -> Result<Option<entity::chats::Model>, ServerError>;
+    async fn get_group_chat_by_name(
+        &self,
+        name: String,
+    ) -> Result<Option<entity::chats::Model>, ServerError>;
+    async fn rename_chat(
+        &self,
+        chat_id: i32,
+        name: String,
+    ) -> Result<entity::chats::Model, ServerError>;
+    async fn add_chat_member(
+        &self,
+        chat_id: i32,
+        user_id: i32,
+        role: Role,
+    ) -> Result<(), ServerError>;
+    async fn remove_chat_member(&self, chat_id: i32, user_id: i32) -> Result<(), ServerError>;
+    async fn set_chat_member_role(
+        &self,
+        chat_id: i32,
+        user_id: i32,
+        role: Role,
+    ) -> Result<(), ServerError>;
+    async fn get_chat_member(
+        &self,
+        chat_id: i32,
+        user_id: i32,
+    ) -> Result<Option<entity::chat_members::Model>, ServerError>;
+    async fn get_chat_members(
+        &self,
+        chat_id: i32,
+    ) -> Result<Vec<entity::chat_members::Model>, ServerError>;
+    async fn get_chat_by_id(
+        &self,
+        chat_id: i32,
+    ) -> Result<Option<entity::chats::Model>, ServerError>;
+    async fn get_user_chats_before(
+        &self,
+        user_id: i32,
+        before: Option<(NaiveDateTime, i32)>,
+        limit: u64,
+    ) -> Result<Vec<(entity::chats::Model, NaiveDateTime)>, ServerError>;
+    async fn get_chat_user_ids(&self, chat_id: i32) -> Result<Vec<i32>, ServerError>;
+    async fn get_other_usernames_in_chat(
+        &self,
+        chat_id: i32,
+        current_user_id: i32,
+    ) -> Result<Vec<String>, ServerError>;
+    async fn get_messages_before(
+        &self,
+        chat_id: i32,
+        before_id: Option<i32>,
+        limit: u64,
+    ) -> Result<Vec<entity::messages::Model>, ServerError>;
+    async fn get_messages_after(
+        &self,
+        chat_id: i32,
+        after_id: i32,
+        limit: u64,
+    ) -> Result<Vec<entity::messages::Model>, ServerError>;
+    async fn send_message(
+        &self,
+        chat_id: i32,
+        sender_id: i32,
+        username: String,
+        content: String,
+        idempotency_key: Option<String>,
+    ) -> Result<entity::messages::Model, ServerError>;
+    async fn send_system_message(
+        &self,
+        chat_id: i32,
+        actor_id: i32,
+        actor_username: String,
+        content: String,
+    ) -> Result<entity::messages::Model, ServerError>;
+    async fn get_message_by_id(
+        &self,
+        message_id: i32,
+    ) -> Result<Option<entity::messages::Model>, ServerError>;
+    async fn get_message_by_idempotency_key(
+        &self,
+        sender_id: i32,
+        idempotency_key: &str,
+    ) -> Result<Option<entity::messages::Model>, ServerError>;
+    async fn edit_message(
+        &self,
+        message: entity::messages::Model,
+        content: String,
+    ) -> Result<entity::messages::Model, ServerError>;
+    async fn delete_message(
+        &self,
+        message: entity::messages::Model,
+    ) -> Result<entity::messages::Model, ServerError>;
+    async fn get_message_edits(
+        &self,
+        message_id: i32,
+    ) -> Result<Vec<entity::message_edits::Model>, ServerError>;
+    async fn get_last_unread_message_id(
+        &self,
+        user_id: i32,
+        chat_id: i32,
+    ) -> Result<Option<i32>, ServerError>;
+    async fn get_unread_counts(
+        &self,
+        user_id: i32,
+        chat_ids: Vec<i32>,
+    ) -> Result<HashMap<i32, u64>, ServerError>;
+    async fn get_total_unread_count(&self, user_id: i32) -> Result<u64, ServerError>;
+    async fn mark_messages_read(
+        &self,
+        user_id: i32,
+        chat_id: i32,
+        last_read_id: i32,
+    ) -> Result<NaiveDateTime, ServerError>;
+    async fn get_message_readers(
+        &self,
+        message_ids: Vec<i32>,
+    ) -> Result<Vec<(i32, i32)>, ServerError>;
+    async fn get_message_receipts(
+        &self,
+        message_id: i32,
+    ) -> Result<Vec<(i32, String, NaiveDateTime)>, ServerError>;
+}
+
+#[async_trait]
+impl<C: ConnectionTrait + Send> ChatRepository for C {
+    async fn create_new_chat(
+        &self,
+        name: Option<String>,
+        is_group: bool,
+    ) -> Result<entity::chats::Model, ServerError> {
+        create_new_chat(name, is_group, self).await
+    }
+
+    async fn get_direct_chat(
+        &self,
+        user_a: i32,
+        user_b: i32,
+    ) -> Result<Option<entity::chats::Model>, ServerError> {
+        get_direct_chat(user_a, user_b, self).await
+    }
+
+    async fn get_group_chat_by_name(
+        &self,
+        name: String,
+    ) -> Result<Option<entity::chats::Model>, ServerError> {
+        get_group_chat_by_name(name, self).await
+    }
+
+    async fn rename_chat(
+        &self,
+        chat_id: i32,
+        name: String,
+    ) -> Result<entity::chats::Model, ServerError> {
+        rename_chat(chat_id, name, self).await
+    }
+
+    async fn add_chat_member(
+        &self,
+        chat_id: i32,
+        user_id: i32,
+        role: Role,
+    ) -> Result<(), ServerError> {
+        add_chat_member(chat_id, user_id, role, self).await
+    }
+
+    async fn remove_chat_member(&self, chat_id: i32, user_id: i32) -> Result<(), ServerError> {
+        remove_chat_member(chat_id, user_id, self).await
+    }
+
+    async fn set_chat_member_role(
+        &self,
+        chat_id: i32,
+        user_id: i32,
+        role: Role,
+    ) -> Result<(), ServerError> {
+        set_chat_member_role(chat_id, user_id, role, self).await
+    }
+
+    async fn get_chat_member(
+        &self,
+        chat_id: i32,
+        user_id: i32,
+    ) -> Result<Option<entity::chat_members::Model>, ServerError> {
+        get_chat_member(chat_id, user_id, self).await
+    }
+
+    async fn get_chat_members(
+        &self,
+        chat_id: i32,
+    ) -> Result<Vec<entity::chat_members::Model>, ServerError> {
+        get_chat_members(chat_id, self).await
+    }
+
+    async fn get_chat_by_id(
+        &self,
+        chat_id: i32,
+    ) -> Result<Option<entity::chats::Model>, ServerError> {
+        get_chat_by_id(chat_id, self).await
+    }
+
+    async fn get_user_chats_before(
+        &self,
+        user_id: i32,
+        before: Option<(NaiveDateTime, i32)>,
+        limit: u64,
+    ) -> Result<Vec<(entity::chats::Model, NaiveDateTime)>, ServerError> {
+        get_user_chats_before(user_id, before, limit, self).await
+    }
+
+    async fn get_chat_user_ids(&self, chat_id: i32) -> Result<Vec<i32>, ServerError> {
+        get_chat_user_ids(chat_id, self).await
+    }
+
+    async fn get_other_usernames_in_chat(
+        &self,
+        chat_id: i32,
+        current_user_id: i32,
+    ) -> Result<Vec<String>, ServerError> {
+        get_other_usernames_in_chat(chat_id, current_user_id, self).await
+    }
+
+    async fn get_messages_before(
+        &self,
+        chat_id: i32,
+        before_id: Option<i32>,
+        limit: u64,
+    ) -> Result<Vec<entity::messages::Model>, ServerError> {
+        get_messages_before(chat_id, before_id, limit, self).await
+    }
+
+    async fn get_messages_after(
+        &self,
+        chat_id: i32,
+        after_id: i32,
+        limit: u64,
+    ) -> Result<Vec<entity::messages::Model>, ServerError> {
+        get_messages_after(chat_id, after_id, limit, self).await
+    }
+
+    async fn send_message(
+        &self,
+        chat_id: i32,
+        sender_id: i32,
+        username: String,
+        content: String,
+        idempotency_key: Option<String>,
+    ) -> Result<entity::messages::Model, ServerError> {
+        send_message(chat_id, sender_id, username, content, idempotency_key, self).await
+    }
+
+    async fn send_system_message(
+        &self,
+        chat_id: i32,
+        actor_id: i32,
+        actor_username: String,
+        content: String,
+    ) -> Result<entity::messages::Model, ServerError> {
+        send_system_message(chat_id, actor_id, actor_username, content, self).await
+    }
+
+    async fn get_message_by_id(
+        &self,
+        message_id: i32,
+    ) -> Result<Option<entity::messages::Model>, ServerError> {
+        get_message_by_id(message_id, self).await
+    }
+
+    async fn get_message_by_idempotency_key(
+        &self,
+        sender_id: i32,
+        idempotency_key: &str,
+    ) -> Result<Option<entity::messages::Model>, ServerError> {
+        get_message_by_idempotency_key(sender_id, idempotency_key, self).await
+    }
+
+    async fn edit_message(
+        &self,
+        message: entity::messages::Model,
+        content: String,
+    ) -> Result<entity::messages::Model, ServerError> {
+        edit_message(message, content, self).await
+    }
+
+    async fn delete_message(
+        &self,
+        message: entity::messages::Model,
+    ) -> Result<entity::messages::Model, ServerError> {
+        delete_message(message, self).await
+    }
+
+    async fn get_message_edits(
+        &self,
+        message_id: i32,
+    ) -> Result<Vec<entity::message_edits::Model>, ServerError> {
+        get_message_edits(message_id, self).await
+    }
+
+    async fn get_last_unread_message_id(
+        &self,
+        user_id: i32,
+        chat_id: i32,
+    ) -> Result<Option<i32>, ServerError> {
+        get_last_unread_message_id(user_id, chat_id, self).await
+    }
+
+    async fn get_unread_counts(
+        &self,
+        user_id: i32,
+        chat_ids: Vec<i32>,
+    ) -> Result<HashMap<i32, u64>, ServerError> {
+        get_unread_counts(user_id, chat_ids, self).await
+    }
+
+    async fn get_total_unread_count(&self, user_id: i32) -> Result<u64, ServerError> {
+        get_total_unread_count(user_id, self).await
+    }
+
+    async fn mark_messages_read(
+        &self,
+        user_id: i32,
+        chat_id: i32,
+        last_read_id: i32,
+    ) -> Result<NaiveDateTime, ServerError> {
+        mark_messages_read(user_id, chat_id, last_read_id, self).await
+    }
+
+    async fn get_message_readers(
+        &self,
+        message_ids: Vec<i32>,
+    ) -> Result<Vec<(i32, i32)>, ServerError> {
+        get_message_readers(message_ids, self).await
+    }
+
+    async fn get_message_receipts(
+        &self,
+        message_id: i32,
+    ) -> Result<Vec<(i32, String, NaiveDateTime)>, ServerError> {
+        get_message_receipts(message_id, self).await
+    }
 }
@@ -0,0 +1,885 @@
+use crate::entity::sea_orm_active_enums::{Role, Status};
+use crate::entity::{
+    blocked_users, chat_members, chats, friend_requests, friends, message_edits, message_reads,
+    messages, users,
+};
+use crate::handlers::repositories::chat_repository::ChatRepository;
+use crate::handlers::repositories::store::{Repositories, Transaction};
+use crate::handlers::repositories::user_repository::UserRepository;
+use crate::utils::errors::server_error::ServerError;
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use sea_orm::{DbErr, ModelTrait, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+// Every table the repositories use, as plain rows in insertion order
+#[derive(Debug, Clone, Default)]
+struct Tables {
+    users: Vec<users::Model>,
+    friends: Vec<friends::Model>,
+    friend_requests: Vec<friend_requests::Model>,
+    blocked_users: Vec<blocked_users::Model>,
+    chats: Vec<chats::Model>,
+    chat_members: Vec<chat_members::Model>,
+    messages: Vec<messages::Model>,
+    message_edits: Vec<message_edits::Model>,
+    message_reads: Vec<message_reads::Model>,
+}
+
+impl Tables {
+    fn is_member(&self, chat_id: i32, user_id: i32) -> bool {
+        self.chat_members
+            .iter()
+            .any(|m| m.chat_id == chat_id && m.user_id == user_id)
+    }
+
+    fn has_read(&self, message_id: i32, user_id: i32) -> bool {
+        self.message_reads
+            .iter()
+            .any(|r| r.message_id == message_id && r.user_id == user_id)
+    }
+
+    fn is_unread(&self, message: &messages::Model, user_id: i32) -> bool {
+        !self.has_read(message.id, user_id)
+    }
+
+    fn insert_message(
+        &mut self,
+        chat_id: i32,
+        sender_id: i32,
+        username: String,
+        content: String,
+        is_system: bool,
+        idempotency_key: Option<String>,
+    ) -> Result<messages::Model, ServerError> {
+        if idempotency_key.is_some()
+            && self
+                .messages
+                .iter()
+                .any(|m| m.sender_id == sender_id && m.idempotency_key == idempotency_key)
+        {
+            return Err(duplicate("messages"));
+        }
+
+        let now = Utc::now().naive_utc();
+        let message = messages::Model {
+            id: next_id(self.messages.iter().map(|m| m.id)),
+            chat_id,
+            sender_id,
+            sender_username: username,
+            content,
+            is_system,
+            read: false,
+            timestamp: now,
+            edited_at: None,
+            deleted_at: None,
+            idempotency_key,
+        };
+        self.messages.push(message.clone());
+        self.message_reads.push(message_reads::Model {
+            message_id: message.id,
+            user_id: sender_id,
+            read_at: now,
+        });
+        Ok(message)
+    }
+
+    fn message_mut(&mut self, message_id: i32) -> Result<&mut messages::Model, ServerError> {
+        self.messages
+            .iter_mut()
+            .find(|m| m.id == message_id)
+            .ok_or_else(not_updated)
+    }
+}
+
+// Ids count up from 1 like an auto-increment column
+fn next_id(ids: impl Iterator<Item = i32>) -> i32 {
+    ids.max().unwrap_or(0) + 1
+}
+
+// What the database reports when a primary or unique key turns a row away
+fn duplicate(table: &str) -> ServerError {
+    ServerError::DatabaseError(DbErr::Custom(format!("Duplicate key in {}", table)))
+}
+
+// What the database reports when an update matches no row
+fn not_updated() -> ServerError {
+    ServerError::DatabaseError(DbErr::RecordNotUpdated)
+}
+
+/// Repositories kept in memory, so services can be tested without a database.
+/// They behave like the sea-orm ones, down to case-insensitive names and the
+/// keys that turn duplicate rows away. A transaction works on a copy of the
+/// tables and committing writes the copy back, so the last of two overlapping
+/// transactions to commit wins.
+#[derive(Debug, Default)]
+pub struct InMemoryRepositories {
+    tables: Arc<Mutex<Tables>>,
+    // The tables a transaction commits to
+    parent: Option<Arc<Mutex<Tables>>>,
+}
+
+impl InMemoryRepositories {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn tables(&self) -> MutexGuard<'_, Tables> {
+        self.tables.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl Repositories for InMemoryRepositories {
+    fn users(&self) -> &dyn UserRepository {
+        self
+    }
+
+    fn chats(&self) -> &dyn ChatRepository {
+        self
+    }
+
+    async fn begin(&self) -> Result<Box<dyn Transaction>, ServerError> {
+        Ok(Box::new(InMemoryRepositories {
+            tables: Arc::new(Mutex::new(self.tables().clone())),
+            parent: Some(self.tables.clone()),
+        }))
+    }
+}
+
+#[async_trait]
+impl Transaction for InMemoryRepositories {
+    async fn commit(self: Box<Self>) -> Result<(), ServerError> {
+        if let Some(parent) = &self.parent {
+            *parent.lock().unwrap_or_else(|e| e.into_inner()) = self.tables().clone();
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UserRepository for InMemoryRepositories {
+    async fn register_user(
+        &self,
+        username: String,
+        hashed: String,
+    ) -> Result<users::Model, ServerError> {
+        let mut tables = self.tables();
+        if tables.users.iter().any(|u| u.username == username) {
+            return Err(duplicate("users"));
+        }
+        let user = users::Model {
+            id: next_id(tables.users.iter().map(|u| u.id)),
+            username,
+            password_hash: hashed,
+            last_seen_at: None,
+        };
+        tables.users.push(user.clone());
+        Ok(user)
+    }
+
+    async fn get_user_by_username(
+        &self,
+        username: String,
+    ) -> Result<Option<users::Model>, ServerError> {
+        let username = username.to_lowercase();
+        Ok(self
+            .tables()
+            .users
+            .iter()
+            .find(|u| u.username.to_lowercase() == username)
+            .cloned())
+    }
+
+    async fn get_user_by_id(&self, id: i32) -> Result<Option<users::Model>, ServerError> {
+        Ok(self.tables().users.iter().find(|u| u.id == id).cloned())
+    }
+
+    async fn create_friend_request(
+        &self,
+        sender_id: i32,
+        receiver_id: i32,
+    ) -> Result<(), ServerError> {
+        let mut tables = self.tables();
+        if tables
+            .friend_requests
+            .iter()
+            .any(|r| r.sender_id == sender_id && r.receiver_id == receiver_id)
+        {
+            return Err(duplicate("friend_requests"));
+        }
+        let request = friend_requests::Model {
+            id: next_id(tables.friend_requests.iter().map(|r| r.id)),
+            sender_id,
+            receiver_id,
+            status: Status::Pending,
+            sent_at: Utc::now().naive_utc(),
+        };
+        tables.friend_requests.push(request);
+        Ok(())
+    }
+
+    async fn get_friend_request(
+        &self,
+        sender_id: i32,
+        receiver_id: i32,
+    ) -> Result<Option<friend_requests::Model>, ServerError> {
+        Ok(self
+            .tables()
+            .friend_requests
+            .iter()
+            .find(|r| {
+                r.sender_id == sender_id
+                    && r.receiver_id == receiver_id
+                    && r.status == Status::Pending
+            })
+            .cloned())
+    }
+
+    async fn update_friend_request_status(
+        &self,
+        sender_id: i32,
+        receiver_id: i32,
+        status: Status,
+    ) -> Result<(), ServerError> {
+        let mut tables = self.tables();
+        let matches =
+            |r: &friend_requests::Model| r.sender_id == sender_id && r.receiver_id == receiver_id;
+        if status == Status::Rejected {
+            tables.friend_requests.retain(|r| !matches(r));
+        } else {
+            for request in tables.friend_requests.iter_mut().filter(|r| matches(r)) {
+                request.status = status.clone();
+            }
+        }
+        Ok(())
+    }
+
+    async fn create_friendship(&self, u1: i32, u2: i32) -> Result<(), ServerError> {
+        let mut tables = self.tables();
+        if tables
+            .friends
+            .iter()
+            .any(|f| f.user_id == u1 && f.friend_id == u2)
+        {
+            return Err(duplicate("friends"));
+        }
+        tables.friends.push(friends::Model {
+            user_id: u1,
+            friend_id: u2,
+        });
+        Ok(())
+    }
+
+    async fn get_user_blocked(
+        &self,
+        sender_id: i32,
+        receiver_id: i32,
+    ) -> Result<Option<blocked_users::Model>, ServerError> {
+        Ok(self
+            .tables()
+            .blocked_users
+            .iter()
+            .find(|b| {
+                (b.user_id == sender_id && b.blocked_id == receiver_id)
+                    || (b.user_id == receiver_id && b.blocked_id == sender_id)
+            })
+            .cloned())
+    }
+
+    async fn get_user_blocks(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<blocked_users::Model>, ServerError> {
+        Ok(self
+            .tables()
+            .blocked_users
+            .iter()
+            .filter(|b| b.user_id == user_id || b.blocked_id == user_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_block(
+        &self,
+        user_id: i32,
+        blocked_id: i32,
+    ) -> Result<Option<blocked_users::Model>, ServerError> {
+        Ok(self
+            .tables()
+            .blocked_users
+            .iter()
+            .find(|b| b.user_id == user_id && b.blocked_id == blocked_id)
+            .cloned())
+    }
+
+    async fn block_user(&self, user_id: i32, blocked_id: i32) -> Result<(), ServerError> {
+        let mut tables = self.tables();
+        if tables
+            .blocked_users
+            .iter()
+            .any(|b| b.user_id == user_id && b.blocked_id == blocked_id)
+        {
+            return Err(duplicate("blocked_users"));
+        }
+        tables.blocked_users.push(blocked_users::Model {
+            user_id,
+            blocked_id,
+        });
+        Ok(())
+    }
+
+    async fn unblock_user(&self, user_id: i32, blocked_id: i32) -> Result<(), ServerError> {
+        self.tables()
+            .blocked_users
+            .retain(|b| !(b.user_id == user_id && b.blocked_id == blocked_id));
+        Ok(())
+    }
+
+    async fn get_blocked_users(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<blocked_users::Model>, ServerError> {
+        Ok(self
+            .tables()
+            .blocked_users
+            .iter()
+            .filter(|b| b.user_id == user_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn delete_friendship(&self, u1: i32, u2: i32) -> Result<(), ServerError> {
+        self.tables().friends.retain(|f| {
+            !((f.user_id == u1 && f.friend_id == u2) || (f.user_id == u2 && f.friend_id == u1))
+        });
+        Ok(())
+    }
+
+    async fn get_friendship(
+        &self,
+        u1: i32,
+        u2: i32,
+    ) -> Result<Option<friends::Model>, ServerError> {
+        Ok(self
+            .tables()
+            .friends
+            .iter()
+            .find(|f| {
+                (f.user_id == u1 && f.friend_id == u2) || (f.user_id == u2 && f.friend_id == u1)
+            })
+            .cloned())
+    }
+
+    async fn delete_friend_requests(&self, u1: i32, u2: i32) -> Result<(), ServerError> {
+        self.tables().friend_requests.retain(|r| {
+            !((r.sender_id == u1 && r.receiver_id == u2)
+                || (r.sender_id == u2 && r.receiver_id == u1))
+        });
+        Ok(())
+    }
+
+    async fn get_user_friends(&self, user_id: i32) -> Result<Vec<friends::Model>, ServerError> {
+        Ok(self
+            .tables()
+            .friends
+            .iter()
+            .filter(|f| f.user_id == user_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_users_from_list(&self, ids: Vec<i32>) -> Result<Vec<users::Model>, ServerError> {
+        Ok(self
+            .tables()
+            .users
+            .iter()
+            .filter(|u| ids.contains(&u.id))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_user_friend_requests(
+        &self,
+        user_id: i32,
+        direction: Option<friend_requests::Column>,
+    ) -> Result<Vec<friend_requests::Model>, ServerError> {
+        let tables = self.tables();
+        let pending = |column: friend_requests::Column| {
+            tables
+                .friend_requests
+                .iter()
+                .filter(move |r| {
+                    r.status == Status::Pending && r.get(column) == Value::Int(Some(user_id))
+                })
+                .cloned()
+        };
+
+        Ok(match direction {
+            Some(direction) => pending(direction).collect(),
+            None => pending(friend_requests::Column::ReceiverId)
+                .chain(pending(friend_requests::Column::SenderId))
+                .collect(),
+        })
+    }
+
+    async fn update_password(
+        &self,
+        username: String,
+        hashed_password: String,
+    ) -> Result<(), ServerError> {
+        let username = username.to_lowercase();
+        let mut tables = self.tables();
+        let user = tables
+            .users
+            .iter_mut()
+            .find(|u| u.username.to_lowercase() == username)
+            .ok_or(ServerError::UserNotFound)?;
+        user.password_hash = hashed_password;
+        Ok(())
+    }
+
+    async fn update_last_seen(
+        &self,
+        user_id: i32,
+        last_seen_at: NaiveDateTime,
+    ) -> Result<NaiveDateTime, ServerError> {
+        for user in self.tables().users.iter_mut().filter(|u| u.id == user_id) {
+            user.last_seen_at = Some(last_seen_at);
+        }
+        Ok(last_seen_at)
+    }
+}
+
+#[async_trait]
+impl ChatRepository for InMemoryRepositories {
+    async fn create_new_chat(
+        &self,
+        name: Option<String>,
+        is_group: bool,
+    ) -> Result<chats::Model, ServerError> {
+        let mut tables = self.tables();
+        let chat = chats::Model {
+            id: next_id(tables.chats.iter().map(|c| c.id)),
+            name,
+            is_group,
+            created_at: Utc::now().naive_utc(),
+        };
+        tables.chats.push(chat.clone());
+        Ok(chat)
+    }
+
+    async fn get_direct_chat(
+        &self,
+        user_a: i32,
+        user_b: i32,
+    ) -> Result<Option<chats::Model>, ServerError> {
+        let tables = self.tables();
+        Ok(tables
+            .chats
+            .iter()
+            .find(|c| {
+                !c.is_group && tables.is_member(c.id, user_a) && tables.is_member(c.id, user_b)
+            })
+            .cloned())
+    }
+
+    async fn get_group_chat_by_name(
+        &self,
+        name: String,
+    ) -> Result<Option<chats::Model>, ServerError> {
+        let name = name.to_lowercase();
+        Ok(self
+            .tables()
+            .chats
+            .iter()
+            .find(|c| c.is_group && c.name.as_ref().map(|n| n.to_lowercase()) == Some(name.clone()))
+            .cloned())
+    }
+
+    async fn rename_chat(&self, chat_id: i32, name: String) -> Result<chats::Model, ServerError> {
+        let mut tables = self.tables();
+        let chat = tables
+            .chats
+            .iter_mut()
+            .find(|c| c.id == chat_id)
+            .ok_or_else(not_updated)?;
+        chat.name = Some(name);
+        Ok(chat.clone())
+    }
+
+    async fn add_chat_member(
+        &self,
+        chat_id: i32,
+        user_id: i32,
+        role: Role,
+    ) -> Result<(), ServerError> {
+        let mut tables = self.tables();
+        if tables.is_member(chat_id, user_id) {
+            return Err(duplicate("chat_members"));
+        }
+        tables.chat_members.push(chat_members::Model {
+            chat_id,
+            user_id,
+            role,
+        });
+        Ok(())
+    }
+
+    async fn remove_chat_member(&self, chat_id: i32, user_id: i32) -> Result<(), ServerError> {
+        self.tables()
+            .chat_members
+            .retain(|m| !(m.chat_id == chat_id && m.user_id == user_id));
+        Ok(())
+    }
+
+    async fn set_chat_member_role(
+        &self,
+        chat_id: i32,
+        user_id: i32,
+        role: Role,
+    ) -> Result<(), ServerError> {
+        let mut tables = self.tables();
+        let member = tables
+            .chat_members
+            .iter_mut()
+            .find(|m| m.chat_id == chat_id && m.user_id == user_id)
+            .ok_or_else(not_updated)?;
+        member.role = role;
+        Ok(())
+    }
+
+    async fn get_chat_member(
+        &self,
+        chat_id: i32,
+        user_id: i32,
+    ) -> Result<Option<chat_members::Model>, ServerError> {
+        Ok(self
+            .tables()
+            .chat_members
+            .iter()
+            .find(|m| m.chat_id == chat_id && m.user_id == user_id)
+            .cloned())
+    }
+
+    async fn get_chat_members(
+        &self,
+        chat_id: i32,
+    ) -> Result<Vec<chat_members::Model>, ServerError> {
+        Ok(self
+            .tables()
+            .chat_members
+            .iter()
+            .filter(|m| m.chat_id == chat_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_chat_by_id(&self, chat_id: i32) -> Result<Option<chats::Model>, ServerError> {
+        Ok(self
+            .tables()
+            .chats
+            .iter()
+            .find(|c| c.id == chat_id)
+            .cloned())
+    }
+
+    async fn get_user_chats_before(
+        &self,
+        user_id: i32,
+        before: Option<(NaiveDateTime, i32)>,
+        limit: u64,
+    ) -> Result<Vec<(chats::Model, NaiveDateTime)>, ServerError> {
+        let tables = self.tables();
+        let mut chats: Vec<(chats::Model, NaiveDateTime)> = tables
+            .chats
+            .iter()
+            .filter(|c| tables.is_member(c.id, user_id))
+            .map(|c| {
+                let last_activity = tables
+                    .messages
+                    .iter()
+                    .filter(|m| m.chat_id == c.id)
+                    .map(|m| m.timestamp)
+                    .max()
+                    .unwrap_or(c.created_at);
+                (c.clone(), last_activity)
+            })
+            .collect();
+        chats.sort_by_key(|(chat, last_activity)| std::cmp::Reverse((*last_activity, chat.id)));
+
+        Ok(chats
+            .into_iter()
+            .filter(|(chat, last_activity)| match before {
+                Some(cursor) => (*last_activity, chat.id) < cursor,
+                None => true,
+            })
+            .take(limit as usize)
+            .collect())
+    }
+
+    async fn get_chat_user_ids(&self, chat_id: i32) -> Result<Vec<i32>, ServerError> {
+        Ok(self
+            .tables()
+            .chat_members
+            .iter()
+            .filter(|m| m.chat_id == chat_id)
+            .map(|m| m.user_id)
+            .collect())
+    }
+
+    async fn get_other_usernames_in_chat(
+        &self,
+        chat_id: i32,
+        current_user_id: i32,
+    ) -> Result<Vec<String>, ServerError> {
+        let tables = self.tables();
+        Ok(tables
+            .users
+            .iter()
+            .filter(|u| u.id != current_user_id && tables.is_member(chat_id, u.id))
+            .map(|u| u.username.clone())
+            .collect())
+    }
+
+    async fn get_messages_before(
+        &self,
+        chat_id: i32,
+        before_id: Option<i32>,
+        limit: u64,
+    ) -> Result<Vec<messages::Model>, ServerError> {
+        let tables = self.tables();
+        let mut messages: Vec<messages::Model> = tables
+            .messages
+            .iter()
+            .rev()
+            .filter(|m| m.chat_id == chat_id && before_id.is_none_or(|id| m.id < id))
+            .take(limit as usize)
+            .cloned()
+            .collect();
+        messages.reverse();
+        Ok(messages)
+    }
+
+    async fn get_messages_after(
+        &self,
+        chat_id: i32,
+        after_id: i32,
+        limit: u64,
+    ) -> Result<Vec<messages::Model>, ServerError> {
+        Ok(self
+            .tables()
+            .messages
+            .iter()
+            .filter(|m| m.chat_id == chat_id && m.id > after_id)
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn send_message(
+        &self,
+        chat_id: i32,
+        sender_id: i32,
+        username: String,
+        content: String,
+        idempotency_key: Option<String>,
+    ) -> Result<messages::Model, ServerError> {
+        self.tables().insert_message(
+            chat_id,
+            sender_id,
+            username,
+            content,
+            false,
+            idempotency_key,
+        )
+    }
+
+    async fn send_system_message(
+        &self,
+        chat_id: i32,
+        actor_id: i32,
+        actor_username: String,
+        content: String,
+    ) -> Result<messages::Model, ServerError> {
+        self.tables()
+            .insert_message(chat_id, actor_id, actor_username, content, true, None)
+    }
+
+    async fn get_message_by_id(
+        &self,
+        message_id: i32,
+    ) -> Result<Option<messages::Model>, ServerError> {
+        Ok(self
+            .tables()
+            .messages
+            .iter()
+            .find(|m| m.id == message_id)
+            .cloned())
+    }
+
+    async fn get_message_by_idempotency_key(
+        &self,
+        sender_id: i32,
+        idempotency_key: &str,
+    ) -> Result<Option<messages::Model>, ServerError> {
+        Ok(self
+            .tables()
+            .messages
+            .iter()
+            .find(|m| {
+                m.sender_id == sender_id && m.idempotency_key.as_deref() == Some(idempotency_key)
+            })
+            .cloned())
+    }
+
+    async fn edit_message(
+        &self,
+        message: messages::Model,
+        content: String,
+    ) -> Result<messages::Model, ServerError> {
+        let now = Utc::now().naive_utc();
+        let mut tables = self.tables();
+        let edit = message_edits::Model {
+            id: next_id(tables.message_edits.iter().map(|e| e.id)),
+            message_id: message.id,
+            previous_content: message.content,
+            edited_at: now,
+        };
+        tables.message_edits.push(edit);
+
+        let stored = tables.message_mut(message.id)?;
+        stored.content = content;
+        stored.edited_at = Some(now);
+        Ok(stored.clone())
+    }
+
+    async fn delete_message(
+        &self,
+        message: messages::Model,
+    ) -> Result<messages::Model, ServerError> {
+        let mut tables = self.tables();
+        tables.message_edits.retain(|e| e.message_id != message.id);
+
+        let stored = tables.message_mut(message.id)?;
+        stored.content = String::new();
+        stored.deleted_at = Some(Utc::now().naive_utc());
+        Ok(stored.clone())
+    }
+
+    async fn get_message_edits(
+        &self,
+        message_id: i32,
+    ) -> Result<Vec<message_edits::Model>, ServerError> {
+        let mut edits: Vec<message_edits::Model> = self
+            .tables()
+            .message_edits
+            .iter()
+            .filter(|e| e.message_id == message_id)
+            .cloned()
+            .collect();
+        edits.sort_by_key(|e| e.edited_at);
+        Ok(edits)
+    }
+
+    async fn get_last_unread_message_id(
+        &self,
+        user_id: i32,
+        chat_id: i32,
+    ) -> Result<Option<i32>, ServerError> {
+        let tables = self.tables();
+        Ok(tables
+            .messages
+            .iter()
+            .filter(|m| m.chat_id == chat_id && tables.is_unread(m, user_id))
+            .map(|m| m.id)
+            .max())
+    }
+
+    async fn get_unread_counts(
+        &self,
+        user_id: i32,
+        chat_ids: Vec<i32>,
+    ) -> Result<HashMap<i32, u64>, ServerError> {
+        let tables = self.tables();
+        let mut counts = HashMap::new();
+        for message in tables
+            .messages
+            .iter()
+            .filter(|m| chat_ids.contains(&m.chat_id) && tables.is_unread(m, user_id))
+        {
+            *counts.entry(message.chat_id).or_insert(0) += 1;
+        }
+        Ok(counts)
+    }
+
+    async fn get_total_unread_count(&self, user_id: i32) -> Result<u64, ServerError> {
+        let tables = self.tables();
+        Ok(tables
+            .messages
+            .iter()
+            .filter(|m| tables.is_member(m.chat_id, user_id) && tables.is_unread(m, user_id))
+            .count() as u64)
+    }
+
+    async fn mark_messages_read(
+        &self,
+        user_id: i32,
+        chat_id: i32,
+        last_read_id: i32,
+    ) -> Result<NaiveDateTime, ServerError> {
+        let now = Utc::now().naive_utc();
+        let mut tables = self.tables();
+        let unread_ids: Vec<i32> = tables
+            .messages
+            .iter()
+            .filter(|m| m.chat_id == chat_id && m.id <= last_read_id)
+            .filter(|m| tables.is_unread(m, user_id))
+            .map(|m| m.id)
+            .collect();
+        tables
+            .message_reads
+            .extend(
+                unread_ids
+                    .into_iter()
+                    .map(|message_id| message_reads::Model {
+                        message_id,
+                        user_id,
+                        read_at: now,
+                    }),
+            );
+        Ok(now)
+    }
+
+    async fn get_message_readers(
+        &self,
+        message_ids: Vec<i32>,
+    ) -> Result<Vec<(i32, i32)>, ServerError> {
+        Ok(self
+            .tables()
+            .message_reads
+            .iter()
+            .filter(|r| message_ids.contains(&r.message_id))
+            .map(|r| (r.message_id, r.user_id))
+            .collect())
+    }
+
+    async fn get_message_receipts(
+        &self,
+        message_id: i32,
+    ) -> Result<Vec<(i32, String, NaiveDateTime)>, ServerError> {
+        let tables = self.tables();
+        let mut receipts: Vec<(i32, String, NaiveDateTime)> = tables
+            .message_reads
+            .iter()
+            .filter(|r| r.message_id == message_id)
+            .filter_map(|r| {
+                let user = tables.users.iter().find(|u| u.id == r.user_id)?;
+                Some((r.user_id, user.username.clone(), r.read_at))
+            })
+            .collect();
+        receipts.sort_by_key(|(user_id, _, read_at)| (*read_at, *user_id));
+        Ok(receipts)
+    }
+}
@@ -1,2 +1,5 @@
 pub mod chat_repository;
+pub mod in_memory;
+pub mod session_repository;
+pub mod store;
 pub mod user_repository;
\ No newline at end of file
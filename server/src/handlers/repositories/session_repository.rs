@@ -0,0 +1,101 @@
+use crate::entity::sessions;
+use crate::utils;
+use chrono::{NaiveDateTime, Utc};
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, NotSet, QueryFilter, QueryOrder,
+    Set,
+};
+use utils::errors::server_error::ServerError;
+
+pub async fn create_session<C: ConnectionTrait>(
+    user_id: i32,
+    refresh_token_hash: String,
+    expires_at: NaiveDateTime,
+    db: &C,
+) -> Result<sessions::Model, ServerError> {
+    let now = Utc::now().naive_utc();
+    let session = sessions::ActiveModel {
+        id: NotSet,
+        user_id: Set(user_id),
+        refresh_token_hash: Set(refresh_token_hash),
+        created_at: Set(now),
+        last_used_at: Set(now),
+        expires_at: Set(expires_at),
+        revoked_at: NotSet,
+    };
+
+    session.insert(db).await.map_err(ServerError::DatabaseError)
+}
+
+pub async fn get_session<C: ConnectionTrait>(
+    session_id: i32,
+    db: &C,
+) -> Result<Option<sessions::Model>, ServerError> {
+    sessions::Entity::find_by_id(session_id)
+        .one(db)
+        .await
+        .map_err(ServerError::DatabaseError)
+}
+
+/// Swaps the refresh token hash, but only if the old one still matches and the
+/// session is live. Returns false when the old token was already used.
+pub async fn rotate_refresh_token<C: ConnectionTrait>(
+    session_id: i32,
+    old_hash: String,
+    new_hash: String,
+    expires_at: NaiveDateTime,
+    db: &C,
+) -> Result<bool, ServerError> {
+    let result = sessions::Entity::update_many()
+        .col_expr(sessions::Column::RefreshTokenHash, Expr::value(new_hash))
+        .col_expr(
+            sessions::Column::LastUsedAt,
+            Expr::value(Utc::now().naive_utc()),
+        )
+        .col_expr(sessions::Column::ExpiresAt, Expr::value(expires_at))
+        .filter(sessions::Column::Id.eq(session_id))
+        .filter(sessions::Column::RefreshTokenHash.eq(old_hash))
+        .filter(sessions::Column::RevokedAt.is_null())
+        .exec(db)
+        .await
+        .map_err(ServerError::DatabaseError)?;
+
+    Ok(result.rows_affected == 1)
+}
+
+/// Revokes one of the user's sessions. Returns false if they have no such live session.
+pub async fn revoke_session<C: ConnectionTrait>(
+    session_id: i32,
+    user_id: i32,
+    db: &C,
+) -> Result<bool, ServerError> {
+    let result = sessions::Entity::update_many()
+        .col_expr(
+            sessions::Column::RevokedAt,
+            Expr::value(Utc::now().naive_utc()),
+        )
+        .filter(sessions::Column::Id.eq(session_id))
+        .filter(sessions::Column::UserId.eq(user_id))
+        .filter(sessions::Column::RevokedAt.is_null())
+        .exec(db)
+        .await
+        .map_err(ServerError::DatabaseError)?;
+
+    Ok(result.rows_affected == 1)
+}
+
+/// Sessions that are neither revoked nor expired, newest first
+pub async fn get_active_sessions<C: ConnectionTrait>(
+    user_id: i32,
+    db: &C,
+) -> Result<Vec<sessions::Model>, ServerError> {
+    sessions::Entity::find()
+        .filter(sessions::Column::UserId.eq(user_id))
+        .filter(sessions::Column::RevokedAt.is_null())
+        .filter(sessions::Column::ExpiresAt.gt(Utc::now().naive_utc()))
+        .order_by_desc(sessions::Column::LastUsedAt)
+        .all(db)
+        .await
+        .map_err(ServerError::DatabaseError)
+}
@@ -0,0 +1,44 @@
+use crate::handlers::repositories::chat_repository::ChatRepository;
+use crate::handlers::repositories::user_repository::UserRepository;
+use crate::utils::errors::server_error::ServerError;
+use async_trait::async_trait;
+use sea_orm::{ConnectionTrait, DatabaseTransaction, TransactionTrait};
+
+/// Where services get their repositories from. A database connection is one,
+/// and so is `InMemoryRepositories`, which lets services run without a database.
+#[async_trait]
+pub trait Repositories: Send + Sync {
+    fn users(&self) -> &dyn UserRepository;
+    fn chats(&self) -> &dyn ChatRepository;
+
+    /// Starts a transaction. Its writes are thrown away unless it is committed.
+    async fn begin(&self) -> Result<Box<dyn Transaction>, ServerError>;
+}
+
+/// Repositories whose writes are kept or dropped together
+#[async_trait]
+pub trait Transaction: Repositories {
+    async fn commit(self: Box<Self>) -> Result<(), ServerError>;
+}
+
+#[async_trait]
+impl<C: ConnectionTrait + TransactionTrait + Send> Repositories for C {
+    fn users(&self) -> &dyn UserRepository {
+        self
+    }
+
+    fn chats(&self) -> &dyn ChatRepository {
+        self
+    }
+
+    async fn begin(&self) -> Result<Box<dyn Transaction>, ServerError> {
+        Ok(Box::new(TransactionTrait::begin(self).await?))
+    }
+}
+
+#[async_trait]
+impl Transaction for DatabaseTransaction {
+    async fn commit(self: Box<Self>) -> Result<(), ServerError> {
+        Ok(DatabaseTransaction::commit(*self).await?)
+    }
+}
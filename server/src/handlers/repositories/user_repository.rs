@@ -1,157 +1,140 @@
 use crate::entity::sea_orm_active_enums::Status;
 use crate::entity::users;
 use crate::{entity, utils};
-use sea_orm::sea_query::Expr;
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, SubsecRound, Utc};
+use sea_orm::sea_query::{Expr, Func, SimpleExpr};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait, NotSet, QueryFilter,
-    Set,
+    ActiveEnum, ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DbBackend, EntityTrait,
+    NotSet, QueryFilter, Set,
 };
-use std::sync::Arc;
 use utils::errors::server_error::ServerError;
 
-pub async fn register_user(
+pub async fn register_user<C: ConnectionTrait>(
     username: String,
     hashed: String,
-    db: Arc<DatabaseConnection>,
-) -> Result<entity::users::ActiveModel, ServerError> {
+    db: &C,
+) -> Result<entity::users::Model, ServerError> {
     // Create a new user
     let new_user = entity::users::ActiveModel {
         id: NotSet,
         username: Set(username),
         password_hash: Set(hashed.clone()),
+        last_seen_at: NotSet,
     };
 
     // Save the user to DB
     new_user
-        .save(&*db)
+        .insert(db)
         .await
-        .map_err(|err| ServerError::DatabaseError(err))
+        .map_err(ServerError::DatabaseError)
 }
 
-pub async fn get_user_by_username(
+pub async fn get_user_by_username<C: ConnectionTrait>(
     username: String,
-    db: Arc<DatabaseConnection>,
+    db: &C,
 ) -> Result<Option<entity::users::Model>, ServerError> {
     entity::users::Entity::find()
-        .filter(entity::users::Column::Username.eq(username.clone()))
-        .one(&*db)
+        .filter(username_is(&username))
+        .one(db)
         .await
-        .map_err(|err| ServerError::DatabaseError(err))
+        .map_err(ServerError::DatabaseError)
 }
 
-pub async fn get_user_by_id(
+// MySQL compares usernames case-insensitively and SQLite and Postgres don't,
+// so lowercase both sides to behave the same everywhere
+fn username_is(username: &str) -> SimpleExpr {
+    Expr::expr(Func::lower(Expr::col(users::Column::Username))).eq(username.to_lowercase())
+}
+
+pub async fn get_user_by_id<C: ConnectionTrait>(
     id: i32,
-    db: Arc<DatabaseConnection>,
+    db: &C,
 ) -> Result<Option<entity::users::Model>, ServerError> {
     entity::users::Entity::find()
         .filter(entity::users::Column::Id.eq(id))
-        .one(&*db)
+        .one(db)
         .await
-        .map_err(|err| ServerError::DatabaseError(err))
+        .map_err(ServerError::DatabaseError)
 }
 
-pub async fn send_friend_request(
+/// Stores a new pending request
+pub async fn create_friend_request<C: ConnectionTrait>(
     sender_id: i32,
     receiver_id: i32,
-    db: Arc<DatabaseConnection>,
+    db: &C,
 ) -> Result<(), ServerError> {
-    // Check if these users are already friends
-    if let Some(_) = get_friendship(sender_id, receiver_id, db.clone()).await? {
-        return Err(ServerError::AlreadyFriends);
-    }
-
-    // Check if this exact request already exists
-    if let Some(_) = get_friend_request(sender_id, receiver_id, db.clone()).await? {
-        return Ok(());
-    }
-
-    // Check for a reverse request
-    let reverse = get_friend_request(receiver_id, sender_id, db.clone()).await?;
-
-    if let Some(existing_request) = reverse {
-        // Accept the reverse request
-        let mut model: entity::friend_requests::ActiveModel = existing_request.into();
-        model.status = Set(Status::Accepted);
-        model.update(&*db).await?;
-
-        // Create mutual friendships
-        for (u1, u2) in [(sender_id, receiver_id), (receiver_id, sender_id)] {
-            create_friendship(u1, u2, db.clone()).await?;
-        }
-
-        return Ok(());
-    }
-
-    // No existing or reverse request, create a new one
     let new_request = entity::friend_requests::ActiveModel {
         sender_id: Set(sender_id),
         receiver_id: Set(receiver_id),
         status: Set(Status::Pending),
+        sent_at: Set(Utc::now().naive_utc()),
         ..Default::default()
     };
 
     new_request
-        .insert(&*db)
+        .insert(db)
         .await
         .map_err(ServerError::DatabaseError)?;
 
     Ok(())
 }
 
-pub async fn get_friend_request(
+pub async fn get_friend_request<C: ConnectionTrait>(
     sender_id: i32,
     receiver_id: i32,
-    db: Arc<DatabaseConnection>,
+    db: &C,
 ) -> Result<Option<entity::friend_requests::Model>, ServerError> {
     Ok(entity::friend_requests::Entity::find()
         .filter(entity::friend_requests::Column::SenderId.eq(sender_id))
         .filter(entity::friend_requests::Column::ReceiverId.eq(receiver_id))
         .filter(entity::friend_requests::Column::Status.eq(Status::Pending))
-        .one(&*db)
+        .one(db)
         .await?)
 }
 
-pub async fn update_friend_request_status(
+pub async fn update_friend_request_status<C: ConnectionTrait>(
     sender_id: i32,
     receiver_id: i32,
     status: Status,
-    db: Arc<DatabaseConnection>,
+    db: &C,
 ) -> Result<(), ServerError> {
     if status == Status::Rejected {
         entity::friend_requests::Entity::delete_many()
             .filter(entity::friend_requests::Column::SenderId.eq(sender_id))
             .filter(entity::friend_requests::Column::ReceiverId.eq(receiver_id))
-            .exec(&*db)
+            .exec(db)
             .await?;
     } else {
         entity::friend_requests::Entity::update_many()
-            .col_expr(entity::friend_requests::Column::Status, Expr::value(status))
+            // as_enum adds the cast Postgres needs for its enum type
+            .col_expr(entity::friend_requests::Column::Status, status.as_enum())
             .filter(entity::friend_requests::Column::SenderId.eq(sender_id))
             .filter(entity::friend_requests::Column::ReceiverId.eq(receiver_id))
-            .exec(&*db)
+            .exec(db)
             .await?;
     }
 
     Ok(())
 }
 
-pub async fn create_friendship(
+pub async fn create_friendship<C: ConnectionTrait>(
     u1: i32,
     u2: i32,
-    db: Arc<DatabaseConnection>,
+    db: &C,
 ) -> Result<(), ServerError> {
     let friendship = entity::friends::ActiveModel {
         user_id: Set(u1),
         friend_id: Set(u2),
     };
-    friendship.insert(&*db).await?;
+    friendship.insert(db).await?;
     Ok(())
 }
 
-pub async fn get_user_blocked(
+pub async fn get_user_blocked<C: ConnectionTrait>(
     sender_id: i32,
     receiver_id: i32,
-    db: Arc<DatabaseConnection>,
+    db: &C,
 ) -> Result<Option<entity::blocked_users::Model>, ServerError> {
     entity::blocked_users::Entity::find()
         .filter(
@@ -167,15 +150,43 @@ pub async fn get_user_blocked(
                         .and(entity::blocked_users::Column::BlockedId.eq(sender_id)),
                 ),
         )
-        .one(&*db)
+        .one(db)
+        .await
+        .map_err(ServerError::DatabaseError)
+}
+
+pub async fn get_user_blocks<C: ConnectionTrait>(
+    user_id: i32,
+    db: &C,
+) -> Result<Vec<entity::blocked_users::Model>, ServerError> {
+    entity::blocked_users::Entity::find()
+        .filter(
+            Condition::any()
+                .add(entity::blocked_users::Column::UserId.eq(user_id))
+                .add(entity::blocked_users::Column::BlockedId.eq(user_id)),
+        )
+        .all(db)
+        .await
+        .map_err(ServerError::DatabaseError)
+}
+
+pub async fn get_block<C: ConnectionTrait>(
+    user_id: i32,
+    blocked_id: i32,
+    db: &C,
+) -> Result<Option<entity::blocked_users::Model>, ServerError> {
+    entity::blocked_users::Entity::find()
+        .filter(entity::blocked_users::Column::UserId.eq(user_id))
+        .filter(entity::blocked_users::Column::BlockedId.eq(blocked_id))
+        .one(db)
         .await
-        .map_err(|err| ServerError::DatabaseError(err))
+        .map_err(ServerError::DatabaseError)
 }
 
-pub async fn block_user(
+pub async fn block_user<C: ConnectionTrait>(
     user_id: i32,
     blocked_id: i32,
-    db: Arc<DatabaseConnection>,
+    db: &C,
 ) -> Result<(), ServerError> {
     let new_block = entity::blocked_users::ActiveModel {
         user_id: Set(user_id),
@@ -183,17 +194,43 @@ pub async fn block_user(
     };
 
     new_block
-        .insert(&*db)
+        .insert(db)
+        .await
+        .map_err(ServerError::DatabaseError)?;
+
+    Ok(())
+}
+
+pub async fn unblock_user<C: ConnectionTrait>(
+    user_id: i32,
+    blocked_id: i32,
+    db: &C,
+) -> Result<(), ServerError> {
+    entity::blocked_users::Entity::delete_many()
+        .filter(entity::blocked_users::Column::UserId.eq(user_id))
+        .filter(entity::blocked_users::Column::BlockedId.eq(blocked_id))
+        .exec(db)
         .await
         .map_err(ServerError::DatabaseError)?;
 
     Ok(())
 }
 
-pub async fn delete_friendship(
+pub async fn get_blocked_users<C: ConnectionTrait>(
+    user_id: i32,
+    db: &C,
+) -> Result<Vec<entity::blocked_users::Model>, ServerError> {
+    entity::blocked_users::Entity::find()
+        .filter(entity::blocked_users::Column::UserId.eq(user_id))
+        .all(db)
+        .await
+        .map_err(ServerError::DatabaseError)
+}
+
+pub async fn delete_friendship<C: ConnectionTrait>(
     u1: i32,
     u2: i32,
-    db: Arc<DatabaseConnection>,
+    db: &C,
 ) -> Result<(), ServerError> {
     entity::friends::Entity::delete_many()
         .filter(
@@ -209,17 +246,17 @@ pub async fn delete_friendship(
                         .and(entity::friends::Column::FriendId.eq(u1)),
                 ),
         )
-        .exec(&*db)
+        .exec(db)
         .await
         .map_err(ServerError::DatabaseError)?;
 
     Ok(())
 }
 
-pub async fn get_friendship(
+pub async fn get_friendship<C: ConnectionTrait>(
     u1: i32,
     u2: i32,
-    db: Arc<DatabaseConnection>,
+    db: &C,
 ) -> Result<Option<entity::friends::Model>, ServerError> {
     entity::friends::Entity::find()
         .filter(
@@ -235,15 +272,15 @@ pub async fn get_friendship(
                         .and(entity::friends::Column::FriendId.eq(u1)),
                 ),
         )
-        .one(&*db)
+        .one(db)
         .await
-        .map_err(|err| ServerError::DatabaseError(err))
+        .map_err(ServerError::DatabaseError)
 }
 
-pub async fn delete_friend_requests(
+pub async fn delete_friend_requests<C: ConnectionTrait>(
     u1: i32,
     u2: i32,
-    db: Arc<DatabaseConnection>,
+    db: &C,
 ) -> Result<(), ServerError> {
     entity::friend_requests::Entity::delete_many()
         .filter(
@@ -259,47 +296,47 @@ pub async fn delete_friend_requests(
                         .and(entity::friend_requests::Column::ReceiverId.eq(u1)),
                 ),
         )
-        .exec(&*db)
+        .exec(db)
         .await
         .map_err(ServerError::DatabaseError)?;
 
     Ok(())
 }
 
-pub async fn get_user_friends(
+pub async fn get_user_friends<C: ConnectionTrait>(
     user_id: i32,
-    db: Arc<DatabaseConnection>,
+    db: &C,
 ) -> Result<Vec<entity::friends::Model>, ServerError> {
     let friends: Vec<entity::friends::Model> = entity::friends::Entity::find()
         .filter(entity::friends::Column::UserId.eq(user_id))
-        .all(&*db)
+        .all(db)
         .await
-        .map_err(|err| ServerError::DatabaseError(err))?;
+        .map_err(ServerError::DatabaseError)?;
     Ok(friends)
 }
 
-pub async fn get_users_from_list(
+pub async fn get_users_from_list<C: ConnectionTrait>(
     ids: Vec<i32>,
-    db: Arc<DatabaseConnection>,
+    db: &C,
 ) -> Result<Vec<entity::users::Model>, ServerError> {
     let users: Vec<entity::users::Model> = entity::users::Entity::find()
         .filter(entity::users::Column::Id.is_in(ids))
-        .all(&*db)
+        .all(db)
         .await
-        .map_err(|err| ServerError::DatabaseError(err))?;
+        .map_err(ServerError::DatabaseError)?;
     Ok(users)
 }
 
-pub async fn get_user_friend_requests(
+pub async fn get_user_friend_requests<C: ConnectionTrait>(
     user_id: i32,
     direction: Option<entity::friend_requests::Column>,
-    db: Arc<DatabaseConnection>,
+    db: &C,
 ) -> Result<Vec<entity::friend_requests::Model>, ServerError> {
     if let Some(direction) = direction {
         let requests: Vec<entity::friend_requests::Model> = entity::friend_requests::Entity::find()
             .filter(direction.eq(user_id))
             .filter(entity::friend_requests::Column::Status.eq(Status::Pending))
-            .all(&*db)
+            .all(db)
             .await?;
         return Ok(requests);
     }
@@ -307,29 +344,29 @@ pub async fn get_user_friend_requests(
     let incoming: Vec<entity::friend_requests::Model> = entity::friend_requests::Entity::find()
         .filter(entity::friend_requests::Column::ReceiverId.eq(user_id))
         .filter(entity::friend_requests::Column::Status.eq(Status::Pending))
-        .all(&*db)
+        .all(db)
         .await?;
 
     let outgoing: Vec<entity::friend_requests::Model> = entity::friend_requests::Entity::find()
         .filter(entity::friend_requests::Column::SenderId.eq(user_id))
         .filter(entity::friend_requests::Column::Status.eq(Status::Pending))
-        .all(&*db)
+        .all(db)
         .await?;
 
-    let requests = incoming.into_iter().chain(outgoing.into_iter()).collect();
+    let requests = incoming.into_iter().chain(outgoing).collect();
 
     Ok(requests)
 }
 
-pub async fn update_password(
+pub async fn update_password<C: ConnectionTrait>(
     username: String,
     hashed_password: String,
-    db: Arc<DatabaseConnection>,
+    db: &C,
 ) -> Result<(), ServerError> {
     // Find the user
     let user = users::Entity::find()
-        .filter(users::Column::Username.eq(username.clone()))
-        .one(&*db)
+        .filter(username_is(&username))
+        .one(db)
         .await
         .map_err(ServerError::DatabaseError)?;
 
@@ -342,10 +379,252 @@ pub async fn update_password(
     active.password_hash = Set(hashed_password);
 
     // Save to DB
-    active
-        .save(&*db)
+    active.save(db).await.map_err(ServerError::DatabaseError)?;
+
+    Ok(())
+}
+
+/// Records when the user was last connected, returning the time as the database keeps it
+pub async fn update_last_seen<C: ConnectionTrait>(
+    user_id: i32,
+    last_seen_at: NaiveDateTime,
+    db: &C,
+) -> Result<NaiveDateTime, ServerError> {
+    // Postgres keeps microseconds and MySQL's DATETIME whole seconds
+    let last_seen_at = match db.get_database_backend() {
+        DbBackend::MySql => last_seen_at.trunc_subsecs(0),
+        _ => last_seen_at.trunc_subsecs(6),
+    };
+    users::Entity::update_many()
+        .col_expr(users::Column::LastSeenAt, Expr::value(last_seen_at))
+        .filter(users::Column::Id.eq(user_id))
+        .exec(db)
         .await
         .map_err(ServerError::DatabaseError)?;
+    Ok(last_seen_at)
+}
+/// The user, friend and block queries services make. The functions above
+/// implement it for any sea-orm connection or transaction.
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn register_user(
+        &self,
+        username: String,
+        hashed: String,
+    ) -> Result<entity::users::Model, ServerError>;
+    async fn get_user_by_username(
+        &self,
+        username: String,
+    ) -> Result<Option<entity::users::Model>, ServerError>;
+    async fn get_user_by_id(&self, id: i32) -> Result<Option<entity::users::Model>, ServerError>;
+    async fn create_friend_request(
+        &self,
+        sender_id: i32,
+        receiver_id: i32,
+    ) -> Result<(), ServerError>;
+    async fn get_friend_request(
+        &self,
+        sender_id: i32,
+        receiver_id: i32,
+    ) -> Result<Option<entity::friend_requests::Model>, ServerError>;
+    async fn update_friend_request_status(
+        &self,
+        sender_id: i32,
+        receiver_id: i32,
+        status: Status,
+    ) -> Result<(), ServerError>;
+    async fn create_friendship(&self, u1: i32, u2: i32) -> Result<(), ServerError>;
+    async fn get_user_blocked(
+        &self,
+        sender_id: i32,
+        receiver_id: i32,
+    ) -> Result<Option<entity::blocked_users::Model>, ServerError>;
+    async fn get_user_blocks(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<entity::blocked_users::Model>, ServerError>;
+    async fn get_block(
+        &self,
+        user_id: i32,
+        blocked_id: i32,
+    ) -> Result<Option<entity::blocked_users::Model>, ServerError>;
+    async fn block_user(&self, user_id: i32, blocked_id: i32) -> Result<(), ServerError>;
+    async fn unblock_user(&self, user_id: i32, blocked_id: i32) -> Result<(), ServerError>;
+    async fn get_blocked_users(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<entity::blocked_users::Model>, ServerError>;
+    async fn delete_friendship(&self, u1: i32, u2: i32) -> Result<(), ServerError>;
+    async fn get_friendship(
+        &self,
+        u1: i32,
+        u2: i32,
+    ) -> Result<Option<entity::friends::Model>, ServerError>;
+    async fn delete_friend_requests(&self, u1: i32, u2: i32) -> Result<(), ServerError>;
+    async fn get_user_friends(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<entity::friends::Model>, ServerError>;
+    async fn get_users_from_list(
+        &self,
+        ids: Vec<i32>,
+    ) -> Result<Vec<entity::users::Model>, ServerError>;
+    async fn get_user_friend_requests(
+        &self,
+        user_id: i32,
+        direction: Option<entity::friend_requests::Column>,
+    ) -> Result<Vec<entity::friend_requests::Model>, ServerError>;
+    async fn update_password(
+        &self,
+        username: String,
+        hashed_password: String,
+    ) -> Result<(), ServerError>;
+    async fn update_last_seen(
+        &self,
+        user_id: i32,
+        last_seen_at: NaiveDateTime,
+    ) -> Result<NaiveDateTime, ServerError>;
+}
 
-    Ok(())
+#[async_trait]
+impl<C: ConnectionTrait + Send> UserRepository for C {
+    async fn register_user(
+        &self,
+        username: String,
+        hashed: String,
+    ) -> Result<entity::users::Model, ServerError> {
+        register_user(username, hashed, self).await
+    }
+
+    async fn get_user_by_username(
+        &self,
+        username: String,
+    ) -> Result<Option<entity::users::Model>, ServerError> {
+        get_user_by_username(username, self).await
+    }
+
+    async fn get_user_by_id(&self, id: i32) -> Result<Option<entity::users::Model>, ServerError> {
+        get_user_by_id(id, self).await
+    }
+
+    async fn create_friend_request(
+        &self,
+        sender_id: i32,
+        receiver_id: i32,
+    ) -> Result<(), ServerError> {
+        create_friend_request(sender_id, receiver_id, self).await
+    }
+
+    async fn get_friend_request(
+        &self,
+        sender_id: i32,
+        receiver_id: i32,
+    ) -> Result<Option<entity::friend_requests::Model>, ServerError> {
+        get_friend_request(sender_id, receiver_id, self).await
+    }
+
+    async fn update_friend_request_status(
+        &self,
+        sender_id: i32,
+        receiver_id: i32,
+        status: Status,
+    ) -> Result<(), ServerError> {
+        update_friend_request_status(sender_id, receiver_id, status, self).await
+    }
+
+    async fn create_friendship(&self, u1: i32, u2: i32) -> Result<(), ServerError> {
+        create_friendship(u1, u2, self).await
+    }
+
+    async fn get_user_blocked(
+        &self,
+        sender_id: i32,
+        receiver_id: i32,
+    ) -> Result<Option<entity::blocked_users::Model>, ServerError> {
+        get_user_blocked(sender_id, receiver_id, self).await
+    }
+
+    async fn get_user_blocks(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<entity::blocked_users::Model>, ServerError> {
+        get_user_blocks(user_id, self).await
+    }
+
+    async fn get_block(
+        &self,
+        user_id: i32,
+        blocked_id: i32,
+    ) -> Result<Option<entity::blocked_users::Model>, ServerError> {
+        get_block(user_id, blocked_id, self).await
+    }
+
+    async fn block_user(&self, user_id: i32, blocked_id: i32) -> Result<(), ServerError> {
+        block_user(user_id, blocked_id, self).await
+    }
+
+    async fn unblock_user(&self, user_id: i32, blocked_id: i32) -> Result<(), ServerError> {
+        unblock_user(user_id, blocked_id, self).await
+    }
+
+    async fn get_blocked_users(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<entity::blocked_users::Model>, ServerError> {
+        get_blocked_users(user_id, self).await
+    }
+
+    async fn delete_friendship(&self, u1: i32, u2: i32) -> Result<(), ServerError> {
+        delete_friendship(u1, u2, self).await
+    }
+
+    async fn get_friendship(
+        &self,
+        u1: i32,
+        u2: i32,
+    ) -> Result<Option<entity::friends::Model>, ServerError> {
+        get_friendship(u1, u2, self).await
+    }
+
+    async fn delete_friend_requests(&self, u1: i32, u2: i32) -> Result<(), ServerError> {
+        delete_friend_requests(u1, u2, self).await
+    }
+
+    async fn get_user_friends(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<entity::friends::Model>, ServerError> {
+        get_user_friends(user_id, self).await
+    }
+
+    async fn get_users_from_list(
+        &self,
+        ids: Vec<i32>,
+    ) -> Result<Vec<entity::users::Model>, ServerError> {
+        get_users_from_list(ids, self).await
+    }
+
+    async fn get_user_friend_requests(
+        &self,
+        user_id: i32,
+        direction: Option<entity::friend_requests::Column>,
+    ) -> Result<Vec<entity::friend_requests::Model>, ServerError> {
+        get_user_friend_requests(user_id, direction, self).await
+    }
+
+    async fn update_password(
+        &self,
+        username: String,
+        hashed_password: String,
+    ) -> Result<(), ServerError> {
+        update_password(username, hashed_password, self).await
+    }
+
+    async fn update_last_seen(
+        &self,
+        user_id: i32,
+        last_seen_at: NaiveDateTime,
+    ) -> Result<NaiveDateTime, ServerError> {
+        update_last_seen(user_id, last_seen_at, self).await
+    }
 }
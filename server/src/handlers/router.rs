@@ -0,0 +1,238 @@
+use crate::handlers::connections::Connection;
+use crate::handlers::policies::session_policy::Identity;
+use crate::utils::errors::server_error::ServerError;
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use serde::Serialize;
+use serde_json::json;
+use shared::client_response::{Command, CommandKind};
+use shared::models::auth_models::AuthResponseModel;
+use shared::server_response::ServerResponse;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+
+/// Whether a command can be sent before the connection logs in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Public,
+    LoggedIn,
+}
+
+/// A command on its way through the middleware to its handler
+pub struct Request {
+    pub command: Command,
+    pub access: Access,
+    pub connection: Connection,
+    // Who the connection is logged in as. Filled in by authentication for
+    // commands that need a login.
+    pub identity: Option<Identity>,
+}
+
+/// Wraps every request before it reaches its handler. Call `next.run` to pass
+/// the request on, or answer it directly to stop it there.
+#[async_trait]
+pub trait Middleware: Send + Sync {
+    async fn handle(&self, request: Request, next: Next<'_>) -> ServerResponse;
+}
+
+/// The middleware still to run for a request, then its handler
+pub struct Next<'a> {
+    middleware: &'a [Arc<dyn Middleware>],
+    handler: &'a Handler,
+}
+
+impl<'a> Next<'a> {
+    pub fn run(self, request: Request) -> BoxFuture<'a, ServerResponse> {
+        match self.middleware.split_first() {
+            Some((first, rest)) => first.handle(
+                request,
+                Next {
+                    middleware: rest,
+                    handler: self.handler,
+                },
+            ),
+            None => (self.handler)(request),
+        }
+    }
+}
+
+/// What a handler answers with. Anything serializable is sent as the
+/// response data.
+pub trait Reply: Send + 'static {
+    fn into_response(self, message: &str) -> ServerResponse;
+}
+
+impl<T: Serialize + Send + 'static> Reply for T {
+    fn into_response(self, message: &str) -> ServerResponse {
+        build_response::<_, ServerError>(Ok(self), None, message)
+    }
+}
+
+/// The answer to a command that logs the connection in. Its access token is
+/// also sent at the top of the response.
+pub struct SessionStarted(pub AuthResponseModel);
+
+impl Reply for SessionStarted {
+    fn into_response(self, message: &str) -> ServerResponse {
+        let jwt = Some(self.0.token.clone());
+        build_response::<_, ServerError>(Ok(self.0), jwt, message)
+    }
+}
+
+/// The fields of one kind of command, which its handler takes in place of
+/// the whole command
+pub trait Payload: Sized + Send + 'static {
+    const KIND: CommandKind;
+
+    /// The command's fields, or None for any other kind of command
+    fn from_command(command: Command) -> Option<Self>;
+}
+
+type Handler = Box<dyn Fn(Request) -> BoxFuture<'static, ServerResponse> + Send + Sync>;
+
+struct Route {
+    access: Access,
+    handler: Handler,
+}
+
+/// Sends each command to the handler registered for it, through the
+/// middleware in the order it was added
+#[derive(Default)]
+pub struct Router {
+    routes: HashMap<CommandKind, Route>,
+    middleware: Vec<Arc<dyn Middleware>>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Router::default()
+    }
+
+    /// Adds a command anyone can send, logged in or not. It is routed by the
+    /// kind of payload its handler takes, and `message` is sent back when it
+    /// succeeds.
+    pub fn public<P, F, Fut, R>(self, message: &'static str, handler: F) -> Self
+    where
+        P: Payload,
+        F: Fn(P, Connection) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R, ServerError>> + Send + 'static,
+        R: Reply,
+    {
+        let handler: Handler = Box::new(move |request: Request| {
+            let Some(payload) = P::from_command(request.command) else {
+                return Box::pin(async { misrouted::<P>() });
+            };
+            let reply = handler(payload, request.connection);
+            Box::pin(async move { respond(reply.await, message) })
+        });
+        self.route(P::KIND, Access::Public, handler)
+    }
+
+    /// Adds a command that runs as the user the connection is logged in as
+    pub fn logged_in<P, F, Fut, R>(self, message: &'static str, handler: F) -> Self
+    where
+        P: Payload,
+        F: Fn(P, Identity, Connection) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R, ServerError>> + Send + 'static,
+        R: Reply,
+    {
+        let handler: Handler = Box::new(move |request: Request| {
+            // Without authentication in front there's nobody to run as
+            let Some(identity) = request.identity else {
+                return Box::pin(async { respond::<()>(Err(ServerError::NotLoggedIn), "") });
+            };
+            let Some(payload) = P::from_command(request.command) else {
+                return Box::pin(async { misrouted::<P>() });
+            };
+            let reply = handler(payload, identity, request.connection);
+            Box::pin(async move { respond(reply.await, message) })
+        });
+        self.route(P::KIND, Access::LoggedIn, handler)
+    }
+
+    fn route(mut self, kind: CommandKind, access: Access, handler: Handler) -> Self {
+        if self
+            .routes
+            .insert(kind, Route { access, handler })
+            .is_some()
+        {
+            panic!("{} has two routes", kind.name());
+        }
+        self
+    }
+
+    /// Whether a handler is registered for the kind of command
+    pub fn handles(&self, kind: CommandKind) -> bool {
+        self.routes.contains_key(&kind)
+    }
+
+    /// Adds middleware around every route
+    pub fn layer(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Runs a command from the connection and answers it
+    pub async fn handle(&self, command: Command, connection: Connection) -> ServerResponse {
+        let Some(route) = self.routes.get(&command.kind()) else {
+            let e = ServerError::UnknownCommand(command.name().to_string());
+            return respond::<()>(Err(e), "");
+        };
+
+        let request = Request {
+            command,
+            access: route.access,
+            connection,
+            identity: None,
+        };
+        let next = Next {
+            middleware: &self.middleware,
+            handler: &route.handler,
+        };
+        next.run(request).await
+    }
+}
+
+// Routes are keyed by their payload's kind, so this can't happen
+fn misrouted<P: Payload>() -> ServerResponse {
+    respond::<()>(
+        Err(ServerError::UnknownCommand(P::KIND.name().to_string())),
+        "",
+    )
+}
+
+fn respond<R: Reply>(result: Result<R, ServerError>, message: &str) -> ServerResponse {
+    match result {
+        Ok(reply) => reply.into_response(message),
+        Err(e) => build_response::<(), _>(Err(e), None, message),
+    }
+}
+
+/// Builds a response based on
+/// 1: The result of controller call
+/// 2: The type of model returned by the controller
+pub fn build_response<T, E>(
+    result: Result<T, E>,
+    jwt: Option<String>,
+    message: &str,
+) -> ServerResponse
+where
+    T: Serialize,
+    E: std::fmt::Display,
+{
+    match result {
+        Ok(data) => ServerResponse {
+            jwt,
+            success: true,
+            message: Some(message.to_string()),
+            data: Some(json!(data)),
+        },
+        Err(e) => ServerResponse {
+            jwt: None,
+            success: false,
+            message: Some(e.to_string()),
+            data: None,
+        },
+    }
+}
@@ -0,0 +1,102 @@
+use crate::handlers::connections::{bind_session, end_session, Connection};
+use crate::handlers::controllers::auth_controller;
+use crate::handlers::policies::session_policy::Identity;
+use crate::handlers::router::SessionStarted;
+use crate::handlers::routes::payloads::{
+    ListSessions, Login, Logout, RefreshToken, Register, ResumeSession, RevokeSession,
+    UpdateProfile,
+};
+use crate::utils::errors::server_error::ServerError;
+use shared::models::auth_models::{AuthResponseModel, SessionList};
+use shared::models::server_models::ServerResponseModel;
+use tracing::info;
+
+fn identity_of(auth: &AuthResponseModel) -> Identity {
+    Identity {
+        user_id: auth.user_id,
+        session_id: auth.session_id,
+    }
+}
+
+pub async fn register(
+    Register { username, password }: Register,
+    connection: Connection,
+) -> Result<SessionStarted, ServerError> {
+    let auth = auth_controller::register(username, password, connection.db()).await?;
+    // User is automatically logged in upon registration
+    bind_session(identity_of(&auth), &connection).await;
+    Ok(SessionStarted(auth))
+}
+
+pub async fn login(
+    Login { username, password }: Login,
+    connection: Connection,
+) -> Result<SessionStarted, ServerError> {
+    let auth = auth_controller::login(username.clone(), password, connection.db()).await?;
+    bind_session(identity_of(&auth), &connection).await;
+    info!("User {} logged in", username);
+    Ok(SessionStarted(auth))
+}
+
+// A new connection picks up an existing session with its access token
+pub async fn resume_session(
+    ResumeSession { token }: ResumeSession,
+    connection: Connection,
+) -> Result<ServerResponseModel, ServerError> {
+    let identity = auth_controller::resume_session(token, connection.db()).await?;
+    bind_session(identity, &connection).await;
+    Ok(ServerResponseModel { success: true })
+}
+
+// Refreshing proves the session is ours, so it also resumes it
+pub async fn refresh_token(
+    RefreshToken { refresh_token }: RefreshToken,
+    connection: Connection,
+) -> Result<SessionStarted, ServerError> {
+    let auth = auth_controller::refresh_token(refresh_token, connection.db()).await?;
+    bind_session(identity_of(&auth), &connection).await;
+    Ok(SessionStarted(auth))
+}
+
+pub async fn update_profile(
+    UpdateProfile { new_password }: UpdateProfile,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ServerResponseModel, ServerError> {
+    auth_controller::update_password(identity.user_id, new_password, connection.db()).await
+}
+
+// Revoking the session stops both its access and refresh tokens working
+pub async fn logout(
+    _: Logout,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ServerResponseModel, ServerError> {
+    let result = auth_controller::logout(identity, connection.db()).await?;
+    end_session(identity, &connection.state, Some(&connection)).await;
+    Ok(result)
+}
+
+pub async fn list_sessions(
+    _: ListSessions,
+    identity: Identity,
+    connection: Connection,
+) -> Result<SessionList, ServerError> {
+    auth_controller::list_sessions(identity, connection.db()).await
+}
+
+pub async fn revoke_session(
+    RevokeSession { session_id }: RevokeSession,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ServerResponseModel, ServerError> {
+    let result =
+        auth_controller::revoke_session(identity.user_id, session_id, connection.db()).await?;
+    // Its connections stop getting events now, not at their next command
+    let revoked = Identity {
+        user_id: identity.user_id,
+        session_id,
+    };
+    end_session(revoked, &connection.state, Some(&connection)).await;
+    Ok(result)
+}
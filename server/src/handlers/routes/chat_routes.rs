@@ -0,0 +1,305 @@
+use crate::handlers::connections::{
+    notify_chat_created, notify_chat_members, notify_chat_message, notify_chat_updated,
+    notify_users, Connection,
+};
+use crate::handlers::controllers::chat_controller;
+use crate::handlers::policies::session_policy::Identity;
+use crate::handlers::routes::payloads::{
+    AddChatMembers, CreateChat, DeleteMessage, EditMessage, GetChatMembers, GetChatMessages,
+    GetChats, GetMessageReceipts, GetUnreadChatMessageCount, GetUnreadMessageCount, LeaveChat,
+    MarkMessagesRead, RemoveChatMember, RenameChat, SendMessage, SetChatMemberRole,
+    TransferOwnership,
+};
+use crate::utils::errors::server_error::ServerError;
+use shared::models::chat_models::{
+    Chat, ChatList, ChatMembers, ChatMessage, ChatMessages, Count, MessageReceipts, ReadReceipt,
+};
+use shared::server_response::ServerEvent;
+
+pub async fn get_chats(
+    GetChats { before, limit }: GetChats,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ChatList, ServerError> {
+    chat_controller::get_user_chats(identity.user_id, before, limit, connection.db()).await
+}
+
+pub async fn create_chat(
+    CreateChat {
+        name,
+        is_group,
+        member_ids,
+    }: CreateChat,
+    identity: Identity,
+    connection: Connection,
+) -> Result<Chat, ServerError> {
+    let chat = chat_controller::create_chat(
+        identity.user_id,
+        name,
+        is_group,
+        member_ids,
+        connection.db(),
+    )
+    .await?;
+    if let Ok(user_ids) = chat_controller::get_chat_user_ids(chat.id, connection.db()).await {
+        notify_chat_created(chat.id, user_ids, &connection.state, Some(&connection)).await;
+    }
+    Ok(chat)
+}
+
+pub async fn get_chat_messages(
+    GetChatMessages {
+        chat_id,
+        before_id,
+        after_id,
+        limit,
+    }: GetChatMessages,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ChatMessages, ServerError> {
+    chat_controller::get_chat_messages(
+        identity.user_id,
+        chat_id,
+        before_id,
+        after_id,
+        limit,
+        connection.db(),
+    )
+    .await
+}
+
+pub async fn send_message(
+    SendMessage {
+        chat_id,
+        content,
+        idempotency_key,
+    }: SendMessage,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ChatMessage, ServerError> {
+    let (message, stored) = chat_controller::send_message(
+        identity.user_id,
+        chat_id,
+        content,
+        idempotency_key,
+        connection.db(),
+    )
+    .await?;
+
+    // Push a new message to the affected online users. A retry of one
+    // already sent was pushed the first time.
+    if stored {
+        let state = &connection.state;
+        notify_chat_message(chat_id, message.clone(), &[], state, Some(&connection)).await;
+    }
+    Ok(message)
+}
+
+pub async fn edit_message(
+    EditMessage {
+        chat_id,
+        message_id,
+        content,
+    }: EditMessage,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ChatMessage, ServerError> {
+    let message = chat_controller::edit_message(
+        identity.user_id,
+        chat_id,
+        message_id,
+        content,
+        connection.db(),
+    )
+    .await?;
+
+    let event = ServerEvent::MessageEdited {
+        chat_id,
+        message: message.clone(),
+    };
+    notify_chat_members(chat_id, event, &connection.state, Some(&connection)).await;
+    Ok(message)
+}
+
+pub async fn delete_message(
+    DeleteMessage {
+        chat_id,
+        message_id,
+    }: DeleteMessage,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ChatMessage, ServerError> {
+    let message =
+        chat_controller::delete_message(identity.user_id, chat_id, message_id, connection.db())
+            .await?;
+
+    let event = ServerEvent::MessageDeleted {
+        chat_id,
+        message_id,
+    };
+    notify_chat_members(chat_id, event, &connection.state, Some(&connection)).await;
+    Ok(message)
+}
+
+pub async fn get_unread_message_count(
+    _: GetUnreadMessageCount,
+    identity: Identity,
+    connection: Connection,
+) -> Result<Count, ServerError> {
+    chat_controller::get_unread_message_count(identity.user_id, connection.db()).await
+}
+
+pub async fn get_unread_chat_message_count(
+    GetUnreadChatMessageCount { chat_id }: GetUnreadChatMessageCount,
+    identity: Identity,
+    connection: Connection,
+) -> Result<Count, ServerError> {
+    chat_controller::get_unread_chat_message_count(identity.user_id, chat_id, connection.db()).await
+}
+
+pub async fn mark_messages_read(
+    MarkMessagesRead { chat_id }: MarkMessagesRead,
+    identity: Identity,
+    connection: Connection,
+) -> Result<Option<ReadReceipt>, ServerError> {
+    let receipt =
+        chat_controller::mark_messages_read(identity.user_id, chat_id, connection.db()).await?;
+    // Members see the receipt, and the reader's other sessions clear their unread count
+    if let Some(receipt) = &receipt {
+        let event = ServerEvent::MessagesRead {
+            receipt: receipt.clone(),
+        };
+        notify_chat_members(chat_id, event, &connection.state, Some(&connection)).await;
+    }
+    Ok(receipt)
+}
+
+pub async fn get_message_receipts(
+    GetMessageReceipts {
+        chat_id,
+        message_id,
+    }: GetMessageReceipts,
+    identity: Identity,
+    connection: Connection,
+) -> Result<MessageReceipts, ServerError> {
+    chat_controller::get_message_receipts(identity.user_id, chat_id, message_id, connection.db())
+        .await
+}
+
+pub async fn get_chat_members(
+    GetChatMembers { chat_id }: GetChatMembers,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ChatMembers, ServerError> {
+    chat_controller::get_chat_members(identity.user_id, chat_id, connection.db()).await
+}
+
+pub async fn add_chat_members(
+    AddChatMembers {
+        chat_id,
+        member_ids,
+    }: AddChatMembers,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ChatMessage, ServerError> {
+    let message = chat_controller::add_chat_members(
+        identity.user_id,
+        chat_id,
+        member_ids.clone(),
+        connection.db(),
+    )
+    .await?;
+
+    // Existing members get the system message, new ones get the whole chat
+    let state = &connection.state;
+    notify_chat_message(chat_id, message.clone(), &member_ids, state, None).await;
+    notify_chat_created(chat_id, member_ids, state, None).await;
+    Ok(message)
+}
+
+pub async fn remove_chat_member(
+    RemoveChatMember { chat_id, user_id }: RemoveChatMember,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ChatMessage, ServerError> {
+    let message =
+        chat_controller::remove_chat_member(identity.user_id, chat_id, user_id, connection.db())
+            .await?;
+
+    let state = &connection.state;
+    notify_chat_message(chat_id, message.clone(), &[], state, None).await;
+    let event = ServerEvent::ChatRemoved { chat_id };
+    notify_users(vec![user_id], event, state, None).await;
+    Ok(message)
+}
+
+pub async fn leave_chat(
+    LeaveChat { chat_id }: LeaveChat,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ChatMessage, ServerError> {
+    let message = chat_controller::leave_chat(identity.user_id, chat_id, connection.db()).await?;
+
+    let state = &connection.state;
+    notify_chat_message(chat_id, message.clone(), &[], state, None).await;
+    // The user's other sessions drop the chat too
+    let event = ServerEvent::ChatRemoved { chat_id };
+    notify_users(vec![identity.user_id], event, state, Some(&connection)).await;
+    Ok(message)
+}
+
+pub async fn rename_chat(
+    RenameChat { chat_id, name }: RenameChat,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ChatMessage, ServerError> {
+    let message =
+        chat_controller::rename_chat(identity.user_id, chat_id, name, connection.db()).await?;
+
+    let state = &connection.state;
+    notify_chat_message(chat_id, message.clone(), &[], state, None).await;
+    notify_chat_updated(chat_id, state).await;
+    Ok(message)
+}
+
+pub async fn transfer_ownership(
+    TransferOwnership {
+        chat_id,
+        new_owner_id,
+    }: TransferOwnership,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ChatMessage, ServerError> {
+    let message = chat_controller::transfer_ownership(
+        identity.user_id,
+        chat_id,
+        new_owner_id,
+        connection.db(),
+    )
+    .await?;
+
+    notify_chat_message(chat_id, message.clone(), &[], &connection.state, None).await;
+    Ok(message)
+}
+
+pub async fn set_chat_member_role(
+    SetChatMemberRole {
+        chat_id,
+        user_id,
+        role,
+    }: SetChatMemberRole,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ChatMessage, ServerError> {
+    let message = chat_controller::set_chat_member_role(
+        identity.user_id,
+        chat_id,
+        user_id,
+        role,
+        connection.db(),
+    )
+    .await?;
+
+    notify_chat_message(chat_id, message.clone(), &[], &connection.state, None).await;
+    Ok(message)
+}
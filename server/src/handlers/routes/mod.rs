@@ -0,0 +1,81 @@
+pub mod auth_routes;
+pub mod chat_routes;
+pub mod payloads;
+pub mod user_routes;
+
+use crate::handlers::middleware::{Authentication, RateLimit, RequestLogging, Timing};
+use crate::handlers::router::Router;
+use crate::utils::config;
+
+/// The router the server runs: every command, behind rate limiting, logging,
+/// timing and authentication
+pub fn router() -> Router {
+    let limits = &config::current().limits;
+    let router = Router::new()
+        .layer(RateLimit::new(
+            limits.requests_per_second,
+            limits.request_burst,
+        ))
+        .layer(RequestLogging)
+        .layer(Timing)
+        .layer(Authentication);
+    add_routes(router)
+}
+
+/// Registers the handler for every command
+pub fn add_routes(router: Router) -> Router {
+    router
+        // Commands that decide who the connection is logged in as
+        .public("Registered", auth_routes::register)
+        .public("Logged in", auth_routes::login)
+        .public("Session Resumed", auth_routes::resume_session)
+        .public("Token Refreshed", auth_routes::refresh_token)
+        .logged_in("Password Updated", auth_routes::update_profile)
+        .logged_in("Logged out", auth_routes::logout)
+        .logged_in("Session List", auth_routes::list_sessions)
+        .logged_in("Session Revoked", auth_routes::revoke_session)
+        // Users, friends and blocks
+        .logged_in("User Info", user_routes::get_info)
+        .logged_in("Presence", user_routes::get_presence)
+        .logged_in("Presence Updated", user_routes::set_away)
+        .logged_in("Friend Request Sent", user_routes::send_friend_request)
+        .logged_in("Friend Request List Sent", user_routes::get_friend_requests)
+        .logged_in(
+            "Friend Request Accepted",
+            user_routes::accept_friend_request,
+        )
+        .logged_in("Friend Request Denied", user_routes::decline_friend_request)
+        .logged_in(
+            "Friend Request Cancelled",
+            user_routes::cancel_friend_request,
+        )
+        .logged_in("Unfriended", user_routes::remove_friend)
+        .logged_in("Friends", user_routes::get_friends)
+        .logged_in("User Blocked", user_routes::block_user)
+        .logged_in("User Unblocked", user_routes::unblock_user)
+        .logged_in("Blocked Users", user_routes::get_blocked_users)
+        // Chats and their messages
+        .logged_in("Chat List", chat_routes::get_chats)
+        .logged_in("Chat Created", chat_routes::create_chat)
+        .logged_in("Chat Messages", chat_routes::get_chat_messages)
+        .logged_in("Message Sent", chat_routes::send_message)
+        .logged_in("Message Edited", chat_routes::edit_message)
+        .logged_in("Message Deleted", chat_routes::delete_message)
+        .logged_in(
+            "Unread Message Count",
+            chat_routes::get_unread_message_count,
+        )
+        .logged_in(
+            "Unread Chat Message Count",
+            chat_routes::get_unread_chat_message_count,
+        )
+        .logged_in("Messages Read", chat_routes::mark_messages_read)
+        .logged_in("Message Receipts", chat_routes::get_message_receipts)
+        .logged_in("Chat Members", chat_routes::get_chat_members)
+        .logged_in("Members Added", chat_routes::add_chat_members)
+        .logged_in("Member Removed", chat_routes::remove_chat_member)
+        .logged_in("Left Chat", chat_routes::leave_chat)
+        .logged_in("Chat Renamed", chat_routes::rename_chat)
+        .logged_in("Ownership Transferred", chat_routes::transfer_ownership)
+        .logged_in("Role Updated", chat_routes::set_chat_member_role)
+}
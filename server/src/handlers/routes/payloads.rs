@@ -0,0 +1,72 @@
+//! The fields of each command, as its handler takes them. Each payload
+//! knows the command it comes from, so a handler can only be routed the
+//! command it was written for.
+
+use crate::handlers::router::Payload;
+use shared::client_response::{Command, CommandKind};
+use shared::models::chat_models::{ChatCursor, ChatRole};
+
+// A payload struct for each command, with the same fields. A field left out
+// or misnamed doesn't compile, as the command's pattern would be incomplete.
+macro_rules! payloads {
+    ($($name:ident { $($field:ident: $ty:ty),* })*) => {
+        $(
+            pub struct $name {
+                $(pub $field: $ty,)*
+            }
+
+            impl Payload for $name {
+                const KIND: CommandKind = CommandKind::$name;
+
+                #[allow(clippy::unneeded_struct_pattern)]
+                fn from_command(command: Command) -> Option<Self> {
+                    match command {
+                        Command::$name { $($field),* } => Some($name { $($field),* }),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+payloads! {
+    Login { username: String, password: String }
+    Register { username: String, password: String }
+    ResumeSession { token: String }
+    RefreshToken { refresh_token: String }
+    ListSessions {}
+    RevokeSession { session_id: i32 }
+    GetInfo {}
+    SendFriendRequest { receiver_username: String }
+    AcceptFriendRequest { sender_id: i32 }
+    DeclineFriendRequest { sender_id: i32 }
+    CancelFriendRequest { receiver_id: i32 }
+    GetFriendRequests {}
+    RemoveFriend { friend_id: i32 }
+    BlockUser { blocked_id: i32 }
+    UnblockUser { blocked_id: i32 }
+    GetBlockedUsers {}
+    GetFriends {}
+    GetPresence { user_ids: Vec<i32> }
+    SetAway { away: bool }
+    CreateChat { name: Option<String>, is_group: bool, member_ids: Vec<i32> }
+    SendMessage { chat_id: i32, content: String, idempotency_key: Option<String> }
+    EditMessage { chat_id: i32, message_id: i32, content: String }
+    DeleteMessage { chat_id: i32, message_id: i32 }
+    GetChats { before: Option<ChatCursor>, limit: u64 }
+    GetChatMessages { chat_id: i32, before_id: Option<i32>, after_id: Option<i32>, limit: u64 }
+    MarkMessagesRead { chat_id: i32 }
+    GetChatMembers { chat_id: i32 }
+    GetMessageReceipts { chat_id: i32, message_id: i32 }
+    AddChatMembers { chat_id: i32, member_ids: Vec<i32> }
+    RemoveChatMember { chat_id: i32, user_id: i32 }
+    LeaveChat { chat_id: i32 }
+    RenameChat { chat_id: i32, name: String }
+    TransferOwnership { chat_id: i32, new_owner_id: i32 }
+    SetChatMemberRole { chat_id: i32, user_id: i32, role: ChatRole }
+    GetUnreadChatMessageCount { chat_id: i32 }
+    UpdateProfile { new_password: String }
+    GetUnreadMessageCount {}
+    Logout {}
+}
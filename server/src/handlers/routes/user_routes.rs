@@ -0,0 +1,174 @@
+use crate::handlers::connections::{
+    notify_friend_request, notify_presence, notify_users, presence_status, Connection,
+};
+use crate::handlers::controllers::user_controller;
+use crate::handlers::policies::session_policy::Identity;
+use crate::handlers::routes::payloads::{
+    AcceptFriendRequest, BlockUser, CancelFriendRequest, DeclineFriendRequest, GetBlockedUsers,
+    GetFriendRequests, GetFriends, GetInfo, GetPresence, RemoveFriend, SendFriendRequest, SetAway,
+    UnblockUser,
+};
+use crate::utils::errors::server_error::ServerError;
+use shared::models::server_models::ServerResponseModel;
+use shared::models::user_models::{FriendRequestList, PresenceList, User, UserList};
+use shared::server_response::ServerEvent;
+
+pub async fn get_info(
+    _: GetInfo,
+    identity: Identity,
+    connection: Connection,
+) -> Result<User, ServerError> {
+    user_controller::get_user_info(identity.user_id, connection.db()).await
+}
+
+pub async fn get_presence(
+    GetPresence { user_ids }: GetPresence,
+    identity: Identity,
+    connection: Connection,
+) -> Result<PresenceList, ServerError> {
+    let live = user_ids
+        .iter()
+        .map(|id| (*id, presence_status(*id, &connection.state)))
+        .collect();
+    user_controller::get_presence(identity.user_id, user_ids, live, connection.db()).await
+}
+
+pub async fn set_away(
+    SetAway { away }: SetAway,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ServerResponseModel, ServerError> {
+    let state = &connection.state;
+    let user_id = identity.user_id;
+    let changed = if away {
+        state.away.insert(user_id)
+    } else {
+        state.away.remove(&user_id).is_some()
+    };
+    if changed {
+        let status = presence_status(user_id, state);
+        notify_presence(user_id, status, None, state).await;
+    }
+    Ok(ServerResponseModel { success: true })
+}
+
+pub async fn send_friend_request(
+    SendFriendRequest { receiver_username }: SendFriendRequest,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ServerResponseModel, ServerError> {
+    let user =
+        user_controller::get_user_by_username(identity.user_id, receiver_username, connection.db())
+            .await?;
+    let result = user_controller::add_friend(identity.user_id, user.id, connection.db()).await?;
+    notify_friend_request(identity.user_id, user, &connection).await;
+    Ok(result)
+}
+
+pub async fn get_friend_requests(
+    _: GetFriendRequests,
+    identity: Identity,
+    connection: Connection,
+) -> Result<FriendRequestList, ServerError> {
+    user_controller::get_friend_requests(identity.user_id, connection.db()).await
+}
+
+pub async fn accept_friend_request(
+    AcceptFriendRequest { sender_id }: AcceptFriendRequest,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ServerResponseModel, ServerError> {
+    let result =
+        user_controller::accept_friend_request(identity.user_id, sender_id, connection.db())
+            .await?;
+    if let Ok(friend) = user_controller::get_user_info(identity.user_id, connection.db()).await {
+        let event = ServerEvent::FriendRequestAccepted { friend };
+        notify_users(vec![sender_id], event, &connection.state, None).await;
+    }
+    Ok(result)
+}
+
+pub async fn decline_friend_request(
+    DeclineFriendRequest { sender_id }: DeclineFriendRequest,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ServerResponseModel, ServerError> {
+    let result =
+        user_controller::decline_friend_request(identity.user_id, sender_id, connection.db())
+            .await?;
+    let event = ServerEvent::FriendRequestRemoved {
+        user_id: identity.user_id,
+    };
+    notify_users(vec![sender_id], event, &connection.state, None).await;
+    Ok(result)
+}
+
+pub async fn cancel_friend_request(
+    CancelFriendRequest { receiver_id }: CancelFriendRequest,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ServerResponseModel, ServerError> {
+    let result =
+        user_controller::cancel_friend_request(identity.user_id, receiver_id, connection.db())
+            .await?;
+    let event = ServerEvent::FriendRequestRemoved {
+        user_id: identity.user_id,
+    };
+    notify_users(vec![receiver_id], event, &connection.state, None).await;
+    Ok(result)
+}
+
+pub async fn remove_friend(
+    RemoveFriend { friend_id }: RemoveFriend,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ServerResponseModel, ServerError> {
+    let result =
+        user_controller::remove_friend(identity.user_id, friend_id, connection.db()).await?;
+    let event = ServerEvent::FriendRemoved {
+        friend_id: identity.user_id,
+    };
+    notify_users(vec![friend_id], event, &connection.state, None).await;
+    Ok(result)
+}
+
+pub async fn get_friends(
+    _: GetFriends,
+    identity: Identity,
+    connection: Connection,
+) -> Result<UserList, ServerError> {
+    user_controller::get_friends(identity.user_id, connection.db()).await
+}
+
+pub async fn block_user(
+    BlockUser { blocked_id }: BlockUser,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ServerResponseModel, ServerError> {
+    let result = user_controller::block_user(identity.user_id, blocked_id, connection.db()).await?;
+    // Blocking ends any friendship or pending request between the users
+    let user_id = identity.user_id;
+    for event in [
+        ServerEvent::FriendRemoved { friend_id: user_id },
+        ServerEvent::FriendRequestRemoved { user_id },
+    ] {
+        notify_users(vec![blocked_id], event, &connection.state, None).await;
+    }
+    Ok(result)
+}
+
+pub async fn unblock_user(
+    UnblockUser { blocked_id }: UnblockUser,
+    identity: Identity,
+    connection: Connection,
+) -> Result<ServerResponseModel, ServerError> {
+    user_controller::unblock_user(identity.user_id, blocked_id, connection.db()).await
+}
+
+pub async fn get_blocked_users(
+    _: GetBlockedUsers,
+    identity: Identity,
+    connection: Connection,
+) -> Result<UserList, ServerError> {
+    user_controller::get_blocked_users(identity.user_id, connection.db()).await
+}
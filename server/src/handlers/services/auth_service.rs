@@ -1,45 +1,77 @@
-use crate::handlers::repositories::user_repository;
+use crate::handlers::policies::session_policy::{self, Identity};
+use crate::handlers::repositories::{session_repository, user_repository};
 use crate::handlers::services::user_service::get_info;
 use crate::utils;
-use sea_orm::DatabaseConnection;
-use shared::models::auth_models::AuthResponseModel;
+use chrono::{Duration, NaiveDateTime, Utc};
+use sea_orm::{ConnectionTrait, DatabaseConnection, TransactionTrait};
+use shared::models::auth_models::{AuthResponseModel, SessionInfo, SessionList};
 use shared::models::server_models::ServerResponseModel;
 use std::sync::Arc;
 use utils::errors::server_error::ServerError;
 use utils::jwt;
 
+// How long a session lasts without being refreshed
+fn refresh_expiry() -> NaiveDateTime {
+    let ttl = Duration::days(utils::config::current().auth.refresh_token_ttl_days);
+    (Utc::now() + ttl).naive_utc()
+}
+
+/// Builds the token pair handed to the client. Refresh tokens are
+/// "{session id}.{secret}", and only the secret's hash is stored.
+fn issue_tokens(
+    user_id: i32,
+    session_id: i32,
+    secret: &str,
+) -> Result<AuthResponseModel, ServerError> {
+    let token = jwt::encode_jwt(user_id, session_id)
+        .map_err(|err| ServerError::JWTCreationError(err.into()))?;
+    Ok(AuthResponseModel {
+        success: true,
+        token,
+        user_id,
+        session_id,
+        refresh_token: format!("{}.{}", session_id, secret),
+    })
+}
+
+/// Opens a new session for the user and returns its tokens
+async fn start_session<C: ConnectionTrait>(
+    user_id: i32,
+    db: &C,
+) -> Result<AuthResponseModel, ServerError> {
+    let secret = utils::security::generate_token_secret();
+    let session = session_repository::create_session(
+        user_id,
+        utils::security::hash_token(&secret),
+        refresh_expiry(),
+        db,
+    )
+    .await?;
+    issue_tokens(user_id, session.id, &secret)
+}
+
 pub async fn register(
     username: String,
     password: String,
     db: Arc<DatabaseConnection>,
 ) -> Result<AuthResponseModel, ServerError> {
     // Check if the username is already in use
-    let existing_user = user_repository::get_user_by_username(username.clone(), db.clone()).await?;
-    if let Some(_) = existing_user {
+    let existing_user = user_repository::get_user_by_username(username.clone(), &*db).await?;
+    if existing_user.is_some() {
         return Err(ServerError::UserAlreadyExists);
     }
 
     let hashed = utils::security::hash_password(password.as_str())?;
 
-    // Register the user in the database
-    let mut user = user_repository::register_user(username, hashed, db.clone()).await?;
-
-    // Generate a response
-    if let Some(user_id) = user.id.take() {
-        // Create a token
-        let token =
-            jwt::encode_jwt(user_id).map_err(|err| ServerError::JWTCreationError(err.into()))?;
-        return Ok(AuthResponseModel {
-            success: true,
-            token,
-            user_id,
-        });
-    }
+    // Register the user in the database. Without a session to log in to, the
+    // user isn't kept either.
+    let txn = db.begin().await?;
+    let user = user_repository::register_user(username, hashed, &txn).await?;
 
-    // If we make it here, there's a problem generating the token
-    Err(ServerError::JWTCreationError(
-        jwt::CreationError::Unexpected,
-    ))
+    // Create a session and its tokens
+    let response = start_session(user.id, &txn).await?;
+    txn.commit().await?;
+    Ok(response)
 }
 
 pub async fn login(
@@ -48,19 +80,13 @@ pub async fn login(
     db: Arc<DatabaseConnection>,
 ) -> Result<AuthResponseModel, ServerError> {
     // Find the user
-    let user = user_repository::get_user_by_username(username.clone(), db.clone()).await?;
+    let user = user_repository::get_user_by_username(username.clone(), &*db).await?;
 
     // If a user is found, verify the password
     if let Some(user) = user {
         return if utils::security::verify_password(password.as_str(), user.password_hash.as_str())?
         {
-            let token = jwt::encode_jwt(user.id)
-                .map_err(|err| ServerError::JWTCreationError(err.into()))?;
-            Ok(AuthResponseModel {
-                success: true,
-                token,
-                user_id: user.id,
-            })
+            start_session(user.id, &*db).await
         } else {
             Err(ServerError::UserNotFound)
         };
@@ -70,7 +96,7 @@ pub async fn login(
 }
 
 pub async fn update_password(
-    jwt: String,
+    user_id: i32,
     new_password: String,
     db: Arc<DatabaseConnection>,
 ) -> Result<ServerResponseModel, ServerError> {
@@ -85,12 +111,96 @@ pub async fn update_password(
     let hashed = utils::security::hash_password(&new_password)?;
 
     // Ensure the user exists
-    let user = get_info(jwt, db.clone()).await?;
+    let user = get_info(user_id, db.clone()).await?;
 
     // Call the repository to update the password in the database
     let username = user.username; // Assuming you have `username` in the user object
-    user_repository::update_password(username, hashed, db.clone()).await?;
+    user_repository::update_password(username, hashed, &*db).await?;
 
     // Return a success response
-    return Ok(ServerResponseModel { success: true });
+    Ok(ServerResponseModel { success: true })
+}
+
+/// Trades a refresh token for a new token pair. Each refresh token works once;
+/// presenting an old one means it leaked, so the whole session is revoked.
+pub async fn refresh_token(
+    refresh_token: String,
+    db: Arc<DatabaseConnection>,
+) -> Result<AuthResponseModel, ServerError> {
+    let invalid = || ServerError::InvalidToken("Invalid refresh token".into());
+    let (session_id, secret) = refresh_token.split_once('.').ok_or_else(invalid)?;
+    let session_id: i32 = session_id.parse().map_err(|_| invalid())?;
+
+    let session = session_repository::get_session(session_id, &*db)
+        .await?
+        .ok_or_else(invalid)?;
+    if session.revoked_at.is_some() || session.expires_at <= Utc::now().naive_utc() {
+        return Err(invalid());
+    }
+
+    let new_secret = utils::security::generate_token_secret();
+    let rotated = session_repository::rotate_refresh_token(
+        session.id,
+        utils::security::hash_token(secret),
+        utils::security::hash_token(&new_secret),
+        refresh_expiry(),
+        &*db,
+    )
+    .await?;
+
+    if !rotated {
+        session_repository::revoke_session(session.id, session.user_id, &*db).await?;
+        return Err(ServerError::InvalidToken(
+            "Refresh token reused, session revoked".into(),
+        ));
+    }
+
+    issue_tokens(session.user_id, session.id, &new_secret)
+}
+
+/// Resumes a session on a new connection with its access token
+pub async fn resume_session(
+    jwt: String,
+    db: Arc<DatabaseConnection>,
+) -> Result<Identity, ServerError> {
+    session_policy::authenticate(&jwt, db).await
+}
+
+pub async fn list_sessions(
+    identity: Identity,
+    db: Arc<DatabaseConnection>,
+) -> Result<SessionList, ServerError> {
+    let sessions = session_repository::get_active_sessions(identity.user_id, &*db)
+        .await?
+        .into_iter()
+        .map(|session| SessionInfo {
+            id: session.id,
+            created_at: session.created_at,
+            last_used_at: session.last_used_at,
+            current: session.id == identity.session_id,
+        })
+        .collect();
+
+    Ok(SessionList { sessions })
+}
+
+pub async fn revoke_session(
+    user_id: i32,
+    session_id: i32,
+    db: Arc<DatabaseConnection>,
+) -> Result<ServerResponseModel, ServerError> {
+    if !session_repository::revoke_session(session_id, user_id, &*db).await? {
+        return Err(ServerError::RequestInvalid("Session not found".into()));
+    }
+
+    Ok(ServerResponseModel { success: true })
+}
+
+/// Revokes the connection's session, so neither its access nor its refresh token work again
+pub async fn logout(
+    identity: Identity,
+    db: Arc<DatabaseConnection>,
+) -> Result<ServerResponseModel, ServerError> {
+    session_repository::revoke_session(identity.session_id, identity.user_id, &*db).await?;
+    Ok(ServerResponseModel { success: true })
 }
@@ -1,28 +1,58 @@
 use crate::entity;
-use crate::handlers::repositories::chat_repository::{get_other_usernames_in_chat, get_read_entry};
-use crate::handlers::repositories::{chat_repository, user_repository};
+use crate::entity::sea_orm_active_enums::Role;
+use crate::handlers::policies::block_policy;
+use crate::handlers::policies::chat_policy::AuthorizedChat;
+use crate::handlers::repositories::store::Repositories;
 use crate::utils::errors::server_error::ServerError;
-use crate::utils::jwt;
 use futures::future::join_all;
-use sea_orm::DatabaseConnection;
 use shared::models::chat_models;
-use shared::models::chat_models::{ChatList, ChatMessage, ChatMessages, Count};
-use shared::models::server_models::ServerResponseModel;
+use shared::models::chat_models::{
+    ChatCursor, ChatList, ChatMember, ChatMembers, ChatMessage, ChatMessages, ChatRole, Count,
+    MessageReceipt, MessageReceipts, ReadReceipt,
+};
+use std::collections::HashSet;
 use std::sync::Arc;
 
+// Most messages or chats returned by a single request
+const MAX_PAGE_SIZE: u64 = 100;
+
+// Long enough for a UUID in any of its usual forms
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 64;
+
 // Create a new chat (group or direct)
-pub async fn create_chat(
-    jwt: String,
+pub async fn create_chat<R: Repositories + ?Sized>(
+    creator_id: i32,
     name: Option<String>,
     is_group: bool,
     member_ids: Vec<i32>,
-    db: Arc<DatabaseConnection>,
-) -> Result<ServerResponseModel, ServerError> {
-    let claim = jwt::decode_jwt(&jwt).map_err(|e| ServerError::InvalidToken(e.to_string()))?;
-    let creator_id = claim.claims.user_id;
-
-    let chat =
-        chat_repository::create_new_chat(name, is_group, member_ids.clone(), db.clone()).await?;
+    repos: Arc<R>,
+) -> Result<chat_models::Chat, ServerError> {
+    // The creator can't put anyone they have a block with in a chat. Blocks
+    // between the other members don't stop a group, only a 1:1 chat is
+    // made read-only by them.
+    block_policy::ensure_not_blocked_by_any(creator_id, &member_ids, &*repos).await?;
+
+    // The chat and its members are stored together or not at all
+    let txn = repos.begin().await?;
+    if is_group {
+        // Group names are unique
+        if let Some(chat_name) = &name {
+            if txn
+                .chats()
+                .get_group_chat_by_name(chat_name.clone())
+                .await?
+                .is_some()
+            {
+                return Err(ServerError::ChatAlreadyExists);
+            }
+        }
+    } else if let [user_a, user_b] = member_ids[..] {
+        // Two users only ever share one 1:1 chat
+        if txn.chats().get_direct_chat(user_a, user_b).await?.is_some() {
+            return Err(ServerError::ChatAlreadyExists);
+        }
+    }
+    let chat = txn.chats().create_new_chat(name, is_group).await?;
 
     let mut members = member_ids;
     if !members.contains(&creator_id) {
@@ -31,248 +61,832 @@ pub async fn create_chat(
 
     let chat_id = chat.id;
 
+    // The creator owns the chat
     for uid in members {
-        chat_repository::add_chat_member(chat_id, uid, db.clone()).await?;
+        let role = if uid == creator_id {
+            Role::Owner
+        } else {
+            Role::Member
+        };
+        txn.chats().add_chat_member(chat_id, uid, role).await?;
     }
+    txn.commit().await?;
 
-    Ok(ServerResponseModel { success: true })
+    // A new chat has no messages yet
+    Ok(build_chat_view(chat, creator_id, 0, repos.clone()).await)
 }
 
-// Send a message to a chat
-pub async fn send_message(
-    jwt: String,
+// Send a message to a chat. Resending with the idempotency key of a message
+// the user already sent returns that message instead of storing it again.
+// The flag is whether this call stored it.
+pub async fn send_message<R: Repositories + ?Sized>(
+    user_id: i32,
     chat_id: i32,
     content: String,
-    db: Arc<DatabaseConnection>,
-) -> Result<ServerResponseModel, ServerError> {
-    let claim = jwt::decode_jwt(&jwt).map_err(|e| ServerError::InvalidToken(e.to_string()))?;
-    let sender_id = claim.claims.user_id;
-
-    let user = user_repository::get_user_by_id(sender_id, db.clone()).await?;
+    idempotency_key: Option<String>,
+    repos: Arc<R>,
+) -> Result<(ChatMessage, bool), ServerError> {
+    let auth = AuthorizedChat::for_user(user_id, chat_id, &*repos).await?;
+    let sender_id = auth.user_id;
+
+    if let Some(key) = &idempotency_key {
+        if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+            return Err(ServerError::RequestInvalid(format!(
+                "Idempotency key must be 1 to {} characters",
+                MAX_IDEMPOTENCY_KEY_LEN
+            )));
+        }
+        if let Some(sent) = get_sent_message(sender_id, chat_id, key, repos.clone()).await? {
+            return Ok((sent, false));
+        }
+    }
 
-    if let Some(user) = user {
-        chat_repository::send_message(chat_id, sender_id, user.username, content, db.clone())
+    // 1:1 chats go read-only once either side blocks the other
+    block_policy::ensure_can_message(&auth.chat, sender_id, &*repos).await?;
+
+    let user = repos
+        .users()
+        .get_user_by_id(sender_id)
+        .await?
+        .ok_or(ServerError::UserNotFound)?;
+
+    // The message and the sender's read of it are stored together
+    let result = async {
+        let txn = repos.begin().await?;
+        let msg = txn
+            .chats()
+            .send_message(
+                chat_id,
+                sender_id,
+                user.username,
+                content,
+                idempotency_key.clone(),
+            )
             .await?;
-        return Ok(ServerResponseModel { success: true });
+        txn.commit().await?;
+        Ok(msg)
     }
+    .await;
+    match (result, idempotency_key) {
+        (Ok(msg), _) => Ok((to_chat_message(msg, true, false), true)),
+        // A retry racing this one stored it first, and the unique key turned this one away
+        (Err(e), Some(key)) => {
+            match get_sent_message(sender_id, chat_id, &key, repos.clone()).await? {
+                Some(sent) => Ok((sent, false)),
+                None => Err(e),
+            }
+        }
+        (Err(e), None) => Err(e),
+    }
+}
 
-    Err(ServerError::UserNotFound)
+// The message the user already sent with this key, if any
+async fn get_sent_message<R: Repositories + ?Sized>(
+    sender_id: i32,
+    chat_id: i32,
+    idempotency_key: &str,
+    repos: Arc<R>,
+) -> Result<Option<ChatMessage>, ServerError> {
+    let Some(msg) = repos
+        .chats()
+        .get_message_by_idempotency_key(sender_id, idempotency_key)
+        .await?
+    else {
+        return Ok(None);
+    };
+    if msg.chat_id != chat_id {
+        return Err(ServerError::RequestInvalid(
+            "Idempotency key was already used in another chat".to_string(),
+        ));
+    }
+    let seen = is_seen(&msg, repos).await?;
+    Ok(Some(to_chat_message(msg, true, seen)))
 }
 
-pub async fn get_user_chats(
-    jwt: String,
-    page: u64,
-    page_size: u64,
-    db: Arc<DatabaseConnection>,
-) -> Result<ChatList, ServerError> {
-    let claim = jwt::decode_jwt(&jwt).map_err(|e| ServerError::InvalidToken(e.to_string()))?;
-    let user_id = claim.claims.user_id;
-
-    let chats = chat_repository::get_user_chats_paged(user_id, page, page_size, db.clone()).await?;
-
-    let futures = chats.into_iter().map(|c| {
-        let db = db.clone();
-        async move {
-            let name = if let Some(name) = &c.name {
-                name.clone()
-            } else {
-                match get_other_usernames_in_chat(c.id, user_id, db.clone()).await {
-                    Ok(usernames) => usernames.join(", "),
-                    Err(_) => String::new(),
-                }
-            };
-
-            let unread_count = get_unread_chat_message_count(user_id, c.id, db.clone()).await;
-
-            chat_models::Chat {
-                id: c.id,
-                chat_name: name,
-                unread_count: unread_count.unwrap_or_else(|_| 0),
-            }
+// Edit a message (its sender only)
+pub async fn edit_message<R: Repositories + ?Sized>(
+    user_id: i32,
+    chat_id: i32,
+    message_id: i32,
+    content: String,
+    repos: Arc<R>,
+) -> Result<ChatMessage, ServerError> {
+    let auth = AuthorizedChat::for_user(user_id, chat_id, &*repos).await?;
+    let message = get_chat_message(&auth, message_id, repos.clone()).await?;
+
+    if message.sender_id != auth.user_id || message.is_system {
+        return Err(ServerError::Forbidden);
+    }
+    if content.trim().is_empty() {
+        return Err(ServerError::RequestInvalid(
+            "Message can't be empty".to_string(),
+        ));
+    }
+
+    // Edits are held to the same rules as sending
+    block_policy::ensure_can_message(&auth.chat, auth.user_id, &*repos).await?;
+
+    let txn = repos.begin().await?;
+    let msg = txn.chats().edit_message(message, content).await?;
+    txn.commit().await?;
+    let seen = is_seen(&msg, repos.clone()).await?;
+    Ok(to_chat_message(msg, true, seen))
+}
+
+// Delete a message, leaving a tombstone. Senders can delete their own
+// messages, and group admins can delete anyone's.
+pub async fn delete_message<R: Repositories + ?Sized>(
+    user_id: i32,
+    chat_id: i32,
+    message_id: i32,
+    repos: Arc<R>,
+) -> Result<ChatMessage, ServerError> {
+    let auth = AuthorizedChat::for_user(user_id, chat_id, &*repos).await?;
+    let message = get_chat_message(&auth, message_id, repos.clone()).await?;
+
+    if message.is_system {
+        return Err(ServerError::Forbidden);
+    }
+    if message.sender_id != auth.user_id {
+        if !auth.chat.is_group {
+            return Err(ServerError::Forbidden);
         }
+        auth.ensure_role(Role::Admin)?;
+    }
+
+    let txn = repos.begin().await?;
+    let msg = txn.chats().delete_message(message).await?;
+    txn.commit().await?;
+    let seen = is_seen(&msg, repos.clone()).await?;
+    Ok(to_chat_message(msg, true, seen))
+}
+
+// Finds a message in the chat that hasn't been deleted
+async fn get_chat_message<R: Repositories + ?Sized>(
+    auth: &AuthorizedChat,
+    message_id: i32,
+    repos: Arc<R>,
+) -> Result<entity::messages::Model, ServerError> {
+    let message = repos
+        .chats()
+        .get_message_by_id(message_id)
+        .await?
+        .filter(|m| m.chat_id == auth.chat.id)
+        .ok_or(ServerError::MessageNotFound)?;
+
+    if message.deleted_at.is_some() {
+        return Err(ServerError::RequestInvalid(
+            "Message was deleted".to_string(),
+        ));
+    }
+    Ok(message)
+}
+
+// Whether anyone other than the sender has read the message
+async fn is_seen<R: Repositories + ?Sized>(
+    msg: &entity::messages::Model,
+    repos: Arc<R>,
+) -> Result<bool, ServerError> {
+    let readers = repos.chats().get_message_readers(vec![msg.id]).await?;
+    Ok(readers.iter().any(|(_, user_id)| *user_id != msg.sender_id))
+}
+
+// Messages sent, edited or deleted by the user count as read by them
+fn to_chat_message(msg: entity::messages::Model, read: bool, seen: bool) -> ChatMessage {
+    ChatMessage {
+        id: msg.id,
+        sender_id: msg.sender_id,
+        username: msg.sender_username,
+        content: msg.content,
+        is_system: msg.is_system,
+        timestamp: msg.timestamp,
+        edited: msg.edited_at.is_some(),
+        edited_at: msg.edited_at,
+        deleted: msg.deleted_at.is_some(),
+        read,
+        seen,
+        idempotency_key: msg.idempotency_key,
+    }
+}
+
+// Get the user's chats, most recently active first, starting after the cursor
+pub async fn get_user_chats<R: Repositories + ?Sized>(
+    user_id: i32,
+    before: Option<ChatCursor>,
+    limit: u64,
+    repos: Arc<R>,
+) -> Result<ChatList, ServerError> {
+    let limit = limit.clamp(1, MAX_PAGE_SIZE);
+    let before = before.map(|c| (c.last_activity, c.chat_id));
+
+    // Fetch one extra chat to tell whether there are more
+    let mut chats = repos
+        .chats()
+        .get_user_chats_before(user_id, before, limit + 1)
+        .await?;
+    let has_more = chats.len() as u64 > limit;
+    chats.truncate(limit as usize);
+
+    let next_cursor = match chats.last() {
+        Some((chat, last_activity)) if has_more => Some(ChatCursor {
+            last_activity: *last_activity,
+            chat_id: chat.id,
+        }),
+        _ => None,
+    };
+
+    // One grouped query covers the unread counts of the whole batch
+    let chat_ids = chats.iter().map(|(c, _)| c.id).collect();
+    let unread_counts = repos.chats().get_unread_counts(user_id, chat_ids).await?;
+
+    let futures = chats.into_iter().map(|(c, _)| {
+        let unread_count = unread_counts.get(&c.id).copied().unwrap_or(0);
+        build_chat_view(c, user_id, unread_count, repos.clone())
     });
 
     let chat_results: Vec<chat_models::Chat> = join_all(futures).await;
 
     Ok(ChatList {
         chats: chat_results,
+        next_cursor,
     })
 }
 
-// Get messages in a chat
-pub async fn get_chat_messages(
-    jwt: String,
+// Get a single chat as it appears in the given user's chat list
+pub async fn get_user_chat<R: Repositories + ?Sized>(
     chat_id: i32,
-    page: u64,
-    page_size: u64,
-    db: Arc<DatabaseConnection>,
-) -> Result<ChatMessages, ServerError> {
-    let claim = jwt::decode_jwt(&jwt).map_err(|e| ServerError::InvalidToken(e.to_string()))?;
-    let user_id = claim.claims.user_id;
+    user_id: i32,
+    repos: Arc<R>,
+) -> Result<chat_models::Chat, ServerError> {
+    let chat = repos
+        .chats()
+        .get_chat_by_id(chat_id)
+        .await?
+        .ok_or(ServerError::RequestInvalid("Chat not found".into()))?;
+
+    let unread_count = get_unread_chat_message_count(user_id, chat_id, repos.clone()).await?;
+    Ok(build_chat_view(chat, user_id, unread_count, repos.clone()).await)
+}
 
+// Names a chat from the user's perspective and attaches their unread count
+async fn build_chat_view<R: Repositories + ?Sized>(
+    chat: entity::chats::Model,
+    user_id: i32,
+    unread_count: u64,
+    repos: Arc<R>,
+) -> chat_models::Chat {
+    let name = if let Some(name) = &chat.name {
+        name.clone()
+    } else {
+        match repos
+            .chats()
+            .get_other_usernames_in_chat(chat.id, user_id)
+            .await
+        {
+            Ok(usernames) => usernames.join(", "),
+            Err(_) => String::new(),
+        }
+    };
+
+    let read_only = block_policy::is_chat_read_only(&chat, user_id, &*repos).await;
+
+    chat_models::Chat {
+        id: chat.id,
+        chat_name: name,
+        unread_count,
+        read_only: read_only.unwrap_or(false),
+    }
+}
+
+// Get messages in a chat, oldest first. Without a cursor this is the newest
+// messages; `before_id` scrolls back and `after_id` catches up.
+pub async fn get_chat_messages<R: Repositories + ?Sized>(
+    user_id: i32,
+    chat_id: i32,
+    before_id: Option<i32>,
+    after_id: Option<i32>,
+    limit: u64,
+    repos: Arc<R>,
+) -> Result<ChatMessages, ServerError> {
     // Confirm user is in chat
-    let is_member = chat_repository::is_user_chat_member(chat_id, user_id, db.clone()).await;
+    let auth = AuthorizedChat::for_user(user_id, chat_id, &*repos).await?;
 
-    if !is_member {
-        return Err(ServerError::Forbidden);
+    if before_id.is_some() && after_id.is_some() {
+        return Err(ServerError::RequestInvalid(
+            "Use either before_id or after_id, not both".to_string(),
+        ));
+    }
+    let limit = limit.clamp(1, MAX_PAGE_SIZE);
+
+    // Fetch one extra message to tell whether there are more
+    let mut messages = match after_id {
+        Some(after_id) => {
+            repos
+                .chats()
+                .get_messages_after(chat_id, after_id, limit + 1)
+                .await?
+        }
+        None => {
+            repos
+                .chats()
+                .get_messages_before(chat_id, before_id, limit + 1)
+                .await?
+        }
+    };
+    let has_more = messages.len() as u64 > limit;
+    if has_more {
+        match after_id {
+            Some(_) => messages.truncate(limit as usize),
+            None => {
+                messages.remove(0);
+            }
+        }
     }
 
-    let messages =
-        chat_repository::get_paginated_messages(chat_id, page, page_size, db.clone()).await?;
+    // Look up who has read each of the page's messages
+    let message_ids: Vec<i32> = messages.iter().map(|m| m.id).collect();
+    let readers = repos.chats().get_message_readers(message_ids).await?;
+    let read_ids: HashSet<i32> = readers
+        .iter()
+        .filter(|(_, user_id)| *user_id == auth.user_id)
+        .map(|(message_id, _)| *message_id)
+        .collect();
 
     let messages: Vec<ChatMessage> = messages
-        .iter()
-        .map(|msg| ChatMessage {
-            user_id,
-            username: msg.sender_username.clone(),
-            content: msg.content.clone(),
+        .into_iter()
+        .map(|msg| {
+            let read = read_ids.contains(&msg.id);
+            let seen = readers
+                .iter()
+                .any(|(message_id, user_id)| *message_id == msg.id && *user_id != msg.sender_id);
+            to_chat_message(msg, read, seen)
         })
         .collect();
 
     Ok(ChatMessages {
         id: chat_id,
         messages,
+        has_more,
     })
 }
 
-pub async fn get_chat_page_count(
-    jwt: String,
+// Mark messages as read (per-user tracking). Returns a receipt for the other
+// members when anything new was read.
+pub async fn mark_messages_read<R: Repositories + ?Sized>(
+    user_id: i32,
+    chat_id: i32,
+    repos: Arc<R>,
+) -> Result<Option<ReadReceipt>, ServerError> {
+    // Only members can read the chat
+    AuthorizedChat::for_user(user_id, chat_id, &*repos).await?;
+
+    // If none, all are read
+    let Some(last_read_id) = repos
+        .chats()
+        .get_last_unread_message_id(user_id, chat_id)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    // One insert covers every unread message up to the newest, however many.
+    // Anything sent since stays unread, so the receipt matches what was marked.
+    let read_at = repos
+        .chats()
+        .mark_messages_read(user_id, chat_id, last_read_id)
+        .await?;
+
+    let user = repos
+        .users()
+        .get_user_by_id(user_id)
+        .await?
+        .ok_or(ServerError::UserNotFound)?;
+
+    Ok(Some(ReadReceipt {
+        chat_id,
+        last_read_id,
+        reader: MessageReceipt {
+            user_id,
+            username: user.username,
+            read_at,
+        },
+    }))
+}
+
+// Who other than the sender has read a message in the chat
+pub async fn get_message_receipts<R: Repositories + ?Sized>(
+    user_id: i32,
     chat_id: i32,
-    page_size: u64,
-    db: Arc<DatabaseConnection>,
+    message_id: i32,
+    repos: Arc<R>,
+) -> Result<MessageReceipts, ServerError> {
+    let auth = AuthorizedChat::for_user(user_id, chat_id, &*repos).await?;
+    let message = repos
+        .chats()
+        .get_message_by_id(message_id)
+        .await?
+        .filter(|m| m.chat_id == auth.chat.id)
+        .ok_or(ServerError::MessageNotFound)?;
+
+    let seen_by = repos
+        .chats()
+        .get_message_receipts(message.id)
+        .await?
+        .into_iter()
+        .filter(|(user_id, _, _)| *user_id != message.sender_id)
+        .map(|(user_id, username, read_at)| MessageReceipt {
+            user_id,
+            username,
+            read_at,
+        })
+        .collect();
+
+    Ok(MessageReceipts {
+        chat_id,
+        message_id,
+        seen_by,
+    })
+}
+
+// Get unread message count for a chat
+pub async fn get_unread_chat_message_count<R: Repositories + ?Sized>(
+    user_id: i32,
+    chat_id: i32,
+    repos: Arc<R>,
+) -> Result<u64, ServerError> {
+    let counts = repos
+        .chats()
+        .get_unread_counts(user_id, vec![chat_id])
+        .await?;
+    Ok(counts.get(&chat_id).copied().unwrap_or(0))
+}
+
+// Get unread message count for a chat the user is a member of
+pub async fn get_chat_unread_count<R: Repositories + ?Sized>(
+    user_id: i32,
+    chat_id: i32,
+    repos: Arc<R>,
 ) -> Result<Count, ServerError> {
-    // Ensure token provided is valid
-    jwt::decode_jwt(&jwt).map_err(|e| ServerError::InvalidToken(e.to_string()))?;
+    let auth = AuthorizedChat::for_user(user_id, chat_id, &*repos).await?;
 
-    let pages = chat_repository::get_chat_page_count(chat_id, page_size, db.clone()).await?;
+    let count = get_unread_chat_message_count(auth.user_id, chat_id, repos.clone()).await?;
 
-    Ok(Count { count: pages })
+    Ok(Count { count })
 }
 
-pub async fn get_chats_page_count(
-    jwt: String,
-    page_size: u64,
-    db: Arc<DatabaseConnection>,
+pub async fn get_unread_message_count<R: Repositories + ?Sized>(
+    user_id: i32,
+    repos: Arc<R>,
 ) -> Result<Count, ServerError> {
-    // Ensure token provided is valid
-    let claim = jwt::decode_jwt(&jwt).map_err(|e| ServerError::InvalidToken(e.to_string()))?;
-    let user_id = claim.claims.user_id;
+    let count = repos.chats().get_total_unread_count(user_id).await?;
 
-    let pages = chat_repository::get_chats_page_count(user_id, page_size, db.clone()).await?;
+    Ok(Count { count })
+}
 
-    Ok(Count { count: pages })
+pub async fn get_chat_user_ids<R: Repositories + ?Sized>(
+    chat_id: i32,
+    repos: Arc<R>,
+) -> Result<Vec<i32>, ServerError> {
+    repos.chats().get_chat_user_ids(chat_id).await
 }
 
-// Mark messages as read (per-user tracking)
-pub async fn mark_messages_read(
-    jwt: String,
+// The typing user's name and who else in the chat should hear about it
+pub async fn get_typing_recipients<R: Repositories + ?Sized>(
     chat_id: i32,
-    db: Arc<DatabaseConnection>,
-) -> Result<ServerResponseModel, ServerError> {
-    let claim = jwt::decode_jwt(&jwt).map_err(|e| ServerError::InvalidToken(e.to_string()))?;
-    let user_id = claim.claims.user_id;
+    user_id: i32,
+    repos: Arc<R>,
+) -> Result<(String, Vec<i32>), ServerError> {
+    let auth = AuthorizedChat::for_user(user_id, chat_id, &*repos).await?;
+    let user = repos
+        .users()
+        .get_user_by_id(auth.user_id)
+        .await?
+        .ok_or(ServerError::UserNotFound)?;
+
+    let user_ids = repos
+        .chats()
+        .get_chat_user_ids(chat_id)
+        .await?
+        .into_iter()
+        .filter(|id| *id != user_id)
+        .collect();
+    Ok((user.username, user_ids))
+}
 
-    let is_member = chat_repository::is_user_chat_member(chat_id, user_id, db.clone()).await;
-    if !is_member {
-        return Err(ServerError::Forbidden);
+// Get the members of a chat and their roles
+pub async fn get_chat_members<R: Repositories + ?Sized>(
+    user_id: i32,
+    chat_id: i32,
+    repos: Arc<R>,
+) -> Result<ChatMembers, ServerError> {
+    let auth = AuthorizedChat::for_user(user_id, chat_id, &*repos).await?;
+
+    let members = repos.chats().get_chat_members(chat_id).await?;
+    let user_ids: Vec<i32> = members.iter().map(|m| m.user_id).collect();
+    let users = repos.users().get_users_from_list(user_ids).await?;
+
+    let members = members
+        .into_iter()
+        .filter_map(|m| {
+            let user = users.iter().find(|u| u.id == m.user_id)?;
+            Some(ChatMember {
+                id: user.id,
+                username: user.username.clone(),
+                role: ChatRole::from(m.role),
+            })
+        })
+        .collect();
+
+    Ok(ChatMembers {
+        chat_id,
+        is_group: auth.chat.is_group,
+        members,
+    })
+}
+
+// Add users to a group chat (admins and the owner only)
+pub async fn add_chat_members<R: Repositories + ?Sized>(
+    user_id: i32,
+    chat_id: i32,
+    member_ids: Vec<i32>,
+    repos: Arc<R>,
+) -> Result<ChatMessage, ServerError> {
+    let auth = AuthorizedChat::for_user(user_id, chat_id, &*repos).await?;
+    auth.ensure_group()?;
+    auth.ensure_role(Role::Admin)?;
+
+    if member_ids.is_empty() {
+        return Err(ServerError::RequestInvalid("No users to add".to_string()));
     }
 
-    // Get all message IDs for a chat
-    let message_ids: Vec<i32> = chat_repository::get_chat_message_ids(chat_id, db.clone()).await?;
+    for user_id in &member_ids {
+        if repos
+            .chats()
+            .get_chat_member(chat_id, *user_id)
+            .await?
+            .is_some()
+        {
+            return Err(ServerError::RequestInvalid(
+                "User is already a member".to_string(),
+            ));
+        }
+    }
 
-    // If none, all are read
-    if message_ids.is_empty() {
-        return Ok(ServerResponseModel { success: true });
+    // The adder can't add anyone they have a block with. Blocks between the
+    // new and existing members are allowed, as in create_chat.
+    block_policy::ensure_not_blocked_by_any(auth.user_id, &member_ids, &*repos).await?;
+
+    let users = repos
+        .users()
+        .get_users_from_list(member_ids.clone())
+        .await?;
+    if users.len() != member_ids.len() {
+        return Err(ServerError::UserNotFound);
     }
 
-    // Get already read message_ids for this user
-    let read_ids: Vec<i32> =
-        chat_repository::get_user_chat_unread_messages(user_id, message_ids.clone(), db.clone())
+    let txn = repos.begin().await?;
+    for user in &users {
+        txn.chats()
+            .add_chat_member(chat_id, user.id, Role::Member)
             .await?;
+    }
 
-    // Compute unread message_ids
-    let unread_ids: Vec<i32> = message_ids
-        .into_iter()
-        .filter(|id| !read_ids.contains(id))
-        .collect();
+    let usernames: Vec<String> = users.into_iter().map(|u| u.username).collect();
+    let msg = send_system_message(
+        &auth,
+        |actor| format!("{} added {}", actor, usernames.join(", ")),
+        &*txn,
+    )
+    .await?;
+    txn.commit().await?;
+    Ok(msg)
+}
 
-    // Bulk insert the missing reads
-    chat_repository::mark_messages_read(user_id, unread_ids, db.clone()).await?;
+// Remove another member from a group chat. Admins can remove members, and
+// the owner can remove anyone.
+pub async fn remove_chat_member<R: Repositories + ?Sized>(
+    requester_id: i32,
+    chat_id: i32,
+    user_id: i32,
+    repos: Arc<R>,
+) -> Result<ChatMessage, ServerError> {
+    let auth = AuthorizedChat::for_user(requester_id, chat_id, &*repos).await?;
+    auth.ensure_group()?;
+
+    if user_id == auth.user_id {
+        return Err(ServerError::RequestInvalid(
+            "Leave the chat instead of removing yourself".to_string(),
+        ));
+    }
 
-    Ok(ServerResponseModel { success: true })
+    let target = get_target_member(chat_id, user_id, repos.clone()).await?;
+    auth.ensure_role(Role::Admin)?;
+    auth.ensure_outranks(&target.role)?;
+
+    let txn = repos.begin().await?;
+    txn.chats().remove_chat_member(chat_id, user_id).await?;
+
+    let username = get_username(user_id, &*txn).await?;
+    let msg = send_system_message(
+        &auth,
+        |actor| format!("{} removed {}", actor, username),
+        &*txn,
+    )
+    .await?;
+    txn.commit().await?;
+    Ok(msg)
 }
 
-// Get unread message count for a chat
-pub async fn get_unread_chat_message_count(
+// Leave a group chat. The owner has to hand the chat over first, unless
+// they are the last member.
+pub async fn leave_chat<R: Repositories + ?Sized>(
     user_id: i32,
     chat_id: i32,
-    db: Arc<DatabaseConnection>,
-) -> Result<u64, ServerError> {
-    let messages = chat_repository::get_chat_messages(chat_id, db.clone()).await?;
+    repos: Arc<R>,
+) -> Result<ChatMessage, ServerError> {
+    let auth = AuthorizedChat::for_user(user_id, chat_id, &*repos).await?;
+    auth.ensure_group()?;
+
+    if auth.role == Role::Owner {
+        let members = repos.chats().get_chat_members(chat_id).await?;
+        if members.len() > 1 {
+            return Err(ServerError::RequestInvalid(
+                "Transfer ownership before leaving the chat".to_string(),
+            ));
+        }
+    }
 
-    let unread_count = get_unread_count(user_id, false, messages, db.clone()).await?;
+    let txn = repos.begin().await?;
+    txn.chats()
+        .remove_chat_member(chat_id, auth.user_id)
+        .await?;
 
-    Ok(unread_count)
+    let msg = send_system_message(&auth, |actor| format!("{} left the chat", actor), &*txn).await?;
+    txn.commit().await?;
+    Ok(msg)
 }
 
-pub async fn get_unread_message_count(
-    jwt: String,
-    db: Arc<DatabaseConnection>,
-) -> Result<Count, ServerError> {
-    let claim = jwt::decode_jwt(&jwt).map_err(|e| ServerError::InvalidToken(e.to_string()))?;
-    let user_id = claim.claims.user_id;
+// Rename a group chat (admins and the owner only)
+pub async fn rename_chat<R: Repositories + ?Sized>(
+    user_id: i32,
+    chat_id: i32,
+    name: String,
+    repos: Arc<R>,
+) -> Result<ChatMessage, ServerError> {
+    let auth = AuthorizedChat::for_user(user_id, chat_id, &*repos).await?;
+    auth.ensure_group()?;
+    auth.ensure_role(Role::Admin)?;
+
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(ServerError::RequestInvalid(
+            "Chat name can't be empty".to_string(),
+        ));
+    }
 
-    let chats = chat_repository::get_user_chats(user_id, db.clone()).await?;
+    // Group names are unique
+    if let Some(existing) = repos.chats().get_group_chat_by_name(name.clone()).await? {
+        if existing.id != chat_id {
+            return Err(ServerError::ChatAlreadyExists);
+        }
+    }
 
-    // Create a future for each chat to get its messages
-    let futures = chats
-        .iter()
-        .map(|chat| chat_repository::get_chat_messages(chat.id, db.clone()));
+    let txn = repos.begin().await?;
+    txn.chats().rename_chat(chat_id, name.clone()).await?;
+
+    let msg = send_system_message(
+        &auth,
+        |actor| format!("{} renamed the chat to {}", actor, name),
+        &*txn,
+    )
+    .await?;
+    txn.commit().await?;
+    Ok(msg)
+}
 
-    // Await all message fetches
-    let results: Vec<Result<Vec<entity::messages::Model>, ServerError>> = join_all(futures).await;
+// Hand the chat over to another member. The old owner becomes an admin.
+pub async fn transfer_ownership<R: Repositories + ?Sized>(
+    user_id: i32,
+    chat_id: i32,
+    new_owner_id: i32,
+    repos: Arc<R>,
+) -> Result<ChatMessage, ServerError> {
+    let auth = AuthorizedChat::for_user(user_id, chat_id, &*repos).await?;
+    auth.ensure_group()?;
+    auth.ensure_role(Role::Owner)?;
+
+    if new_owner_id == auth.user_id {
+        return Err(ServerError::RequestInvalid(
+            "You already own this chat".to_string(),
+        ));
+    }
 
-    // Flatten and collect all successful message vectors
-    let messages: Vec<entity::messages::Model> = results
-        .into_iter()
-        .collect::<Result<Vec<_>, _>>()? // Propagate error if any
-        .into_iter()
-        .flatten()
-        .collect();
+    get_target_member(chat_id, new_owner_id, repos.clone()).await?;
+
+    // A chat is never left with no owner, or two
+    let txn = repos.begin().await?;
+    txn.chats()
+        .set_chat_member_role(chat_id, new_owner_id, Role::Owner)
+        .await?;
+    txn.chats()
+        .set_chat_member_role(chat_id, auth.user_id, Role::Admin)
+        .await?;
+
+    let username = get_username(new_owner_id, &*txn).await?;
+    let msg = send_system_message(
+        &auth,
+        |actor| format!("{} made {} the owner", actor, username),
+        &*txn,
+    )
+    .await?;
+    txn.commit().await?;
+    Ok(msg)
+}
 
-    let unread_count = get_unread_count(user_id, true, messages, db.clone()).await?;
+// Make a member an admin, or an admin a member (owner only)
+pub async fn set_chat_member_role<R: Repositories + ?Sized>(
+    requester_id: i32,
+    chat_id: i32,
+    user_id: i32,
+    role: Role,
+    repos: Arc<R>,
+) -> Result<ChatMessage, ServerError> {
+    let auth = AuthorizedChat::for_user(requester_id, chat_id, &*repos).await?;
+    auth.ensure_group()?;
+    auth.ensure_role(Role::Owner)?;
+
+    if role == Role::Owner {
+        return Err(ServerError::RequestInvalid(
+            "Transfer ownership to change the owner".to_string(),
+        ));
+    }
+    if user_id == auth.user_id {
+        return Err(ServerError::RequestInvalid(
+            "You can't change your own role".to_string(),
+        ));
+    }
 
-    Ok(Count {
-        count: unread_count,
-    })
+    get_target_member(chat_id, user_id, repos.clone()).await?;
+
+    let txn = repos.begin().await?;
+    txn.chats()
+        .set_chat_member_role(chat_id, user_id, role)
+        .await?;
+
+    let username = get_username(user_id, &*txn).await?;
+    let msg = send_system_message(
+        &auth,
+        |actor| match role {
+            Role::Admin => format!("{} made {} an admin", actor, username),
+            _ => format!("{} removed {} as an admin", actor, username),
+        },
+        &*txn,
+    )
+    .await?;
+    txn.commit().await?;
+    Ok(msg)
 }
 
-pub async fn get_unread_count(
+async fn get_target_member<R: Repositories + ?Sized>(
+    chat_id: i32,
     user_id: i32,
-    multiple_chats: bool,
-    messages: Vec<entity::messages::Model>,
-    db: Arc<DatabaseConnection>,
-) -> Result<u64, ServerError> {
-    let mut unread_count = 0;
-    for msg in messages {
-        let read_entry = get_read_entry(user_id, msg.id, db.clone()).await?;
-
-        if read_entry.is_some() {
-            if !multiple_chats {
-                break; // Stop counting once the first read message is found
-            } else {
-                continue;
-            }
-        }
+    repos: Arc<R>,
+) -> Result<entity::chat_members::Model, ServerError> {
+    repos
+        .chats()
+        .get_chat_member(chat_id, user_id)
+        .await?
+        .ok_or(ServerError::RequestInvalid(
+            "User is not a member of this chat".to_string(),
+        ))
+}
 
-        unread_count += 1;
-    }
-    Ok(unread_count)
+async fn get_username<R: Repositories + ?Sized>(
+    user_id: i32,
+    repos: &R,
+) -> Result<String, ServerError> {
+    repos
+        .users()
+        .get_user_by_id(user_id)
+        .await?
+        .map(|u| u.username)
+        .ok_or(ServerError::UserNotFound)
 }
 
-pub async fn get_chat_user_ids(
-    chat_id: i32,
-    db: Arc<DatabaseConnection>,
-) -> Result<Vec<i32>, ServerError> {
-    chat_repository::get_chat_user_ids(chat_id, db.clone()).await
+// Records a change to the chat in its timeline, worded by `describe` from the
+// name of the user who made it. It goes in the same transaction as the change.
+async fn send_system_message<R: Repositories + ?Sized>(
+    auth: &AuthorizedChat,
+    describe: impl FnOnce(&str) -> String,
+    repos: &R,
+) -> Result<ChatMessage, ServerError> {
+    let actor = get_username(auth.user_id, repos).await?;
+    let content = describe(&actor);
+
+    let msg = repos
+        .chats()
+        .send_system_message(auth.chat.id, auth.user_id, actor, content)
+        .await?;
+
+    Ok(to_chat_message(msg, true, false))
 }
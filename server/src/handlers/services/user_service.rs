@@ -1,21 +1,22 @@
 use crate::entity::sea_orm_active_enums::Status;
-use crate::handlers::repositories::user_repository;
-use crate::utils::jwt;
+use crate::handlers::policies::block_policy;
+use crate::handlers::repositories::store::Repositories;
 use crate::{entity, utils};
-use sea_orm::DatabaseConnection;
+use chrono::{NaiveDateTime, Utc};
 use shared::models::server_models::ServerResponseModel;
-use shared::models::user_models::{FriendRequestList, User, UserList};
+use shared::models::user_models::{
+    FriendRequestList, Presence, PresenceList, PresenceStatus, User, UserList,
+};
+use std::collections::HashMap;
 use std::sync::Arc;
 use utils::errors::server_error::ServerError;
 
-pub async fn get_info(jwt: String, db: Arc<DatabaseConnection>) -> Result<User, ServerError> {
-    // Decode JWT
-    let claim = match jwt::decode_jwt(&jwt) {
-        Ok(claim) => claim,
-        Err(e) => return Err(ServerError::InvalidToken(e.to_string())),
-    };
-    // Get the user by their id from the jwt
-    let user = user_repository::get_user_by_id(claim.claims.user_id, db).await?;
+pub async fn get_info<R: Repositories + ?Sized>(
+    user_id: i32,
+    repos: Arc<R>,
+) -> Result<User, ServerError> {
+    // Get the user by their id
+    let user = repos.users().get_user_by_id(user_id).await?;
 
     // If a user is found, return their info
     match user {
@@ -27,11 +28,18 @@ pub async fn get_info(jwt: String, db: Arc<DatabaseConnection>) -> Result<User,
     }
 }
 
-pub async fn get_user_by_username(
+pub async fn get_user_by_username<R: Repositories + ?Sized>(
+    user_id: i32,
     username: String,
-    db: Arc<DatabaseConnection>,
+    repos: Arc<R>,
 ) -> Result<User, ServerError> {
-    let user = user_repository::get_user_by_username(username, db).await?;
+    let user = repos.users().get_user_by_username(username).await?;
+
+    // Users on either side of a block can't find each other
+    let user = match user {
+        Some(user) if block_policy::is_blocked(user_id, user.id, &*repos).await? => None,
+        user => user,
+    };
 
     match user {
         Some(user) => Ok(User {
@@ -42,131 +50,208 @@ pub async fn get_user_by_username(
     }
 }
 
-pub async fn send_friend_request(
-    jwt: String,
+// Ids of the user's friends, who hear about their presence changes
+pub async fn get_friend_ids<R: Repositories + ?Sized>(
+    user_id: i32,
+    repos: Arc<R>,
+) -> Result<Vec<i32>, ServerError> {
+    let friends = repos.users().get_user_friends(user_id).await?;
+    Ok(friends.into_iter().map(|f| f.friend_id).collect())
+}
+
+// Presence of the requested users who are friends of the user.
+// `live` holds the status of everyone currently connected; anyone missing
+// from it is offline.
+pub async fn get_presence<R: Repositories + ?Sized>(
+    user_id: i32,
+    user_ids: Vec<i32>,
+    live: HashMap<i32, PresenceStatus>,
+    repos: Arc<R>,
+) -> Result<PresenceList, ServerError> {
+    let friend_ids = get_friend_ids(user_id, repos.clone()).await?;
+    let user_ids: Vec<i32> = user_ids
+        .into_iter()
+        .filter(|id| friend_ids.contains(id))
+        .collect();
+
+    let users = repos.users().get_users_from_list(user_ids).await?;
+    let presences = users
+        .into_iter()
+        .map(|u| Presence {
+            user_id: u.id,
+            status: live.get(&u.id).copied().unwrap_or(PresenceStatus::Offline),
+            last_seen_at: u.last_seen_at,
+        })
+        .collect();
+
+    Ok(PresenceList { presences })
+}
+
+// Stamps the user's last_seen_at once their final session ends
+pub async fn record_last_seen<R: Repositories + ?Sized>(
+    user_id: i32,
+    repos: Arc<R>,
+) -> Result<NaiveDateTime, ServerError> {
+    repos
+        .users()
+        .update_last_seen(user_id, Utc::now().naive_utc())
+        .await
+}
+
+pub async fn are_friends<R: Repositories + ?Sized>(
+    user_id: i32,
+    other_id: i32,
+    repos: Arc<R>,
+) -> Result<bool, ServerError> {
+    let friendship = repos.users().get_friendship(user_id, other_id).await?;
+    Ok(friendship.is_some())
+}
+
+pub async fn send_friend_request<R: Repositories + ?Sized>(
+    sender_id: i32,
     receiver_id: i32,
-    db: Arc<DatabaseConnection>,
+    repos: Arc<R>,
 ) -> Result<ServerResponseModel, ServerError> {
-    let claim = jwt::decode_jwt(&jwt).map_err(|e| ServerError::InvalidToken(e.to_string()))?;
-    let sender_id = claim.claims.user_id;
-
     // Check if either user has blocked the other
-    let blocked = user_repository::get_user_blocked(sender_id, receiver_id, db.clone()).await?;
+    block_policy::ensure_not_blocked(sender_id, receiver_id, &*repos).await?;
+
+    // Check if these users are already friends
+    if repos
+        .users()
+        .get_friendship(sender_id, receiver_id)
+        .await?
+        .is_some()
+    {
+        return Err(ServerError::AlreadyFriends);
+    }
 
-    if blocked.is_some() {
-        return Err(ServerError::ActionBlocked);
+    // Check if this exact request already exists
+    if repos
+        .users()
+        .get_friend_request(sender_id, receiver_id)
+        .await?
+        .is_some()
+    {
+        return Ok(ServerResponseModel { success: true });
     }
 
-    // Send friend request through the database
-    user_repository::send_friend_request(sender_id, receiver_id, db.clone()).await?;
+    // A request the other way means both want to be friends, so accept it.
+    // That writes several rows, so they go in together.
+    let txn = repos.begin().await?;
+    let reverse = txn
+        .users()
+        .get_friend_request(receiver_id, sender_id)
+        .await?;
+    if reverse.is_some() {
+        txn.users()
+            .update_friend_request_status(receiver_id, sender_id, Status::Accepted)
+            .await?;
+
+        // Create mutual friendships
+        for (u1, u2) in [(sender_id, receiver_id), (receiver_id, sender_id)] {
+            txn.users().create_friendship(u1, u2).await?;
+        }
+    } else {
+        txn.users()
+            .create_friend_request(sender_id, receiver_id)
+            .await?;
+    }
+    txn.commit().await?;
 
     Ok(ServerResponseModel { success: true })
 }
 
-pub async fn accept_friend_request(
-    jwt: String,
+pub async fn accept_friend_request<R: Repositories + ?Sized>(
+    receiver_id: i32,
     sender_id: i32,
-    db: Arc<DatabaseConnection>,
+    repos: Arc<R>,
 ) -> Result<ServerResponseModel, ServerError> {
-    let claim = jwt::decode_jwt(&jwt).map_err(|e| ServerError::InvalidToken(e.to_string()))?;
-    let receiver_id = claim.claims.user_id;
+    let txn = repos.begin().await?;
+
+    // Only a pending request can be accepted, and not across a block
+    if txn
+        .users()
+        .get_friend_request(sender_id, receiver_id)
+        .await?
+        .is_none()
+    {
+        return Err(ServerError::RequestInvalid(
+            "No pending friend request from this user".to_string(),
+        ));
+    }
+    block_policy::ensure_not_blocked(receiver_id, sender_id, &*txn).await?;
 
     // Update request to accept
-    user_repository::update_friend_request_status(
-        sender_id,
-        receiver_id,
-        Status::Accepted,
-        db.clone(),
-    )
-    .await?;
+    txn.users()
+        .update_friend_request_status(sender_id, receiver_id, Status::Accepted)
+        .await?;
 
     // Create mutual friendships
     for (u1, u2) in [(sender_id, receiver_id), (receiver_id, sender_id)] {
-        user_repository::create_friendship(u1, u2, db.clone()).await?;
+        txn.users().create_friendship(u1, u2).await?;
     }
 
+    txn.commit().await?;
+
     Ok(ServerResponseModel { success: true })
 }
 
-pub async fn decline_friend_request(
-    jwt: String,
+pub async fn decline_friend_request<R: Repositories + ?Sized>(
+    receiver_id: i32,
     sender_id: i32,
-    db: Arc<DatabaseConnection>,
+    repos: Arc<R>,
 ) -> Result<ServerResponseModel, ServerError> {
-    let claim = jwt::decode_jwt(&jwt).map_err(|e| ServerError::InvalidToken(e.to_string()))?;
-    let receiver_id = claim.claims.user_id;
-
     // Mark request as rejected and delete it
-    user_repository::update_friend_request_status(
-        sender_id,
-        receiver_id,
-        Status::Rejected,
-        db.clone(),
-    )
-    .await?;
+    repos
+        .users()
+        .update_friend_request_status(sender_id, receiver_id, Status::Rejected)
+        .await?;
 
     Ok(ServerResponseModel { success: true })
 }
 
-pub async fn cancel_friend_request(
-    jwt: String,
+pub async fn cancel_friend_request<R: Repositories + ?Sized>(
+    sender_id: i32,
     receiver_id: i32,
-    db: Arc<DatabaseConnection>,
+    repos: Arc<R>,
 ) -> Result<ServerResponseModel, ServerError> {
-    let claim = jwt::decode_jwt(&jwt).map_err(|e| ServerError::InvalidToken(e.to_string()))?;
-    let sender_id = claim.claims.user_id;
-
     // Check if either user has blocked the other
-    let blocked = user_repository::get_user_blocked(sender_id, receiver_id, db.clone()).await?;
-
-    if blocked.is_some() {
-        return Err(ServerError::ActionBlocked);
-    }
+    block_policy::ensure_not_blocked(sender_id, receiver_id, &*repos).await?;
 
     // Delete friend request through the database
-    user_repository::update_friend_request_status(
-        sender_id,
-        receiver_id,
-        Status::Rejected,
-        db.clone(),
-    )
-    .await?;
+    repos
+        .users()
+        .update_friend_request_status(sender_id, receiver_id, Status::Rejected)
+        .await?;
 
     Ok(ServerResponseModel { success: true })
 }
 
-pub async fn get_friend_requests(
-    jwt: String,
-    db: Arc<DatabaseConnection>,
+pub async fn get_friend_requests<R: Repositories + ?Sized>(
+    user_id: i32,
+    repos: Arc<R>,
 ) -> Result<FriendRequestList, ServerError> {
-    let claim = jwt::decode_jwt(&jwt).map_err(|e| ServerError::InvalidToken(e.to_string()))?;
-    let user_id = claim.claims.user_id;
-
     // Incoming: others sent to user
-    let incoming_requests = user_repository::get_user_friend_requests(
-        user_id,
-        Some(entity::friend_requests::Column::ReceiverId),
-        db.clone(),
-    )
-    .await?;
+    let incoming_requests = repos
+        .users()
+        .get_user_friend_requests(user_id, Some(entity::friend_requests::Column::ReceiverId))
+        .await?;
 
     // Outgoing: User sent to others
-    let outgoing_requests = user_repository::get_user_friend_requests(
-        user_id,
-        Some(entity::friend_requests::Column::SenderId),
-        db.clone(),
-    )
-    .await?;
+    let outgoing_requests = repos
+        .users()
+        .get_user_friend_requests(user_id, Some(entity::friend_requests::Column::SenderId))
+        .await?;
 
     // Collect ids
     let incoming_ids: Vec<i32> = incoming_requests.iter().map(|r| r.sender_id).collect();
     let outgoing_ids: Vec<i32> = outgoing_requests.iter().map(|r| r.receiver_id).collect();
 
     // Get incoming user info
-    let incoming_users = user_repository::get_users_from_list(incoming_ids, db.clone()).await?;
+    let incoming_users = repos.users().get_users_from_list(incoming_ids).await?;
 
     // Get outgoing user info
-    let outgoing_users = user_repository::get_users_from_list(outgoing_ids, db.clone()).await?;
+    let outgoing_users = repos.users().get_users_from_list(outgoing_ids).await?;
 
     // Create incoming JSON vector
     let incoming = incoming_users
@@ -189,56 +274,112 @@ pub async fn get_friend_requests(
     Ok(FriendRequestList { incoming, outgoing })
 }
 
-pub async fn remove_friend(
-    jwt: String,
+pub async fn remove_friend<R: Repositories + ?Sized>(
+    user_id: i32,
     friend_id: i32,
-    db: Arc<DatabaseConnection>,
+    repos: Arc<R>,
 ) -> Result<ServerResponseModel, ServerError> {
-    let claim = jwt::decode_jwt(&jwt).map_err(|e| ServerError::InvalidToken(e.to_string()))?;
-    let user_id = claim.claims.user_id;
+    let txn = repos.begin().await?;
 
     // Delete the friendship from the database
-    user_repository::delete_friendship(user_id, friend_id, db.clone()).await?;
+    txn.users().delete_friendship(user_id, friend_id).await?;
 
     // Delete all friend requests
-    user_repository::delete_friend_requests(user_id, friend_id, db.clone()).await?;
+    txn.users()
+        .delete_friend_requests(user_id, friend_id)
+        .await?;
+
+    txn.commit().await?;
 
     Ok(ServerResponseModel { success: true })
 }
 
-pub async fn block_user(
-    jwt: String,
+pub async fn block_user<R: Repositories + ?Sized>(
+    user_id: i32,
     blocked_id: i32,
-    db: Arc<DatabaseConnection>,
+    repos: Arc<R>,
 ) -> Result<ServerResponseModel, ServerError> {
-    let claim = jwt::decode_jwt(&jwt).map_err(|e| ServerError::InvalidToken(e.to_string()))?;
-    let user_id = claim.claims.user_id;
+    if user_id == blocked_id {
+        return Err(ServerError::RequestInvalid(
+            "You can't block yourself".to_string(),
+        ));
+    }
+
+    if repos.users().get_user_by_id(blocked_id).await?.is_none() {
+        return Err(ServerError::UserNotFound);
+    }
 
-    // Block the user in the database
-    user_repository::block_user(user_id, blocked_id, db.clone()).await?;
+    let txn = repos.begin().await?;
+
+    // Block the user in the database, unless this user already has
+    let block = txn.users().get_block(user_id, blocked_id).await?;
+    if block.is_none() {
+        txn.users().block_user(user_id, blocked_id).await?;
+    }
 
     // Remove any existing friendship (bidirectional)
-    user_repository::delete_friendship(user_id, blocked_id, db.clone()).await?;
+    txn.users().delete_friendship(user_id, blocked_id).await?;
 
     // Delete any active friend requests (bidirectional)
-    user_repository::delete_friend_requests(user_id, blocked_id, db.clone()).await?;
+    txn.users()
+        .delete_friend_requests(user_id, blocked_id)
+        .await?;
+
+    txn.commit().await?;
+
+    Ok(ServerResponseModel { success: true })
+}
+
+pub async fn unblock_user<R: Repositories + ?Sized>(
+    user_id: i32,
+    blocked_id: i32,
+    repos: Arc<R>,
+) -> Result<ServerResponseModel, ServerError> {
+    // A block placed by the other user can't be lifted from this side
+    let block = repos.users().get_block(user_id, blocked_id).await?;
+    if block.is_none() {
+        return Err(ServerError::RequestInvalid(
+            "User is not blocked".to_string(),
+        ));
+    }
+
+    repos.users().unblock_user(user_id, blocked_id).await?;
 
     Ok(ServerResponseModel { success: true })
 }
 
-pub async fn get_friends(
-    jwt: String,
-    db: Arc<DatabaseConnection>,
+pub async fn get_blocked_users<R: Repositories + ?Sized>(
+    user_id: i32,
+    repos: Arc<R>,
+) -> Result<UserList, ServerError> {
+    // Get the ids of every user this user has blocked
+    let blocked = repos.users().get_blocked_users(user_id).await?;
+    let blocked_ids: Vec<i32> = blocked.into_iter().map(|b| b.blocked_id).collect();
+
+    let users = repos.users().get_users_from_list(blocked_ids).await?;
+    let blocked = users
+        .into_iter()
+        .map(|u| User {
+            id: u.id,
+            username: u.username,
+        })
+        .collect();
+
+    Ok(UserList { users: blocked })
+}
+
+pub async fn get_friends<R: Repositories + ?Sized>(
+    user_id: i32,
+    repos: Arc<R>,
 ) -> Result<UserList, ServerError> {
-    let claim = jwt::decode_jwt(&jwt).map_err(|e| ServerError::InvalidToken(e.to_string()))?;
-    let user = user_repository::get_user_by_id(claim.claims.user_id, db.clone()).await?;
+    let user = repos.users().get_user_by_id(user_id).await?;
     if let Some(user) = user {
         // Get user friends and collect them into a vector
-        let friends = user_repository::get_user_friends(user.id, db.clone()).await?;
+        let friends = repos.users().get_user_friends(user.id).await?;
         let friend_ids: Vec<i32> = friends.into_iter().map(|f| f.friend_id).collect();
 
         // Get all friends user info and collect them into a JSON response
-        let users = user_repository::get_users_from_list(friend_ids, db.clone()).await?;
+        let users = repos.users().get_users_from_list(friend_ids).await?;
         let friends = users
             .into_iter()
             .map(|u| User {
@@ -1,620 +1,272 @@
-pub mod entity;
-pub mod handlers;
-pub mod utils;
-
-use crate::handlers::controllers::{auth_controller, chat_controller, user_controller};
-use dashmap::DashMap;
+use clap::Parser;
+use migration::{Migrator, MigratorTrait};
 use quinn::{Endpoint, RecvStream, SendStream};
 use sea_orm::DatabaseConnection;
-use serde::Serialize;
-use serde_json::json;
+use server::handlers::connections::{
+    handle_typing, unbind_session, Connection, EventStream, ServerState,
+};
+use server::handlers::router::{build_response, Router};
+use server::handlers::routes;
+use server::utils;
+use server::utils::config::{self, Cli, LogFormat, MigrateAction, ServerCommand, ServerConfig};
 use server::utils::errors::server_error::ServerError;
-use shared::client_response::{ClientRequest, Command};
-use shared::models::server_models::ServerResponseModel;
+use shared::client_response::{ClientRequest, TypingSignal};
+use shared::codec::{
+    self, ClientHello, Codec, CodecError, ServerHello, HANDSHAKE_CODEC, MAX_HANDSHAKE_SIZE,
+    SUPPORTED_VERSIONS,
+};
 use shared::server_response::ServerResponse;
-use std::net::SocketAddr;
+use std::io;
 use std::sync::Arc;
-use tokio::sync::Mutex;
-use tracing::{error, info};
-use tracing_subscriber;
+use std::time::Duration;
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+
+// How long a new connection has to send its hello
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
 
-const MAX_MESSAGE_SIZE: usize = 65536; // 64 KB
+// Close code for connections that fail the handshake
+const HANDSHAKE_FAILED: u32 = 1;
+
+// Close code for connections whose session ended from another connection
+const SESSION_ENDED: u32 = 2;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     dotenvy::dotenv().ok();
 
-    tracing_subscriber::fmt::init();
+    let mut cli = Cli::parse();
+    if let Some(ServerCommand::Migrate { action }) = cli.command.take() {
+        return migrate(cli, action.unwrap_or(MigrateAction::Up { steps: None })).await;
+    }
+
+    // Bad config stops the server before it touches the database or network
+    let config = match ServerConfig::load(cli) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    init_logging(&config);
+    config::init(config.clone());
 
     // Establish DB connection
-    let db_url = utils::constants::DATABASE_URL.to_string();
-    let db: DatabaseConnection = sea_orm::Database::connect(&db_url).await?;
-    let db_arc = Arc::new(db);
+    let db: DatabaseConnection = sea_orm::Database::connect(config.connect_options()).await?;
+    if config.database.migrate_on_startup {
+        let pending = Migrator::get_pending_migrations(&db).await?.len();
+        if pending > 0 {
+            info!("Applying {} pending migrations", pending);
+            Migrator::up(&db, None).await?;
+        }
+    }
+    let state = ServerState::new(Arc::new(db));
+    let router = Arc::new(routes::router());
 
-    let addr: SocketAddr = "0.0.0.0:8080".parse()?;
-    let endpoint = Endpoint::server(utils::cert::generate_self_signed_cert(), addr)?;
+    let addr = config.bind_addr;
+    let endpoint = Endpoint::server(utils::cert::load_or_create_server_config(&config)?, addr)?;
 
     info!("Server listening on {}", addr);
 
-    // List of Logged_In Users
-    let logged_in = Arc::new(DashMap::<i32, Vec<Arc<Mutex<SendStream>>>>::new());
-
     while let Some(conn) = endpoint.accept().await {
-        tokio::spawn(handle_connection(conn, db_arc.clone(), logged_in.clone()));
+        tokio::spawn(handle_connection(conn, state.clone(), router.clone()));
     }
 
     Ok(())
 }
 
-async fn handle_connection(
-    conn: quinn::Connecting,
-    db: Arc<DatabaseConnection>,
-    logged_in: Arc<DashMap<i32, Vec<Arc<Mutex<SendStream>>>>>,
-) {
+async fn handle_connection(conn: quinn::Connecting, state: ServerState, router: Arc<Router>) {
     match conn.await {
         Ok(connection) => {
             info!("New connection from {}", connection.remote_address());
 
-            let current_user: Arc<Mutex<Option<i32>>> = Arc::new(Mutex::new(None));
+            // Agree on a protocol version and codec before anything else
+            let codec = match handshake(&connection).await {
+                Ok(codec) => codec,
+                Err(e) => {
+                    warn!(
+                        "Handshake with {} failed: {}",
+                        connection.remote_address(),
+                        e
+                    );
+                    connection.close(HANDSHAKE_FAILED.into(), e.to_string().as_bytes());
+                    return;
+                }
+            };
 
-            let send_refresh = match connection.open_uni().await {
+            let send_events = match connection.open_uni().await {
                 Ok(send) => send,
                 Err(err) => {
                     error!("{}", err);
                     return;
                 }
             };
-            let refresh_stream = Arc::new(Mutex::new(send_refresh));
+            let client = Connection::new(
+                state,
+                connection.remote_address(),
+                EventStream::new(send_events, codec),
+            );
 
             {
-                let logged_in = logged_in.clone();
-                let current_user = current_user.clone();
+                let client = client.clone();
                 let connection_clone = connection.clone();
-                let refresh_clone = refresh_stream.clone();
 
                 tokio::spawn(async move {
-                    let _ = connection_clone.closed().await;
-                    if let Some(user_id) = *current_user.lock().await {
-                        let current_id = { refresh_clone.lock().await.id() };
-                        let mut remove_user = false;
-                        if let Some(mut vec) = logged_in.get_mut(&user_id) {
-                            for (i, entry) in vec.clone().iter().enumerate() {
-                                let stream = entry.lock().await;
-                                if stream.id().eq(&current_id) {
-                                    vec.remove(i);
-                                    break;
-                                }
-                            }
-
-                            if vec.is_empty() {
-                                remove_user = true;
-                            }
+                    tokio::select! {
+                        _ = connection_clone.closed() => {}
+                        // Its session was revoked or logged out elsewhere
+                        _ = client.ended.notified() => {
+                            connection_clone.close(SESSION_ENDED.into(), b"Session ended");
                         }
+                    }
+                    unbind_session(&client).await;
+                });
+            }
+
+            // Typing signals arrive as datagrams rather than requests
+            {
+                let connection = connection.clone();
+                let client = client.clone();
 
-                        if remove_user {
-                            logged_in.remove(&user_id);
+                tokio::spawn(async move {
+                    while let Ok(datagram) = connection.read_datagram().await {
+                        let Some(identity) = *client.session.lock().await else {
+                            continue;
+                        };
+                        match codec.decode::<TypingSignal>(&datagram) {
+                            Ok(signal) => {
+                                handle_typing(signal, identity.user_id, client.state.clone()).await;
+                            }
+                            Err(e) => error!("Invalid datagram: {}", e),
                         }
                     }
                 });
             }
 
             while let Ok((mut send, mut recv)) = connection.accept_bi().await {
-                let db = db.clone();
-                let logged_in = logged_in.clone();
-                let current_user = current_user.clone();
-                let refresh_clone = refresh_stream.clone();
+                let client = client.clone();
+                let router = router.clone();
                 tokio::spawn(async move {
                     // Receive messages from the client and respond to them until the connection closes
-                    // Get a ClientRequest JSON
-                    let req = match get_client_request(&mut recv).await {
+                    let req = match get_client_request(&mut recv, codec).await {
                         Ok(req) => req,
                         Err(ServerError::Disconnected) => {
                             info!("Client closed stream");
                             return;
                         }
                         Err(e) => {
-                            println!("Client error: {:?}", e);
+                            warn!("Client error: {:?}", e);
                             // Respond with error JSON and continue listening
                             if let Err(e) = send_response(
                                 &mut send,
                                 build_response::<(), ServerError>(Err(e), None, ""),
+                                codec,
                             )
                             .await
                             {
-                                eprintln!("Error sending error response, closing...: {:?}", e);
+                                error!("Error sending error response, closing...: {:?}", e);
                                 return;
                             }
                             return;
                         }
                     };
 
-                    // Match command and forward message to the appropriate controller
-                    let response = handle_command(
-                        req,
-                        db.clone(),
-                        logged_in.clone(),
-                        refresh_clone,
-                        current_user.clone(),
-                    )
-                    .await;
-
-                    let users: Vec<i32> = logged_in.iter().map(|r| r.key().clone()).collect();
-                    info!("List Of Logged In Users: {:?}", users);
+                    // Run the command through the router to its handler
+                    let response = router.handle(req.command, client.clone()).await;
 
                     // Send the response
-                    if let Err(e) = send_response(&mut send, response).await {
+                    if let Err(e) = send_response(&mut send, response, codec).await {
                         error!("Error sending response, closing...: {:?}", e);
-                        return;
                     }
                 });
             }
         }
-        Err(e) => eprintln!("Connection failed: {:?}", e),
+        Err(e) => warn!("Connection failed: {:?}", e),
     }
 }
 
-/// Matches the ClientRequest command to one recognized by the system
-/// and returns a response given by the controller for that command
-async fn handle_command(
-    req: ClientRequest,
-    db: Arc<DatabaseConnection>,
-    logged_in: Arc<DashMap<i32, Vec<Arc<Mutex<SendStream>>>>>,
-    refresh_stream: Arc<Mutex<SendStream>>,
-    current_user: Arc<Mutex<Option<i32>>>,
-) -> ServerResponse {
-    match req.command {
-        Command::Register { username, password } => {
-            let result = auth_controller::register(username.clone(), password, db.clone()).await;
-            // User is automatically logged in upon registration
-            if let Ok(auth) = &result {
-                logged_in.insert(auth.user_id, vec![refresh_stream]);
-                current_user.lock().await.replace(auth.user_id);
-            }
-            let jwt = result.as_ref().ok().map(|r| r.token.clone());
-            build_response(result, jwt, "Registered")
-        }
-
-        Command::Login { username, password } => {
-            let result = auth_controller::login(username.clone(), password, db.clone()).await;
-            if let Ok(auth) = &result {
-                let vec = logged_in.get_mut(&auth.user_id);
-                if let Some(mut vec) = vec {
-                    vec.push(refresh_stream);
-                } else {
-                    logged_in.insert(auth.user_id, vec![refresh_stream]);
-                }
-                info!("User {} logged in", username);
-                current_user.lock().await.replace(auth.user_id);
-            }
-            let jwt = result.as_ref().ok().map(|r| r.token.clone());
-            build_response(result, jwt, "Logged in")
-        }
-
-        Command::UpdateProfile { new_password } => {
-            if let Some(jwt) = req.jwt {
-                let result =
-                    auth_controller::update_password(jwt.clone(), new_password, db.clone()).await;
-                build_response(result, Some(jwt), "Password Updated")
-            } else {
-                build_response::<(), ServerError>(
-                    Err(ServerError::InvalidToken("No token provided".into())),
-                    None,
-                    "",
-                )
-            }
-        }
-
-        Command::Logout { username } => {
-            let result: Result<_, ServerError> = Ok(ServerResponseModel { success: true });
-            let user = user_controller::get_user_by_username(username.clone(), db.clone()).await;
-            match user {
-                Ok(user) => {
-                    // Find the stream corresponding to the current session
-                    let current_id = { refresh_stream.lock().await.id() };
-
-                    let mut remove_user = false;
-                    if let Some(mut vec) = logged_in.get_mut(&user.id) {
-                        for (i, entry) in vec.clone().iter().enumerate() {
-                            let stream = entry.lock().await;
-                            if stream.id().eq(&current_id) {
-                                vec.remove(i);
-                                break;
-                            }
-                        }
-
-                        if vec.is_empty() {
-                            remove_user = true;
-                        }
-                    }
-
-                    if remove_user {
-                        logged_in.remove(&user.id);
-                    }
-
-                    build_response(result, req.jwt, "Logged out")
-                }
-                Err(e) => {
-                    build_response::<(), utils::errors::server_error::ServerError>(Err(e), None, "")
-                }
-            }
-        }
-
-        Command::SendFriendRequest { receiver_username } => {
-            if let Some(jwt) = req.jwt {
-                let user =
-                    user_controller::get_user_by_username(receiver_username.clone(), db.clone())
-                        .await;
-                match user {
-                    Ok(user) => {
-                        let result =
-                            user_controller::add_friend(jwt.clone(), user.id, db.clone()).await;
-                        if result.is_ok() {
-                            notify_users(vec![user.id], logged_in.clone()).await;
-                        }
-                        build_response(
-                            user_controller::add_friend(jwt.clone(), user.id, db.clone()).await,
-                            Some(jwt.clone()),
-                            "Friend Request Sent",
-                        )
-                    }
-                    Err(e) => build_response::<(), utils::errors::server_error::ServerError>(
-                        Err(e),
-                        None,
-                        "",
-                    ),
-                }
-            } else {
-                build_response::<(), ServerError>(
-                    Err(ServerError::InvalidToken("No token provided".to_string())),
-                    None,
-                    "",
-                )
-            }
-        }
-
-        Command::GetFriendRequests {} => {
-            let jwt = req.jwt;
-            let result =
-                user_controller::get_friend_requests(jwt.clone().unwrap(), db.clone()).await;
-            build_response(result, jwt.clone(), "Friend Request List Sent")
-        }
-
-        Command::AcceptFriendRequest { sender_id } => {
-            let jwt = req.jwt;
-            let result =
-                user_controller::accept_friend_request(jwt.clone().unwrap(), sender_id, db.clone())
-                    .await;
-            notify_users(vec![sender_id], logged_in.clone()).await;
-            build_response(result, jwt.clone(), "Friend Request Accepted")
-        }
-
-        Command::DeclineFriendRequest { sender_id } => {
-            let jwt = req.jwt;
-            let result = user_controller::decline_friend_request(
-                jwt.clone().unwrap(),
-                sender_id,
-                db.clone(),
-            )
-            .await;
-            build_response(result, jwt.clone(), "Friend Request Denied")
-        }
-
-        Command::RemoveFriend { friend_id } => {
-            let jwt = req.jwt;
-            let result =
-                user_controller::remove_friend(jwt.clone().unwrap(), friend_id, db.clone()).await;
-            notify_users(vec![friend_id], logged_in.clone()).await;
-            build_response(result, jwt.clone(), "Unfriended")
-        }
-
-        Command::GetChats { page, page_size } => {
-            if let Some(jwt) = req.jwt {
-                build_response(
-                    chat_controller::get_user_chats(jwt, page, page_size, db.clone()).await,
-                    None,
-                    "Chat List",
-                )
-            } else {
-                build_response::<(), ServerError>(
-                    Err(ServerError::InvalidToken("No token provided".to_string())),
-                    None,
-                    "",
-                )
-            }
-        }
-
-        Command::GetChatMessages {
-            chat_id,
-            page,
-            page_size,
-        } => {
-            if let Some(jwt) = req.jwt {
-                build_response(
-                    chat_controller::get_chat_messages(jwt, chat_id, page, page_size, db.clone())
-                        .await,
-                    None,
-                    "Chat Messages",
-                )
-            } else {
-                build_response::<(), ServerError>(
-                    Err(ServerError::InvalidToken("No token provided".to_string())),
-                    None,
-                    "",
-                )
-            }
-        }
-
-        Command::SendMessage { chat_id, content } => {
-            if let Some(jwt) = req.jwt {
-                let result = chat_controller::send_message(jwt, chat_id, content, db.clone()).await;
-
-                // If a message is sent, get affected online users and notify them
-                if result.is_ok() {
-                    let user_ids = chat_controller::get_chat_user_ids(chat_id, db.clone()).await;
-                    if let Ok(user_ids) = user_ids {
-                        notify_users(user_ids, logged_in.clone()).await;
-                    }
-                }
-
-                // Send the response back
-                build_response(result, None, "Message Sent")
-            } else {
-                build_response::<(), ServerError>(
-                    Err(ServerError::InvalidToken("No token provided".to_string())),
-                    None,
-                    "",
-                )
+/// Answers the client's hello on the connection's first stream. Returns the
+/// codec the rest of the connection is encoded with.
+async fn handshake(connection: &quinn::Connection) -> Result<Codec, CodecError> {
+    let exchange = async {
+        let (mut send, mut recv) = connection.accept_bi().await.map_err(io::Error::other)?;
+        let hello: ClientHello =
+            codec::read_message(&mut recv, HANDSHAKE_CODEC, MAX_HANDSHAKE_SIZE).await?;
+        let reply = hello.negotiate(SUPPORTED_VERSIONS, &Codec::ALL);
+        codec::write_message(&mut send, HANDSHAKE_CODEC, &reply).await?;
+        send.finish().await.map_err(io::Error::other)?;
+
+        match reply {
+            ServerHello::Accepted { version, codec } => {
+                info!("Negotiated protocol v{} with the {} codec", version, codec);
+                Ok(codec)
             }
+            ServerHello::Rejected { reason } => Err(CodecError::Rejected(reason)),
         }
-
-        Command::GetChatsPages { page_size } => {
-            if let Some(jwt) = req.jwt {
-                build_response(
-                    chat_controller::get_chats_page_count(jwt, page_size, db.clone()).await,
-                    None,
-                    "Chats Page Count",
-                )
-            } else {
-                build_response::<(), ServerError>(
-                    Err(ServerError::InvalidToken("No token provided".to_string())),
-                    None,
-                    "",
-                )
-            }
-        }
-
-        Command::GetChatPages { chat_id, page_size } => {
-            if let Some(jwt) = req.jwt {
-                build_response(
-                    chat_controller::get_chat_page_count(jwt, chat_id, page_size, db.clone()).await,
-                    None,
-                    "Chat Page Count",
-                )
-            } else {
-                build_response::<(), ServerError>(
-                    Err(ServerError::InvalidToken("No token provided".to_string())),
-                    None,
-                    "",
-                )
-            }
-        }
-
-        Command::GetFriends => {
-            if let Some(jwt) = req.jwt {
-                build_response(
-                    user_controller::get_friends(jwt, db.clone()).await,
-                    None,
-                    "Friends",
-                )
-            } else {
-                build_response::<(), ServerError>(
-                    Err(ServerError::InvalidToken("No token provided".to_string())),
-                    None,
-                    "",
-                )
-            }
-        }
-
-        Command::CreateChat {
-            member_ids,
-            name,
-            is_group,
-        } => {
-            if let Some(jwt) = req.jwt {
-                let result = chat_controller::create_chat(
-                    jwt,
-                    name,
-                    is_group,
-                    member_ids.clone(),
-                    db.clone(),
-                )
-                .await;
-                if result.is_ok() {
-                    notify_users(member_ids, logged_in.clone()).await;
-                }
-                build_response(result, None, "Chat Page Count")
-            } else {
-                build_response::<(), ServerError>(
-                    Err(ServerError::InvalidToken("No token provided".to_string())),
-                    None,
-                    "",
-                )
-            }
-        }
-
-        Command::GetUnreadMessageCount => {
-            if let Some(jwt) = req.jwt {
-                build_response(
-                    chat_controller::get_unread_message_count(jwt, db.clone()).await,
-                    None,
-                    "Unread Message Count",
-                )
-            } else {
-                build_response::<(), ServerError>(
-                    Err(ServerError::InvalidToken("No token provided".to_string())),
-                    None,
-                    "",
-                )
-            }
-        }
-
-        Command::MarkMessagesRead { chat_id } => {
-            if let Some(jwt) = req.jwt {
-                build_response(
-                    chat_controller::mark_messages_read(jwt, chat_id, db.clone()).await,
-                    None,
-                    "Unread Message Count",
-                )
-            } else {
-                build_response::<(), ServerError>(
-                    Err(ServerError::InvalidToken("No token provided".to_string())),
-                    None,
-                    "",
-                )
-            }
-        }
-
-        other => {
-            // Shouldn't be possible, but covering the case.
-            build_response::<(), ServerError>(
-                Err(ServerError::RequestInvalid(format!("{:?}", other))),
-                None,
-                "",
-            )
-        }
-    }
-}
-
-/// Builds a response based on
-/// 1: The result of controller call
-/// 2: The type of model returned by the controller
-pub fn build_response<T, E>(
-    result: Result<T, E>,
-    jwt: Option<String>,
-    message: &str,
-) -> ServerResponse
-where
-    T: Serialize,
-    E: std::fmt::Display,
-{
-    match result {
-        Ok(data) => ServerResponse {
-            jwt,
-            success: true,
-            message: Some(message.to_string()),
-            data: Some(json!(data)),
-        },
-        Err(e) => ServerResponse {
-            jwt: None,
-            success: false,
-            message: Some(e.to_string()),
-            data: None,
-        },
-    }
+    };
+    tokio::time::timeout(HANDSHAKE_TIMEOUT, exchange)
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "No hello from the client"))?
 }
 
 /// Uses the QUIC sending stream to send a ServerResponse
 async fn send_response(
     send: &mut SendStream,
     resp: ServerResponse,
+    codec: Codec,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    // Send response
-    let bytes = serde_json::to_vec(&resp).expect("Failed to serialize response");
-    let len = (bytes.len() as u32).to_be_bytes();
-    send.write_all(&len).await?;
-    send.write_all(&bytes).await?;
+    codec::write_message(send, codec, &resp).await?;
     send.finish().await?;
     Ok(())
 }
 
 /// Receives a message from the client through the QUIC receive stream and
-/// deserializes it into a ClientRequest, or returns a ServerError if
-/// anything goes wrong
-async fn get_client_request(recv: &mut RecvStream) -> Result<ClientRequest, ServerError> {
-    // Read the JSON message from the stream
-    let mut buf = match receive_msg(recv).await {
-        Ok(buf) => buf,
-        Err(e) => {
-            return Err(e);
-        }
-    };
-
-    // Deserialize ClientRequest
-    deserialize_client_request(&mut buf).await
+/// decodes it into a ClientRequest, or returns a ServerError if anything
+/// goes wrong
+async fn get_client_request(
+    recv: &mut RecvStream,
+    codec: Codec,
+) -> Result<ClientRequest, ServerError> {
+    // Anything over the limit is refused before it's read (protecting against DDoS)
+    let max_len = config::current().limits.max_message_size;
+    codec::read_message(recv, codec, max_len)
+        .await
+        .map_err(|e| {
+            if !matches!(e, CodecError::Closed) {
+                error!("Invalid request: {}", e);
+            }
+            e.into()
+        })
 }
 
-/// Gets the client message as a Vec of bytes
-async fn receive_msg(recv: &mut RecvStream) -> Result<Vec<u8>, ServerError> {
-    // Read exactly 4 bytes for message length
-    let mut len_buf = [0u8; 4];
-    match recv.read_exact(&mut len_buf).await {
-        Ok(_) => {}
+/// Runs `server migrate`: changes the schema and exits without starting the server
+async fn migrate(cli: Cli, action: MigrateAction) -> Result<(), Box<dyn std::error::Error>> {
+    let config = match ServerConfig::load_for_migrations(cli) {
+        Ok(config) => config,
         Err(e) => {
-            println!("Read error: {:?}", e);
-            return if e.to_string().contains("early eof") {
-                Err(ServerError::Disconnected)
-            } else {
-                Err(ServerError::RequestInvalid(
-                    "Couldn't read message length".into(),
-                ))
-            };
+            eprintln!("{}", e);
+            std::process::exit(2);
         }
-    }
-    let msg_len = u32::from_be_bytes(len_buf) as usize;
-
-    // Check that message isn't too large (protecting against DDoS
-    if msg_len > MAX_MESSAGE_SIZE {
-        error!("Received message exceeding max allowed size");
-        return Err(ServerError::RequestInvalid(
-            "Received message exceeding max allowed size".to_string(),
-        ));
-    }
+    };
+    init_logging(&config);
 
-    // Read exactly `msg_len` bytes for the message
-    let mut buf = vec![0u8; msg_len];
-    if recv.read_exact(&mut buf).await.is_err() {
-        return Err(ServerError::RequestInvalid(
-            "Couldn't read JSON".to_string(),
-        )); // Connection closed or error
-    }
-
-    Ok(buf)
-}
-
-/// Deserializes a Vec of bytes into a ClientRequest
-async fn deserialize_client_request(buf: &mut Vec<u8>) -> Result<ClientRequest, ServerError> {
-    match serde_json::from_slice(&buf) {
-        Ok(r) => Ok(r),
-        Err(e) => {
-            println!("{}", e.to_string());
-            Err(ServerError::RequestInvalid("Invalid JSON".to_string()))
-        }
+    let db = sea_orm::Database::connect(config.connect_options()).await?;
+    match action {
+        MigrateAction::Up { steps } => Migrator::up(&db, steps).await?,
+        MigrateAction::Down { steps } => Migrator::down(&db, Some(steps)).await?,
+        MigrateAction::Status => Migrator::status(&db).await?,
     }
+    Ok(())
 }
 
-async fn notify_users(
-    user_ids: Vec<i32>,
-    stream_map: Arc<DashMap<i32, Vec<Arc<Mutex<SendStream>>>>>,
-) {
-    for user_id in user_ids {
-        if let Some(stream_vec) = stream_map.get_mut(&user_id) {
-            for stream in stream_vec.iter() {
-                let mut stream_lock = stream.lock().await;
-                println!("Notifying user {} in stream map", user_id);
-                let bytes = serde_json::to_vec(&shared::server_response::Refresh)
-                    .expect("Failed to serialize refresh ping");
-                let len = (bytes.len() as u32).to_be_bytes();
-                if let Err(e) = stream_lock.write_all(&len).await {
-                    eprintln!("Failed to notify user {}: {}", user_id, e);
-                    stream_map.remove(&user_id);
-                    continue;
-                }
-                if let Err(e) = stream_lock.write_all(&bytes).await {
-                    eprintln!("Failed to notify user {}: {}", user_id, e);
-                    stream_map.remove(&user_id);
-                    continue;
-                }
-            }
-        }
+/// Sets up the log output in the configured format and filter
+fn init_logging(config: &ServerConfig) {
+    // Already validated when the config was loaded
+    let filter = EnvFilter::new(&config.log.level);
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match config.log.format {
+        LogFormat::Full => builder.init(),
+        LogFormat::Compact => builder.compact().init(),
+        LogFormat::Json => builder.json().init(),
     }
 }
@@ -1,36 +1,142 @@
 use quinn::{ServerConfig, TransportConfig};
 use rcgen::generate_simple_self_signed;
 use rustls::{Certificate, PrivateKey};
+use shared::tls::fingerprint;
+use std::fs;
+use std::io::{self, BufReader, Write};
+use std::path::Path;
 use std::sync::Arc;
-use std::time::Duration;
+use tracing::{info, warn};
 
-pub fn generate_self_signed_cert() -> ServerConfig {
-    // Generate self-signed certificate using rcgen
-    let cert = generate_simple_self_signed(vec!["localhost".into()])
-        .expect("Failed to generate self-signed certificate");
+use crate::utils::config::ServerConfig as Config;
 
-    let cert_der = cert.serialize_der().expect("Failed to serialize cert");
-    let key_der = cert.serialize_private_key_der();
+/// Builds the QUIC server config from the certificate and key at the configured paths,
+/// generating and saving a self-signed pair on first start
+pub fn load_or_create_server_config(config: &Config) -> io::Result<ServerConfig> {
+    let (cert_chain, private_key) = load_or_create_identity(
+        &config.tls.cert_path,
+        &config.tls.key_path,
+        config.tls.hostnames.clone(),
+    )?;
 
-    let cert_chain = vec![Certificate(cert_der)];
-    let private_key = PrivateKey(key_der);
+    // Clients pin this, so log it for admins to hand out or compare against
+    info!(
+        "Server certificate fingerprint: {}",
+        fingerprint(&cert_chain[0].0)
+    );
 
-    // Create server config with single cert
+    server_config(cert_chain, private_key, config)
+}
+
+/// Loads the certificate chain and key from PEM files. If neither file exists yet,
+/// a self-signed certificate for `hostnames` is generated and written to them so the
+/// server keeps the same identity across restarts.
+pub fn load_or_create_identity(
+    cert_path: &Path,
+    key_path: &Path,
+    hostnames: Vec<String>,
+) -> io::Result<(Vec<Certificate>, PrivateKey)> {
+    match (cert_path.exists(), key_path.exists()) {
+        (true, true) => {}
+        (false, false) => {
+            warn!(
+                "No certificate found at {}, generating a self-signed one",
+                cert_path.display()
+            );
+            generate_identity(cert_path, key_path, hostnames)?;
+        }
+        // Never overwrite half of an existing identity
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "Expected both {} and {} to exist",
+                    cert_path.display(),
+                    key_path.display()
+                ),
+            ))
+        }
+    }
+
+    Ok((load_certs(cert_path)?, load_key(key_path)?))
+}
+
+fn generate_identity(cert_path: &Path, key_path: &Path, hostnames: Vec<String>) -> io::Result<()> {
+    let cert = generate_simple_self_signed(hostnames).map_err(io::Error::other)?;
+    let cert_pem = cert.serialize_pem().map_err(io::Error::other)?;
+    let key_pem = cert.serialize_private_key_pem();
+
+    for path in [cert_path, key_path] {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+    }
+    fs::write(cert_path, cert_pem)?;
+
+    // The key is only readable by the user running the server
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(key_path)?.write_all(key_pem.as_bytes())
+}
+
+fn load_certs(path: &Path) -> io::Result<Vec<Certificate>> {
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    let certs: Vec<Certificate> = rustls_pemfile::certs(&mut reader)?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+    if certs.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("No certificates found in {}", path.display()),
+        ));
+    }
+    Ok(certs)
+}
+
+fn load_key(path: &Path) -> io::Result<PrivateKey> {
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    while let Some(item) = rustls_pemfile::read_one(&mut reader)? {
+        match item {
+            rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::ECKey(key) => return Ok(PrivateKey(key)),
+            _ => continue,
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("No private key found in {}", path.display()),
+    ))
+}
+
+pub fn server_config(
+    cert_chain: Vec<Certificate>,
+    private_key: PrivateKey,
+    config: &Config,
+) -> io::Result<ServerConfig> {
+    // Create server config with the loaded cert
     let mut server_config = ServerConfig::with_single_cert(cert_chain, private_key)
-        .expect("Failed to create server config");
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
 
     // Configure transport settings
     let mut transport_config = TransportConfig::default();
 
-    transport_config.max_concurrent_bidi_streams(100_u32.into());
+    transport_config.max_concurrent_bidi_streams(config.limits.max_concurrent_streams.into());
 
-    transport_config.max_idle_timeout(Some(
-        Duration::from_secs(300) //Connection is closed and user logged out after 5 min
-            .try_into()
-            .expect("valid idle timeout"),
-    ));
+    // Connection is closed and user logged out once it has been idle this long
+    let idle_timeout = config
+        .idle_timeout()
+        .try_into()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Idle timeout is too long"))?;
+    transport_config.max_idle_timeout(Some(idle_timeout));
 
     server_config.transport = Arc::new(transport_config);
 
-    server_config
+    Ok(server_config)
 }
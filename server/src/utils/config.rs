@@ -0,0 +1,495 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use sea_orm::{ConnectOptions, DbBackend};
+use serde::Deserialize;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+use thiserror::Error;
+use tracing_subscriber::EnvFilter;
+
+// Read when --config isn't given, if it exists
+const DEFAULT_CONFIG_PATH: &str = "server.toml";
+
+// Longest a session can go unrefreshed. Keeps token expiry times well inside
+// what chrono can represent.
+const MAX_REFRESH_TOKEN_TTL_DAYS: i64 = 3650;
+
+static CONFIG: OnceLock<ServerConfig> = OnceLock::new();
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Couldn't read config file {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid config file {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("Invalid config: {0}")]
+    Invalid(String),
+}
+
+/// Command line flags. Each one can also be set with the env var named after it,
+/// and both take priority over the config file.
+#[derive(Debug, Parser)]
+#[command(name = "server", about = "QUIC messaging server")]
+pub struct Cli {
+    /// TOML config file
+    #[arg(long, env = "SERVER_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Address to listen on
+    #[arg(long, env = "BIND_ADDR")]
+    pub bind_addr: Option<SocketAddr>,
+
+    #[arg(long, env = "DATABASE_URL", hide_env_values = true)]
+    pub database_url: Option<String>,
+
+    #[arg(long, env = "DB_MAX_CONNECTIONS")]
+    pub db_max_connections: Option<u32>,
+
+    #[arg(long, env = "TLS_CERT_PATH")]
+    pub tls_cert_path: Option<PathBuf>,
+
+    #[arg(long, env = "TLS_KEY_PATH")]
+    pub tls_key_path: Option<PathBuf>,
+
+    /// Names a generated certificate is valid for, comma separated
+    #[arg(long, env = "TLS_HOSTNAMES", value_delimiter = ',')]
+    pub tls_hostnames: Option<Vec<String>>,
+
+    /// Largest request the server will read, in bytes
+    #[arg(long, env = "MAX_MESSAGE_SIZE")]
+    pub max_message_size: Option<usize>,
+
+    #[arg(long, env = "IDLE_TIMEOUT_SECS")]
+    pub idle_timeout_secs: Option<u64>,
+
+    #[arg(long, env = "ACCESS_TOKEN_TTL_MINUTES")]
+    pub access_token_ttl_minutes: Option<i64>,
+
+    #[arg(long, env = "REFRESH_TOKEN_TTL_DAYS")]
+    pub refresh_token_ttl_days: Option<i64>,
+
+    #[arg(long, env = "LOG_FORMAT")]
+    pub log_format: Option<LogFormat>,
+
+    /// Log filter, e.g. "info" or "server=debug,sea_orm=warn"
+    #[arg(long, env = "LOG_LEVEL")]
+    pub log_level: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<ServerCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ServerCommand {
+    /// Manage the database schema without starting the server
+    Migrate {
+        #[command(subcommand)]
+        action: Option<MigrateAction>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Subcommand)]
+pub enum MigrateAction {
+    /// Apply pending migrations (the default)
+    Up {
+        /// Only apply this many
+        #[arg(long, short)]
+        steps: Option<u32>,
+    },
+    /// Roll back applied migrations, newest first
+    Down {
+        #[arg(long, short, default_value_t = 1)]
+        steps: u32,
+    },
+    /// List every migration and whether it has been applied
+    Status,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind_addr: SocketAddr,
+    pub database: DatabaseConfig,
+    pub tls: TlsConfig,
+    pub limits: LimitsConfig,
+    pub timeouts: TimeoutsConfig,
+    pub auth: AuthConfig,
+    pub log: LogConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub connect_timeout_secs: u64,
+    // Apply pending migrations before accepting connections
+    pub migrate_on_startup: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+    // Names a generated certificate is valid for
+    pub hostnames: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    pub max_message_size: usize,
+    pub max_concurrent_streams: u32,
+    // Requests a client can make each second on average, and in a burst
+    pub requests_per_second: u32,
+    pub request_burst: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeoutsConfig {
+    // Connections are closed and their user logged out after this long without traffic
+    pub idle_timeout_secs: u64,
+    // How long a typing indicator lasts without a fresh signal
+    pub typing_timeout_secs: u64,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    // Signs access tokens. Best set with the SECRET env var rather than in the file.
+    pub secret: String,
+    pub access_token_ttl_minutes: i64,
+    // How long a session lasts without being refreshed
+    pub refresh_token_ttl_days: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub format: LogFormat,
+    pub level: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Full,
+    Compact,
+    Json,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            bind_addr: SocketAddr::from(([0, 0, 0, 0], 8080)),
+            database: DatabaseConfig::default(),
+            tls: TlsConfig::default(),
+            limits: LimitsConfig::default(),
+            timeouts: TimeoutsConfig::default(),
+            auth: AuthConfig::default(),
+            log: LogConfig::default(),
+        }
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            url: String::new(),
+            max_connections: 10,
+            min_connections: 1,
+            connect_timeout_secs: 8,
+            migrate_on_startup: true,
+        }
+    }
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        TlsConfig {
+            cert_path: "certs/cert.pem".into(),
+            key_path: "certs/key.pem".into(),
+            hostnames: vec!["localhost".to_string()],
+        }
+    }
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        LimitsConfig {
+            max_message_size: 65536, // 64 KB
+            max_concurrent_streams: 100,
+            requests_per_second: 20,
+            request_burst: 40,
+        }
+    }
+}
+
+impl Default for TimeoutsConfig {
+    fn default() -> Self {
+        TimeoutsConfig {
+            idle_timeout_secs: 300,
+            typing_timeout_secs: 5,
+        }
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            secret: String::new(),
+            access_token_ttl_minutes: 15,
+            refresh_token_ttl_days: 30,
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            format: LogFormat::Full,
+            level: "info".to_string(),
+        }
+    }
+}
+
+// Keeps the secret out of logs and error output
+impl std::fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthConfig")
+            .field("secret", &"<hidden>")
+            .field("access_token_ttl_minutes", &self.access_token_ttl_minutes)
+            .field("refresh_token_ttl_days", &self.refresh_token_ttl_days)
+            .finish()
+    }
+}
+
+impl ServerConfig {
+    /// Reads the config file, applies env and command line overrides on top and
+    /// checks the result. A missing default file is fine; a missing --config file isn't.
+    pub fn load(cli: Cli) -> Result<Self, ConfigError> {
+        let config = Self::read(cli)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Like `load`, but only checks the database settings, which is all `server migrate` needs
+    pub fn load_for_migrations(cli: Cli) -> Result<Self, ConfigError> {
+        let config = Self::read(cli)?;
+        config.validate_database()?;
+        config.validate_log()?;
+        Ok(config)
+    }
+
+    fn read(cli: Cli) -> Result<Self, ConfigError> {
+        let config = match &cli.config {
+            Some(path) => Self::from_file(path.clone())?,
+            None if PathBuf::from(DEFAULT_CONFIG_PATH).exists() => {
+                Self::from_file(DEFAULT_CONFIG_PATH.into())?
+            }
+            None => Self::default(),
+        };
+        Ok(config.with_overrides(cli))
+    }
+
+    fn from_file(path: PathBuf) -> Result<Self, ConfigError> {
+        let contents = fs::read_to_string(&path).map_err(|source| ConfigError::Read {
+            path: path.clone(),
+            source,
+        })?;
+        toml::from_str(&contents).map_err(|source| ConfigError::Parse { path, source })
+    }
+
+    /// Parses a config file's contents without applying overrides or validating
+    pub fn from_toml(contents: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(contents)
+    }
+
+    pub fn with_overrides(mut self, cli: Cli) -> Self {
+        if let Some(bind_addr) = cli.bind_addr {
+            self.bind_addr = bind_addr;
+        }
+        if let Some(url) = cli.database_url {
+            self.database.url = url;
+        }
+        if let Some(max) = cli.db_max_connections {
+            self.database.max_connections = max;
+        }
+        if let Some(path) = cli.tls_cert_path {
+            self.tls.cert_path = path;
+        }
+        if let Some(path) = cli.tls_key_path {
+            self.tls.key_path = path;
+        }
+        if let Some(hostnames) = cli.tls_hostnames {
+            self.tls.hostnames = hostnames;
+        }
+        if let Some(size) = cli.max_message_size {
+            self.limits.max_message_size = size;
+        }
+        if let Some(secs) = cli.idle_timeout_secs {
+            self.timeouts.idle_timeout_secs = secs;
+        }
+        if let Some(minutes) = cli.access_token_ttl_minutes {
+            self.auth.access_token_ttl_minutes = minutes;
+        }
+        if let Some(days) = cli.refresh_token_ttl_days {
+            self.auth.refresh_token_ttl_days = days;
+        }
+        if let Some(format) = cli.log_format {
+            self.log.format = format;
+        }
+        if let Some(level) = cli.log_level {
+            self.log.level = level;
+        }
+        // Never a flag, so it doesn't show up in the process list
+        if let Ok(secret) = std::env::var("SECRET") {
+            self.auth.secret = secret;
+        }
+        self
+    }
+
+    /// Checks every setting, naming the first one that's wrong
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.validate_database()?;
+        if self.tls.hostnames.iter().all(|name| name.trim().is_empty()) {
+            return invalid("tls.hostnames needs at least one name");
+        }
+        if self.limits.max_message_size == 0 || self.limits.max_message_size > u32::MAX as usize {
+            return invalid("limits.max_message_size must be between 1 and 4294967295 bytes");
+        }
+        if self.limits.max_concurrent_streams == 0 {
+            return invalid("limits.max_concurrent_streams must be at least 1");
+        }
+        if self.limits.requests_per_second == 0 {
+            return invalid("limits.requests_per_second must be at least 1");
+        }
+        if self.limits.request_burst < self.limits.requests_per_second {
+            return invalid("limits.request_burst can't be less than limits.requests_per_second");
+        }
+        if self.timeouts.idle_timeout_secs == 0 {
+            return invalid("timeouts.idle_timeout_secs must be at least 1");
+        }
+        if self.timeouts.typing_timeout_secs == 0 {
+            return invalid("timeouts.typing_timeout_secs must be at least 1");
+        }
+        self.validate_auth()?;
+        self.validate_log()
+    }
+
+    fn validate_database(&self) -> Result<(), ConfigError> {
+        if self.database.url.is_empty() {
+            return invalid("database.url is required (or set DATABASE_URL)");
+        }
+        if self.backend().is_none() {
+            return invalid("database.url must start with mysql://, postgres:// or sqlite:");
+        }
+        if self.database.max_connections == 0 {
+            return invalid("database.max_connections must be at least 1");
+        }
+        if self.database.min_connections > self.database.max_connections {
+            return invalid("database.min_connections can't be more than database.max_connections");
+        }
+        Ok(())
+    }
+
+    fn validate_log(&self) -> Result<(), ConfigError> {
+        if let Err(e) = EnvFilter::try_new(&self.log.level) {
+            return invalid(&format!(
+                "log.level {:?} is not a valid filter: {}",
+                self.log.level, e
+            ));
+        }
+        Ok(())
+    }
+
+    fn validate_auth(&self) -> Result<(), ConfigError> {
+        if self.auth.secret.is_empty() {
+            return invalid("auth.secret is required (or set SECRET)");
+        }
+        if self.auth.access_token_ttl_minutes <= 0 {
+            return invalid("auth.access_token_ttl_minutes must be at least 1");
+        }
+        if !(1..=MAX_REFRESH_TOKEN_TTL_DAYS).contains(&self.auth.refresh_token_ttl_days) {
+            return invalid(&format!(
+                "auth.refresh_token_ttl_days must be between 1 and {}",
+                MAX_REFRESH_TOKEN_TTL_DAYS
+            ));
+        }
+        // Can't overflow once the refresh lifetime is in range
+        if self.auth.access_token_ttl_minutes >= self.auth.refresh_token_ttl_days * 24 * 60 {
+            return invalid(
+                "auth.access_token_ttl_minutes must be shorter than the refresh token lifetime",
+            );
+        }
+        Ok(())
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.timeouts.idle_timeout_secs)
+    }
+
+    pub fn typing_timeout(&self) -> Duration {
+        Duration::from_secs(self.timeouts.typing_timeout_secs)
+    }
+
+    /// The database the URL points at, picked by its scheme
+    pub fn backend(&self) -> Option<DbBackend> {
+        let (scheme, _) = self.database.url.split_once(':')?;
+        match scheme {
+            "mysql" => Some(DbBackend::MySql),
+            "postgres" | "postgresql" => Some(DbBackend::Postgres),
+            "sqlite" => Some(DbBackend::Sqlite),
+            _ => None,
+        }
+    }
+
+    pub fn connect_options(&self) -> ConnectOptions {
+        let mut options = ConnectOptions::new(self.database.url.clone());
+        options
+            .max_connections(self.database.max_connections)
+            .min_connections(self.database.min_connections)
+            .connect_timeout(Duration::from_secs(self.database.connect_timeout_secs));
+        // Every connection to an in-memory SQLite database gets its own empty one
+        if self.database.url.starts_with("sqlite::memory:") {
+            options.max_connections(1).min_connections(1);
+        }
+        options
+    }
+}
+
+fn invalid(message: &str) -> Result<(), ConfigError> {
+    Err(ConfigError::Invalid(message.to_string()))
+}
+
+/// Makes the config available to the rest of the server. Called once at startup.
+pub fn init(config: ServerConfig) {
+    if CONFIG.set(config).is_err() {
+        panic!("Server config was already initialized");
+    }
+}
+
+/// The running server's config. Code run without `init`, like the tests, gets the
+/// defaults with env overrides, which only need the auth settings to be valid.
+pub fn current() -> &'static ServerConfig {
+    CONFIG.get_or_init(|| {
+        let config = ServerConfig::default().with_overrides(Cli::parse_from(["server"]));
+        if let Err(e) = config.validate_auth() {
+            panic!("{}", e);
+        }
+        config
+    })
+}
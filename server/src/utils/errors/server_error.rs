@@ -1,5 +1,8 @@
 use crate::utils::jwt::CreationError;
 use sea_orm::DbErr;
+use shared::codec::CodecError;
+use shared::models::auth_models::{NOT_LOGGED_IN, TOKEN_EXPIRED};
+use shared::server_response::{ACTION_BLOCKED, FORBIDDEN, REQUEST_INVALID};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -19,24 +22,51 @@ pub enum ServerError {
     #[error("User not found")]
     UserNotFound,
 
-    #[error("Action blocked")]
+    #[error("Message not found")]
+    MessageNotFound,
+
+    #[error("{}", ACTION_BLOCKED)]
     ActionBlocked,
 
-    #[error("Action forbidden")]
+    #[error("{}", FORBIDDEN)]
     Forbidden,
 
     #[error("Invalid Token: {0}")]
     InvalidToken(String),
 
+    #[error("{}", TOKEN_EXPIRED)]
+    TokenExpired,
+
+    #[error("{}", NOT_LOGGED_IN)]
+    NotLoggedIn,
+
     #[error("JWT creation error: {0}")]
     JWTCreationError(#[from] CreationError),
 
     #[error("Invalid Password: {0}")]
     PasswordInvalid(String),
 
-    #[error("Invalid Request: {0}")]
+    #[error("{}: {}", REQUEST_INVALID, .0)]
     RequestInvalid(String),
 
+    #[error("Unknown command: {0}")]
+    UnknownCommand(String),
+
+    #[error("Too many requests, slow down")]
+    RateLimited,
+
     #[error("Stream has been disconnected")]
     Disconnected,
 }
+
+impl From<CodecError> for ServerError {
+    fn from(e: CodecError) -> Self {
+        match e {
+            CodecError::Closed => ServerError::Disconnected,
+            CodecError::TooLarge(..) => ServerError::RequestInvalid(
+                "Received message exceeding max allowed size".to_string(),
+            ),
+            e => ServerError::RequestInvalid(e.to_string()),
+        }
+    }
+}
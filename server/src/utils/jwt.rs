@@ -1,4 +1,4 @@
-use super::constants;
+use super::config;
 use chrono::{Duration, Utc};
 use jsonwebtoken::errors::Error as JWTLibError;
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, TokenData, Validation};
@@ -10,6 +10,8 @@ pub struct Claims {
     pub exp: usize,
     pub iat: usize,
     pub user_id: i32,
+    // The session the token was issued for, checked on every request so revoked sessions stop working
+    pub sid: i32,
 }
 
 #[derive(Debug, Error)]
@@ -21,18 +23,20 @@ pub enum CreationError {
     Unexpected,
 }
 
-/// Encodes user info into a JWT string
-pub fn encode_jwt(user_id: i32) -> Result<String, jsonwebtoken::errors::Error> {
+/// Encodes user and session info into a JWT string
+pub fn encode_jwt(user_id: i32, session_id: i32) -> Result<String, jsonwebtoken::errors::Error> {
     let now = Utc::now();
-    let expire = Duration::hours(24);
+    // Access tokens are short-lived and renewed with the session's refresh token
+    let expire = Duration::minutes(config::current().auth.access_token_ttl_minutes);
 
     let claim = Claims {
         exp: (now + expire).timestamp() as usize,
         iat: now.timestamp() as usize,
         user_id,
+        sid: session_id,
     };
 
-    let secret = constants::SECRET.clone();
+    let secret = &config::current().auth.secret;
 
     encode(
         &Header::default(),
@@ -43,7 +47,7 @@ pub fn encode_jwt(user_id: i32) -> Result<String, jsonwebtoken::errors::Error> {
 
 /// Decodes a JWT and returns the embedded claims
 pub fn decode_jwt(jwt: &str) -> Result<TokenData<Claims>, jsonwebtoken::errors::Error> {
-    let secret = constants::SECRET.clone();
+    let secret = &config::current().auth.secret;
 
     decode(
         jwt,
@@ -1,5 +1,5 @@
 pub mod cert;
-pub mod constants;
+pub mod config;
 pub mod errors;
 pub mod jwt;
 pub mod security;
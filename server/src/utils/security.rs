@@ -1,6 +1,10 @@
 use crate::utils::errors::server_error::ServerError;
-use argon2::password_hash::{rand_core::OsRng, PasswordHash, SaltString};
+use argon2::password_hash::{
+    rand_core::{OsRng, RngCore},
+    PasswordHash, SaltString,
+};
 use argon2::{Argon2, PasswordHasher, PasswordVerifier};
+use sha2::{Digest, Sha256};
 
 pub fn hash_password(password: &str) -> Result<String, ServerError> {
     let salt = SaltString::generate(&mut OsRng);
@@ -21,3 +25,20 @@ pub fn verify_password(password: &str, hashed: &str) -> Result<bool, ServerError
         .verify_password(password.as_bytes(), &parsed_hash)
         .is_ok())
 }
+
+/// Random 32 byte secret, hex encoded, for refresh tokens
+pub fn generate_token_secret() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    to_hex(&bytes)
+}
+
+/// Refresh tokens are only stored as a SHA-256 hash. They are random, so
+/// a fast hash is enough and keeps refreshing cheap.
+pub fn hash_token(token: &str) -> String {
+    to_hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,52 @@
+#[cfg(test)]
+mod tests {
+    use server::utils::cert::{load_or_create_identity, server_config};
+    use shared::tls::fingerprint;
+    use server::utils::config::ServerConfig;
+    use std::fs;
+    use std::path::PathBuf;
+
+    // A fresh directory per test so runs don't see each other's certificates
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("quic-messaging-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_generated_identity_is_reused_across_restarts() {
+        let dir = temp_dir("reuse");
+        let (cert_path, key_path) = (dir.join("cert.pem"), dir.join("key.pem"));
+
+        let (first_chain, first_key) = load_or_create_identity(&cert_path, &key_path, vec!["localhost".into()]).unwrap();
+        assert!(cert_path.exists() && key_path.exists());
+        assert!(server_config(first_chain.clone(), first_key, &ServerConfig::default()).is_ok());
+
+        // The second start loads the same certificate instead of minting a new one
+        let (second_chain, _) = load_or_create_identity(&cert_path, &key_path, vec!["localhost".into()]).unwrap();
+        assert_eq!(fingerprint(&first_chain[0].0), fingerprint(&second_chain[0].0));
+        assert_eq!(fingerprint(&first_chain[0].0).split(':').count(), 32);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&key_path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_missing_key_is_an_error_not_a_new_identity() {
+        let dir = temp_dir("missing-key");
+        let (cert_path, key_path) = (dir.join("cert.pem"), dir.join("key.pem"));
+        load_or_create_identity(&cert_path, &key_path, vec!["localhost".into()]).unwrap();
+        let cert = fs::read(&cert_path).unwrap();
+
+        fs::remove_file(&key_path).unwrap();
+        assert!(load_or_create_identity(&cert_path, &key_path, vec!["localhost".into()]).is_err());
+        // The certificate on disk was left alone
+        assert_eq!(fs::read(&cert_path).unwrap(), cert);
+        assert!(!key_path.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{DatabaseConnection, EntityTrait};
+    use crate::common;
+    use std::sync::Arc;
+    use server::entity::{chats, messages, message_reads};
+    use server::handlers::controllers::chat_controller;
+    use server::handlers::services::auth_service;
+    use server::utils::errors::server_error::ServerError;
+    use server::handlers::connections::{Connection, EventStream, ServerState};
+    use server::handlers::routes;
+    use shared::client_response::{Command, CommandKind};
+    use shared::codec::Codec;
+    use shared::models::chat_models::ChatRole;
+
+    async fn setup_in_memory_db() -> Arc<DatabaseConnection> {
+        let db = Arc::new(common::connect().await);
+        auth_service::register("Alice".to_owned(), "Password".to_string(), db.clone()).await.expect("Failed to register in DB setup");
+        auth_service::register("Bob".to_owned(), "Password".to_string(), db.clone()).await.expect("Failed to register in DB setup");
+        auth_service::register("Mallory".to_owned(), "Password".to_string(), db.clone()).await.expect("Failed to register in DB setup");
+
+        db
+    }
+
+    // Alice and Bob share a chat with one message in it, Mallory (user 3) isn't a member
+    async fn setup_chat(db: Arc<DatabaseConnection>) -> i32 {
+        let alice = 1;
+        chat_controller::create_chat(alice, None, false, vec![2], db.clone()).await.unwrap();
+        let chat = chats::Entity::find().one(&*db).await.unwrap().unwrap();
+        chat_controller::send_message(alice, chat.id, "Secret".into(), None, db.clone()).await.unwrap();
+        chat.id
+    }
+
+    #[tokio::test]
+    async fn test_non_member_cannot_send_message() {
+        let db = setup_in_memory_db().await;
+        let chat_id = setup_chat(db.clone()).await;
+
+        let result = chat_controller::send_message(3, chat_id, "Hi".into(), None, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+        assert_eq!(messages::Entity::find().all(&*db).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_non_member_cannot_get_messages() {
+        let db = setup_in_memory_db().await;
+        let chat_id = setup_chat(db.clone()).await;
+
+        let result = chat_controller::get_chat_messages(3, chat_id, None, None, 10, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+    }
+
+    #[tokio::test]
+    async fn test_non_member_cannot_scroll_back() {
+        let db = setup_in_memory_db().await;
+        let chat_id = setup_chat(db.clone()).await;
+
+        let result = chat_controller::get_chat_messages(3, chat_id, Some(100), None, 10, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+    }
+
+    #[tokio::test]
+    async fn test_non_member_cannot_mark_read() {
+        let db = setup_in_memory_db().await;
+        let chat_id = setup_chat(db.clone()).await;
+
+        let result = chat_controller::mark_messages_read(3, chat_id, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+        assert!(message_reads::Entity::find().all(&*db).await.unwrap().iter().all(|r| r.user_id != 3));
+    }
+
+    #[tokio::test]
+    async fn test_typing_only_reaches_other_members() {
+        let db = setup_in_memory_db().await;
+        let chat_id = setup_chat(db.clone()).await;
+
+        let (username, recipients) = chat_controller::get_typing_recipients(chat_id, 1, db.clone()).await.unwrap();
+        assert_eq!(username, "Alice");
+        assert_eq!(recipients, vec![2]);
+
+        let result = chat_controller::get_typing_recipients(chat_id, 3, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+    }
+
+    #[tokio::test]
+    async fn test_non_member_cannot_get_unread_count() {
+        let db = setup_in_memory_db().await;
+        let chat_id = setup_chat(db.clone()).await;
+
+        let result = chat_controller::get_unread_chat_message_count(3, chat_id, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+    }
+
+    #[tokio::test]
+    async fn test_missing_chat_is_forbidden() {
+        let db = setup_in_memory_db().await;
+        let chat_id = setup_chat(db.clone()).await;
+
+        let result = chat_controller::send_message(1, chat_id + 100, "Hi".into(), None, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+    }
+
+    #[tokio::test]
+    async fn test_members_are_authorized() {
+        let db = setup_in_memory_db().await;
+        let chat_id = setup_chat(db.clone()).await;
+        let bob = 2;
+
+        assert!(chat_controller::send_message(bob, chat_id, "Hi".into(), None, db.clone()).await.is_ok());
+        assert!(chat_controller::get_chat_messages(bob, chat_id, None, None, 10, db.clone()).await.is_ok());
+        assert!(chat_controller::get_chat_messages(bob, chat_id, None, Some(0), 10, db.clone()).await.is_ok());
+        assert!(chat_controller::get_unread_chat_message_count(bob, chat_id, db.clone()).await.is_ok());
+        assert!(chat_controller::mark_messages_read(bob, chat_id, db.clone()).await.is_ok());
+    }
+
+    // The command of the given kind aimed at the chat, for every command that acts on one chat. Commands that don't return None.
+    // The match has no catch-all, so a new command won't compile until it is sorted into one or the other.
+    fn chat_command(kind: CommandKind, chat_id: i32, message_id: i32) -> Option<Command> {
+        let command = match kind {
+            CommandKind::SendMessage => Command::SendMessage { chat_id, content: "Hi".into(), idempotency_key: None },
+            CommandKind::EditMessage => Command::EditMessage { chat_id, message_id, content: "Changed".into() },
+            CommandKind::DeleteMessage => Command::DeleteMessage { chat_id, message_id },
+            CommandKind::GetChatMessages => Command::GetChatMessages { chat_id, before_id: None, after_id: None, limit: 10 },
+            CommandKind::MarkMessagesRead => Command::MarkMessagesRead { chat_id },
+            CommandKind::GetChatMembers => Command::GetChatMembers { chat_id },
+            CommandKind::GetMessageReceipts => Command::GetMessageReceipts { chat_id, message_id },
+            CommandKind::AddChatMembers => Command::AddChatMembers { chat_id, member_ids: vec![3] },
+            CommandKind::RemoveChatMember => Command::RemoveChatMember { chat_id, user_id: 2 },
+            CommandKind::LeaveChat => Command::LeaveChat { chat_id },
+            CommandKind::RenameChat => Command::RenameChat { chat_id, name: "Taken".into() },
+            CommandKind::TransferOwnership => Command::TransferOwnership { chat_id, new_owner_id: 3 },
+            CommandKind::SetChatMemberRole => Command::SetChatMemberRole { chat_id, user_id: 3, role: ChatRole::Admin },
+            CommandKind::GetUnreadChatMessageCount => Command::GetUnreadChatMessageCount { chat_id },
+            CommandKind::Login | CommandKind::Register | CommandKind::ResumeSession | CommandKind::RefreshToken | CommandKind::ListSessions
+            | CommandKind::RevokeSession | CommandKind::GetInfo | CommandKind::SendFriendRequest | CommandKind::AcceptFriendRequest
+            | CommandKind::DeclineFriendRequest | CommandKind::CancelFriendRequest | CommandKind::GetFriendRequests | CommandKind::RemoveFriend
+            | CommandKind::BlockUser | CommandKind::UnblockUser | CommandKind::GetBlockedUsers | CommandKind::GetFriends | CommandKind::GetPresence
+            | CommandKind::SetAway | CommandKind::CreateChat | CommandKind::GetChats | CommandKind::UpdateProfile | CommandKind::GetUnreadMessageCount
+            | CommandKind::Logout => return None,
+        };
+        Some(command)
+    }
+
+    #[tokio::test]
+    async fn test_non_member_is_forbidden_every_chat_command() {
+        let state = ServerState::new(Arc::new(common::connect().await));
+        let router = routes::router();
+        let connect = |port: u16| {
+            let (send, _) = tokio::io::duplex(64 * 1024);
+            Connection::new(state.clone(), format!("127.0.0.1:{}", port).parse().unwrap(), EventStream::new(send, Codec::Json))
+        };
+        let (alice, mallory) = (connect(5000), connect(5001));
+        for (name, connection) in [("Alice", &alice), ("Bob", &connect(5002)), ("Mallory", &mallory)] {
+            let register = Command::Register { username: name.into(), password: "Password".into() };
+            assert!(router.handle(register, connection.clone()).await.success);
+        }
+
+        // Alice's group with Bob, which Mallory (user 3) isn't in
+        let create = Command::CreateChat { name: Some("Group".into()), is_group: true, member_ids: vec![2] };
+        let chat_id = router.handle(create, alice.clone()).await.data.unwrap()["id"].as_i64().unwrap() as i32;
+        let send = Command::SendMessage { chat_id, content: "Secret".into(), idempotency_key: None };
+        let message_id = router.handle(send, alice.clone()).await.data.unwrap()["id"].as_i64().unwrap() as i32;
+
+        let mut checked = 0;
+        for kind in CommandKind::ALL {
+            let Some(command) = chat_command(kind, chat_id, message_id) else { continue };
+            let response = router.handle(command, mallory.clone()).await;
+            assert!(!response.success, "{} succeeded for a non-member", kind.name());
+            assert_eq!(response.message.unwrap(), ServerError::Forbidden.to_string(), "{} answered a non-member", kind.name());
+            checked += 1;
+        }
+        assert_eq!(checked, 14);
+
+        // Paging the chat list never turns up the chat either
+        let response = router.handle(Command::GetChats { before: None, limit: 50 }, mallory.clone()).await;
+        assert!(response.data.unwrap()["chats"].as_array().unwrap().is_empty());
+        let response = router.handle(Command::GetChats { before: None, limit: 50 }, alice.clone()).await;
+        assert_eq!(response.data.unwrap()["chats"].as_array().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,336 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use server::entity::sea_orm_active_enums::Role;
+    use server::handlers::repositories::in_memory::InMemoryRepositories;
+    use server::handlers::repositories::store::Repositories;
+    use server::handlers::services::{chat_service, user_service};
+    use server::utils::errors::server_error::ServerError;
+    use shared::models::chat_models::ChatRole;
+
+    const ALICE: i32 = 1;
+    const BOB: i32 = 2;
+    const CAROL: i32 = 3;
+    const DYLAN: i32 = 4;
+
+    // Chat flows against in-memory repositories, so no database is needed
+    async fn setup_repos() -> Arc<InMemoryRepositories> {
+        let repos = Arc::new(InMemoryRepositories::new());
+        for name in ["Alice", "Bob", "Carol", "Dylan"] {
+            repos.users().register_user(name.to_owned(), "hash".to_string()).await.expect("Failed to register in setup");
+        }
+        repos
+    }
+
+    // Alice owns a group with Bob and Carol
+    async fn create_group(repos: Arc<InMemoryRepositories>) -> i32 {
+        chat_service::create_chat(ALICE, Some("Group".into()), true, vec![ALICE, BOB, CAROL], repos.clone()).await.unwrap().id
+    }
+
+    async fn send(user_id: i32, chat_id: i32, content: &str, repos: Arc<InMemoryRepositories>) -> i32 {
+        chat_service::send_message(user_id, chat_id, content.to_string(), None, repos.clone()).await.unwrap().0.id
+    }
+
+    #[tokio::test]
+    async fn test_direct_chat_is_named_after_the_other_user() {
+        let repos = setup_repos().await;
+
+        let chat = chat_service::create_chat(ALICE, None, false, vec![ALICE, BOB], repos.clone()).await.unwrap();
+        assert_eq!(chat.chat_name, "Bob");
+        assert!(!chat.read_only);
+        assert_eq!(chat_service::get_user_chat(chat.id, BOB, repos.clone()).await.unwrap().chat_name, "Alice");
+
+        // Two users only share one 1:1 chat, whoever starts it
+        let result = chat_service::create_chat(BOB, None, false, vec![BOB, ALICE], repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::ChatAlreadyExists)));
+        chat_service::create_chat(ALICE, None, false, vec![ALICE, CAROL], repos.clone()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_group_names_are_unique() {
+        let repos = setup_repos().await;
+        let chat_id = create_group(repos.clone()).await;
+
+        let result = chat_service::create_chat(DYLAN, Some("GROUP".into()), true, vec![DYLAN, BOB, CAROL], repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::ChatAlreadyExists)));
+
+        let other_id = chat_service::create_chat(DYLAN, Some("Other".into()), true, vec![DYLAN, BOB, CAROL], repos.clone()).await.unwrap().id;
+        let result = chat_service::rename_chat(DYLAN, other_id, "group".into(), repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::ChatAlreadyExists)));
+
+        // Renaming a chat to its own name is fine
+        chat_service::rename_chat(ALICE, chat_id, "Group".into(), repos.clone()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_messages_and_unread_counts() {
+        let repos = setup_repos().await;
+        let chat_id = create_group(repos.clone()).await;
+
+        send(ALICE, chat_id, "Hello", repos.clone()).await;
+        send(BOB, chat_id, "Hi", repos.clone()).await;
+
+        let messages = chat_service::get_chat_messages(CAROL, chat_id, None, None, 50, repos.clone()).await.unwrap();
+        let contents: Vec<&str> = messages.messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["Hello", "Hi"]);
+        assert!(messages.messages.iter().all(|m| !m.read));
+        assert!(!messages.has_more);
+
+        // Senders have read their own messages
+        assert_eq!(chat_service::get_chat_unread_count(ALICE, chat_id, repos.clone()).await.unwrap().count, 1);
+        assert_eq!(chat_service::get_chat_unread_count(CAROL, chat_id, repos.clone()).await.unwrap().count, 2);
+        assert_eq!(chat_service::get_unread_message_count(CAROL, repos.clone()).await.unwrap().count, 2);
+
+        let receipt = chat_service::mark_messages_read(CAROL, chat_id, repos.clone()).await.unwrap().expect("Carol had unread messages");
+        assert_eq!(receipt.last_read_id, messages.messages[1].id);
+        assert_eq!(receipt.reader.username, "Carol");
+        assert_eq!(chat_service::get_chat_unread_count(CAROL, chat_id, repos.clone()).await.unwrap().count, 0);
+        assert!(chat_service::mark_messages_read(CAROL, chat_id, repos.clone()).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_receipts_and_seen() {
+        let repos = setup_repos().await;
+        let chat_id = create_group(repos.clone()).await;
+        let message_id = send(ALICE, chat_id, "Hello", repos.clone()).await;
+
+        let receipts = chat_service::get_message_receipts(ALICE, chat_id, message_id, repos.clone()).await.unwrap();
+        assert!(receipts.seen_by.is_empty());
+
+        chat_service::mark_messages_read(BOB, chat_id, repos.clone()).await.unwrap();
+        let receipts = chat_service::get_message_receipts(ALICE, chat_id, message_id, repos.clone()).await.unwrap();
+        let readers: Vec<&str> = receipts.seen_by.iter().map(|r| r.username.as_str()).collect();
+        assert_eq!(readers, vec!["Bob"]);
+
+        let messages = chat_service::get_chat_messages(ALICE, chat_id, None, None, 50, repos.clone()).await.unwrap();
+        assert!(messages.messages[0].read);
+        assert!(messages.messages[0].seen);
+    }
+
+    #[tokio::test]
+    async fn test_message_pages() {
+        let repos = setup_repos().await;
+        let chat_id = create_group(repos.clone()).await;
+        let mut ids = Vec::new();
+        for i in 0..5 {
+            ids.push(send(ALICE, chat_id, &format!("Message {}", i), repos.clone()).await);
+        }
+
+        let newest = chat_service::get_chat_messages(BOB, chat_id, None, None, 2, repos.clone()).await.unwrap();
+        assert_eq!(newest.messages.iter().map(|m| m.id).collect::<Vec<_>>(), ids[3..]);
+        assert!(newest.has_more);
+
+        let older = chat_service::get_chat_messages(BOB, chat_id, Some(ids[3]), None, 2, repos.clone()).await.unwrap();
+        assert_eq!(older.messages.iter().map(|m| m.id).collect::<Vec<_>>(), ids[1..3]);
+
+        let newer = chat_service::get_chat_messages(BOB, chat_id, None, Some(ids[1]), 10, repos.clone()).await.unwrap();
+        assert_eq!(newer.messages.iter().map(|m| m.id).collect::<Vec<_>>(), ids[2..]);
+        assert!(!newer.has_more);
+
+        let result = chat_service::get_chat_messages(BOB, chat_id, Some(ids[3]), Some(ids[1]), 10, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::RequestInvalid(_))));
+    }
+
+    #[tokio::test]
+    async fn test_resent_message_is_stored_once() {
+        let repos = setup_repos().await;
+        let chat_id = create_group(repos.clone()).await;
+
+        let (first, stored) = chat_service::send_message(ALICE, chat_id, "Hello".into(), Some("key-1".into()), repos.clone()).await.unwrap();
+        assert!(stored);
+        let (again, stored) = chat_service::send_message(ALICE, chat_id, "Hello".into(), Some("key-1".into()), repos.clone()).await.unwrap();
+        assert!(!stored);
+        assert_eq!(first.id, again.id);
+
+        // Keys belong to their sender
+        let (other, stored) = chat_service::send_message(BOB, chat_id, "Hello".into(), Some("key-1".into()), repos.clone()).await.unwrap();
+        assert!(stored);
+        assert_ne!(other.id, first.id);
+
+        let result = chat_service::send_message(ALICE, chat_id, "Hello".into(), Some(String::new()), repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::RequestInvalid(_))));
+        assert_eq!(chat_service::get_chat_messages(CAROL, chat_id, None, None, 50, repos.clone()).await.unwrap().messages.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_edit_and_delete_rules() {
+        let repos = setup_repos().await;
+        let chat_id = create_group(repos.clone()).await;
+        let message_id = send(BOB, chat_id, "Helo", repos.clone()).await;
+
+        let result = chat_service::edit_message(CAROL, chat_id, message_id, "Hello".into(), repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+        let result = chat_service::edit_message(BOB, chat_id, message_id, " ".into(), repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::RequestInvalid(_))));
+
+        let edited = chat_service::edit_message(BOB, chat_id, message_id, "Hello".into(), repos.clone()).await.unwrap();
+        assert!(edited.edited);
+        assert_eq!(edited.content, "Hello");
+        assert_eq!(repos.chats().get_message_edits(message_id).await.unwrap()[0].previous_content, "Helo");
+
+        // Members can't delete each other's messages, but the owner can
+        let result = chat_service::delete_message(CAROL, chat_id, message_id, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+        let deleted = chat_service::delete_message(ALICE, chat_id, message_id, repos.clone()).await.unwrap();
+        assert!(deleted.deleted);
+        assert!(deleted.content.is_empty());
+        assert!(repos.chats().get_message_edits(message_id).await.unwrap().is_empty());
+
+        let result = chat_service::edit_message(BOB, chat_id, message_id, "Hello again".into(), repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::RequestInvalid(_))));
+    }
+
+    #[tokio::test]
+    async fn test_system_messages_cannot_be_changed() {
+        let repos = setup_repos().await;
+        let chat_id = create_group(repos.clone()).await;
+
+        let message = chat_service::rename_chat(ALICE, chat_id, "Renamed".into(), repos.clone()).await.unwrap();
+        assert!(message.is_system);
+        assert_eq!(message.content, "Alice renamed the chat to Renamed");
+
+        let result = chat_service::edit_message(ALICE, chat_id, message.id, "Hi".into(), repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+        let result = chat_service::delete_message(ALICE, chat_id, message.id, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+    }
+
+    #[tokio::test]
+    async fn test_group_roles() {
+        let repos = setup_repos().await;
+        let chat_id = create_group(repos.clone()).await;
+
+        let result = chat_service::add_chat_members(BOB, chat_id, vec![DYLAN], repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+        chat_service::set_chat_member_role(ALICE, chat_id, BOB, Role::Admin, repos.clone()).await.unwrap();
+        let message = chat_service::add_chat_members(BOB, chat_id, vec![DYLAN], repos.clone()).await.unwrap();
+        assert_eq!(message.content, "Bob added Dylan");
+
+        // Admins can't remove each other, only the owner can
+        chat_service::set_chat_member_role(ALICE, chat_id, CAROL, Role::Admin, repos.clone()).await.unwrap();
+        let result = chat_service::remove_chat_member(BOB, chat_id, CAROL, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+        let message = chat_service::remove_chat_member(ALICE, chat_id, CAROL, repos.clone()).await.unwrap();
+        assert_eq!(message.content, "Alice removed Carol");
+
+        let result = chat_service::set_chat_member_role(ALICE, chat_id, BOB, Role::Owner, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::RequestInvalid(_))));
+
+        let members = chat_service::get_chat_members(DYLAN, chat_id, repos.clone()).await.unwrap();
+        let roles: Vec<(i32, ChatRole)> = members.members.iter().map(|m| (m.id, m.role)).collect();
+        assert_eq!(roles.len(), 3);
+        assert!(roles.contains(&(ALICE, ChatRole::Owner)));
+        assert!(roles.contains(&(BOB, ChatRole::Admin)));
+        assert!(roles.contains(&(DYLAN, ChatRole::Member)));
+    }
+
+    #[tokio::test]
+    async fn test_owner_hands_over_before_leaving() {
+        let repos = setup_repos().await;
+        let chat_id = create_group(repos.clone()).await;
+
+        let result = chat_service::leave_chat(ALICE, chat_id, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::RequestInvalid(_))));
+
+        let message = chat_service::transfer_ownership(ALICE, chat_id, CAROL, repos.clone()).await.unwrap();
+        assert_eq!(message.content, "Alice made Carol the owner");
+        let message = chat_service::leave_chat(ALICE, chat_id, repos.clone()).await.unwrap();
+        assert_eq!(message.content, "Alice left the chat");
+
+        let result = chat_service::get_chat_members(ALICE, chat_id, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+        let members = chat_service::get_chat_members(BOB, chat_id, repos.clone()).await.unwrap();
+        assert_eq!(members.members.iter().find(|m| m.id == CAROL).map(|m| m.role), Some(ChatRole::Owner));
+        assert_eq!(chat_service::get_chat_user_ids(chat_id, repos.clone()).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_direct_chats_are_not_managed() {
+        let repos = setup_repos().await;
+        let chat_id = chat_service::create_chat(ALICE, None, false, vec![ALICE, BOB], repos.clone()).await.unwrap().id;
+
+        let result = chat_service::add_chat_members(ALICE, chat_id, vec![CAROL], repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::RequestInvalid(_))));
+        let result = chat_service::leave_chat(BOB, chat_id, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::RequestInvalid(_))));
+    }
+
+    #[tokio::test]
+    async fn test_block_makes_direct_chat_read_only() {
+        let repos = setup_repos().await;
+        let chat_id = chat_service::create_chat(ALICE, None, false, vec![ALICE, BOB], repos.clone()).await.unwrap().id;
+        let message_id = send(BOB, chat_id, "Hello", repos.clone()).await;
+
+        user_service::block_user(ALICE, BOB, repos.clone()).await.unwrap();
+        assert!(chat_service::get_user_chat(chat_id, BOB, repos.clone()).await.unwrap().read_only);
+        let result = chat_service::send_message(BOB, chat_id, "Hi".into(), None, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::ActionBlocked)));
+        let result = chat_service::edit_message(BOB, chat_id, message_id, "Hi".into(), repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::ActionBlocked)));
+
+        // The history stays readable, and no new chats can be started
+        assert_eq!(chat_service::get_chat_messages(ALICE, chat_id, None, None, 50, repos.clone()).await.unwrap().messages.len(), 1);
+        let result = chat_service::create_chat(BOB, Some("Again".into()), true, vec![BOB, ALICE, CAROL], repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::ActionBlocked)));
+
+        user_service::unblock_user(ALICE, BOB, repos.clone()).await.unwrap();
+        send(BOB, chat_id, "Hi", repos.clone()).await;
+    }
+
+    #[tokio::test]
+    async fn test_only_the_acting_user_is_checked_for_blocks() {
+        let repos = setup_repos().await;
+        user_service::block_user(BOB, CAROL, repos.clone()).await.unwrap();
+
+        // Bob and Carol's block doesn't stop Alice grouping them together
+        let chat_id = create_group(repos.clone()).await;
+        user_service::block_user(DYLAN, BOB, repos.clone()).await.unwrap();
+        chat_service::add_chat_members(ALICE, chat_id, vec![DYLAN], repos.clone()).await.unwrap();
+        assert!(!chat_service::get_user_chat(chat_id, BOB, repos.clone()).await.unwrap().read_only);
+        send(CAROL, chat_id, "Hello", repos.clone()).await;
+
+        // But Bob can't add someone he has a block with
+        let other = chat_service::create_chat(BOB, Some("Other".into()), true, vec![BOB, ALICE], repos.clone()).await.unwrap().id;
+        let result = chat_service::add_chat_members(BOB, other, vec![CAROL], repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::ActionBlocked)));
+    }
+
+    #[tokio::test]
+    async fn test_outsiders_are_forbidden() {
+        let repos = setup_repos().await;
+        let chat_id = create_group(repos.clone()).await;
+        let message_id = send(ALICE, chat_id, "Hello", repos.clone()).await;
+
+        let result = chat_service::send_message(DYLAN, chat_id, "Hi".into(), None, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+        let result = chat_service::get_chat_messages(DYLAN, chat_id, None, None, 50, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+        let result = chat_service::mark_messages_read(DYLAN, chat_id, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+        let result = chat_service::get_message_receipts(DYLAN, chat_id, message_id, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+
+        // Chats that don't exist look the same as ones the user isn't in
+        let result = chat_service::get_chat_members(ALICE, 99, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+    }
+
+    #[tokio::test]
+    async fn test_chat_list_follows_activity() {
+        let repos = setup_repos().await;
+        let group_id = create_group(repos.clone()).await;
+        let direct_id = chat_service::create_chat(ALICE, None, false, vec![ALICE, BOB], repos.clone()).await.unwrap().id;
+        let other_id = chat_service::create_chat(ALICE, None, false, vec![ALICE, CAROL], repos.clone()).await.unwrap().id;
+
+        send(BOB, group_id, "Bump", repos.clone()).await;
+
+        let first = chat_service::get_user_chats(ALICE, None, 2, repos.clone()).await.unwrap();
+        assert_eq!(first.chats.iter().map(|c| c.id).collect::<Vec<_>>(), vec![group_id, other_id]);
+        assert_eq!(first.chats[0].unread_count, 1);
+        let cursor = first.next_cursor.expect("A third chat is left");
+
+        let rest = chat_service::get_user_chats(ALICE, Some(cursor), 2, repos.clone()).await.unwrap();
+        assert_eq!(rest.chats.iter().map(|c| c.id).collect::<Vec<_>>(), vec![direct_id]);
+        assert!(rest.next_cursor.is_none());
+    }
+}
@@ -0,0 +1,50 @@
+use migration::{Migrator, MigratorTrait};
+use sea_orm::{ConnectOptions, ConnectionTrait, Database, DatabaseConnection, DbBackend};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static SCHEMAS: AtomicUsize = AtomicUsize::new(0);
+
+/// A fresh, migrated database for one test. Runs on in-memory SQLite unless
+/// TEST_DATABASE_URL points at a Postgres server, where each test gets its own schema.
+pub async fn connect() -> DatabaseConnection {
+    let db = connect_empty().await;
+    Migrator::up(&db, None).await.unwrap();
+    db
+}
+
+/// A fresh database for one test with no tables in it
+pub async fn connect_empty() -> DatabaseConnection {
+    match std::env::var("TEST_DATABASE_URL") {
+        Ok(url) if url.starts_with("postgres") => connect_postgres(&url).await,
+        Ok(url) if !url.starts_with("sqlite") => panic!("TEST_DATABASE_URL must be a postgres:// or sqlite: URL"),
+        Ok(url) => Database::connect(url).await.unwrap(),
+        Err(_) => Database::connect("sqlite::memory:").await.unwrap(),
+    }
+}
+
+async fn connect_postgres(url: &str) -> DatabaseConnection {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().subsec_nanos();
+    let schema = format!("test_{}_{}_{}", std::process::id(), nanos, SCHEMAS.fetch_add(1, Ordering::SeqCst));
+
+    let admin = Database::connect(url).await.unwrap();
+    admin.execute_unprepared(&format!("CREATE SCHEMA {}", schema)).await.unwrap();
+    admin.close().await.unwrap();
+
+    let mut options = ConnectOptions::new(url);
+    options.set_schema_search_path(schema);
+    Database::connect(options).await.unwrap()
+}
+
+/// Makes every `event` (INSERT, UPDATE or DELETE) on `table` whose row matches `condition` fail,
+/// so a test can break a service call partway through. The condition refers to the row as NEW or OLD.
+#[allow(dead_code)]
+pub async fn fail_writes(db: &DatabaseConnection, event: &str, table: &str, condition: &str) {
+    let name = format!("fail_{}_{}", event.to_lowercase(), table);
+    if db.get_database_backend() == DbBackend::Postgres {
+        db.execute_unprepared("CREATE OR REPLACE FUNCTION inject_failure() RETURNS trigger LANGUAGE plpgsql AS $$ BEGIN RAISE EXCEPTION 'injected failure'; END $$").await.unwrap();
+        db.execute_unprepared(&format!("CREATE TRIGGER {} BEFORE {} ON {} FOR EACH ROW WHEN ({}) EXECUTE FUNCTION inject_failure()", name, event, table, condition)).await.unwrap();
+    } else {
+        db.execute_unprepared(&format!("CREATE TRIGGER {} BEFORE {} ON {} FOR EACH ROW WHEN {} BEGIN SELECT RAISE(ABORT, 'injected failure'); END", name, event, table, condition)).await.unwrap();
+    }
+}
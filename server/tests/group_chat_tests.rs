@@ -0,0 +1,146 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::DatabaseConnection;
+    use crate::common;
+    use std::sync::Arc;
+    use server::entity::sea_orm_active_enums::Role;
+    use server::handlers::services::{chat_service, auth_service, user_service};
+    use server::utils::errors::server_error::ServerError;
+    use shared::models::chat_models::ChatRole;
+
+    async fn setup_in_memory_db() -> Arc<DatabaseConnection> {
+        let db = Arc::new(common::connect().await);
+        for name in ["Alice", "Bob", "Carol", "Dylan"] {
+            auth_service::register(name.to_owned(), "Password".to_string(), db.clone()).await.expect("Failed to register in DB setup");
+        }
+
+        db
+    }
+
+    // Alice (1) owns a group with Bob (2) and Carol (3)
+    async fn create_group(db: Arc<DatabaseConnection>) -> i32 {
+        let chat = chat_service::create_chat(1, Some("Group".into()), true, vec![1, 2, 3], db.clone()).await.unwrap();
+        chat.id
+    }
+
+    fn role_of(members: &shared::models::chat_models::ChatMembers, user_id: i32) -> Option<ChatRole> {
+        members.members.iter().find(|m| m.id == user_id).map(|m| m.role)
+    }
+
+    #[tokio::test]
+    async fn test_creator_is_owner() {
+        let db = setup_in_memory_db().await;
+        let chat_id = create_group(db.clone()).await;
+
+        let members = chat_service::get_chat_members(2, chat_id, db.clone()).await.unwrap();
+        assert!(members.is_group);
+        assert_eq!(members.members.len(), 3);
+        assert_eq!(role_of(&members, 1), Some(ChatRole::Owner));
+        assert_eq!(role_of(&members, 2), Some(ChatRole::Member));
+
+        // Outsiders can't list the members
+        let result = chat_service::get_chat_members(4, chat_id, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+    }
+
+    #[tokio::test]
+    async fn test_only_admins_can_add_and_remove() {
+        let db = setup_in_memory_db().await;
+        let chat_id = create_group(db.clone()).await;
+
+        let result = chat_service::add_chat_members(2, chat_id, vec![4], db.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+        let result = chat_service::remove_chat_member(2, chat_id, 3, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+
+        chat_service::set_chat_member_role(1, chat_id, 2, Role::Admin, db.clone()).await.unwrap();
+
+        let message = chat_service::add_chat_members(2, chat_id, vec![4], db.clone()).await.unwrap();
+        assert!(message.is_system);
+        assert_eq!(message.content, "Bob added Dylan");
+
+        // Adding an existing member is rejected
+        let result = chat_service::add_chat_members(2, chat_id, vec![4], db.clone()).await;
+        assert!(matches!(result, Err(ServerError::RequestInvalid(_))));
+
+        chat_service::remove_chat_member(2, chat_id, 4, db.clone()).await.unwrap();
+        let result = chat_service::get_chat_members(4, chat_id, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+
+        // Admins can't remove the owner
+        let result = chat_service::remove_chat_member(2, chat_id, 1, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+    }
+
+    #[tokio::test]
+    async fn test_cannot_add_blocked_user() {
+        let db = setup_in_memory_db().await;
+        let chat_id = create_group(db.clone()).await;
+
+        user_service::block_user(4, 1, db.clone()).await.unwrap();
+
+        let result = chat_service::add_chat_members(1, chat_id, vec![4], db.clone()).await;
+        assert!(matches!(result, Err(ServerError::ActionBlocked)));
+    }
+
+    #[tokio::test]
+    async fn test_owner_must_transfer_before_leaving() {
+        let db = setup_in_memory_db().await;
+        let chat_id = create_group(db.clone()).await;
+
+        let result = chat_service::leave_chat(1, chat_id, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::RequestInvalid(_))));
+
+        // Only the owner can hand the chat over
+        let result = chat_service::transfer_ownership(2, chat_id, 3, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+
+        let message = chat_service::transfer_ownership(1, chat_id, 2, db.clone()).await.unwrap();
+        assert_eq!(message.content, "Alice made Bob the owner");
+
+        let members = chat_service::get_chat_members(1, chat_id, db.clone()).await.unwrap();
+        assert_eq!(role_of(&members, 1), Some(ChatRole::Admin));
+        assert_eq!(role_of(&members, 2), Some(ChatRole::Owner));
+
+        let message = chat_service::leave_chat(1, chat_id, db.clone()).await.unwrap();
+        assert_eq!(message.content, "Alice left the chat");
+
+        let members = chat_service::get_chat_members(2, chat_id, db.clone()).await.unwrap();
+        assert_eq!(members.members.len(), 2);
+        assert_eq!(role_of(&members, 1), None);
+    }
+
+    #[tokio::test]
+    async fn test_rename_chat() {
+        let db = setup_in_memory_db().await;
+        let chat_id = create_group(db.clone()).await;
+
+        let result = chat_service::rename_chat(2, chat_id, "Mine".into(), db.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+        let result = chat_service::rename_chat(1, chat_id, "   ".into(), db.clone()).await;
+        assert!(matches!(result, Err(ServerError::RequestInvalid(_))));
+
+        chat_service::rename_chat(1, chat_id, " Team ".into(), db.clone()).await.unwrap();
+        let chat = chat_service::get_user_chat(chat_id, 2, db.clone()).await.unwrap();
+        assert_eq!(chat.chat_name, "Team");
+
+        // System messages show up in the chat history for everyone
+        let messages = chat_service::get_chat_messages(3, chat_id, None, None, 10, db.clone()).await.unwrap();
+        let last = messages.messages.last().unwrap();
+        assert!(last.is_system);
+        assert_eq!(last.content, "Alice renamed the chat to Team");
+    }
+
+    #[tokio::test]
+    async fn test_direct_chats_cannot_be_managed() {
+        let db = setup_in_memory_db().await;
+        let chat = chat_service::create_chat(1, None, false, vec![1, 2], db.clone()).await.unwrap();
+
+        let result = chat_service::add_chat_members(1, chat.id, vec![3], db.clone()).await;
+        assert!(matches!(result, Err(ServerError::RequestInvalid(_))));
+        let result = chat_service::leave_chat(1, chat.id, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::RequestInvalid(_))));
+    }
+}
@@ -0,0 +1,113 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::DatabaseConnection;
+    use crate::common;
+    use std::sync::Arc;
+    use server::entity::sea_orm_active_enums::Role;
+    use server::handlers::repositories::chat_repository;
+    use server::handlers::services::{chat_service, auth_service, user_service};
+    use server::utils::errors::server_error::ServerError;
+
+    async fn setup_in_memory_db() -> Arc<DatabaseConnection> {
+        let db = Arc::new(common::connect().await);
+        for name in ["Alice", "Bob", "Carol"] {
+            auth_service::register(name.to_owned(), "Password".to_string(), db.clone()).await.expect("Failed to register in DB setup");
+        }
+
+        db
+    }
+
+    #[tokio::test]
+    async fn test_edit_keeps_history() {
+        let db = setup_in_memory_db().await;
+        let chat = chat_service::create_chat(1, None, false, vec![1, 2], db.clone()).await.unwrap();
+        let message = chat_service::send_message(1, chat.id, "Helo".into(), None, db.clone()).await.unwrap().0;
+        assert!(!message.edited);
+
+        let edited = chat_service::edit_message(1, chat.id, message.id, "Hello".into(), db.clone()).await.unwrap();
+        assert_eq!(edited.id, message.id);
+        assert_eq!(edited.content, "Hello");
+        assert!(edited.edited);
+        assert!(edited.edited_at.is_some());
+
+        chat_service::edit_message(1, chat.id, message.id, "Hello!".into(), db.clone()).await.unwrap();
+
+        let edits = chat_repository::get_message_edits(message.id, &*db).await.unwrap();
+        let history: Vec<&str> = edits.iter().map(|e| e.previous_content.as_str()).collect();
+        assert_eq!(history, vec!["Helo", "Hello"]);
+
+        let messages = chat_service::get_chat_messages(2, chat.id, None, None, 10, db.clone()).await.unwrap();
+        assert_eq!(messages.messages[0].content, "Hello!");
+        assert!(messages.messages[0].edited);
+    }
+
+    #[tokio::test]
+    async fn test_only_sender_can_edit() {
+        let db = setup_in_memory_db().await;
+        let chat = chat_service::create_chat(1, Some("Group".into()), true, vec![1, 2, 3], db.clone()).await.unwrap();
+        let message = chat_service::send_message(2, chat.id, "Hi".into(), None, db.clone()).await.unwrap().0;
+
+        // Not even the owner can edit someone else's message
+        let result = chat_service::edit_message(1, chat.id, message.id, "Bye".into(), db.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+
+        let result = chat_service::edit_message(2, chat.id, message.id, "  ".into(), db.clone()).await;
+        assert!(matches!(result, Err(ServerError::RequestInvalid(_))));
+
+        // The message id has to belong to the chat named in the request
+        let other = chat_service::create_chat(2, None, false, vec![2, 3], db.clone()).await.unwrap();
+        let result = chat_service::edit_message(2, other.id, message.id, "Bye".into(), db.clone()).await;
+        assert!(matches!(result, Err(ServerError::MessageNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_delete_leaves_tombstone() {
+        let db = setup_in_memory_db().await;
+        let chat = chat_service::create_chat(1, None, false, vec![1, 2], db.clone()).await.unwrap();
+        let message = chat_service::send_message(1, chat.id, "Secret".into(), None, db.clone()).await.unwrap().0;
+        chat_service::edit_message(1, chat.id, message.id, "Secret!".into(), db.clone()).await.unwrap();
+
+        // Bob can't delete Alice's message in a direct chat
+        let result = chat_service::delete_message(2, chat.id, message.id, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+
+        let deleted = chat_service::delete_message(1, chat.id, message.id, db.clone()).await.unwrap();
+        assert!(deleted.deleted);
+        assert!(deleted.content.is_empty());
+        assert!(chat_repository::get_message_edits(message.id, &*db).await.unwrap().is_empty());
+
+        let messages = chat_service::get_chat_messages(2, chat.id, None, None, 10, db.clone()).await.unwrap();
+        assert_eq!(messages.messages.len(), 1);
+        assert!(messages.messages[0].deleted);
+
+        let result = chat_service::edit_message(1, chat.id, message.id, "Back".into(), db.clone()).await;
+        assert!(matches!(result, Err(ServerError::RequestInvalid(_))));
+    }
+
+    #[tokio::test]
+    async fn test_group_admin_can_delete() {
+        let db = setup_in_memory_db().await;
+        let chat = chat_service::create_chat(1, Some("Group".into()), true, vec![1, 2, 3], db.clone()).await.unwrap();
+        let message = chat_service::send_message(3, chat.id, "Spam".into(), None, db.clone()).await.unwrap().0;
+
+        let result = chat_service::delete_message(2, chat.id, message.id, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::Forbidden)));
+
+        chat_service::set_chat_member_role(1, chat.id, 2, Role::Admin, db.clone()).await.unwrap();
+        assert!(chat_service::delete_message(2, chat.id, message.id, db.clone()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_cannot_edit_in_blocked_chat() {
+        let db = setup_in_memory_db().await;
+        let chat = chat_service::create_chat(1, None, false, vec![1, 2], db.clone()).await.unwrap();
+        let message = chat_service::send_message(1, chat.id, "Hi".into(), None, db.clone()).await.unwrap().0;
+
+        user_service::block_user(2, 1, db.clone()).await.unwrap();
+
+        let result = chat_service::edit_message(1, chat.id, message.id, "Hey".into(), db.clone()).await;
+        assert!(matches!(result, Err(ServerError::ActionBlocked)));
+    }
+}
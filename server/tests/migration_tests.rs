@@ -0,0 +1,70 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common;
+    use migration::{Migrator, MigratorTrait, SchemaManager};
+    use sea_orm::{ConnectionTrait, DatabaseConnection, EntityTrait};
+    use server::entity::sea_orm_active_enums::Role;
+    use server::entity::{chat_members, messages, users};
+    use server::handlers::services::chat_service;
+    use std::sync::Arc;
+
+    const TABLES: [&str; 10] = ["users", "friends", "blocked_users", "friend_requests", "chats", "chat_members", "messages", "message_edits", "message_reads", "sessions"];
+
+    async fn existing_tables(db: &DatabaseConnection) -> usize {
+        let manager = SchemaManager::new(db);
+        let mut count = 0;
+        for table in TABLES {
+            if manager.has_table(table).await.unwrap() {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    #[tokio::test]
+    async fn test_migrations_apply_roll_back_and_reapply() {
+        let db = common::connect().await;
+        assert!(Migrator::get_pending_migrations(&db).await.unwrap().is_empty());
+        assert_eq!(existing_tables(&db).await, TABLES.len());
+
+        // Running them again is a no-op
+        Migrator::up(&db, None).await.unwrap();
+
+        Migrator::down(&db, None).await.unwrap();
+        assert_eq!(existing_tables(&db).await, 0);
+        assert_eq!(Migrator::get_pending_migrations(&db).await.unwrap().len(), Migrator::migrations().len());
+
+        Migrator::up(&db, None).await.unwrap();
+        assert_eq!(existing_tables(&db).await, TABLES.len());
+    }
+
+    #[tokio::test]
+    async fn test_database_from_init_sql_is_brought_up_to_date() {
+        // The first four migrations make what init.sql did. A database set up from it has their tables but no record of them.
+        let db = common::connect_empty().await;
+        Migrator::up(&db, Some(4)).await.unwrap();
+        db.execute_unprepared("DROP TABLE seaql_migrations").await.unwrap();
+        assert!(!SchemaManager::new(&db).has_column("messages", "is_system").await.unwrap());
+        for statement in ["INSERT INTO users (username, password_hash) VALUES ('Alice', 'hash')", "INSERT INTO users (username, password_hash) VALUES ('Bob', 'hash')", "INSERT INTO chats (name, is_group) VALUES (NULL, FALSE)", "INSERT INTO chat_members (chat_id, user_id) VALUES (1, 1)", "INSERT INTO chat_members (chat_id, user_id) VALUES (1, 2)", "INSERT INTO messages (chat_id, sender_id, sender_username, content) VALUES (1, 1, 'Alice', 'Hi')"] {
+            db.execute_unprepared(statement).await.unwrap();
+        }
+
+        Migrator::up(&db, None).await.unwrap();
+        assert!(Migrator::get_pending_migrations(&db).await.unwrap().is_empty());
+        assert_eq!(existing_tables(&db).await, TABLES.len());
+
+        // The existing rows are kept and pick up the new columns' defaults
+        assert!(users::Entity::find().all(&db).await.unwrap().iter().all(|u| u.last_seen_at.is_none()));
+        assert!(chat_members::Entity::find().all(&db).await.unwrap().iter().all(|m| m.role == Role::Member));
+        let message = messages::Entity::find().one(&db).await.unwrap().unwrap();
+        assert!(!message.is_system && message.edited_at.is_none() && message.deleted_at.is_none() && message.idempotency_key.is_none());
+
+        // And the server works against the upgraded schema. Bob's own message is read already, so he reads up to Alice's.
+        let db = Arc::new(db);
+        chat_service::send_message(2, 1, "Hello".into(), Some("key-1".into()), db.clone()).await.unwrap();
+        chat_service::edit_message(2, 1, 2, "Hello!".into(), db.clone()).await.unwrap();
+        assert_eq!(chat_service::mark_messages_read(2, 1, db.clone()).await.unwrap().unwrap().last_read_id, 1);
+    }
+}
@@ -0,0 +1,235 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use crate::common;
+    use async_trait::async_trait;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use server::handlers::connections::{Connection, EventStream, ServerState};
+    use server::handlers::middleware::{Authentication, RateLimit};
+    use server::handlers::router::{Middleware, Next, Request, Router};
+    use server::handlers::routes;
+    use server::handlers::routes::payloads::{GetInfo, Login};
+    use server::utils::errors::server_error::ServerError;
+    use shared::client_response::{Command, CommandKind};
+    use shared::codec::{self, Codec};
+    use shared::server_response::{ServerEvent, ServerResponse};
+    use tokio::io::DuplexStream;
+
+    async fn setup_state() -> ServerState {
+        ServerState::new(Arc::new(common::connect().await))
+    }
+
+    // A connection from the given address, and the other end of its event stream
+    fn connect(state: &ServerState, addr: &str) -> (Connection, DuplexStream) {
+        let (send, recv) = tokio::io::duplex(64 * 1024);
+        let connection = Connection::new(state.clone(), addr.parse().unwrap(), EventStream::new(send, Codec::Json));
+        (connection, recv)
+    }
+
+    fn register(username: &str) -> Command {
+        Command::Register { username: username.to_string(), password: "Password".to_string() }
+    }
+
+    fn error_of(response: &ServerResponse) -> &str {
+        assert!(!response.success);
+        response.message.as_deref().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_logged_in_commands_need_a_login() {
+        let state = setup_state().await;
+        let router = routes::router();
+        let (connection, _events) = connect(&state, "127.0.0.1:5000");
+
+        let response = router.handle(Command::GetInfo {}, connection.clone()).await;
+        assert_eq!(error_of(&response), ServerError::NotLoggedIn.to_string());
+
+        // Registering logs the connection in and hands back its token
+        let response = router.handle(register("Alice"), connection.clone()).await;
+        assert!(response.success);
+        assert_eq!(response.message.as_deref(), Some("Registered"));
+        assert_eq!(response.jwt.as_deref(), response.data.as_ref().unwrap()["token"].as_str());
+
+        let response = router.handle(Command::GetInfo {}, connection.clone()).await;
+        assert!(response.success);
+        assert_eq!(response.data.unwrap()["username"], "Alice");
+        assert!(response.jwt.is_none());
+
+        router.handle(Command::Logout, connection.clone()).await;
+        let response = router.handle(Command::GetInfo {}, connection.clone()).await;
+        assert_eq!(error_of(&response), ServerError::NotLoggedIn.to_string());
+        assert!(state.logged_in_users().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_revoked_session_logs_its_connection_out() {
+        let state = setup_state().await;
+        let router = routes::router();
+        let (laptop, _laptop_events) = connect(&state, "127.0.0.1:5000");
+        let (phone, _phone_events) = connect(&state, "127.0.0.1:5001");
+
+        router.handle(register("Alice"), laptop.clone()).await;
+        let login = Command::Login { username: "Alice".to_string(), password: "Password".to_string() };
+        let response = router.handle(login, phone.clone()).await;
+        let session_id = response.data.unwrap()["session_id"].as_i64().unwrap() as i32;
+
+        let response = router.handle(Command::RevokeSession { session_id }, laptop.clone()).await;
+        assert!(response.success);
+
+        let response = router.handle(Command::GetInfo {}, phone.clone()).await;
+        assert_eq!(error_of(&response), ServerError::NotLoggedIn.to_string());
+        assert!(phone.session.lock().await.is_none());
+
+        // The laptop's session is untouched, so Alice stays online
+        assert!(router.handle(Command::GetInfo {}, laptop.clone()).await.success);
+        assert_eq!(state.logged_in_users().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_ended_session_closes_its_connections_at_once() {
+        let state = setup_state().await;
+        let router = routes::router();
+        let (laptop, _laptop_events) = connect(&state, "127.0.0.1:5000");
+        let (phone, _phone_events) = connect(&state, "127.0.0.1:5001");
+        let (resumed, _resumed_events) = connect(&state, "127.0.0.1:5002");
+
+        let response = router.handle(register("Alice"), laptop.clone()).await;
+        let laptop_token = response.jwt.unwrap();
+        let login = Command::Login { username: "Alice".to_string(), password: "Password".to_string() };
+        let response = router.handle(login, phone.clone()).await;
+        let session_id = response.data.as_ref().unwrap()["session_id"].as_i64().unwrap() as i32;
+        let token = response.jwt.unwrap();
+        assert!(router.handle(Command::ResumeSession { token }, resumed.clone()).await.success);
+        assert_eq!(state.logged_in.get(&1).unwrap().len(), 3);
+
+        // Both connections on the phone's session are logged out and closed without sending anything
+        router.handle(Command::RevokeSession { session_id }, laptop.clone()).await;
+        for connection in [&phone, &resumed] {
+            assert!(connection.session.lock().await.is_none());
+            tokio::time::timeout(Duration::from_secs(1), connection.ended.notified()).await.expect("Connection wasn't closed");
+        }
+        assert_eq!(state.logged_in.get(&1).unwrap().len(), 1);
+
+        // Logging out ends the laptop's other connections, but the one that asked stays open
+        let (other, _other_events) = connect(&state, "127.0.0.1:5003");
+        router.handle(Command::ResumeSession { token: laptop_token }, other.clone()).await;
+        assert!(router.handle(Command::Logout, laptop.clone()).await.success);
+        tokio::time::timeout(Duration::from_secs(1), other.ended.notified()).await.expect("Connection wasn't closed");
+        assert!(tokio::time::timeout(Duration::from_millis(50), laptop.ended.notified()).await.is_err());
+        assert!(state.logged_in_users().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_friend_request_is_stored_once_and_pushed() {
+        let state = setup_state().await;
+        let router = routes::router();
+        let (alice, _alice_events) = connect(&state, "127.0.0.1:5000");
+        let (bob, mut bob_events) = connect(&state, "127.0.0.1:5001");
+        router.handle(register("Bob"), bob.clone()).await;
+        router.handle(register("Alice"), alice.clone()).await;
+
+        let response = router.handle(Command::SendFriendRequest { receiver_username: "Bob".to_string() }, alice.clone()).await;
+        assert!(response.success);
+        assert_eq!(response.message.as_deref(), Some("Friend Request Sent"));
+
+        let event: ServerEvent = codec::read_message(&mut bob_events, Codec::Json, 65536).await.unwrap();
+        assert!(matches!(event, ServerEvent::FriendRequestReceived { sender } if sender.username == "Alice"));
+
+        let response = router.handle(Command::GetFriendRequests {}, bob.clone()).await;
+        assert_eq!(response.data.unwrap()["incoming"].as_array().unwrap().len(), 1);
+
+        let response = router.handle(Command::SendFriendRequest { receiver_username: "Nobody".to_string() }, alice.clone()).await;
+        assert_eq!(error_of(&response), ServerError::UserNotFound.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_unrouted_and_unauthenticated_commands_are_refused() {
+        let state = setup_state().await;
+        let (connection, _events) = connect(&state, "127.0.0.1:5000");
+
+        let router = Router::new().public("Logged in", |_: Login, _| async { Ok::<_, ServerError>(true) });
+        let response = router.handle(Command::GetInfo {}, connection.clone()).await;
+        assert_eq!(error_of(&response), "Unknown command: GetInfo");
+
+        // Without authentication in front, logged in commands have nobody to run as
+        let router = Router::new().logged_in("User Info", |_: GetInfo, identity, _| async move { Ok::<_, ServerError>(identity.user_id) });
+        let response = router.handle(Command::GetInfo {}, connection.clone()).await;
+        assert_eq!(error_of(&response), ServerError::NotLoggedIn.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_client_drops_only_rejected_requests() {
+        // The client's outbox gives up on these and retries the rest
+        let state = setup_state().await;
+        let (connection, _events) = connect(&state, "127.0.0.1:5000");
+        let errors: [(fn() -> ServerError, bool); 6] = [(|| ServerError::Forbidden, true), (|| ServerError::ActionBlocked, true), (|| ServerError::RequestInvalid("Message is empty".into()), true), (|| ServerError::RateLimited, false), (|| ServerError::DatabaseError(sea_orm::DbErr::Custom("Connection reset".into())), false), (|| ServerError::NotLoggedIn, false)];
+        for (error, rejected) in errors {
+            let router = Router::new().public("Logged in", move |_: Login, _| async move { Err::<i32, _>(error()) });
+            let response = router.handle(Command::Login { username: "Alice".into(), password: "Password".into() }, connection.clone()).await;
+            assert_eq!(response.is_rejected(), rejected, "{}", error());
+        }
+    }
+
+    #[test]
+    fn test_every_command_has_a_route() {
+        let router = routes::add_routes(Router::new());
+        let missing: Vec<&str> = CommandKind::ALL.iter().filter(|kind| !router.handles(**kind)).map(|kind| kind.name()).collect();
+        assert!(missing.is_empty(), "No route for {:?}", missing);
+    }
+
+    #[tokio::test]
+    async fn test_each_address_is_rate_limited() {
+        let state = setup_state().await;
+        let router = routes::add_routes(Router::new().layer(RateLimit::new(1, 2)).layer(Authentication));
+        let (first, _first_events) = connect(&state, "127.0.0.1:5000");
+        let (second, _second_events) = connect(&state, "127.0.0.1:5001");
+        let (other, _other_events) = connect(&state, "10.0.0.1:5000");
+
+        let login = || Command::Login { username: "Nobody".to_string(), password: "Password".to_string() };
+        let limited = ServerError::RateLimited.to_string();
+
+        assert_ne!(error_of(&router.handle(login(), first.clone()).await), limited);
+        assert_ne!(error_of(&router.handle(login(), first.clone()).await), limited);
+
+        // The burst is shared by every connection from the address
+        assert_eq!(error_of(&router.handle(login(), second.clone()).await), limited);
+        assert_eq!(error_of(&router.handle(login(), first.clone()).await), limited);
+        assert_ne!(error_of(&router.handle(login(), other.clone()).await), limited);
+    }
+
+    // Records the order requests pass through it
+    struct Record(&'static str, Arc<Mutex<Vec<&'static str>>>);
+
+    #[async_trait]
+    impl Middleware for Record {
+        async fn handle(&self, request: Request, next: Next<'_>) -> ServerResponse {
+            self.1.lock().unwrap().push(self.0);
+            let response = next.run(request).await;
+            self.1.lock().unwrap().push(self.0);
+            response
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_wraps_handlers_in_order() {
+        let state = setup_state().await;
+        let (connection, _events) = connect(&state, "127.0.0.1:5000");
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let handler_log = log.clone();
+        let router = Router::new()
+            .layer(Record("outer", log.clone()))
+            .layer(Record("inner", log.clone()))
+            .public("Answered", move |_: GetInfo, _| {
+                handler_log.lock().unwrap().push("handler");
+                async { Ok::<_, ServerError>(vec![1, 2]) }
+            });
+
+        let response = router.handle(Command::GetInfo {}, connection).await;
+        assert_eq!(response.message.as_deref(), Some("Answered"));
+        assert_eq!(response.data.unwrap(), serde_json::json!([1, 2]));
+        assert_eq!(*log.lock().unwrap(), vec!["outer", "inner", "handler", "inner", "outer"]);
+    }
+}
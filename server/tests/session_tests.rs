@@ -0,0 +1,102 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use sea_orm::DatabaseConnection;
+    use crate::common;
+    use std::sync::Arc;
+    use server::handlers::policies::session_policy::{self, Identity};
+    use server::handlers::services::auth_service;
+    use server::utils::config;
+    use server::utils::errors::server_error::ServerError;
+    use server::utils::jwt::Claims;
+    use shared::models::auth_models::AuthResponseModel;
+
+    async fn setup_in_memory_db() -> Arc<DatabaseConnection> {
+        Arc::new(common::connect().await)
+    }
+
+    fn identity(auth: &AuthResponseModel) -> Identity {
+        Identity { user_id: auth.user_id, session_id: auth.session_id }
+    }
+
+    #[tokio::test]
+    async fn test_refresh_rotates_and_detects_reuse() {
+        let db = setup_in_memory_db().await;
+        let auth = auth_service::register("Alice".to_owned(), "Password".to_string(), db.clone()).await.unwrap();
+
+        let refreshed = auth_service::refresh_token(auth.refresh_token.clone(), db.clone()).await.unwrap();
+        assert_ne!(refreshed.refresh_token, auth.refresh_token);
+        assert_eq!(refreshed.user_id, auth.user_id);
+        assert_eq!(auth_service::resume_session(refreshed.token.clone(), db.clone()).await.unwrap(), identity(&auth));
+
+        // Replaying the old refresh token means it leaked, so the whole session goes
+        let result = auth_service::refresh_token(auth.refresh_token.clone(), db.clone()).await;
+        assert!(matches!(result, Err(ServerError::InvalidToken(_))));
+        let result = auth_service::refresh_token(refreshed.refresh_token.clone(), db.clone()).await;
+        assert!(matches!(result, Err(ServerError::InvalidToken(_))));
+        let result = auth_service::resume_session(refreshed.token, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::InvalidToken(_))));
+        let result = session_policy::ensure_active(identity(&auth), db.clone()).await;
+        assert!(matches!(result, Err(ServerError::NotLoggedIn)));
+
+        let result = auth_service::refresh_token("garbage".into(), db.clone()).await;
+        assert!(matches!(result, Err(ServerError::InvalidToken(_))));
+    }
+
+    #[tokio::test]
+    async fn test_logout_and_revoke_reject_tokens() {
+        let db = setup_in_memory_db().await;
+        let laptop = auth_service::register("Alice".to_owned(), "Password".to_string(), db.clone()).await.unwrap();
+        let phone = auth_service::login("Alice".to_owned(), "Password".to_string(), db.clone()).await.unwrap();
+        let bob = auth_service::register("Bob".to_owned(), "Password".to_string(), db.clone()).await.unwrap();
+
+        let list = auth_service::list_sessions(identity(&laptop), db.clone()).await.unwrap();
+        assert_eq!(list.sessions.len(), 2);
+        assert_eq!(list.sessions.iter().filter(|s| s.current).count(), 1);
+        assert_eq!(list.sessions.iter().find(|s| !s.current).unwrap().id, phone.session_id);
+
+        // Bob can't touch Alice's sessions
+        let result = auth_service::revoke_session(bob.user_id, phone.session_id, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::RequestInvalid(_))));
+        assert!(session_policy::ensure_active(identity(&phone), db.clone()).await.is_ok());
+
+        // A revoked session stops working on its open connection, and can't be resumed or refreshed
+        auth_service::revoke_session(laptop.user_id, phone.session_id, db.clone()).await.unwrap();
+        let result = session_policy::ensure_active(identity(&phone), db.clone()).await;
+        assert!(matches!(result, Err(ServerError::NotLoggedIn)));
+        let result = auth_service::resume_session(phone.token, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::InvalidToken(_))));
+        let result = auth_service::refresh_token(phone.refresh_token, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::InvalidToken(_))));
+
+        let list = auth_service::list_sessions(identity(&laptop), db.clone()).await.unwrap();
+        assert_eq!(list.sessions.len(), 1);
+
+        // Logging out revokes the session server-side, even though the jwt hasn't expired
+        auth_service::logout(identity(&laptop), db.clone()).await.unwrap();
+        let result = session_policy::ensure_active(identity(&laptop), db.clone()).await;
+        assert!(matches!(result, Err(ServerError::NotLoggedIn)));
+        assert!(auth_service::resume_session(laptop.token, db.clone()).await.is_err());
+        assert!(session_policy::ensure_active(identity(&bob), db.clone()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_is_reported_for_refresh() {
+        let db = setup_in_memory_db().await;
+        let auth = auth_service::register("Alice".to_owned(), "Password".to_string(), db.clone()).await.unwrap();
+
+        let issued = Utc::now() - Duration::hours(1);
+        let claims = Claims { exp: (issued + Duration::minutes(15)).timestamp() as usize, iat: issued.timestamp() as usize, user_id: auth.user_id, sid: 1 };
+        let expired = encode(&Header::default(), &claims, &EncodingKey::from_secret(config::current().auth.secret.as_ref())).unwrap();
+
+        let result = auth_service::resume_session(expired, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::TokenExpired)));
+
+        // The refresh token still renews the session
+        let refreshed = auth_service::refresh_token(auth.refresh_token, db.clone()).await.unwrap();
+        assert!(auth_service::resume_session(refreshed.token, db.clone()).await.is_ok());
+    }
+}
@@ -0,0 +1,136 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{DatabaseConnection, EntityTrait, PaginatorTrait};
+    use crate::common;
+    use std::sync::Arc;
+    use server::entity::{blocked_users, chat_members, chats, friends, message_edits, messages, users};
+    use server::handlers::services::{auth_service, chat_service, user_service};
+    use shared::models::chat_models::ChatRole;
+
+    async fn setup_db() -> Arc<DatabaseConnection> {
+        let db = Arc::new(common::connect().await);
+        for name in ["Alice", "Bob", "Dylan"] {
+            auth_service::register(name.to_owned(), "Password".to_string(), db.clone()).await.expect("Failed to register in DB setup");
+        }
+        db
+    }
+
+    #[tokio::test]
+    async fn test_create_chat_leaves_nothing_behind_when_a_member_fails() {
+        let db = setup_db().await;
+        common::fail_writes(&db, "INSERT", "chat_members", "NEW.user_id = 3").await;
+
+        // Bob is added before Dylan fails
+        let result = chat_service::create_chat(1, Some("Study Group".into()), true, vec![2, 3], db.clone()).await;
+        assert!(result.is_err());
+        assert_eq!(chats::Entity::find().count(&*db).await.unwrap(), 0);
+        assert_eq!(chat_members::Entity::find().count(&*db).await.unwrap(), 0);
+
+        // The name wasn't taken by a half-made chat
+        let chat = chat_service::create_chat(1, Some("Study Group".into()), true, vec![2], db.clone()).await.unwrap();
+        assert_eq!(chat_service::get_chat_members(1, chat.id, db.clone()).await.unwrap().members.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_accept_friend_request_is_all_or_nothing() {
+        let db = setup_db().await;
+        let alice = 1;
+        let bob = 2;
+        user_service::send_friend_request(alice, bob, db.clone()).await.unwrap();
+
+        // Alice's side of the friendship goes in, then Bob's fails
+        common::fail_writes(&db, "INSERT", "friends", "NEW.user_id = 2").await;
+        assert!(user_service::accept_friend_request(bob, alice, db.clone()).await.is_err());
+
+        assert_eq!(friends::Entity::find().count(&*db).await.unwrap(), 0);
+        let requests = user_service::get_friend_requests(bob, db.clone()).await.unwrap();
+        assert_eq!(requests.incoming.len(), 1);
+        assert_eq!(requests.incoming[0].username, "Alice");
+    }
+
+    #[tokio::test]
+    async fn test_crossed_friend_requests_are_all_or_nothing() {
+        let db = setup_db().await;
+        let alice = 1;
+        let bob = 2;
+        user_service::send_friend_request(alice, bob, db.clone()).await.unwrap();
+
+        // Bob's request accepts Alice's, which fails halfway through the friendships
+        common::fail_writes(&db, "INSERT", "friends", "NEW.user_id = 1").await;
+        assert!(user_service::send_friend_request(bob, alice, db.clone()).await.is_err());
+
+        assert_eq!(friends::Entity::find().count(&*db).await.unwrap(), 0);
+        let requests = user_service::get_friend_requests(alice, db.clone()).await.unwrap();
+        assert_eq!(requests.outgoing.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_block_user_is_all_or_nothing() {
+        let db = setup_db().await;
+        let alice = 1;
+        let bob = 2;
+        user_service::send_friend_request(alice, bob, db.clone()).await.unwrap();
+        user_service::accept_friend_request(bob, alice, db.clone()).await.unwrap();
+
+        // The block is stored, then removing the friendship fails
+        common::fail_writes(&db, "DELETE", "friends", "OLD.user_id = 2").await;
+        assert!(user_service::block_user(alice, bob, db.clone()).await.is_err());
+
+        assert_eq!(blocked_users::Entity::find().count(&*db).await.unwrap(), 0);
+        assert_eq!(friends::Entity::find().count(&*db).await.unwrap(), 2);
+        assert!(user_service::are_friends(alice, bob, db.clone()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_message_is_not_stored_without_its_read() {
+        let db = setup_db().await;
+        let chat = chat_service::create_chat(1, None, false, vec![2], db.clone()).await.unwrap();
+
+        common::fail_writes(&db, "INSERT", "message_reads", "1 = 1").await;
+        let result = chat_service::send_message(1, chat.id, "Hello".into(), Some("key-1".into()), db.clone()).await;
+        assert!(result.is_err());
+        assert_eq!(messages::Entity::find().count(&*db).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_edit_keeps_history_and_content_together() {
+        let db = setup_db().await;
+        let chat = chat_service::create_chat(1, None, false, vec![2], db.clone()).await.unwrap();
+        let (msg, _) = chat_service::send_message(1, chat.id, "Hello".into(), None, db.clone()).await.unwrap();
+
+        // The old content is saved to the history, then updating the message fails
+        common::fail_writes(&db, "UPDATE", "messages", "1 = 1").await;
+        assert!(chat_service::edit_message(1, chat.id, msg.id, "Hi".into(), db.clone()).await.is_err());
+
+        assert_eq!(message_edits::Entity::find().count(&*db).await.unwrap(), 0);
+        let stored = messages::Entity::find_by_id(msg.id).one(&*db).await.unwrap().unwrap();
+        assert_eq!(stored.content, "Hello");
+        assert!(stored.edited_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_transfer_ownership_rolls_back_with_its_system_message() {
+        let db = setup_db().await;
+        let chat = chat_service::create_chat(1, Some("Study Group".into()), true, vec![2], db.clone()).await.unwrap();
+
+        // Both roles change, then the system message announcing it fails
+        common::fail_writes(&db, "INSERT", "messages", "NEW.is_system").await;
+        assert!(chat_service::transfer_ownership(1, chat.id, 2, db.clone()).await.is_err());
+
+        let members = chat_service::get_chat_members(1, chat.id, db.clone()).await.unwrap();
+        let role_of = |id: i32| members.members.iter().find(|m| m.id == id).map(|m| m.role);
+        assert_eq!(role_of(1), Some(ChatRole::Owner));
+        assert_eq!(role_of(2), Some(ChatRole::Member));
+    }
+
+    #[tokio::test]
+    async fn test_register_keeps_no_user_without_a_session() {
+        let db = Arc::new(common::connect().await);
+        common::fail_writes(&db, "INSERT", "sessions", "1 = 1").await;
+
+        assert!(auth_service::register("Alice".to_owned(), "Password".to_string(), db.clone()).await.is_err());
+        assert_eq!(users::Entity::find().count(&*db).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,102 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use sea_orm::{DatabaseConnection, EntityTrait, Set};
+    use crate::common;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use server::entity::messages;
+    use server::handlers::services::{chat_service, auth_service};
+
+    // Sets up the schema and returns the db along with a counter of every statement run against it
+    async fn setup_counted_db() -> (Arc<DatabaseConnection>, Arc<AtomicUsize>) {
+        let mut db = common::connect().await;
+        let queries = Arc::new(AtomicUsize::new(0));
+        let counter = queries.clone();
+        db.set_metric_callback(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let db = Arc::new(db);
+        for name in ["Alice", "Bob", "Dylan"] {
+            auth_service::register(name.to_owned(), "Password".to_string(), db.clone()).await.expect("Failed to register in DB setup");
+        }
+
+        (db, queries)
+    }
+
+    // Inserts messages from Alice straight into the table, skipping the per-message read bookkeeping
+    async fn insert_messages(chat_id: i32, count: usize, db: Arc<DatabaseConnection>) {
+        let start = Utc::now().naive_utc();
+        let batch: Vec<messages::ActiveModel> = (0..count).map(|i| messages::ActiveModel {
+            chat_id: Set(chat_id),
+            sender_id: Set(1),
+            sender_username: Set("Alice".to_string()),
+            content: Set(format!("message {}", i)),
+            is_system: Set(false),
+            read: Set(false),
+            timestamp: Set(start + Duration::milliseconds(i as i64)),
+            ..Default::default()
+        }).collect();
+
+        for chunk in batch.chunks(500) {
+            messages::Entity::insert_many(chunk.to_vec()).exec(&*db).await.unwrap();
+        }
+    }
+
+    // Runs everything the client does to draw its chat list, returning the queries it took and the unread counts
+    async fn load_chat_list(user_id: i32, chat_id: i32, db: Arc<DatabaseConnection>, queries: &AtomicUsize) -> (usize, Vec<u64>, u64, u64) {
+        let before = queries.load(Ordering::SeqCst);
+        let list = chat_service::get_user_chats(user_id, None, 20, db.clone()).await.unwrap();
+        let total = chat_service::get_unread_message_count(user_id, db.clone()).await.unwrap().count;
+        let chat = chat_service::get_chat_unread_count(user_id, chat_id, db.clone()).await.unwrap().count;
+        let used = queries.load(Ordering::SeqCst) - before;
+
+        (used, list.chats.iter().map(|c| c.unread_count).collect(), total, chat)
+    }
+
+    #[tokio::test]
+    async fn test_unread_counts_take_constant_queries() {
+        let (db, queries) = setup_counted_db().await;
+        let alice = 1;
+        let bob = 2;
+
+        let direct = chat_service::create_chat(alice, None, false, vec![2], db.clone()).await.unwrap();
+        let group = chat_service::create_chat(alice, Some("Study Group".into()), true, vec![2, 3], db.clone()).await.unwrap();
+        insert_messages(direct.id, 2, db.clone()).await;
+        insert_messages(group.id, 3, db.clone()).await;
+
+        let (small_queries, small_counts, small_total, small_chat) = load_chat_list(bob, group.id, db.clone(), &queries).await;
+        assert_eq!(small_total, 5);
+        assert_eq!(small_chat, 3);
+        let mut sorted = small_counts.clone();
+        sorted.sort();
+        assert_eq!(sorted, vec![2, 3]);
+
+        // Thousands more messages don't add a single query
+        insert_messages(direct.id, 2000, db.clone()).await;
+        insert_messages(group.id, 3000, db.clone()).await;
+
+        let (large_queries, large_counts, large_total, large_chat) = load_chat_list(bob, group.id, db.clone(), &queries).await;
+        assert_eq!(large_queries, small_queries);
+        assert_eq!(large_total, 5005);
+        assert_eq!(large_chat, 3003);
+        // The group got the newest messages, so it sorts first
+        assert_eq!(large_counts, vec![3003, 2002]);
+
+        // Marking a chat read is also a fixed number of queries, whatever its size: one lookup and one INSERT ... SELECT
+        let before = queries.load(Ordering::SeqCst);
+        chat_service::mark_messages_read(bob, group.id, db.clone()).await.unwrap();
+        let mark_queries = queries.load(Ordering::SeqCst) - before;
+        let before = queries.load(Ordering::SeqCst);
+        chat_service::mark_messages_read(bob, direct.id, db.clone()).await.unwrap();
+        assert_eq!(queries.load(Ordering::SeqCst) - before, mark_queries);
+
+        let (_, counts, total, chat) = load_chat_list(bob, group.id, db.clone(), &queries).await;
+        assert_eq!(total, 0);
+        assert_eq!(chat, 0);
+        assert_eq!(counts, vec![0, 0]);
+    }
+}
@@ -0,0 +1,239 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use server::handlers::repositories::in_memory::InMemoryRepositories;
+    use server::handlers::repositories::store::Repositories;
+    use server::handlers::services::user_service;
+    use server::utils::errors::server_error::ServerError;
+    use shared::models::user_models::PresenceStatus;
+
+    const ALICE: i32 = 1;
+    const BOB: i32 = 2;
+    const DYLAN: i32 = 3;
+
+    // Friend and block flows against in-memory repositories, so no database is needed
+    async fn setup_repos() -> Arc<InMemoryRepositories> {
+        let repos = Arc::new(InMemoryRepositories::new());
+        for name in ["Alice", "Bob", "Dylan"] {
+            repos.users().register_user(name.to_owned(), "hash".to_string()).await.expect("Failed to register in setup");
+        }
+        repos
+    }
+
+    async fn make_friends(u1: i32, u2: i32, repos: Arc<InMemoryRepositories>) {
+        user_service::send_friend_request(u1, u2, repos.clone()).await.unwrap();
+        user_service::accept_friend_request(u2, u1, repos.clone()).await.unwrap();
+    }
+
+    fn usernames(users: &[shared::models::user_models::User]) -> Vec<&str> {
+        users.iter().map(|u| u.username.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_accepted_request_makes_mutual_friends() {
+        let repos = setup_repos().await;
+
+        user_service::send_friend_request(ALICE, BOB, repos.clone()).await.unwrap();
+        let alice_requests = user_service::get_friend_requests(ALICE, repos.clone()).await.unwrap();
+        let bob_requests = user_service::get_friend_requests(BOB, repos.clone()).await.unwrap();
+        assert_eq!(usernames(&alice_requests.outgoing), vec!["Bob"]);
+        assert!(alice_requests.incoming.is_empty());
+        assert_eq!(usernames(&bob_requests.incoming), vec!["Alice"]);
+
+        user_service::accept_friend_request(BOB, ALICE, repos.clone()).await.unwrap();
+        assert!(user_service::are_friends(ALICE, BOB, repos.clone()).await.unwrap());
+        assert!(user_service::are_friends(BOB, ALICE, repos.clone()).await.unwrap());
+        assert_eq!(usernames(&user_service::get_friends(ALICE, repos.clone()).await.unwrap().users), vec!["Bob"]);
+        assert_eq!(usernames(&user_service::get_friends(BOB, repos.clone()).await.unwrap().users), vec!["Alice"]);
+
+        // The request is no longer pending on either side
+        let bob_requests = user_service::get_friend_requests(BOB, repos.clone()).await.unwrap();
+        assert!(bob_requests.incoming.is_empty());
+        assert!(user_service::get_friend_requests(ALICE, repos.clone()).await.unwrap().outgoing.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sending_a_request_twice_keeps_one() {
+        let repos = setup_repos().await;
+
+        user_service::send_friend_request(ALICE, BOB, repos.clone()).await.unwrap();
+        user_service::send_friend_request(ALICE, BOB, repos.clone()).await.unwrap();
+        assert_eq!(user_service::get_friend_requests(BOB, repos.clone()).await.unwrap().incoming.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_crossed_requests_make_friends() {
+        let repos = setup_repos().await;
+
+        user_service::send_friend_request(ALICE, BOB, repos.clone()).await.unwrap();
+        user_service::send_friend_request(BOB, ALICE, repos.clone()).await.unwrap();
+
+        assert!(user_service::are_friends(ALICE, BOB, repos.clone()).await.unwrap());
+        assert_eq!(user_service::get_friends(BOB, repos.clone()).await.unwrap().users.len(), 1);
+        assert!(user_service::get_friend_requests(ALICE, repos.clone()).await.unwrap().outgoing.is_empty());
+        assert!(user_service::get_friend_requests(BOB, repos.clone()).await.unwrap().outgoing.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_friends_cannot_send_requests() {
+        let repos = setup_repos().await;
+        make_friends(ALICE, BOB, repos.clone()).await;
+
+        let result = user_service::send_friend_request(BOB, ALICE, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::AlreadyFriends)));
+    }
+
+    #[tokio::test]
+    async fn test_declined_and_cancelled_requests_are_removed() {
+        let repos = setup_repos().await;
+
+        user_service::send_friend_request(ALICE, BOB, repos.clone()).await.unwrap();
+        user_service::decline_friend_request(BOB, ALICE, repos.clone()).await.unwrap();
+        assert!(user_service::get_friend_requests(BOB, repos.clone()).await.unwrap().incoming.is_empty());
+        assert!(!user_service::are_friends(ALICE, BOB, repos.clone()).await.unwrap());
+
+        // A declined request can be sent again, and then taken back
+        user_service::send_friend_request(ALICE, BOB, repos.clone()).await.unwrap();
+        user_service::cancel_friend_request(ALICE, BOB, repos.clone()).await.unwrap();
+        assert!(user_service::get_friend_requests(ALICE, repos.clone()).await.unwrap().outgoing.is_empty());
+        assert!(user_service::get_friend_requests(BOB, repos.clone()).await.unwrap().incoming.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_removed_friends_can_befriend_again() {
+        let repos = setup_repos().await;
+        make_friends(ALICE, BOB, repos.clone()).await;
+        make_friends(ALICE, DYLAN, repos.clone()).await;
+
+        user_service::remove_friend(BOB, ALICE, repos.clone()).await.unwrap();
+        assert!(!user_service::are_friends(ALICE, BOB, repos.clone()).await.unwrap());
+        assert_eq!(usernames(&user_service::get_friends(ALICE, repos.clone()).await.unwrap().users), vec!["Dylan"]);
+        assert!(user_service::get_friends(BOB, repos.clone()).await.unwrap().users.is_empty());
+
+        make_friends(BOB, ALICE, repos.clone()).await;
+        assert!(user_service::are_friends(ALICE, BOB, repos.clone()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_block_ends_friendship_and_requests() {
+        let repos = setup_repos().await;
+        make_friends(ALICE, BOB, repos.clone()).await;
+        user_service::send_friend_request(DYLAN, ALICE, repos.clone()).await.unwrap();
+
+        user_service::block_user(ALICE, BOB, repos.clone()).await.unwrap();
+        user_service::block_user(ALICE, DYLAN, repos.clone()).await.unwrap();
+
+        assert!(!user_service::are_friends(ALICE, BOB, repos.clone()).await.unwrap());
+        assert!(user_service::get_friend_requests(ALICE, repos.clone()).await.unwrap().incoming.is_empty());
+        let blocked = user_service::get_blocked_users(ALICE, repos.clone()).await.unwrap();
+        assert_eq!(usernames(&blocked.users), vec!["Bob", "Dylan"]);
+
+        // Neither side can send a request while the block stands
+        let result = user_service::send_friend_request(BOB, ALICE, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::ActionBlocked)));
+        let result = user_service::send_friend_request(ALICE, BOB, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::ActionBlocked)));
+    }
+
+    #[tokio::test]
+    async fn test_block_rules() {
+        let repos = setup_repos().await;
+
+        let result = user_service::block_user(ALICE, ALICE, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::RequestInvalid(_))));
+        let result = user_service::block_user(ALICE, 99, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::UserNotFound)));
+
+        // Blocking twice is harmless, but only the blocker can lift it
+        user_service::block_user(ALICE, BOB, repos.clone()).await.unwrap();
+        user_service::block_user(ALICE, BOB, repos.clone()).await.unwrap();
+        let result = user_service::unblock_user(BOB, ALICE, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::RequestInvalid(_))));
+
+        user_service::unblock_user(ALICE, BOB, repos.clone()).await.unwrap();
+        assert!(user_service::get_blocked_users(ALICE, repos.clone()).await.unwrap().users.is_empty());
+        let result = user_service::unblock_user(ALICE, BOB, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::RequestInvalid(_))));
+        user_service::send_friend_request(BOB, ALICE, repos.clone()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_accepting_needs_a_pending_request() {
+        let repos = setup_repos().await;
+
+        let result = user_service::accept_friend_request(BOB, ALICE, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::RequestInvalid(_))));
+        assert!(!user_service::are_friends(ALICE, BOB, repos.clone()).await.unwrap());
+
+        // A request can't be accepted twice either
+        make_friends(ALICE, DYLAN, repos.clone()).await;
+        user_service::remove_friend(DYLAN, ALICE, repos.clone()).await.unwrap();
+        let result = user_service::accept_friend_request(DYLAN, ALICE, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::RequestInvalid(_))));
+        assert!(!user_service::are_friends(ALICE, DYLAN, repos.clone()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_blocked_request_cannot_be_accepted() {
+        let repos = setup_repos().await;
+        user_service::send_friend_request(ALICE, BOB, repos.clone()).await.unwrap();
+
+        // A block that leaves the request pending still stops it being accepted
+        repos.users().block_user(ALICE, BOB).await.unwrap();
+        let result = user_service::accept_friend_request(BOB, ALICE, repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::ActionBlocked)));
+        assert!(!user_service::are_friends(ALICE, BOB, repos.clone()).await.unwrap());
+        assert_eq!(user_service::get_friend_requests(BOB, repos.clone()).await.unwrap().incoming.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_search_ignores_case_and_hides_blocks() {
+        let repos = setup_repos().await;
+
+        let user = user_service::get_user_by_username(ALICE, "bOB".to_string(), repos.clone()).await.unwrap();
+        assert_eq!(user.id, BOB);
+        let result = user_service::get_user_by_username(ALICE, "Nobody".to_string(), repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::UserNotFound)));
+
+        // Whoever placed the block, neither side can find the other
+        user_service::block_user(BOB, ALICE, repos.clone()).await.unwrap();
+        let result = user_service::get_user_by_username(ALICE, "Bob".to_string(), repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::UserNotFound)));
+        let result = user_service::get_user_by_username(BOB, "Alice".to_string(), repos.clone()).await;
+        assert!(matches!(result, Err(ServerError::UserNotFound)));
+        assert!(user_service::get_user_by_username(DYLAN, "Alice".to_string(), repos.clone()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_presence_is_for_friends_only() {
+        let repos = setup_repos().await;
+        make_friends(ALICE, BOB, repos.clone()).await;
+
+        let last_seen = user_service::record_last_seen(BOB, repos.clone()).await.unwrap();
+        let live = HashMap::from([(DYLAN, PresenceStatus::Online)]);
+        let presence = user_service::get_presence(ALICE, vec![BOB, DYLAN], live, repos.clone()).await.unwrap();
+
+        assert_eq!(presence.presences.len(), 1);
+        assert_eq!(presence.presences[0].user_id, BOB);
+        assert_eq!(presence.presences[0].status, PresenceStatus::Offline);
+        assert_eq!(presence.presences[0].last_seen_at, Some(last_seen));
+        assert_eq!(user_service::get_friend_ids(BOB, repos.clone()).await.unwrap(), vec![ALICE]);
+    }
+
+    #[tokio::test]
+    async fn test_uncommitted_writes_are_dropped() {
+        let repos = setup_repos().await;
+
+        let txn = repos.begin().await.unwrap();
+        txn.users().create_friendship(ALICE, BOB).await.unwrap();
+        assert!(txn.users().get_friendship(ALICE, BOB).await.unwrap().is_some());
+        drop(txn);
+        assert!(!user_service::are_friends(ALICE, BOB, repos.clone()).await.unwrap());
+
+        let txn = repos.begin().await.unwrap();
+        txn.users().create_friendship(ALICE, BOB).await.unwrap();
+        txn.commit().await.unwrap();
+        assert!(user_service::are_friends(ALICE, BOB, repos.clone()).await.unwrap());
+    }
+}
@@ -0,0 +1,120 @@
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::DatabaseConnection;
+    use crate::common;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use server::handlers::services::{user_service, auth_service};
+    use server::utils::errors::server_error::ServerError;
+    use shared::models::user_models::PresenceStatus;
+
+    async fn setup_in_memory_db() -> Arc<DatabaseConnection> {
+        let db = Arc::new(common::connect().await);
+        auth_service::register("Alice".to_owned(), "Password".to_string(), db.clone()).await.expect("Failed to register in DB setup");
+        auth_service::register("Bob".to_owned(), "Password".to_string(), db.clone()).await.expect("Failed to register in DB setup");
+
+        db
+    }
+
+    #[tokio::test]
+    async fn test_block_removes_friendship_and_blocks_requests() {
+        let db = setup_in_memory_db().await;
+
+        let alice = 1;
+        let bob = 2;
+
+        user_service::send_friend_request(alice, 2, db.clone()).await.unwrap();
+        user_service::accept_friend_request(bob, 1, db.clone()).await.unwrap();
+        assert!(user_service::are_friends(1, 2, db.clone()).await.unwrap());
+
+        // Blocking twice is harmless
+        user_service::block_user(alice, 2, db.clone()).await.unwrap();
+        user_service::block_user(alice, 2, db.clone()).await.unwrap();
+        assert!(!user_service::are_friends(1, 2, db.clone()).await.unwrap());
+
+        let blocked = user_service::get_blocked_users(alice, db.clone()).await.unwrap();
+        assert_eq!(blocked.users.len(), 1);
+        assert_eq!(blocked.users[0].username, "Bob");
+
+        // Bob can't send a request to Alice, and can't lift Alice's block
+        let result = user_service::send_friend_request(bob, 1, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::ActionBlocked)));
+        let result = user_service::unblock_user(bob, 1, db.clone()).await;
+        assert!(matches!(result, Err(ServerError::RequestInvalid(_))));
+
+        user_service::unblock_user(alice, 2, db.clone()).await.unwrap();
+        let blocked = user_service::get_blocked_users(alice, db.clone()).await.unwrap();
+        assert!(blocked.users.is_empty());
+        assert!(user_service::send_friend_request(bob, 1, db.clone()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_presence_only_shared_with_friends() {
+        let db = setup_in_memory_db().await;
+        auth_service::register("Dylan".to_owned(), "Password".to_string(), db.clone()).await.unwrap();
+
+        let alice = 1;
+        let bob = 2;
+
+        user_service::send_friend_request(alice, 2, db.clone()).await.unwrap();
+        user_service::accept_friend_request(bob, 1, db.clone()).await.unwrap();
+        assert_eq!(user_service::get_friend_ids(1, db.clone()).await.unwrap(), vec![2]);
+
+        // Bob hasn't been seen yet and isn't connected
+        let list = user_service::get_presence(alice, vec![2, 3], HashMap::new(), db.clone()).await.unwrap();
+        assert_eq!(list.presences.len(), 1);
+        assert_eq!(list.presences[0].user_id, 2);
+        assert_eq!(list.presences[0].status, PresenceStatus::Offline);
+        assert!(list.presences[0].last_seen_at.is_none());
+
+        // Once he disconnects his last seen time sticks
+        let last_seen = user_service::record_last_seen(2, db.clone()).await.unwrap();
+        let list = user_service::get_presence(alice, vec![2], HashMap::new(), db.clone()).await.unwrap();
+        assert_eq!(list.presences[0].last_seen_at, Some(last_seen));
+
+        // Live status comes from the connected sessions
+        let live = HashMap::from([(2, PresenceStatus::Away), (3, PresenceStatus::Online)]);
+        let list = user_service::get_presence(alice, vec![2, 3], live, db.clone()).await.unwrap();
+        assert_eq!(list.presences.len(), 1);
+        assert_eq!(list.presences[0].status, PresenceStatus::Away);
+
+        // Dylan isn't a friend of Alice's, so he sees nothing
+        let list = user_service::get_presence(3, vec![1, 2], HashMap::new(), db.clone()).await.unwrap();
+        assert!(list.presences.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_friend_request() {
+        let db = setup_in_memory_db().await;
+
+        let alice = 1;
+        let bob = 2;
+
+        user_service::send_friend_request(alice, 2, db.clone()).await.unwrap();
+        let requests = user_service::get_friend_requests(bob, db.clone()).await.unwrap();
+        assert_eq!(requests.incoming.len(), 1);
+
+        user_service::cancel_friend_request(alice, 2, db.clone()).await.unwrap();
+        let requests = user_service::get_friend_requests(bob, db.clone()).await.unwrap();
+        assert!(requests.incoming.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_blocked_users_hidden_from_search() {
+        let db = setup_in_memory_db().await;
+
+        let alice = 1;
+        let bob = 2;
+
+        assert!(user_service::get_user_by_username(bob, "Alice".into(), db.clone()).await.is_ok());
+
+        user_service::block_user(alice, 2, db.clone()).await.unwrap();
+
+        let result = user_service::get_user_by_username(bob, "Alice".into(), db.clone()).await;
+        assert!(matches!(result, Err(ServerError::UserNotFound)));
+        let result = user_service::get_user_by_username(alice, "Bob".into(), db.clone()).await;
+        assert!(matches!(result, Err(ServerError::UserNotFound)));
+    }
+}
@@ -1,12 +1,23 @@
+use crate::models::chat_models::{ChatCursor, ChatRole};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A command from the client. Who it runs as is fixed by the connection
+/// logging in, not by anything in the request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClientRequest {
-    pub jwt: Option<String>,
     pub command: Command,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Sent as a QUIC datagram while the user types in a chat. It is best effort
+/// and never stored, so a lost datagram only delays the indicator.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TypingSignal {
+    pub chat_id: i32,
+    // False once the input is cleared or the message is sent
+    pub typing: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "data")]
 pub enum Command {
     Login {
@@ -17,6 +28,19 @@ pub enum Command {
         username: String,
         password: String,
     },
+    // Logs a new connection in to an existing session with its access token
+    ResumeSession {
+        token: String,
+    },
+    // Trades a refresh token for a new access token and refresh token,
+    // logging the connection in to the session
+    RefreshToken {
+        refresh_token: String,
+    },
+    ListSessions,
+    RevokeSession {
+        session_id: i32,
+    },
     GetInfo {},
     SendFriendRequest {
         receiver_username: String,
@@ -37,7 +61,19 @@ pub enum Command {
     BlockUser {
         blocked_id: i32,
     },
+    UnblockUser {
+        blocked_id: i32,
+    },
+    GetBlockedUsers,
     GetFriends,
+    // Presence of the given friends. Anyone who isn't a friend is left out.
+    GetPresence {
+        user_ids: Vec<i32>,
+    },
+    // Marks every session of the user as away (idle) or back online
+    SetAway {
+        away: bool,
+    },
     CreateChat {
         name: Option<String>,
         is_group: bool,
@@ -46,26 +82,63 @@ pub enum Command {
     SendMessage {
         chat_id: i32,
         content: String,
+        // Picked by the client so a retried send is only stored once
+        #[serde(default)]
+        idempotency_key: Option<String>,
+    },
+    EditMessage {
+        chat_id: i32,
+        message_id: i32,
+        content: String,
+    },
+    DeleteMessage {
+        chat_id: i32,
+        message_id: i32,
     },
     GetChats {
-        page: u64,
-        page_size: u64,
+        before: Option<ChatCursor>,
+        limit: u64,
     },
     GetChatMessages {
         chat_id: i32,
-        page: u64,
-        page_size: u64,
+        before_id: Option<i32>,
+        after_id: Option<i32>,
+        limit: u64,
+    },
+    MarkMessagesRead {
+        chat_id: i32,
     },
-    GetChatsPages {
-        page_size: u64,
+    GetChatMembers {
+        chat_id: i32,
     },
-    GetChatPages {
+    GetMessageReceipts {
         chat_id: i32,
-        page_size: u64,
+        message_id: i32,
     },
-    MarkMessagesRead {
+    AddChatMembers {
+        chat_id: i32,
+        member_ids: Vec<i32>,
+    },
+    RemoveChatMember {
+        chat_id: i32,
+        user_id: i32,
+    },
+    LeaveChat {
         chat_id: i32,
     },
+    RenameChat {
+        chat_id: i32,
+        name: String,
+    },
+    TransferOwnership {
+        chat_id: i32,
+        new_owner_id: i32,
+    },
+    SetChatMemberRole {
+        chat_id: i32,
+        user_id: i32,
+        role: ChatRole,
+    },
     GetUnreadChatMessageCount {
         chat_id: i32,
     },
@@ -73,7 +146,187 @@ pub enum Command {
         new_password: String,
     },
     GetUnreadMessageCount,
-    Logout {
-        username: String,
-    },
+    Logout,
+}
+
+impl Command {
+    /// Which command this is, which requests are routed by
+    pub fn kind(&self) -> CommandKind {
+        match self {
+            Command::Login { .. } => CommandKind::Login,
+            Command::Register { .. } => CommandKind::Register,
+            Command::ResumeSession { .. } => CommandKind::ResumeSession,
+            Command::RefreshToken { .. } => CommandKind::RefreshToken,
+            Command::ListSessions => CommandKind::ListSessions,
+            Command::RevokeSession { .. } => CommandKind::RevokeSession,
+            Command::GetInfo { .. } => CommandKind::GetInfo,
+            Command::SendFriendRequest { .. } => CommandKind::SendFriendRequest,
+            Command::AcceptFriendRequest { .. } => CommandKind::AcceptFriendRequest,
+            Command::DeclineFriendRequest { .. } => CommandKind::DeclineFriendRequest,
+            Command::CancelFriendRequest { .. } => CommandKind::CancelFriendRequest,
+            Command::GetFriendRequests { .. } => CommandKind::GetFriendRequests,
+            Command::RemoveFriend { .. } => CommandKind::RemoveFriend,
+            Command::BlockUser { .. } => CommandKind::BlockUser,
+            Command::UnblockUser { .. } => CommandKind::UnblockUser,
+            Command::GetBlockedUsers => CommandKind::GetBlockedUsers,
+            Command::GetFriends => CommandKind::GetFriends,
+            Command::GetPresence { .. } => CommandKind::GetPresence,
+            Command::SetAway { .. } => CommandKind::SetAway,
+            Command::CreateChat { .. } => CommandKind::CreateChat,
+            Command::SendMessage { .. } => CommandKind::SendMessage,
+            Command::EditMessage { .. } => CommandKind::EditMessage,
+            Command::DeleteMessage { .. } => CommandKind::DeleteMessage,
+            Command::GetChats { .. } => CommandKind::GetChats,
+            Command::GetChatMessages { .. } => CommandKind::GetChatMessages,
+            Command::MarkMessagesRead { .. } => CommandKind::MarkMessagesRead,
+            Command::GetChatMembers { .. } => CommandKind::GetChatMembers,
+            Command::GetMessageReceipts { .. } => CommandKind::GetMessageReceipts,
+            Command::AddChatMembers { .. } => CommandKind::AddChatMembers,
+            Command::RemoveChatMember { .. } => CommandKind::RemoveChatMember,
+            Command::LeaveChat { .. } => CommandKind::LeaveChat,
+            Command::RenameChat { .. } => CommandKind::RenameChat,
+            Command::TransferOwnership { .. } => CommandKind::TransferOwnership,
+            Command::SetChatMemberRole { .. } => CommandKind::SetChatMemberRole,
+            Command::GetUnreadChatMessageCount { .. } => CommandKind::GetUnreadChatMessageCount,
+            Command::UpdateProfile { .. } => CommandKind::UpdateProfile,
+            Command::GetUnreadMessageCount => CommandKind::GetUnreadMessageCount,
+            Command::Logout => CommandKind::Logout,
+        }
+    }
+
+    /// The command's name. Unlike its Debug output, it is safe to log since
+    /// it leaves out passwords and tokens.
+    pub fn name(&self) -> &'static str {
+        self.kind().name()
+    }
+}
+
+/// Each kind of command, without its fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CommandKind {
+    Login,
+    Register,
+    ResumeSession,
+    RefreshToken,
+    ListSessions,
+    RevokeSession,
+    GetInfo,
+    SendFriendRequest,
+    AcceptFriendRequest,
+    DeclineFriendRequest,
+    CancelFriendRequest,
+    GetFriendRequests,
+    RemoveFriend,
+    BlockUser,
+    UnblockUser,
+    GetBlockedUsers,
+    GetFriends,
+    GetPresence,
+    SetAway,
+    CreateChat,
+    SendMessage,
+    EditMessage,
+    DeleteMessage,
+    GetChats,
+    GetChatMessages,
+    MarkMessagesRead,
+    GetChatMembers,
+    GetMessageReceipts,
+    AddChatMembers,
+    RemoveChatMember,
+    LeaveChat,
+    RenameChat,
+    TransferOwnership,
+    SetChatMemberRole,
+    GetUnreadChatMessageCount,
+    UpdateProfile,
+    GetUnreadMessageCount,
+    Logout,
+}
+
+impl CommandKind {
+    /// Every kind, so a server can check each one has a handler
+    pub const ALL: [CommandKind; 38] = [
+        CommandKind::Login,
+        CommandKind::Register,
+        CommandKind::ResumeSession,
+        CommandKind::RefreshToken,
+        CommandKind::ListSessions,
+        CommandKind::RevokeSession,
+        CommandKind::GetInfo,
+        CommandKind::SendFriendRequest,
+        CommandKind::AcceptFriendRequest,
+        CommandKind::DeclineFriendRequest,
+        CommandKind::CancelFriendRequest,
+        CommandKind::GetFriendRequests,
+        CommandKind::RemoveFriend,
+        CommandKind::BlockUser,
+        CommandKind::UnblockUser,
+        CommandKind::GetBlockedUsers,
+        CommandKind::GetFriends,
+        CommandKind::GetPresence,
+        CommandKind::SetAway,
+        CommandKind::CreateChat,
+        CommandKind::SendMessage,
+        CommandKind::EditMessage,
+        CommandKind::DeleteMessage,
+        CommandKind::GetChats,
+        CommandKind::GetChatMessages,
+        CommandKind::MarkMessagesRead,
+        CommandKind::GetChatMembers,
+        CommandKind::GetMessageReceipts,
+        CommandKind::AddChatMembers,
+        CommandKind::RemoveChatMember,
+        CommandKind::LeaveChat,
+        CommandKind::RenameChat,
+        CommandKind::TransferOwnership,
+        CommandKind::SetChatMemberRole,
+        CommandKind::GetUnreadChatMessageCount,
+        CommandKind::UpdateProfile,
+        CommandKind::GetUnreadMessageCount,
+        CommandKind::Logout,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CommandKind::Login => "Login",
+            CommandKind::Register => "Register",
+            CommandKind::ResumeSession => "ResumeSession",
+            CommandKind::RefreshToken => "RefreshToken",
+            CommandKind::ListSessions => "ListSessions",
+            CommandKind::RevokeSession => "RevokeSession",
+            CommandKind::GetInfo => "GetInfo",
+            CommandKind::SendFriendRequest => "SendFriendRequest",
+            CommandKind::AcceptFriendRequest => "AcceptFriendRequest",
+            CommandKind::DeclineFriendRequest => "DeclineFriendRequest",
+            CommandKind::CancelFriendRequest => "CancelFriendRequest",
+            CommandKind::GetFriendRequests => "GetFriendRequests",
+            CommandKind::RemoveFriend => "RemoveFriend",
+            CommandKind::BlockUser => "BlockUser",
+            CommandKind::UnblockUser => "UnblockUser",
+            CommandKind::GetBlockedUsers => "GetBlockedUsers",
+            CommandKind::GetFriends => "GetFriends",
+            CommandKind::GetPresence => "GetPresence",
+            CommandKind::SetAway => "SetAway",
+            CommandKind::CreateChat => "CreateChat",
+            CommandKind::SendMessage => "SendMessage",
+            CommandKind::EditMessage => "EditMessage",
+            CommandKind::DeleteMessage => "DeleteMessage",
+            CommandKind::GetChats => "GetChats",
+            CommandKind::GetChatMessages => "GetChatMessages",
+            CommandKind::MarkMessagesRead => "MarkMessagesRead",
+            CommandKind::GetChatMembers => "GetChatMembers",
+            CommandKind::GetMessageReceipts => "GetMessageReceipts",
+            CommandKind::AddChatMembers => "AddChatMembers",
+            CommandKind::RemoveChatMember => "RemoveChatMember",
+            CommandKind::LeaveChat => "LeaveChat",
+            CommandKind::RenameChat => "RenameChat",
+            CommandKind::TransferOwnership => "TransferOwnership",
+            CommandKind::SetChatMemberRole => "SetChatMemberRole",
+            CommandKind::GetUnreadChatMessageCount => "GetUnreadChatMessageCount",
+            CommandKind::UpdateProfile => "UpdateProfile",
+            CommandKind::GetUnreadMessageCount => "GetUnreadMessageCount",
+            CommandKind::Logout => "Logout",
+        }
+    }
 }
@@ -0,0 +1,215 @@
+//! The wire protocol. Every message is a 4 byte big-endian length followed by
+//! the message in the codec the connection negotiated. A connection starts with
+//! a handshake on its first stream, always in JSON, where the client says which
+//! protocol versions and codecs it speaks and the server picks one of each.
+//!
+//! The payload of a `ServerResponse` is still a `serde_json::Value`, so with
+//! MessagePack the payload is built as JSON values before it is encoded, and
+//! the client reads it back through `serde_json::from_value`. The codec saves
+//! bytes on the wire, not that conversion. Typed payloads would need a new
+//! protocol version.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// The newest protocol version this build speaks
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// Every protocol version this build speaks
+pub const SUPPORTED_VERSIONS: &[u16] = &[PROTOCOL_VERSION];
+
+/// The handshake is always JSON, so any version can read it
+pub const HANDSHAKE_CODEC: Codec = Codec::Json;
+
+/// Largest handshake message either side accepts
+pub const MAX_HANDSHAKE_SIZE: usize = 4 * 1024;
+
+/// How messages are encoded once the handshake is done
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Codec {
+    /// Readable, for debugging
+    Json,
+    /// Compact binary
+    MessagePack,
+}
+
+impl Codec {
+    /// Every codec, most preferred first
+    pub const ALL: [Codec; 2] = [Codec::MessagePack, Codec::Json];
+
+    pub fn encode<T: Serialize + ?Sized>(self, value: &T) -> Result<Vec<u8>, CodecError> {
+        match self {
+            Codec::Json => serde_json::to_vec(value).map_err(|e| CodecError::Encode(e.to_string())),
+            // Structs as maps, so optional and defaulted fields work like in JSON
+            Codec::MessagePack => {
+                rmp_serde::to_vec_named(value).map_err(|e| CodecError::Encode(e.to_string()))
+            }
+        }
+    }
+
+    pub fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, CodecError> {
+        match self {
+            Codec::Json => {
+                serde_json::from_slice(bytes).map_err(|e| CodecError::Decode(e.to_string()))
+            }
+            Codec::MessagePack => {
+                rmp_serde::from_slice(bytes).map_err(|e| CodecError::Decode(e.to_string()))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Codec::Json => write!(f, "json"),
+            Codec::MessagePack => write!(f, "msgpack"),
+        }
+    }
+}
+
+impl FromStr for Codec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(Codec::Json),
+            "msgpack" | "messagepack" => Ok(Codec::MessagePack),
+            _ => Err(format!("Unknown codec {:?}, expected json or msgpack", s)),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CodecError {
+    #[error("Stream closed")]
+    Closed,
+
+    #[error("Message of {0} bytes is over the {1} byte limit")]
+    TooLarge(usize, usize),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Couldn't encode message: {0}")]
+    Encode(String),
+
+    #[error("Couldn't decode message: {0}")]
+    Decode(String),
+
+    #[error("Handshake rejected: {0}")]
+    Rejected(String),
+}
+
+/// Writes one length-prefixed message
+pub async fn write_frame<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    bytes: &[u8],
+) -> Result<(), CodecError> {
+    let len = u32::try_from(bytes.len())
+        .map_err(|_| CodecError::TooLarge(bytes.len(), u32::MAX as usize))?;
+    writer.write_all(&len.to_be_bytes()).await?;
+    writer.write_all(bytes).await?;
+    Ok(())
+}
+
+/// Reads one length-prefixed message, refusing any longer than `max_len`.
+/// A stream that ends before the next message gives `CodecError::Closed`.
+pub async fn read_frame<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    max_len: usize,
+) -> Result<Vec<u8>, CodecError> {
+    let mut len_buf = [0u8; 4];
+    match reader.read_exact(&mut len_buf).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Err(CodecError::Closed),
+        Err(e) => return Err(e.into()),
+    }
+    let len = u32::from_be_bytes(len_buf) as usize;
+    if len > max_len {
+        return Err(CodecError::TooLarge(len, max_len));
+    }
+
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf).await?;
+    Ok(buf)
+}
+
+/// Encodes a message and writes it as one frame
+pub async fn write_message<W, T>(writer: &mut W, codec: Codec, value: &T) -> Result<(), CodecError>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize + ?Sized,
+{
+    write_frame(writer, &codec.encode(value)?).await
+}
+
+/// Reads one frame and decodes the message in it
+pub async fn read_message<R, T>(
+    reader: &mut R,
+    codec: Codec,
+    max_len: usize,
+) -> Result<T, CodecError>
+where
+    R: AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    codec.decode(&read_frame(reader, max_len).await?)
+}
+
+/// The first message on a connection, from the client
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClientHello {
+    pub versions: Vec<u16>,
+    // Most preferred first
+    pub codecs: Vec<Codec>,
+}
+
+/// The server's answer to a ClientHello
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum ServerHello {
+    Accepted { version: u16, codec: Codec },
+    Rejected { reason: String },
+}
+
+impl ClientHello {
+    /// Offers every version this build speaks and the given codecs
+    pub fn new(codecs: Vec<Codec>) -> Self {
+        ClientHello {
+            versions: SUPPORTED_VERSIONS.to_vec(),
+            codecs,
+        }
+    }
+
+    /// Picks the newest version both sides speak, and the first of the
+    /// client's codecs the server supports
+    pub fn negotiate(&self, versions: &[u16], codecs: &[Codec]) -> ServerHello {
+        let Some(version) = self.versions.iter().filter(|v| versions.contains(v)).max() else {
+            return ServerHello::Rejected {
+                reason: format!(
+                    "No common protocol version: client speaks {:?}, server speaks {:?}",
+                    self.versions, versions
+                ),
+            };
+        };
+        let Some(codec) = self.codecs.iter().find(|c| codecs.contains(c)) else {
+            return ServerHello::Rejected {
+                reason: format!(
+                    "No common codec: client offered {:?}, server supports {:?}",
+                    self.codecs, codecs
+                ),
+            };
+        };
+        ServerHello::Accepted {
+            version: *version,
+            codec: *codec,
+        }
+    }
+}
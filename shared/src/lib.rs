@@ -1,3 +1,5 @@
 pub mod client_response;
+pub mod codec;
 pub mod models;
 pub mod server_response;
+pub mod tls;
@@ -1,5 +1,13 @@
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
+/// Error message the server sends for an expired access token, so the client
+/// knows to renew it with the refresh token
+pub const TOKEN_EXPIRED: &str = "Token expired";
+
+/// Error message for commands sent on a connection with no live session
+pub const NOT_LOGGED_IN: &str = "Not logged in";
+
 #[derive(Serialize, Deserialize)]
 pub struct AuthModel {
     pub username: String,
@@ -11,4 +19,21 @@ pub struct AuthResponseModel {
     pub success: bool,
     pub token: String,
     pub user_id: i32,
+    pub session_id: i32,
+    // Exchanged for a new token pair with RefreshToken. Each one only works once.
+    pub refresh_token: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionInfo {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: NaiveDateTime,
+    // The session making the request
+    pub current: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SessionList {
+    pub sessions: Vec<SessionInfo>,
 }
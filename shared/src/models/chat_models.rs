@@ -1,28 +1,101 @@
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ChatList {
     pub chats: Vec<Chat>,
+    // Where to continue from to get older chats, if there are any
+    pub next_cursor: Option<ChatCursor>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Position in a chat list, which is ordered by last activity (newest first)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChatCursor {
+    pub last_activity: NaiveDateTime,
+    pub chat_id: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Chat {
     pub id: i32,
     pub chat_name: String,
     pub unread_count: u64,
+    pub read_only: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ChatMessages {
     pub id: i32,
     pub messages: Vec<ChatMessage>,
+    // Whether there are more messages past the ones returned
+    pub has_more: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ChatMessage {
-    pub user_id: i32,
+    pub id: i32,
+    pub sender_id: i32,
     pub username: String,
     pub content: String,
+    pub is_system: bool,
+    pub timestamp: NaiveDateTime,
+    pub edited: bool,
+    pub edited_at: Option<NaiveDateTime>,
+    // Deleted messages keep their place in the chat but lose their content
+    pub deleted: bool,
+    // Whether the user the message was fetched for has read it
+    pub read: bool,
+    // Whether anyone other than the sender has read it
+    pub seen: bool,
+    // The key the sender's client sent it with, if any
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+}
+
+/// A member who has read a message, and when
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MessageReceipt {
+    pub user_id: i32,
+    pub username: String,
+    pub read_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MessageReceipts {
+    pub chat_id: i32,
+    pub message_id: i32,
+    // Everyone but the sender who has read the message, earliest first
+    pub seen_by: Vec<MessageReceipt>,
+}
+
+/// Sent to a chat's members when one of them reads it up to `last_read_id`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReadReceipt {
+    pub chat_id: i32,
+    pub last_read_id: i32,
+    pub reader: MessageReceipt,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    Owner,
+    Admin,
+    Member,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChatMember {
+    pub id: i32,
+    pub username: String,
+    pub role: ChatRole,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ChatMembers {
+    pub chat_id: i32,
+    pub is_group: bool,
+    pub members: Vec<ChatMember>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -1,3 +1,4 @@
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -21,4 +22,36 @@ pub struct UserList {
 pub struct FriendRequestList {
     pub incoming: Vec<User>,
     pub outgoing: Vec<User>,
-}
\ No newline at end of file
+}
+
+impl FriendRequestList {
+    /// Total number of incoming and outgoing requests
+    pub fn len(&self) -> usize {
+        self.incoming.len() + self.outgoing.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.incoming.is_empty() && self.outgoing.is_empty()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PresenceStatus {
+    Online,
+    Away,
+    Offline,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Presence {
+    pub user_id: i32,
+    pub status: PresenceStatus,
+    // When the user was last connected. Only meaningful while offline.
+    pub last_seen_at: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PresenceList {
+    pub presences: Vec<Presence>,
+}
@@ -1,3 +1,5 @@
+use crate::models::chat_models::{Chat, ChatMessage, ReadReceipt};
+use crate::models::user_models::{Presence, User};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -5,8 +7,80 @@ pub struct ServerResponse {
     pub jwt: Option<String>,
     pub success: bool,
     pub message: Option<String>,
+    // Untyped in every codec, see the codec module docs
     pub data: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Refresh;
+/// Error message for a request the user isn't allowed to make
+pub const FORBIDDEN: &str = "Action forbidden";
+
+/// Error message for a request a block between the users stops
+pub const ACTION_BLOCKED: &str = "Action blocked";
+
+/// Start of the error message for a request the server can't accept as sent
+pub const REQUEST_INVALID: &str = "Invalid Request";
+
+impl ServerResponse {
+    /// Whether the server turned the request down for good, so sending it
+    /// again won't help. Other failures, like rate limiting or a database
+    /// error, may pass on a later try.
+    pub fn is_rejected(&self) -> bool {
+        !self.success
+            && self.message.as_deref().is_some_and(|message| {
+                message == FORBIDDEN
+                    || message == ACTION_BLOCKED
+                    || message.starts_with(REQUEST_INVALID)
+            })
+    }
+}
+
+/// Events pushed to a logged-in client over its unidirectional event stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum ServerEvent {
+    NewMessage {
+        chat_id: i32,
+        message: ChatMessage,
+    },
+    MessageEdited {
+        chat_id: i32,
+        message: ChatMessage,
+    },
+    MessageDeleted {
+        chat_id: i32,
+        message_id: i32,
+    },
+    ChatCreated {
+        chat: Chat,
+    },
+    ChatUpdated {
+        chat: Chat,
+    },
+    ChatRemoved {
+        chat_id: i32,
+    },
+    FriendRequestReceived {
+        sender: User,
+    },
+    FriendRequestAccepted {
+        friend: User,
+    },
+    FriendRemoved {
+        friend_id: i32,
+    },
+    FriendRequestRemoved {
+        user_id: i32,
+    },
+    MessagesRead {
+        receipt: ReadReceipt,
+    },
+    Typing {
+        chat_id: i32,
+        user_id: i32,
+        username: String,
+        typing: bool,
+    },
+    PresenceChanged {
+        presence: Presence,
+    },
+}
@@ -0,0 +1,11 @@
+use sha2::{Digest, Sha256};
+
+/// SHA-256 of a DER certificate as colon separated hex. The server logs it
+/// and clients pin it, so both sides must agree on the format.
+pub fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
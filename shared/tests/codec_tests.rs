@@ -0,0 +1,223 @@
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use serde::Serialize;
+    use serde_json::json;
+    use shared::client_response::{ClientRequest, Command, CommandKind, TypingSignal};
+    use shared::codec::{self, ClientHello, Codec, CodecError, ServerHello, PROTOCOL_VERSION};
+    use shared::models::chat_models::{ChatCursor, ChatRole};
+    use shared::server_response::{ServerEvent, ServerResponse};
+
+    // One of every command. Add new commands here too, variant_index won't compile until they are.
+    fn every_command() -> Vec<Command> {
+        let cursor = ChatCursor { last_activity: NaiveDate::from_ymd_opt(2025, 5, 1).unwrap().and_hms_opt(12, 30, 0).unwrap(), chat_id: 7 };
+        vec![
+            Command::Login { username: "Alice".into(), password: "Password".into() },
+            Command::Register { username: "Bob".into(), password: "Pässwörd 🔑".into() },
+            Command::ResumeSession { token: "access".into() },
+            Command::RefreshToken { refresh_token: "refresh".into() },
+            Command::ListSessions,
+            Command::RevokeSession { session_id: 3 },
+            Command::GetInfo {},
+            Command::SendFriendRequest { receiver_username: "Dylan".into() },
+            Command::AcceptFriendRequest { sender_id: 2 },
+            Command::DeclineFriendRequest { sender_id: 2 },
+            Command::CancelFriendRequest { receiver_id: 3 },
+            Command::GetFriendRequests {},
+            Command::RemoveFriend { friend_id: 2 },
+            Command::BlockUser { blocked_id: 2 },
+            Command::UnblockUser { blocked_id: 2 },
+            Command::GetBlockedUsers,
+            Command::GetFriends,
+            Command::GetPresence { user_ids: vec![1, 2, 3] },
+            Command::SetAway { away: true },
+            Command::CreateChat { name: Some("Study Group".into()), is_group: true, member_ids: vec![2, 3] },
+            Command::CreateChat { name: None, is_group: false, member_ids: vec![] },
+            Command::SendMessage { chat_id: 1, content: "Hello\nWorld".into(), idempotency_key: Some("0b7c2c4e-4a59-4c4e-9d0e-5f8f0f5d2a11".into()) },
+            Command::SendMessage { chat_id: 1, content: String::new(), idempotency_key: None },
+            Command::EditMessage { chat_id: 1, message_id: 4, content: "Fixed".into() },
+            Command::DeleteMessage { chat_id: 1, message_id: 4 },
+            Command::GetChats { before: Some(cursor), limit: 20 },
+            Command::GetChats { before: None, limit: u64::MAX },
+            Command::GetChatMessages { chat_id: 1, before_id: Some(10), after_id: None, limit: 30 },
+            Command::MarkMessagesRead { chat_id: 1 },
+            Command::GetChatMembers { chat_id: 1 },
+            Command::GetMessageReceipts { chat_id: 1, message_id: 4 },
+            Command::AddChatMembers { chat_id: 1, member_ids: vec![4, 5] },
+            Command::RemoveChatMember { chat_id: 1, user_id: 4 },
+            Command::LeaveChat { chat_id: 1 },
+            Command::RenameChat { chat_id: 1, name: "Renamed".into() },
+            Command::TransferOwnership { chat_id: 1, new_owner_id: 2 },
+            Command::SetChatMemberRole { chat_id: 1, user_id: 2, role: ChatRole::Admin },
+            Command::GetUnreadChatMessageCount { chat_id: 1 },
+            Command::UpdateProfile { new_password: "NewPassword".into() },
+            Command::GetUnreadMessageCount,
+            Command::Logout,
+        ]
+    }
+
+    const COMMAND_VARIANTS: usize = 38;
+
+    fn variant_index(command: &Command) -> usize {
+        match command {
+            Command::Login { .. } => 0,
+            Command::Register { .. } => 1,
+            Command::ResumeSession { .. } => 2,
+            Command::RefreshToken { .. } => 3,
+            Command::ListSessions => 4,
+            Command::RevokeSession { .. } => 5,
+            Command::GetInfo {} => 6,
+            Command::SendFriendRequest { .. } => 7,
+            Command::AcceptFriendRequest { .. } => 8,
+            Command::DeclineFriendRequest { .. } => 9,
+            Command::CancelFriendRequest { .. } => 10,
+            Command::GetFriendRequests {} => 11,
+            Command::RemoveFriend { .. } => 12,
+            Command::BlockUser { .. } => 13,
+            Command::UnblockUser { .. } => 14,
+            Command::GetBlockedUsers => 15,
+            Command::GetFriends => 16,
+            Command::GetPresence { .. } => 17,
+            Command::SetAway { .. } => 18,
+            Command::CreateChat { .. } => 19,
+            Command::SendMessage { .. } => 20,
+            Command::EditMessage { .. } => 21,
+            Command::DeleteMessage { .. } => 22,
+            Command::GetChats { .. } => 23,
+            Command::GetChatMessages { .. } => 24,
+            Command::MarkMessagesRead { .. } => 25,
+            Command::GetChatMembers { .. } => 26,
+            Command::GetMessageReceipts { .. } => 27,
+            Command::AddChatMembers { .. } => 28,
+            Command::RemoveChatMember { .. } => 29,
+            Command::LeaveChat { .. } => 30,
+            Command::RenameChat { .. } => 31,
+            Command::TransferOwnership { .. } => 32,
+            Command::SetChatMemberRole { .. } => 33,
+            Command::GetUnreadChatMessageCount { .. } => 34,
+            Command::UpdateProfile { .. } => 35,
+            Command::GetUnreadMessageCount => 36,
+            Command::Logout => 37,
+        }
+    }
+
+    #[test]
+    fn test_every_command_kind_is_listed() {
+        let mut kinds: Vec<CommandKind> = Vec::new();
+        for command in every_command() {
+            assert_eq!(command.kind().name(), command.name());
+            if !kinds.contains(&command.kind()) {
+                kinds.push(command.kind());
+            }
+        }
+        assert_eq!(kinds, CommandKind::ALL.to_vec());
+    }
+
+    // The protocol types don't implement PartialEq, so compare them as JSON values
+    fn same<T: Serialize>(a: &T, b: &T) -> bool {
+        serde_json::to_value(a).unwrap() == serde_json::to_value(b).unwrap()
+    }
+
+    #[test]
+    fn test_every_command_round_trips() {
+        let commands = every_command();
+        let mut covered: Vec<usize> = commands.iter().map(variant_index).collect();
+        covered.sort();
+        covered.dedup();
+        assert_eq!(covered, (0..COMMAND_VARIANTS).collect::<Vec<_>>(), "every_command is missing a command");
+
+        for codec in Codec::ALL {
+            for command in commands.clone() {
+                let request = ClientRequest { command };
+                let bytes = codec.encode(&request).unwrap();
+                let decoded: ClientRequest = codec.decode(&bytes).unwrap();
+                assert!(same(&request, &decoded), "{} changed {:?} into {:?}", codec, request, decoded);
+            }
+        }
+    }
+
+    #[test]
+    fn test_responses_and_events_round_trip() {
+        let response = ServerResponse { jwt: Some("token".into()), success: true, message: Some("Chat Messages".into()), data: Some(json!({ "id": 1, "messages": [{ "id": 4, "content": "Hi", "timestamp": "2025-05-01T12:30:00", "edited_at": null, "seen": true }], "has_more": false, "big": u64::MAX, "negative": -3, "ratio": 0.5 })) };
+        let error = ServerResponse { jwt: None, success: false, message: Some("Not logged in".into()), data: None };
+        let events = vec![
+            ServerEvent::ChatRemoved { chat_id: 1 },
+            ServerEvent::Typing { chat_id: 1, user_id: 2, username: "Bob".into(), typing: true },
+            ServerEvent::MessageDeleted { chat_id: 1, message_id: 4 },
+        ];
+
+        for codec in Codec::ALL {
+            for response in [&response, &error] {
+                let decoded: ServerResponse = codec.decode(&codec.encode(response).unwrap()).unwrap();
+                assert!(same(response, &decoded));
+            }
+            for event in &events {
+                let decoded: ServerEvent = codec.decode(&codec.encode(event).unwrap()).unwrap();
+                assert!(same(event, &decoded));
+            }
+            let signal = TypingSignal { chat_id: 1, typing: false };
+            let decoded: TypingSignal = codec.decode(&codec.encode(&signal).unwrap()).unwrap();
+            assert!(same(&signal, &decoded));
+        }
+    }
+
+    #[test]
+    fn test_messagepack_is_smaller_than_json() {
+        let request = ClientRequest { command: Command::GetChatMessages { chat_id: 1, before_id: Some(10), after_id: None, limit: 30 } };
+        assert!(Codec::MessagePack.encode(&request).unwrap().len() < Codec::Json.encode(&request).unwrap().len());
+    }
+
+    #[test]
+    fn test_requests_from_before_idempotency_keys_still_decode() {
+        let decoded: ClientRequest = Codec::Json.decode(br#"{"command":{"type":"SendMessage","data":{"chat_id":1,"content":"Hi"}}}"#).unwrap();
+        assert!(matches!(decoded.command, Command::SendMessage { chat_id: 1, idempotency_key: None, .. }));
+    }
+
+    #[tokio::test]
+    async fn test_frames_round_trip_and_respect_the_limit() {
+        let (mut client, mut server) = tokio::io::duplex(64 * 1024);
+
+        let request = ClientRequest { command: Command::SendMessage { chat_id: 1, content: "x".repeat(1000), idempotency_key: None } };
+        codec::write_message(&mut client, Codec::MessagePack, &request).await.unwrap();
+        codec::write_message(&mut client, Codec::Json, &request).await.unwrap();
+        codec::write_message(&mut client, Codec::Json, &request).await.unwrap();
+        drop(client);
+
+        let first: ClientRequest = codec::read_message(&mut server, Codec::MessagePack, 2048).await.unwrap();
+        assert!(same(&request, &first));
+        let second: ClientRequest = codec::read_message(&mut server, Codec::Json, 2048).await.unwrap();
+        assert!(same(&request, &second));
+
+        // Refused on the length alone
+        let result = codec::read_frame(&mut server, 100).await;
+        assert!(matches!(result, Err(CodecError::TooLarge(_, 100))));
+
+        let (client, mut server) = tokio::io::duplex(64);
+        drop(client);
+        assert!(matches!(codec::read_frame(&mut server, 100).await, Err(CodecError::Closed)));
+    }
+
+    #[test]
+    fn test_handshake_negotiation() {
+        let hello = ClientHello::new(vec![Codec::MessagePack, Codec::Json]);
+        assert_eq!(hello.negotiate(&[PROTOCOL_VERSION], &Codec::ALL), ServerHello::Accepted { version: PROTOCOL_VERSION, codec: Codec::MessagePack });
+
+        // The newest version both speak, and the client's favourite codec the server has
+        let hello = ClientHello { versions: vec![1, 2, 3], codecs: vec![Codec::MessagePack, Codec::Json] };
+        assert_eq!(hello.negotiate(&[1, 2], &[Codec::Json]), ServerHello::Accepted { version: 2, codec: Codec::Json });
+
+        let hello = ClientHello { versions: vec![9], codecs: vec![Codec::Json] };
+        assert!(matches!(hello.negotiate(&[1], &Codec::ALL), ServerHello::Rejected { .. }));
+
+        let hello = ClientHello::new(vec![]);
+        assert!(matches!(hello.negotiate(&[PROTOCOL_VERSION], &Codec::ALL), ServerHello::Rejected { .. }));
+
+        // The hello itself is always JSON
+        let bytes = codec::HANDSHAKE_CODEC.encode(&ClientHello::new(vec![Codec::Json])).unwrap();
+        assert_eq!(String::from_utf8(bytes).unwrap(), format!(r#"{{"versions":[{}],"codecs":["json"]}}"#, PROTOCOL_VERSION));
+
+        assert_eq!("msgpack".parse::<Codec>().unwrap(), Codec::MessagePack);
+        assert_eq!("JSON".parse::<Codec>().unwrap(), Codec::Json);
+        assert!("yaml".parse::<Codec>().is_err());
+    }
+}